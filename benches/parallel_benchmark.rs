@@ -0,0 +1,81 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use brunch::Bench;
+use physis::DatFile;
+use physis::sqpack::SqPackWriter;
+use physis::tex::Texture;
+
+/// Writes a dat file containing a single 8MB file, which is split into hundreds of blocks.
+fn build_large_dat() -> String {
+    let data: Vec<u8> = (0..8_000_000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8 & 0x3F)
+        .collect();
+
+    let mut writer = SqPackWriter::new();
+    writer.add_file("sound/large.scd", &data);
+    let (_, dat) = writer.finish().unwrap();
+
+    let mut path = std::env::temp_dir();
+    path.push("physis-parallel-benchmark.win32.dat0");
+    std::fs::write(&path, dat).unwrap();
+
+    path.to_str().unwrap().to_string()
+}
+
+fn bench_read_serial(path: &str) {
+    let mut dat = DatFile::from_existing(path).unwrap();
+    dat.read_from_offset(0x800).unwrap();
+}
+
+fn bench_read_parallel(path: &str) {
+    let mut dat = DatFile::from_existing(path).unwrap();
+    dat.read_from_offset_parallel(0x800).unwrap();
+}
+
+/// Builds a 256x256 BC1 texture, about the size of an icon.
+fn build_bc1_texture(seed: u8) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(&0x800000u32.to_le_bytes()); // 2D texture
+    data.extend_from_slice(&0x3420u32.to_le_bytes()); // BC1
+    data.extend_from_slice(&256u16.to_le_bytes());
+    data.extend_from_slice(&256u16.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&[0; 12]);
+    data.extend_from_slice(&80u32.to_le_bytes());
+    data.resize(80, 0);
+    data.extend((0..256 * 256 / 2).map(|i| (i as u8).wrapping_mul(seed)));
+
+    data
+}
+
+fn build_bc1_textures() -> Vec<Vec<u8>> {
+    (0..64).map(build_bc1_texture).collect()
+}
+
+fn bench_decode_textures_serial(textures: &[Vec<u8>]) {
+    for texture in textures {
+        Texture::from_existing(texture);
+    }
+}
+
+fn bench_decode_textures_parallel(textures: &[Vec<u8>]) {
+    let buffers: Vec<&[u8]> = textures.iter().map(|texture| texture.as_slice()).collect();
+    Texture::from_existing_batch(&buffers);
+}
+
+brunch::benches!(
+    Bench::new("large entry serial inflate")
+        .with_samples(50)
+        .run_seeded_with(build_large_dat, |path| bench_read_serial(&path)),
+    Bench::new("large entry parallel inflate")
+        .with_samples(50)
+        .run_seeded_with(build_large_dat, |path| bench_read_parallel(&path)),
+    Bench::new("texture batch serial decoding")
+        .with_samples(100)
+        .run_seeded_with(build_bc1_textures, |textures| bench_decode_textures_serial(&textures)),
+    Bench::new("texture batch parallel decoding")
+        .with_samples(100)
+        .run_seeded_with(build_bc1_textures, |textures| bench_decode_textures_parallel(&textures)),
+);
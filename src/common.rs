@@ -4,12 +4,14 @@
 use std::fs;
 use std::path::Path;
 
-use binrw::binrw;
+use binrw::{binrw, Endian};
+
+use crate::ByteSpan;
 
 #[binrw]
 #[brw(repr(u8))]
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 /// The language the game data is written for. Some of these languages are supported in the Global region.
 pub enum Language {
     /// Used for data that is language-agnostic, such as item data.
@@ -44,6 +46,22 @@ pub fn get_language_code(lang: &Language) -> &'static str {
     }
 }
 
+/// Builds the game path to the texture of an icon, such as "ui/icon/051000/051474.tex" for icon 51474. Icons are
+/// grouped into folders of a thousand.
+///
+/// Icons with text on them have a copy for each language, which is selected by `language`. If `hd` is true, the
+/// high resolution version is used instead.
+pub fn build_icon_path(icon_id: u32, language: Option<Language>, hd: bool) -> String {
+    let folder = icon_id / 1000 * 1000;
+    let language_folder = match language.map(|language| get_language_code(&language)) {
+        Some(code) if !code.is_empty() => format!("{code}/"),
+        _ => String::new(),
+    };
+    let suffix = if hd { "_hr1" } else { "" };
+
+    format!("ui/icon/{folder:06}/{language_folder}{icon_id:06}{suffix}.tex")
+}
+
 /// The region of the game. Used to denote the region a patch is meant for.
 #[binrw]
 #[brw(repr = i16)]
@@ -68,6 +86,16 @@ pub enum Platform {
     // TODO: confirm if there is a separate PS5, Xbox platform
 }
 
+impl Platform {
+    /// The byte order of data built for this platform, the PS3 is the only big-endian one.
+    pub fn endianness(&self) -> Endian {
+        match self {
+            Platform::PS3 => Endian::Big,
+            Platform::Win32 | Platform::PS4 => Endian::Little,
+        }
+    }
+}
+
 pub fn get_platform_string(id: &Platform) -> &'static str {
     match &id {
         Platform::Win32 => "win32",
@@ -75,3 +103,148 @@ pub fn get_platform_string(id: &Platform) -> &'static str {
         Platform::PS4 => "ps4", // TODO: confirm if this "ps4" is correct
     }
 }
+
+/// The kind of a game file, as guessed by [detect_file_type].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// A model (MDL).
+    Model,
+    /// A texture (TEX).
+    Texture,
+    /// An Excel header (EXH).
+    ExcelHeader,
+    /// An Excel data page (EXD).
+    ExcelData,
+    /// An Excel list (EXL).
+    ExcelList,
+    /// A sound container (SCD).
+    ScdAudio,
+    /// A game or boot patch (ZiPatch).
+    ZiPatch,
+    /// A shader package (SHPK).
+    ShaderPackage,
+    /// An animation container (PAP).
+    Animation,
+    /// The file isn't recognized.
+    Unknown,
+}
+
+/// Guesses the kind of a file from its contents, such as one extracted from a SqPack without a path.
+///
+/// Most formats are recognized by their magic. Models and textures don't have one, so their headers are checked
+/// for a known model version or texture format instead, which is less reliable. Models and textures are only
+/// recognized with the `visual_data` feature.
+pub fn detect_file_type(buffer: ByteSpan) -> FileKind {
+    const MAGICS: [(&[u8], FileKind); 7] = [
+        (b"EXHF", FileKind::ExcelHeader),
+        (b"EXDF", FileKind::ExcelData),
+        (b"EXLT", FileKind::ExcelList),
+        (b"SEDBSSCF", FileKind::ScdAudio),
+        (b"\x91ZIPATCH", FileKind::ZiPatch),
+        (b"ShPk", FileKind::ShaderPackage),
+        (b"pap ", FileKind::Animation),
+    ];
+
+    if let Some((_, kind)) = MAGICS.iter().find(|(magic, _)| buffer.starts_with(magic)) {
+        return *kind;
+    }
+
+    #[cfg(feature = "visual_data")]
+    {
+        use crate::model::{ModelFileHeader, MAX_LODS, MDL_VERSION_5, MDL_VERSION_6};
+        use binrw::BinRead;
+
+        if let Ok(header) = ModelFileHeader::read_le(&mut std::io::Cursor::new(buffer)) {
+            if (header.version == MDL_VERSION_5 || header.version == MDL_VERSION_6)
+                && header.lod_count <= MAX_LODS
+            {
+                return FileKind::Model;
+            }
+        }
+
+        if crate::tex::Texture::is_texture(buffer) {
+            return FileKind::Texture;
+        }
+    }
+
+    FileKind::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_path() {
+        assert_eq!(
+            build_icon_path(51474, None, false),
+            "ui/icon/051000/051474.tex"
+        );
+        assert_eq!(
+            build_icon_path(51474, None, true),
+            "ui/icon/051000/051474_hr1.tex"
+        );
+        assert_eq!(
+            build_icon_path(121, Some(Language::English), false),
+            "ui/icon/000000/en/000121.tex"
+        );
+        assert_eq!(
+            build_icon_path(71001, Some(Language::Japanese), true),
+            "ui/icon/071000/ja/071001_hr1.tex"
+        );
+
+        // language-agnostic icons don't have a language folder
+        assert_eq!(
+            build_icon_path(51474, Some(Language::None), false),
+            "ui/icon/051000/051474.tex"
+        );
+    }
+
+    #[test]
+    fn test_detect_file_type() {
+        assert_eq!(detect_file_type(b"EXHF\0\x03"), FileKind::ExcelHeader);
+        assert_eq!(detect_file_type(b"EXDF\0\x02"), FileKind::ExcelData);
+        assert_eq!(detect_file_type(b"EXLT,2\r\n"), FileKind::ExcelList);
+        assert_eq!(detect_file_type(b"SEDBSSCF\x04"), FileKind::ScdAudio);
+        assert_eq!(
+            detect_file_type(b"\x91ZIPATCH\r\n\x1a\n"),
+            FileKind::ZiPatch
+        );
+        assert_eq!(detect_file_type(b"ShPk\0\x0b"), FileKind::ShaderPackage);
+        assert_eq!(detect_file_type(b"pap \x01\x02"), FileKind::Animation);
+
+        // too short for any header
+        assert_eq!(detect_file_type(b""), FileKind::Unknown);
+        assert_eq!(detect_file_type(b"EXH"), FileKind::Unknown);
+    }
+
+    #[test]
+    #[cfg(feature = "visual_data")]
+    fn test_detect_model_and_texture() {
+        use std::fs::read;
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+
+        let model = read(d.join("c0201e0038_top_zeroed.mdl")).unwrap();
+        assert_eq!(detect_file_type(&model), FileKind::Model);
+
+        // a 2D BC1 texture, 4x4 and with one mip level
+        let mut texture = Vec::new();
+        texture.extend_from_slice(&0x800000u32.to_le_bytes());
+        texture.extend_from_slice(&0x3420u32.to_le_bytes());
+        texture.extend_from_slice(&[4, 0, 4, 0, 1, 0, 1, 0]);
+        texture.resize(80 + 8, 0);
+        assert_eq!(detect_file_type(&texture), FileKind::Texture);
+
+        // an unknown format
+        texture[4] = 0xFF;
+        assert_eq!(detect_file_type(&texture), FileKind::Unknown);
+
+        assert_eq!(
+            detect_file_type(&read(d.join("random")).unwrap()),
+            FileKind::Unknown
+        );
+    }
+}
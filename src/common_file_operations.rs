@@ -58,6 +58,16 @@ pub(crate) fn strings_parser(
     Ok(strings)
 }
 
+/// Converts the bits of a half-precision float into a f32. This conversion is lossless.
+pub(crate) fn decode_half(bits: u16) -> f32 {
+    f16::from_bits(bits).to_f32()
+}
+
+/// Converts a f32 into the bits of a half-precision float, rounding to the nearest representable value.
+pub(crate) fn encode_half(value: f32) -> u16 {
+    f16::from_f32(value).to_bits()
+}
+
 fn read_half1(data: [u16; 1]) -> Half1 {
     Half1 {
         value: f16::from_bits(data[0]),
@@ -74,7 +84,7 @@ pub(crate) struct Half1 {
 fn read_half2(data: [u16; 2]) -> Half2 {
     Half2 {
         x: f16::from_bits(data[0]),
-        y: f16::from_bits(data[0]),
+        y: f16::from_bits(data[1]),
     }
 }
 
@@ -89,8 +99,8 @@ pub(crate) struct Half2 {
 fn read_half3(data: [u16; 3]) -> Half3 {
     Half3 {
         r: f16::from_bits(data[0]),
-        g: f16::from_bits(data[0]),
-        b: f16::from_bits(data[0]),
+        g: f16::from_bits(data[1]),
+        b: f16::from_bits(data[2]),
     }
 }
 
@@ -105,6 +115,8 @@ pub(crate) struct Half3 {
 
 #[cfg(test)]
 mod tests {
+    use std::io::Cursor;
+
     use super::*;
 
     const DATA: [u8; 2] = [0u8, 1u8];
@@ -143,4 +155,57 @@ mod tests {
         // Supposed to include the nul terminator
         assert_eq!(crate::common_file_operations::get_string_len(&"FOO".to_string()), 4);
     }
+
+    #[test]
+    fn decode_half_values() {
+        assert_eq!(decode_half(0x3C00), 1.0);
+        assert_eq!(decode_half(0xC000), -2.0);
+
+        // +/-0
+        assert_eq!(decode_half(0x0000).to_bits(), 0.0f32.to_bits());
+        assert_eq!(decode_half(0x8000).to_bits(), (-0.0f32).to_bits());
+
+        // smallest and largest subnormals
+        assert_eq!(decode_half(0x0001), 2.0f32.powi(-24));
+        assert_eq!(decode_half(0x03FF), 1023.0 * 2.0f32.powi(-24));
+
+        // max finite value
+        assert_eq!(decode_half(0x7BFF), 65504.0);
+        assert_eq!(decode_half(0x7C00), f32::INFINITY);
+    }
+
+    #[test]
+    fn encode_half_values() {
+        assert_eq!(encode_half(1.0), 0x3C00);
+        assert_eq!(encode_half(-0.0), 0x8000);
+        assert_eq!(encode_half(2.0f32.powi(-24)), 0x0001);
+        assert_eq!(encode_half(65504.0), 0x7BFF);
+
+        // rounds to the nearest half, instead of truncating
+        assert_eq!(encode_half(decode_half(0x3C00) + 0.0009), 0x3C01);
+    }
+
+    #[test]
+    fn half_round_trip() {
+        for bits in 0..=u16::MAX {
+            let value = decode_half(bits);
+            if value.is_nan() {
+                continue;
+            }
+
+            assert_eq!(encode_half(value), bits);
+            assert_eq!(decode_half(encode_half(value)).to_bits(), value.to_bits());
+        }
+    }
+
+    #[test]
+    fn read_half3() {
+        let data = [0x00u8, 0x3C, 0x00, 0x40, 0x00, 0xC0];
+
+        let half = Cursor::new(data).read_le::<Half3>().unwrap();
+        assert_eq!(
+            [half.r.to_f32(), half.g.to_f32(), half.b.to_f32()],
+            [1.0, 2.0, -2.0]
+        );
+    }
 }
@@ -101,12 +101,86 @@ pub fn no_header_decompress(in_data: &mut [u8], out_data: &mut [u8]) -> bool {
         strm.next_out = out_data.as_mut_ptr();
 
         let ret = inflate(&mut strm, Z_NO_FLUSH);
+        let total_out = strm.total_out as usize;
+
+        inflateEnd(&mut strm);
+
+        // a stream that ends early or doesn't fill the buffer means the block is corrupt
+        ret == Z_STREAM_END && total_out == out_data.len()
+    }
+}
+
+pub fn no_header_compress(in_data: &[u8]) -> Option<Vec<u8>> {
+    #[allow(unused_imports)]
+    use crate::compression::flate2_zallocation::{zalloc, zfree};
+
+    unsafe {
+        let mut strm = z_stream {
+            next_in: null_mut(),
+            avail_in: in_data.len() as u32,
+            total_in: 0,
+            next_out: null_mut(),
+            avail_out: 0,
+            total_out: 0,
+            msg: null_mut(),
+            state: null_mut(),
+            zalloc,
+            zfree,
+            opaque: null_mut(),
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        };
+
+        let ret = deflateInit2_(
+            &mut strm,
+            Z_DEFAULT_COMPRESSION,
+            Z_DEFLATED,
+            -15,
+            8,
+            Z_DEFAULT_STRATEGY,
+            zlibVersion(),
+            core::mem::size_of::<z_stream>() as i32,
+        );
+        if ret != Z_OK {
+            return None;
+        }
+
+        let mut out_data = vec![0u8; deflateBound(&mut strm, in_data.len() as _) as usize];
+
+        // zlib never writes through next_in, it's only mutable for historical reasons
+        strm.next_in = in_data.as_ptr() as *mut u8;
+        strm.avail_out = out_data.len() as u32;
+        strm.next_out = out_data.as_mut_ptr();
+
+        let ret = deflate(&mut strm, Z_FINISH);
+        let compressed_size = strm.total_out as usize;
+
+        deflateEnd(&mut strm);
+
         if ret != Z_STREAM_END {
-            return false;
+            return None;
         }
 
-        inflateEnd(&mut strm);
+        out_data.truncate(compressed_size);
+
+        Some(out_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compress_round_trip() {
+        let data: Vec<u8> = (0..4096).map(|x| (x % 7) as u8).collect();
+
+        let mut compressed = no_header_compress(&data).unwrap();
+        assert!(compressed.len() < data.len());
 
-        true
+        let mut decompressed = vec![0u8; data.len()];
+        assert!(no_header_decompress(&mut compressed, &mut decompressed));
+        assert_eq!(decompressed, data);
     }
 }
@@ -48,6 +48,11 @@ fn crc32(crc: u32, s: &[u8]) -> u32 {
     unsafe { libz_sys::crc32(crc.into(), s.as_ptr(), s.len() as u32) as u32 }
 }
 
+/// The standard CRC-32, used by ZiPatch chunks
+pub(crate) fn crc32_checksum(bytes: &[u8]) -> u32 {
+    crc32(0, bytes)
+}
+
 fn crc32_combine(crc1: u32, crc2: u32, len2: usize) -> u32 {
     unsafe { libz_sys::crc32_combine(crc1.into(), crc2.into(), len2 as z_off_t) as u32 }
 }
@@ -135,6 +140,17 @@ mod tests {
         assert_eq!(JAMCR.checksum(&bytes), CRC.checksum(&bytes))
     }
 
+    #[test]
+    fn check_crc32() {
+        use crc::{Crc, CRC_32_ISO_HDLC};
+
+        const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+        let bytes: [u8; 9] = [1, 1, 2, 4, 5, 6, 12, 12, 12];
+
+        assert_eq!(CRC32.checksum(&bytes), crc32_checksum(&bytes))
+    }
+
     #[test]
     fn check_xivcrc() {
         const CRC_32_TEST: Algorithm<u32> = Algorithm {
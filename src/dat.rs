@@ -9,17 +9,22 @@ use binrw::BinRead;
 use binrw::BinWrite;
 use binrw::{binrw, BinReaderExt};
 
+use crate::common::Platform;
 use crate::common_file_operations::read_bool_from;
+use crate::compression::no_header_decompress;
+use crate::index::{write_header, SqPackHeader};
 #[cfg(feature = "visual_data")]
 use crate::model::ModelFileHeader;
-use crate::sqpack::read_data_block;
+use crate::sha1::Sha1;
+use crate::sqpack::{read_data_block, write_data_block};
+use tracing::{field, info_span};
 
 #[binrw]
 #[brw(repr = i32)]
 #[derive(Debug, PartialEq, Eq)]
 /// The file type of the data entry.
 pub enum FileType {
-    /// Empty entry, usually invalid.
+    /// Empty or placeholder entry. If it has any data, it's stored as-is right after the header.
     Empty = 1,
     /// Encompasses every file that is not a model or a texture, which are stored in a special fashion.
     Standard,
@@ -143,8 +148,10 @@ struct FileInfo {
 #[binrw]
 #[br(little)]
 pub struct Block {
-    #[br(pad_after = 4)]
     offset: i32,
+    // skips over the compressed size
+    #[br(pad_before = 2)]
+    decompressed_size: u16,
 }
 
 #[binrw]
@@ -182,10 +189,224 @@ pub struct BlockHeader {
     pub compression: CompressionMode,
 }
 
+/// The header following the SqPack header in dat files.
+#[binrw]
+#[brw(little)]
+pub(crate) struct SqPackDataHeader {
+    pub(crate) size: u32,
+    #[brw(pad_before = 4)]
+    pub(crate) unknown: u32,
+    /// Size of the file entries following both headers.
+    pub(crate) data_size: u32,
+    /// Which dat in the chain this is, starting at 1.
+    pub(crate) spanned_dat: u32,
+    #[brw(pad_before = 4)]
+    pub(crate) max_file_size: u64,
+    pub(crate) data_hash: [u8; 20],
+}
+
+/// Recalculates the size and hash of the file entries in the data header of `file`, after entries were added to it.
+pub(crate) fn update_data_header<F: Read + Write + Seek>(file: &mut F) -> std::io::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let sqpack_header = SqPackHeader::read_le(file).map_err(std::io::Error::other)?;
+
+    let header_start = sqpack_header.size as u64;
+    file.seek(SeekFrom::Start(header_start))?;
+    let mut data_header = SqPackDataHeader::read(file).map_err(std::io::Error::other)?;
+
+    let data_start = header_start + data_header.size as u64;
+    let end = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(data_start))?;
+
+    let mut sha1 = Sha1::new();
+    let mut chunk = vec![0; 0x10000];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        sha1.update(&chunk[..read]);
+    }
+
+    data_header.data_size = (end - data_start) as u32;
+    data_header.data_hash = sha1.digest().bytes();
+
+    let header = write_header(&data_header).ok_or(std::io::ErrorKind::InvalidData)?;
+    file.seek(SeekFrom::Start(header_start))?;
+    file.write_all(&header)
+}
+
+/// The headers at the start of a dat file, see [DatFile::header_info].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatHeaderInfo {
+    /// The platform this dat file is for.
+    pub platform: Platform,
+    /// The version of the SqPack header, usually 1.
+    pub version: u32,
+    /// The SqPack file type, which is 1 for dat files.
+    pub file_type: u32,
+    /// Size of the file entries following the headers.
+    pub data_size: u64,
+    /// Which dat in the chain this is, starting at 1.
+    pub spanned_dat: u32,
+    /// How large this dat file is allowed to grow before a new one is started.
+    pub max_file_size: u64,
+}
+
+/// Where a segment of a model was written in a model file entry.
+#[cfg(feature = "visual_data")]
+#[derive(Clone, Copy, Default)]
+struct ModelSegment {
+    uncompressed_size: u32,
+    compressed_size: u32,
+    offset: u32,
+    block_index: u16,
+    block_count: u16,
+}
+
+/// Standard files are split into blocks of this size before compression.
+const STANDARD_BLOCK_SIZE: usize = 16000;
+
 pub struct DatFile {
     file: std::fs::File,
 }
 
+/// Errors that can occur while reading a file from a dat file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatError {
+    /// The entry at `offset` is empty, or its header can't be read.
+    InvalidEntry { offset: u64 },
+    /// The data block at `offset` is truncated, has an invalid header or fails to decompress.
+    CorruptBlock { offset: u64 },
+}
+
+/// A block of a file as it's stored in a dat file, see [DatFile::read_raw_blocks].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawBlock {
+    /// Where the block header starts in the dat file.
+    pub offset: u64,
+    /// Whether `data` is compressed with raw deflate, otherwise it's stored as-is.
+    pub compressed: bool,
+    /// Size of `data` once decompressed.
+    pub decompressed_length: u32,
+    /// The contents of the block, without its header.
+    pub data: ByteBuffer,
+}
+
+impl RawBlock {
+    /// Decompresses the contents of this block. Returns None if it fails to decompress.
+    pub fn decompress(&self) -> Option<ByteBuffer> {
+        if !self.compressed {
+            return Some(self.data.clone());
+        }
+
+        let mut compressed_data = self.data.clone();
+        let mut decompressed_data = vec![0; self.decompressed_length as usize];
+        if !no_header_decompress(&mut compressed_data, &mut decompressed_data) {
+            return None;
+        }
+
+        Some(decompressed_data)
+    }
+}
+
+/// A block of a file that hasn't been decompressed yet.
+struct LazyBlock {
+    /// Where the block header starts in the dat file.
+    file_offset: u64,
+    /// Where the block's data starts in the decompressed file.
+    start: u64,
+    size: u64,
+}
+
+/// Reads a file inside of a dat, decompressing its blocks on demand.
+pub struct DatFileReader {
+    /// None if the file is already fully decompressed into the cached block.
+    file: Option<std::fs::File>,
+    blocks: Vec<LazyBlock>,
+    size: u64,
+    position: u64,
+    /// The most recently decompressed block, so reads and seeks within it don't inflate it again.
+    cached_block: Option<(usize, ByteBuffer)>,
+}
+
+impl DatFileReader {
+    fn from_buffer(buffer: ByteBuffer) -> Self {
+        DatFileReader {
+            file: None,
+            blocks: vec![LazyBlock {
+                file_offset: 0,
+                start: 0,
+                size: buffer.len() as u64,
+            }],
+            size: buffer.len() as u64,
+            position: 0,
+            cached_block: Some((0, buffer)),
+        }
+    }
+
+    fn load_block(&mut self, index: usize) -> std::io::Result<&[u8]> {
+        if self.cached_block.as_ref().map(|(i, _)| *i) != Some(index) {
+            let data = self
+                .file
+                .as_mut()
+                .and_then(|file| read_data_block(file, self.blocks[index].file_offset))
+                .ok_or(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Failed to read data block.",
+                ))?;
+
+            self.cached_block = Some((index, data));
+        }
+
+        Ok(&self.cached_block.as_ref().unwrap().1)
+    }
+}
+
+impl Read for DatFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.position >= self.size {
+            return Ok(0);
+        }
+
+        let position = self.position;
+        let index = self
+            .blocks
+            .partition_point(|block| block.start + block.size <= position);
+        if index >= self.blocks.len() {
+            return Ok(0);
+        }
+
+        let offset_in_block = (position - self.blocks[index].start) as usize;
+        let block = self.load_block(index)?;
+
+        let remaining = block.get(offset_in_block..).unwrap_or_default();
+        let read_size = remaining.len().min(buf.len());
+        buf[..read_size].copy_from_slice(&remaining[..read_size]);
+
+        self.position += read_size as u64;
+
+        Ok(read_size)
+    }
+}
+
+impl Seek for DatFileReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        self.position = new_position.ok_or(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Tried to seek before the start of the file.",
+        ))?;
+
+        Ok(self.position)
+    }
+}
+
 // from https://users.rust-lang.org/t/how-best-to-convert-u8-to-u16/57551/4
 fn to_u8_slice(slice: &mut [u16]) -> &mut [u8] {
     let byte_len = 2 * slice.len();
@@ -200,19 +421,92 @@ impl DatFile {
         })
     }
 
+    /// Reads the SqPack and data headers at the start of this dat file, which is useful for diagnostics. Returns None if
+    /// they can't be read.
+    pub fn header_info(&self) -> Option<DatHeaderInfo> {
+        let mut file = &self.file;
+
+        file.seek(SeekFrom::Start(0)).ok()?;
+        let sqpack_header = SqPackHeader::read_le(&mut file).ok()?;
+
+        file.seek(SeekFrom::Start(sqpack_header.size.into())).ok()?;
+        let data_header = SqPackDataHeader::read(&mut file).ok()?;
+
+        Some(DatHeaderInfo {
+            platform: sqpack_header.platform_id,
+            version: sqpack_header.version,
+            file_type: sqpack_header.file_type,
+            data_size: data_header.data_size.into(),
+            spanned_dat: data_header.spanned_dat,
+            max_file_size: data_header.max_file_size,
+        })
+    }
+
     /// Reads from a certain offset inside of the dat file. This offset will be fixed automatically
     /// by the function.
     ///
-    /// If the block of data is successfully parsed, it returns the file data - otherwise is None.
-    pub fn read_from_offset(&mut self, offset: u64) -> Option<ByteBuffer> {
+    /// If the block of data is successfully parsed, it returns the file data - otherwise an error
+    /// describing which part of the entry is invalid.
+    ///
+    /// This decompresses every block of the file, use [DatFile::read_raw_blocks] to get them as they are stored.
+    ///
+    /// Reading happens inside of a `read_from_offset` tracing span, which records the size of the
+    /// file. Subscribers that track span timings can use it to profile extraction.
+    pub fn read_from_offset(&mut self, offset: u64) -> Result<ByteBuffer, DatError> {
+        let span = info_span!("read_from_offset", offset, bytes = field::Empty).entered();
+
+        let data = self.read_file(offset);
+        if let Ok(data) = &data {
+            span.record("bytes", data.len());
+        }
+
+        data
+    }
+
+    /// Same as [DatFile::read_from_offset], but the blocks of standard files are decompressed concurrently and then
+    /// joined in order. This is faster for large files that span many blocks, such as sound banks, while other file
+    /// types are read as usual.
+    #[cfg(feature = "parallel")]
+    pub fn read_from_offset_parallel(&mut self, offset: u64) -> Result<ByteBuffer, DatError> {
+        use rayon::prelude::*;
+
+        let span = info_span!("read_from_offset", offset, bytes = field::Empty).entered();
+
+        // the block table has to be read first, to know where each block is
+        let blocks = match self.read_raw_blocks(offset) {
+            Ok(blocks) => blocks,
+            Err(DatError::InvalidEntry { .. }) => return self.read_file(offset),
+            Err(err) => return Err(err),
+        };
+
+        let data = blocks
+            .par_iter()
+            .map(|block| {
+                block
+                    .decompress()
+                    .filter(|x| x.len() == block.decompressed_length as usize)
+                    .ok_or(DatError::CorruptBlock {
+                        offset: block.offset,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+        span.record("bytes", data.len());
+
+        Ok(data)
+    }
+
+    fn read_file(&mut self, offset: u64) -> Result<ByteBuffer, DatError> {
+        let invalid_entry = DatError::InvalidEntry { offset };
+
         self.file
             .seek(SeekFrom::Start(offset))
-            .expect("Unable to find offset in file.");
+            .map_err(|_| invalid_entry)?;
 
-        let file_info = FileInfo::read(&mut self.file).ok()?;
+        let file_info = FileInfo::read(&mut self.file).map_err(|_| invalid_entry)?;
 
         match file_info.file_type {
-            FileType::Empty => None,
+            FileType::Empty => self.read_empty_file(offset, &file_info),
             FileType::Standard => self.read_standard_file(offset, &file_info),
             FileType::Model => {
                 #[cfg(feature = "visual_data")]
@@ -229,37 +523,319 @@ impl DatFile {
         }
     }
 
+    /// Reads the blocks of the standard file at `offset` without decompressing them, for tools that want to copy
+    /// them elsewhere as-is. Decompressing and joining the blocks gives the same data as [DatFile::read_from_offset].
+    ///
+    /// Only standard files are supported, model and texture files return [DatError::InvalidEntry].
+    pub fn read_raw_blocks(&mut self, offset: u64) -> Result<Vec<RawBlock>, DatError> {
+        let invalid_entry = DatError::InvalidEntry { offset };
+
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .map_err(|_| invalid_entry)?;
+
+        let file_info = FileInfo::read(&mut self.file).map_err(|_| invalid_entry)?;
+        let standard_file_info = file_info.standard_info.as_ref().ok_or(invalid_entry)?;
+
+        let mut blocks: Vec<Block> = Vec::with_capacity(standard_file_info.num_blocks as usize);
+        for _ in 0..standard_file_info.num_blocks {
+            blocks.push(Block::read(&mut self.file).map_err(|_| invalid_entry)?);
+        }
+
+        let starting_position = offset + (file_info.size as u64);
+
+        blocks
+            .iter()
+            .map(|block| {
+                let block_offset = starting_position + (block.offset as u64);
+                let corrupt_block = DatError::CorruptBlock {
+                    offset: block_offset,
+                };
+
+                self.file
+                    .seek(SeekFrom::Start(block_offset))
+                    .map_err(|_| corrupt_block)?;
+                let block_header = BlockHeader::read(&mut self.file).map_err(|_| corrupt_block)?;
+
+                let (compressed, length) = match block_header.compression {
+                    CompressionMode::Compressed {
+                        compressed_length, ..
+                    } => (true, compressed_length),
+                    CompressionMode::Uncompressed { file_size } => (false, file_size),
+                };
+
+                let mut data = vec![0; usize::try_from(length).map_err(|_| corrupt_block)?];
+                self.file
+                    .seek(SeekFrom::Start(block_offset + block_header.size as u64))
+                    .map_err(|_| corrupt_block)?;
+                self.file.read_exact(&mut data).map_err(|_| corrupt_block)?;
+
+                Ok(RawBlock {
+                    offset: block_offset,
+                    compressed,
+                    decompressed_length: block.decompressed_size as u32,
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    /// Creates a reader for the file at `offset`, which decompresses blocks as they are needed instead
+    /// of all at once. Only standard files are read lazily, other file types are decompressed up front.
+    pub fn reader_from_offset(mut self, offset: u64) -> Option<DatFileReader> {
+        self.file.seek(SeekFrom::Start(offset)).ok()?;
+
+        let file_info = FileInfo::read(&mut self.file).ok()?;
+
+        let Some(standard_file_info) = file_info.standard_info.as_ref() else {
+            return Some(DatFileReader::from_buffer(
+                self.read_from_offset(offset).ok()?,
+            ));
+        };
+
+        let starting_position = offset + (file_info.size as u64);
+
+        let mut blocks = Vec::with_capacity(standard_file_info.num_blocks as usize);
+        let mut start = 0;
+        for _ in 0..standard_file_info.num_blocks {
+            let block = Block::read(&mut self.file).ok()?;
+
+            blocks.push(LazyBlock {
+                file_offset: starting_position + (block.offset as u64),
+                start,
+                size: block.decompressed_size as u64,
+            });
+            start += block.decompressed_size as u64;
+        }
+
+        Some(DatFileReader {
+            file: Some(self.file),
+            blocks,
+            size: file_info.file_size as u64,
+            position: 0,
+            cached_block: None,
+        })
+    }
+
+    /// Serializes `data` as a standard file entry, which can be placed at any 128-byte aligned offset in a dat file.
+    pub(crate) fn write_standard_file(data: &[u8]) -> Option<ByteBuffer> {
+        let mut block_data = ByteBuffer::new();
+        let mut blocks: Vec<(u32, u16, u16)> = Vec::new();
+        {
+            let mut cursor = Cursor::new(&mut block_data);
+            for chunk in data.chunks(STANDARD_BLOCK_SIZE) {
+                let offset = cursor.position() as u32;
+                let block_size = write_data_block(&mut cursor, chunk)?;
+
+                blocks.push((offset, block_size as u16, chunk.len() as u16));
+            }
+        }
+
+        let header_size = (24 + blocks.len() * 8 + 127) & !127;
+
+        let mut buffer = ByteBuffer::with_capacity(header_size + block_data.len());
+        {
+            let mut cursor = Cursor::new(&mut buffer);
+
+            (header_size as u32).write_le(&mut cursor).ok()?;
+            FileType::Standard.write_le(&mut cursor).ok()?;
+            (data.len() as u32).write_le(&mut cursor).ok()?;
+            [0u32; 2].write_le(&mut cursor).ok()?;
+            (blocks.len() as u32).write_le(&mut cursor).ok()?;
+
+            for (offset, compressed_size, decompressed_size) in blocks {
+                offset.write_le(&mut cursor).ok()?;
+                compressed_size.write_le(&mut cursor).ok()?;
+                decompressed_size.write_le(&mut cursor).ok()?;
+            }
+        }
+
+        buffer.resize(header_size, 0);
+        buffer.append(&mut block_data);
+
+        Some(buffer)
+    }
+
+    /// Serializes an MDL file as a model file entry, which splits it into its stack, runtime, vertex, edge geometry and index segments.
+    #[cfg(feature = "visual_data")]
+    pub(crate) fn write_model_file(data: &[u8]) -> Option<ByteBuffer> {
+        let header = ModelFileHeader::read_le(&mut Cursor::new(data)).ok()?;
+
+        let mut block_data = ByteBuffer::new();
+        let mut block_sizes: Vec<u16> = Vec::new();
+
+        let mut write_segment = |offset: u32, size: u32| -> Option<ModelSegment> {
+            let segment = data.get(offset as usize..(offset + size) as usize)?;
+
+            let compressed_offset = block_data.len() as u32;
+            let block_index = block_sizes.len() as u16;
+
+            let mut cursor = Cursor::new(&mut block_data);
+            cursor.seek(SeekFrom::End(0)).ok()?;
+            for chunk in segment.chunks(STANDARD_BLOCK_SIZE) {
+                block_sizes.push(write_data_block(&mut cursor, chunk)? as u16);
+            }
+
+            Some(ModelSegment {
+                uncompressed_size: size,
+                compressed_size: block_data.len() as u32 - compressed_offset,
+                offset: compressed_offset,
+                block_index,
+                block_count: block_sizes.len() as u16 - block_index,
+            })
+        };
+
+        let stack = write_segment(0x44, header.stack_size)?;
+        let runtime = write_segment(0x44 + header.stack_size, header.runtime_size)?;
+
+        let mut vertex = [ModelSegment::default(); 3];
+        let mut edge = [ModelSegment::default(); 3];
+        let mut index = [ModelSegment::default(); 3];
+        for i in 0..3 {
+            vertex[i] = write_segment(header.vertex_offsets[i], header.vertex_buffer_size[i])?;
+
+            // edge geometry has no offset in the header, it sits between the vertex and index data
+            let vertex_end = header.vertex_offsets[i] + header.vertex_buffer_size[i];
+            let edge_size = if header.has_edge_geometry && header.vertex_offsets[i] != 0 {
+                header.index_offsets[i].saturating_sub(vertex_end)
+            } else {
+                0
+            };
+            edge[i] = write_segment(vertex_end, edge_size)?;
+
+            index[i] = write_segment(header.index_offsets[i], header.index_buffer_size[i])?;
+        }
+
+        fn sizes<T: for<'a> AnyNumberType<'a>>(
+            segments: (
+                &ModelSegment,
+                &ModelSegment,
+                &[ModelSegment; 3],
+                &[ModelSegment; 3],
+                &[ModelSegment; 3],
+            ),
+            get: fn(&ModelSegment) -> T,
+        ) -> ModelMemorySizes<T> {
+            ModelMemorySizes {
+                stack_size: get(segments.0),
+                runtime_size: get(segments.1),
+                vertex_buffer_size: segments.2.each_ref().map(get),
+                edge_geometry_vertex_buffer_size: segments.3.each_ref().map(get),
+                index_buffer_size: segments.4.each_ref().map(get),
+            }
+        }
+        let segments = (&stack, &runtime, &vertex, &edge, &index);
+
+        let header_size = (0xD0 + block_sizes.len() * 2 + 127) & !127;
+
+        let mut buffer = ByteBuffer::with_capacity(header_size + block_data.len());
+        {
+            let mut cursor = Cursor::new(&mut buffer);
+
+            (header_size as u32).write_le(&mut cursor).ok()?;
+            FileType::Model.write_le(&mut cursor).ok()?;
+            (data.len() as u32).write_le(&mut cursor).ok()?;
+            (block_sizes.len() as u32).write_le(&mut cursor).ok()?;
+            (block_sizes.len() as u32).write_le(&mut cursor).ok()?;
+            header.version.write_le(&mut cursor).ok()?;
+
+            sizes(segments, |x| x.uncompressed_size)
+                .write_le(&mut cursor)
+                .ok()?;
+            sizes(segments, |x| x.compressed_size)
+                .write_le(&mut cursor)
+                .ok()?;
+            sizes(segments, |x| x.offset).write_le(&mut cursor).ok()?;
+            sizes(segments, |x| x.block_index)
+                .write_le(&mut cursor)
+                .ok()?;
+            sizes(segments, |x| x.block_count)
+                .write_le(&mut cursor)
+                .ok()?;
+
+            header.vertex_declaration_count.write_le(&mut cursor).ok()?;
+            header.material_count.write_le(&mut cursor).ok()?;
+            header.lod_count.write_le(&mut cursor).ok()?;
+            (header.index_buffer_streaming_enabled as u8)
+                .write_le(&mut cursor)
+                .ok()?;
+            (header.has_edge_geometry as u8)
+                .write_le(&mut cursor)
+                .ok()?;
+            0u8.write_le(&mut cursor).ok()?;
+
+            block_sizes.write_le(&mut cursor).ok()?;
+        }
+
+        buffer.resize(header_size, 0);
+        buffer.append(&mut block_data);
+
+        Some(buffer)
+    }
+
+    /// Reads an empty (type 1) file, which isn't split into blocks. Its data, if any, is stored uncompressed.
+    fn read_empty_file(
+        &mut self,
+        offset: u64,
+        file_info: &FileInfo,
+    ) -> Result<ByteBuffer, DatError> {
+        let invalid_entry = DatError::InvalidEntry { offset };
+
+        self.file
+            .seek(SeekFrom::Start(offset + file_info.size as u64))
+            .map_err(|_| invalid_entry)?;
+
+        let mut data = vec![0; file_info.file_size as usize];
+        self.file.read_exact(&mut data).map_err(|_| invalid_entry)?;
+
+        Ok(data)
+    }
+
     /// Reads a standard file block.
-    fn read_standard_file(&mut self, offset: u64, file_info: &FileInfo) -> Option<ByteBuffer> {
-        let standard_file_info = file_info.standard_info.as_ref()?;
+    fn read_standard_file(
+        &mut self,
+        offset: u64,
+        file_info: &FileInfo,
+    ) -> Result<ByteBuffer, DatError> {
+        let invalid_entry = DatError::InvalidEntry { offset };
+
+        let standard_file_info = file_info.standard_info.as_ref().ok_or(invalid_entry)?;
 
         let mut blocks: Vec<Block> = Vec::with_capacity(standard_file_info.num_blocks as usize);
 
         for _ in 0..standard_file_info.num_blocks {
-            blocks.push(Block::read(&mut self.file).ok()?);
+            blocks.push(Block::read(&mut self.file).map_err(|_| invalid_entry)?);
         }
 
         let mut data: Vec<u8> = Vec::with_capacity(file_info.file_size as usize);
 
         let starting_position = offset + (file_info.size as u64);
 
-        for i in 0..standard_file_info.num_blocks {
-            data.append(
-                &mut read_data_block(
-                    &mut self.file,
-                    starting_position + (blocks[i as usize].offset as u64),
-                )
-                .expect("Failed to read data block."),
-            );
+        for block in &blocks {
+            let block_offset = starting_position + (block.offset as u64);
+
+            let mut block_data = read_data_block(&mut self.file, block_offset)
+                .filter(|x| x.len() == block.decompressed_size as usize)
+                .ok_or(DatError::CorruptBlock {
+                    offset: block_offset,
+                })?;
+
+            data.append(&mut block_data);
         }
 
-        Some(data)
+        Ok(data)
     }
 
     /// Reads a model file block.
     #[cfg(feature = "visual_data")]
-    fn read_model_file(&mut self, offset: u64, file_info: &FileInfo) -> Option<ByteBuffer> {
-        let model_file_info = file_info.model_info.as_ref()?;
+    fn read_model_file(
+        &mut self,
+        offset: u64,
+        file_info: &FileInfo,
+    ) -> Result<ByteBuffer, DatError> {
+        let invalid_entry = DatError::InvalidEntry { offset };
+
+        let model_file_info = file_info.model_info.as_ref().ok_or(invalid_entry)?;
 
         let mut buffer = Cursor::new(Vec::new());
 
@@ -270,95 +846,76 @@ impl DatFile {
         let mut compressed_block_sizes: Vec<u16> = vec![0; total_blocks as usize];
         let slice: &mut [u8] = to_u8_slice(&mut compressed_block_sizes);
 
-        self.file.read_exact(slice).ok()?;
+        self.file.read_exact(slice).map_err(|_| invalid_entry)?;
 
         let mut current_block = 0;
 
         let mut vertex_data_offsets: [u32; 3] = [0; 3];
         let mut vertex_data_sizes: [u32; 3] = [0; 3];
 
+        let mut edge_data_offsets: [u32; 3] = [0; 3];
+        let mut edge_data_sizes: [u32; 3] = [0; 3];
+
         let mut index_data_offsets: [u32; 3] = [0; 3];
         let mut index_data_sizes: [u32; 3] = [0; 3];
 
         // start writing at 0x44
-        buffer.seek(SeekFrom::Start(0x44)).ok()?;
-
-        self.file
-            .seek(SeekFrom::Start(
-                base_offset + (model_file_info.offset.stack_size as u64),
-            ))
-            .ok()?;
-
-        // read from stack blocks
-        let mut read_model_blocks = |offset: u64, size: usize| -> Option<u64> {
-            self.file.seek(SeekFrom::Start(base_offset + offset)).ok()?;
-            let stack_start = buffer.position();
-            for _ in 0..size {
-                let last_pos = &self.file.stream_position().ok()?;
-
-                let data =
-                    read_data_block(&self.file, *last_pos).expect("Unable to read block data.");
-                // write to buffer
-                buffer.write_all(data.as_slice()).ok()?;
-
-                self.file
-                    .seek(SeekFrom::Start(
-                        last_pos + (compressed_block_sizes[current_block] as u64),
-                    ))
-                    .ok()?;
+        buffer
+            .seek(SeekFrom::Start(0x44))
+            .map_err(|_| invalid_entry)?;
+
+        // reads `count` blocks starting at `offset`, returns where they were written to and how many bytes were decompressed
+        let mut read_model_blocks = |offset: u64, count: usize| -> Result<(u32, u32), DatError> {
+            let mut block_offset = base_offset + offset;
+            let start = buffer.position();
+
+            for _ in 0..count {
+                let corrupt_block = DatError::CorruptBlock {
+                    offset: block_offset,
+                };
+
+                let data = read_data_block(&self.file, block_offset).ok_or(corrupt_block)?;
+                buffer
+                    .write_all(data.as_slice())
+                    .map_err(|_| invalid_entry)?;
+
+                block_offset += *compressed_block_sizes
+                    .get(current_block)
+                    .ok_or(invalid_entry)? as u64;
                 current_block += 1;
             }
 
-            Some(buffer.position() - stack_start)
+            Ok((start as u32, (buffer.position() - start) as u32))
         };
 
-        let stack_size = read_model_blocks(
+        let (_, stack_size) = read_model_blocks(
             model_file_info.offset.stack_size as u64,
             model_file_info.num.stack_size as usize,
-        )? as u32;
-        let runtime_size = read_model_blocks(
+        )?;
+        let (_, runtime_size) = read_model_blocks(
             model_file_info.offset.runtime_size as u64,
             model_file_info.num.runtime_size as usize,
-        )? as u32;
-
-        let mut process_model_data =
-            |i: usize,
-             size: u32,
-             offset: u32,
-             offsets: &mut [u32; 3],
-             data_sizes: &mut [u32; 3]| {
-                if size != 0 {
-                    let current_vertex_offset = buffer.position() as u32;
-                    if i == 0 || current_vertex_offset != offsets[i - 1] {
-                        offsets[i] = current_vertex_offset;
-                    } else {
-                        offsets[i] = 0;
-                    }
-
-                    self.file
-                        .seek(SeekFrom::Start(base_offset + (offset as u64)))
-                        .ok();
-
-                    for _ in 0..size {
-                        let last_pos = self.file.stream_position().unwrap();
-
-                        let data = read_data_block(&self.file, last_pos)
-                            .expect("Unable to read raw model block!");
-
-                        buffer
-                            .write_all(data.as_slice())
-                            .expect("Unable to write to memory buffer!");
-
-                        data_sizes[i] += data.len() as u32;
-                        self.file
-                            .seek(SeekFrom::Start(
-                                last_pos + (compressed_block_sizes[current_block] as u64),
-                            ))
-                            .expect("Unable to seek properly.");
-                        current_block += 1;
-                    }
+        )?;
+
+        let mut process_model_data = |i: usize,
+                                      size: u32,
+                                      offset: u32,
+                                      offsets: &mut [u32; 3],
+                                      data_sizes: &mut [u32; 3]|
+         -> Result<(), DatError> {
+            if size != 0 {
+                let (current_offset, data_size) = read_model_blocks(offset as u64, size as usize)?;
+                if i == 0 || current_offset != offsets[i - 1] {
+                    offsets[i] = current_offset;
+                } else {
+                    offsets[i] = 0;
                 }
-            };
+
+                data_sizes[i] += data_size;
+            }
+
+            Ok(())
+        };
 
         // process all 3 lods
         for i in 0..3 {
@@ -369,9 +926,16 @@ impl DatFile {
                 model_file_info.offset.vertex_buffer_size[i],
                 &mut vertex_data_offsets,
                 &mut vertex_data_sizes,
-            );
+            )?;
 
-            // TODO: process edges
+            // process edges, the header has no room for them but they are kept between the vertices and indices
+            process_model_data(
+                i,
+                model_file_info.num.edge_geometry_vertex_buffer_size[i] as u32,
+                model_file_info.offset.edge_geometry_vertex_buffer_size[i],
+                &mut edge_data_offsets,
+                &mut edge_data_sizes,
+            )?;
 
             // process indices
             process_model_data(
@@ -380,7 +944,7 @@ impl DatFile {
                 model_file_info.offset.index_buffer_size[i],
                 &mut index_data_offsets,
                 &mut index_data_sizes,
-            );
+            )?;
         }
 
         let header = ModelFileHeader {
@@ -398,34 +962,44 @@ impl DatFile {
             has_edge_geometry: model_file_info.edge_geometry_enabled,
         };
 
-        buffer.seek(SeekFrom::Start(0)).ok()?;
+        buffer.seek(SeekFrom::Start(0)).map_err(|_| invalid_entry)?;
 
-        header.write(&mut buffer).ok()?;
+        header.write_le(&mut buffer).map_err(|_| invalid_entry)?;
 
-        Some(buffer.into_inner())
+        Ok(buffer.into_inner())
     }
 
     /// Reads a texture file block.
-    fn read_texture_file(&mut self, offset: u64, file_info: &FileInfo) -> Option<ByteBuffer> {
-        let texture_file_info = file_info.texture_info.as_ref()?;
+    fn read_texture_file(
+        &mut self,
+        offset: u64,
+        file_info: &FileInfo,
+    ) -> Result<ByteBuffer, DatError> {
+        let invalid_entry = DatError::InvalidEntry { offset };
+
+        let texture_file_info = file_info.texture_info.as_ref().ok_or(invalid_entry)?;
 
         let mut data: Vec<u8> = Vec::with_capacity(file_info.file_size as usize);
 
         // write the header if it exists
         let mipmap_size = texture_file_info.lods[0].compressed_size;
         if mipmap_size != 0 {
-            let original_pos = self.file.stream_position().ok()?;
+            let original_pos = self.file.stream_position().map_err(|_| invalid_entry)?;
 
             self.file
                 .seek(SeekFrom::Start(offset + file_info.size as u64))
-                .ok()?;
+                .map_err(|_| invalid_entry)?;
 
             let mut header = vec![0u8; texture_file_info.lods[0].compressed_offset as usize];
-            self.file.read_exact(&mut header).ok()?;
+            self.file
+                .read_exact(&mut header)
+                .map_err(|_| invalid_entry)?;
 
             data.append(&mut header);
 
-            self.file.seek(SeekFrom::Start(original_pos)).ok()?;
+            self.file
+                .seek(SeekFrom::Start(original_pos))
+                .map_err(|_| invalid_entry)?;
         }
 
         for i in 0..texture_file_info.num_blocks {
@@ -435,17 +1009,24 @@ impl DatFile {
                 + (file_info.size as u64);
 
             for _ in 0..texture_file_info.lods[i as usize].block_count {
-                let original_pos = self.file.stream_position().ok()?;
+                let original_pos = self.file.stream_position().map_err(|_| invalid_entry)?;
 
-                data.append(&mut read_data_block(&self.file, running_block_total)?);
+                data.append(&mut read_data_block(&self.file, running_block_total).ok_or(
+                    DatError::CorruptBlock {
+                        offset: running_block_total,
+                    },
+                )?);
 
-                self.file.seek(SeekFrom::Start(original_pos)).ok()?;
+                self.file
+                    .seek(SeekFrom::Start(original_pos))
+                    .map_err(|_| invalid_entry)?;
 
-                running_block_total += self.file.read_le::<i16>().ok()? as u64;
+                running_block_total +=
+                    self.file.read_le::<i16>().map_err(|_| invalid_entry)? as u64;
             }
         }
 
-        Some(data)
+        Ok(data)
     }
 }
 
@@ -472,10 +1053,158 @@ mod tests {
             texture_info: None,
         };
 
-        // Reading invalid data should just be nothing, but no panics
-        assert!(dat.read_from_offset(0).is_none());
-        assert!(dat.read_standard_file(0, &empty_file_info).is_none());
-        assert!(dat.read_model_file(0, &empty_file_info).is_none());
-        assert!(dat.read_texture_file(0, &empty_file_info).is_none());
+        // Reading invalid data should just be an error, but no panics
+        assert!(dat.read_from_offset(0).is_err());
+        assert!(dat.read_standard_file(0, &empty_file_info).is_err());
+        assert!(dat.read_model_file(0, &empty_file_info).is_err());
+        assert!(dat.read_texture_file(0, &empty_file_info).is_err());
+        assert!(dat.read_raw_blocks(0).is_err());
+        assert!(dat.header_info().is_none());
+    }
+
+    #[test]
+    fn test_read_raw_blocks() {
+        // large enough to be split into several blocks
+        let large_file: Vec<u8> = (0..40000u32).map(|x| (x % 251) as u8).collect();
+
+        let mut writer = crate::sqpack::SqPackWriter::new();
+        writer.add_file("common/large.bin", &large_file);
+        let (_, dat) = writer.finish().unwrap();
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-dat-raw-blocks");
+        std::fs::create_dir_all(&d).unwrap();
+        let dat_path = d.join("000000.win32.dat0");
+        std::fs::write(&dat_path, &dat).unwrap();
+
+        let mut dat_file = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        let blocks = dat_file.read_raw_blocks(0x800).unwrap();
+        assert!(blocks.len() > 1);
+        assert!(blocks.iter().all(|block| block.compressed));
+
+        let data: Vec<u8> = blocks
+            .iter()
+            .flat_map(|block| block.decompress().unwrap())
+            .collect();
+        assert_eq!(data, large_file);
+        assert_eq!(data, dat_file.read_from_offset(0x800).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_read_parallel() {
+        let large_file: Vec<u8> = (0..100000u32).map(|x| (x % 241) as u8).collect();
+
+        let mut writer = crate::sqpack::SqPackWriter::new();
+        writer.add_file("common/large.bin", &large_file);
+        let (_, dat) = writer.finish().unwrap();
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-dat-parallel");
+        std::fs::create_dir_all(&d).unwrap();
+        let dat_path = d.join("000000.win32.dat0");
+        std::fs::write(&dat_path, &dat).unwrap();
+
+        let mut dat_file = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            dat_file.read_from_offset_parallel(0x800).unwrap(),
+            large_file
+        );
+
+        // reading past the end fails the same way
+        let end = dat.len() as u64 + 0x80;
+        assert_eq!(
+            dat_file.read_from_offset_parallel(end),
+            dat_file.read_from_offset(end)
+        );
+    }
+
+    #[test]
+    fn test_header_info() {
+        let mut writer = crate::sqpack::SqPackWriter::new();
+        writer.add_file("exd/root.exl", b"EXLT,2");
+        let (_, dat) = writer.finish().unwrap();
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-dat-header-info");
+        std::fs::create_dir_all(&d).unwrap();
+        let dat_path = d.join("0a0000.win32.dat0");
+        std::fs::write(&dat_path, &dat).unwrap();
+
+        let dat_file = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            dat_file.header_info().unwrap(),
+            DatHeaderInfo {
+                platform: Platform::Win32,
+                version: 1,
+                file_type: 1,
+                data_size: dat.len() as u64 - 0x800,
+                spanned_dat: 1,
+                max_file_size: 2_000_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_entry() {
+        let mut entry = vec![];
+        entry.extend_from_slice(&128u32.to_le_bytes()); // header size
+        entry.extend_from_slice(&1i32.to_le_bytes()); // empty
+        entry.extend_from_slice(&5u32.to_le_bytes()); // file size
+        entry.resize(128, 0);
+        entry.extend_from_slice(b"hello");
+
+        let mut dir = std::env::temp_dir();
+        dir.push("physis-dat-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let dat_path = dir.join("empty.dat0");
+        std::fs::write(&dat_path, &entry).unwrap();
+
+        let mut dat = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        assert_eq!(dat.read_from_offset(0), Ok(b"hello".to_vec()));
+
+        // claiming more data than there is should fail
+        entry[8..12].copy_from_slice(&6u32.to_le_bytes());
+        std::fs::write(&dat_path, &entry).unwrap();
+
+        let mut dat = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            dat.read_from_offset(0),
+            Err(DatError::InvalidEntry { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_corrupt_block() {
+        let data: Vec<u8> = (0..1000u32).map(|x| (x % 251) as u8).collect();
+        let entry = DatFile::write_standard_file(&data).unwrap();
+
+        let mut cursor = Cursor::new(&entry);
+        let file_info = FileInfo::read(&mut cursor).unwrap();
+        let block = Block::read(&mut cursor).unwrap();
+        let block_offset = file_info.size as u64 + block.offset as u64;
+
+        let mut dir = std::env::temp_dir();
+        dir.push("physis-dat-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // cut the entry off in the middle of its only data block
+        let dat_path = dir.join("truncated.dat0");
+        std::fs::write(&dat_path, &entry[..block_offset as usize + 24]).unwrap();
+
+        let mut dat = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            dat.read_from_offset(0),
+            Err(DatError::CorruptBlock {
+                offset: block_offset
+            })
+        );
+
+        // the intact entry still reads back fine
+        std::fs::write(&dat_path, &entry).unwrap();
+
+        let mut dat = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+        assert_eq!(dat.read_from_offset(0), Ok(data));
     }
 }
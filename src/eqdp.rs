@@ -0,0 +1,168 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::Cursor;
+
+use crate::equipment::Slot;
+use crate::ByteSpan;
+use binrw::binread;
+use binrw::BinRead;
+
+/// Marks a block without any entries, where every model id falls back to another race.
+const EMPTY_BLOCK: u16 = 0xFFFF;
+
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+struct EqdpHeader {
+    /// Number of model ids in each block, after an unused identifier.
+    #[br(pad_before = 2)]
+    block_size: u16,
+    #[br(temp)]
+    block_count: u16,
+    /// Where each block starts in the entries, in number of entries.
+    #[br(count = block_count)]
+    block_offsets: Vec<u16>,
+}
+
+/// Equipment deformer parameters (EQDP), which describe which equipment has a model and material made for a
+/// specific race. When it doesn't, the game uses the model of another race instead, such as Hyur Midlander.
+///
+/// There is one file per race, such as "chara/xls/charadb/equipmentdeformerparameter/c0101.eqdp" for the head, body,
+/// hands, legs and feet, and "chara/xls/charadb/accessorydeformerparameter/c0101.eqdp" for accessories.
+#[derive(Debug)]
+pub struct EquipmentDeformerParameters {
+    /// Number of model ids covered by each block.
+    pub block_size: u16,
+    /// The entries of each block, or None if the block is empty.
+    blocks: Vec<Option<Vec<u16>>>,
+}
+
+impl EquipmentDeformerParameters {
+    /// Reads an existing EQDP file
+    pub fn from_existing(buffer: ByteSpan) -> Option<EquipmentDeformerParameters> {
+        let mut cursor = Cursor::new(buffer);
+        let header = EqdpHeader::read(&mut cursor).ok()?;
+
+        let entries: Vec<u16> = buffer
+            .get(cursor.position() as usize..)?
+            .chunks_exact(2)
+            .map(|entry| u16::from_le_bytes([entry[0], entry[1]]))
+            .collect();
+
+        let block_size = header.block_size as usize;
+        let blocks = header
+            .block_offsets
+            .iter()
+            .map(|offset| {
+                if *offset == EMPTY_BLOCK {
+                    return Some(None);
+                }
+
+                let start = *offset as usize;
+                Some(Some(entries.get(start..start + block_size)?.to_vec()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(EquipmentDeformerParameters {
+            block_size: header.block_size,
+            blocks,
+        })
+    }
+
+    /// Returns the raw entry for `model_id`, which has two bits per slot: the lower one for the material and the upper
+    /// one for the model. Model ids in an empty block, or past the end of the file, have no bits set.
+    pub fn entry(&self, model_id: u16) -> u16 {
+        if self.block_size == 0 {
+            return 0;
+        }
+
+        let block_size = self.block_size as usize;
+        self.blocks
+            .get(model_id as usize / block_size)
+            .and_then(|block| block.as_ref())
+            .and_then(|block| block.get(model_id as usize % block_size))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Whether this race has its own material for `model_id` in `slot`.
+    pub fn has_material(&self, model_id: u16, slot: Slot) -> bool {
+        self.entry(model_id) & (1 << Self::slot_shift(slot)) != 0
+    }
+
+    /// Whether this race has its own model for `model_id` in `slot`. If not, the model of another race is used.
+    pub fn has_model(&self, model_id: u16, slot: Slot) -> bool {
+        self.entry(model_id) & (1 << (Self::slot_shift(slot) + 1)) != 0
+    }
+
+    /// Where the bits for `slot` are in an entry. Accessories are in their own file, so they share bits with the
+    /// equipment slots.
+    fn slot_shift(slot: Slot) -> u16 {
+        match slot {
+            Slot::Head | Slot::Earring => 0,
+            Slot::Body | Slot::Neck => 2,
+            Slot::Hands | Slot::Wrists => 4,
+            Slot::Legs | Slot::RingRight => 6,
+            Slot::Feet | Slot::RingLeft => 8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("test.eqdp");
+
+        let eqdp = EquipmentDeformerParameters::from_existing(&read(d).unwrap()).unwrap();
+
+        assert_eq!(eqdp.block_size, 4);
+        assert_eq!(eqdp.entry(2), 0x3FF);
+        assert_eq!(eqdp.entry(10), 0x155);
+
+        // only the head material and model
+        assert!(eqdp.has_material(1, Slot::Head));
+        assert!(eqdp.has_model(1, Slot::Head));
+        assert!(!eqdp.has_model(1, Slot::Body));
+
+        // only the body model
+        assert!(!eqdp.has_material(3, Slot::Body));
+        assert!(eqdp.has_model(3, Slot::Body));
+
+        // everything
+        assert!(Slot::all()
+            .iter()
+            .all(|slot| eqdp.has_model(2, slot.clone()) && eqdp.has_material(2, slot.clone())));
+
+        // accessories share the bits of the equipment slots
+        assert!(eqdp.has_model(8, Slot::RingLeft));
+        assert!(eqdp.has_model(8, Slot::Feet));
+        assert!(eqdp.has_material(10, Slot::Wrists));
+        assert!(!eqdp.has_model(10, Slot::Wrists));
+        assert!(eqdp.has_model(11, Slot::Neck));
+        assert!(!eqdp.has_material(11, Slot::Neck));
+
+        // empty block, and past the end of the file
+        assert_eq!(eqdp.entry(5), 0);
+        assert!(!eqdp.has_model(5, Slot::Head));
+        assert_eq!(eqdp.entry(1000), 0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
+
+        // Feeding it invalid data should not panic
+        EquipmentDeformerParameters::from_existing(&read(d).unwrap());
+    }
+}
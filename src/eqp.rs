@@ -0,0 +1,177 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::ByteSpan;
+use bitflags::bitflags;
+
+/// Number of model ids in each block.
+const EQP_BLOCK_SIZE: usize = 160;
+
+/// Number of blocks, one for each bit of the block mask.
+const EQP_BLOCK_COUNT: usize = 64;
+
+// Flags are adapted from Penumbra (https://github.com/xivdev/Penumbra/blob/master/Penumbra.GameData/Structs/EqpEntry.cs)
+bitflags! {
+    /// Which parts of the body and other slots are shown when a piece of equipment is worn.
+    /// Each slot only uses its own flags, for example a head piece only has `HEAD_*` flags set.
+    pub struct EqpEntry : u64 {
+        const BODY_ENABLED = 1 << 0;
+        const BODY_HIDE_WAIST = 1 << 1;
+        const BODY_HIDE_GLOVES_S = 1 << 3;
+        const BODY_HIDE_GLOVES_M = 1 << 5;
+        const BODY_HIDE_GLOVES_L = 1 << 6;
+        const BODY_HIDE_GORGET = 1 << 7;
+        const BODY_SHOW_LEG = 1 << 8;
+        const BODY_SHOW_HAND = 1 << 9;
+        const BODY_SHOW_HEAD = 1 << 10;
+        const BODY_SHOW_NECKLACE = 1 << 11;
+        const BODY_SHOW_BRACELET = 1 << 12;
+        const BODY_SHOW_TAIL = 1 << 13;
+        const BODY_DISABLE_BREAST_PHYSICS = 1 << 14;
+        const BODY_USES_EVP_TABLE = 1 << 15;
+
+        const LEGS_ENABLED = 1 << 16;
+        const LEGS_HIDE_KNEE_PADS = 1 << 17;
+        const LEGS_HIDE_BOOTS_S = 1 << 18;
+        const LEGS_HIDE_BOOTS_M = 1 << 19;
+        const LEGS_SHOW_FOOT = 1 << 21;
+        const LEGS_SHOW_TAIL = 1 << 22;
+
+        const HANDS_ENABLED = 1 << 24;
+        const HANDS_HIDE_ELBOW = 1 << 25;
+        const HANDS_HIDE_FOREARM = 1 << 26;
+        const HANDS_SHOW_BRACELET = 1 << 28;
+        const HANDS_SHOW_RING_L = 1 << 29;
+        const HANDS_SHOW_RING_R = 1 << 30;
+
+        const FEET_ENABLED = 1 << 32;
+        const FEET_HIDE_KNEE = 1 << 33;
+        const FEET_HIDE_CALF = 1 << 34;
+        const FEET_HIDE_ANKLE = 1 << 35;
+
+        const HEAD_ENABLED = 1 << 40;
+        const HEAD_HIDE_SCALP = 1 << 41;
+        const HEAD_HIDE_HAIR = 1 << 42;
+        const HEAD_SHOW_HAIR_OVERRIDE = 1 << 43;
+        const HEAD_HIDE_NECK = 1 << 44;
+        const HEAD_SHOW_NECKLACE = 1 << 45;
+        const HEAD_SHOW_EARRINGS = 1 << 47;
+        const HEAD_SHOW_EARRINGS_HUMAN = 1 << 48;
+        const HEAD_SHOW_EARRINGS_AURA = 1 << 49;
+        const HEAD_SHOW_EAR_HUMAN = 1 << 50;
+        const HEAD_SHOW_EAR_MIQOTE = 1 << 51;
+        const HEAD_SHOW_EAR_AURA = 1 << 52;
+        const HEAD_SHOW_EAR_VIERA = 1 << 53;
+        const HEAD_SHOW_HROTHGAR_HAT = 1 << 56;
+        const HEAD_SHOW_VIERA_HAT = 1 << 57;
+        const HEAD_USES_EVP_TABLE = 1 << 58;
+    }
+}
+
+/// The equipment parameter file (EQP) at "chara/xls/equipmentparameter/equipmentparameter.eqp", which controls what
+/// parts of the body and other slots are hidden by each piece of equipment.
+#[derive(Debug)]
+pub struct Eqp {
+    /// Which blocks are present in the file.
+    block_mask: u64,
+    /// The entries of every present block, one after another.
+    entries: Vec<u64>,
+}
+
+impl Eqp {
+    /// Reads an existing EQP file
+    pub fn from_existing(buffer: ByteSpan) -> Option<Eqp> {
+        let entries: Vec<u64> = buffer
+            .chunks_exact(8)
+            .map(|entry| u64::from_le_bytes(entry.try_into().unwrap()))
+            .collect();
+
+        // the first entry doubles as the mask of which blocks are stored
+        let block_mask = *entries.first()?;
+        if entries.len() < block_mask.count_ones() as usize * EQP_BLOCK_SIZE {
+            return None;
+        }
+
+        Some(Eqp {
+            block_mask,
+            entries,
+        })
+    }
+
+    /// Returns the entry for `model_id`. Model ids without an entry, including 0 which holds the block mask, have no
+    /// flags set. Bits without a known meaning are dropped.
+    pub fn get(&self, model_id: u16) -> EqpEntry {
+        let block = model_id as usize / EQP_BLOCK_SIZE;
+        if model_id == 0 || block >= EQP_BLOCK_COUNT || self.block_mask & (1 << block) == 0 {
+            return EqpEntry::empty();
+        }
+
+        // missing blocks aren't stored, so skip over the ones before it that are
+        let stored_block = (self.block_mask & ((1 << block) - 1)).count_ones() as usize;
+        let index = stored_block * EQP_BLOCK_SIZE + model_id as usize % EQP_BLOCK_SIZE;
+
+        EqpEntry::from_bits_truncate(self.entries.get(index).copied().unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Builds an EQP file with only the first and third blocks.
+    fn build_eqp() -> Vec<u8> {
+        let mut entries = vec![0u64; EQP_BLOCK_SIZE * 2];
+        entries[0] = 0b101;
+        entries[1] =
+            (EqpEntry::HEAD_ENABLED | EqpEntry::HEAD_HIDE_NECK | EqpEntry::HEAD_HIDE_HAIR).bits();
+        entries[EQP_BLOCK_SIZE + 5] =
+            (EqpEntry::BODY_ENABLED | EqpEntry::BODY_SHOW_HAND | EqpEntry::BODY_SHOW_NECKLACE)
+                .bits()
+                | 1 << 63;
+
+        entries
+            .iter()
+            .flat_map(|entry| entry.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_parsing() {
+        let eqp = Eqp::from_existing(&build_eqp()).unwrap();
+
+        let head = eqp.get(1);
+        assert!(head.contains(EqpEntry::HEAD_HIDE_NECK));
+        assert!(head.contains(EqpEntry::HEAD_HIDE_HAIR));
+        assert!(!head.contains(EqpEntry::HEAD_SHOW_NECKLACE));
+
+        // model 325 is in the third block, which is stored right after the first one
+        let body = eqp.get(2 * EQP_BLOCK_SIZE as u16 + 5);
+        assert_eq!(
+            body,
+            EqpEntry::BODY_ENABLED | EqpEntry::BODY_SHOW_HAND | EqpEntry::BODY_SHOW_NECKLACE
+        );
+        assert!(!body.contains(EqpEntry::BODY_SHOW_BRACELET));
+
+        // the block mask, a missing block and an empty entry
+        assert!(eqp.get(0).is_empty());
+        assert!(eqp.get(EQP_BLOCK_SIZE as u16 + 5).is_empty());
+        assert!(eqp.get(2).is_empty());
+
+        // cutting off the last block makes the file too short for its block mask
+        let data = build_eqp();
+        assert!(Eqp::from_existing(&data[..data.len() - 8]).is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
+
+        // Feeding it invalid data should not panic
+        Eqp::from_existing(&read(d).unwrap());
+    }
+}
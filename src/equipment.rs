@@ -1,7 +1,9 @@
 // SPDX-FileCopyrightText: 2023 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::race::{get_race_id, Gender, Race, Subrace};
+use crate::eqdp::EquipmentDeformerParameters;
+use crate::gamedata::GameData;
+use crate::race::{get_fallback_race_id, get_race_id, Gender, Race, Subrace};
 
 #[repr(u8)]
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -29,6 +31,48 @@ pub enum Slot {
     Wrists,
 }
 
+impl Slot {
+    /// Returns every slot, in declaration order.
+    pub fn all() -> &'static [Slot] {
+        &[
+            Slot::Head,
+            Slot::Hands,
+            Slot::Legs,
+            Slot::Feet,
+            Slot::Body,
+            Slot::Earring,
+            Slot::Neck,
+            Slot::RingLeft,
+            Slot::RingRight,
+            Slot::Wrists,
+        ]
+    }
+
+    /// Returns the id of this slot, the reverse of [get_slot_from_id].
+    pub fn id(&self) -> i32 {
+        match self {
+            Slot::Head => 3,
+            Slot::Hands => 5,
+            Slot::Legs => 7,
+            Slot::Feet => 8,
+            Slot::Body => 4,
+            Slot::Earring => 9,
+            Slot::Neck => 10,
+            Slot::RingLeft => 12,
+            Slot::RingRight => 13,
+            Slot::Wrists => 11,
+        }
+    }
+
+    /// Whether this slot is an accessory (earrings, neck, wrists and rings), which are stored separately from the rest of the equipment.
+    pub fn is_accessory(&self) -> bool {
+        matches!(
+            self,
+            Slot::Earring | Slot::Neck | Slot::RingLeft | Slot::RingRight | Slot::Wrists
+        )
+    }
+}
+
 /// Returns the shorthand abbreviation of `slot`. For example, Body's shorthand is "top".
 pub fn get_slot_abbreviation(slot: Slot) -> &'static str {
     match slot {
@@ -45,6 +89,15 @@ pub fn get_slot_abbreviation(slot: Slot) -> &'static str {
     }
 }
 
+/// Determines the slot from its shorthand abbreviation, the reverse of [get_slot_abbreviation]. For example, "top"
+/// is Body. Returns None when no slot matches that abbreviation.
+pub fn slot_from_abbreviation(abbreviation: &str) -> Option<Slot> {
+    Slot::all()
+        .iter()
+        .find(|slot| get_slot_abbreviation((*slot).clone()) == abbreviation)
+        .cloned()
+}
+
 /// Determines the correct slot from an id. This can fail, so a None is returned when no slot matches
 /// that id.
 pub fn get_slot_from_id(id: i32) -> Option<Slot> {
@@ -63,8 +116,8 @@ pub fn get_slot_from_id(id: i32) -> Option<Slot> {
     }
 }
 
-/// Determines the correct slot from an id. This can fail, so a None is returned when no slot matches
-/// that id.
+/// Determines the correct slot from its shorthand abbreviation, the reverse of [get_slot_abbreviation].
+/// This can fail, so a None is returned when no slot matches that abbreviation. Same as [slot_from_abbreviation].
 pub fn get_slot_from_abbreviation(abrev: &str) -> Option<Slot> {
     match abrev {
         "met" => Some(Slot::Head),
@@ -81,7 +134,7 @@ pub fn get_slot_from_abbreviation(abrev: &str) -> Option<Slot> {
     }
 }
 
-/// Builds a game path to the equipment specified.
+/// Builds a game path to the equipment specified. Accessories are placed under "chara/accessory" instead.
 pub fn build_equipment_path(
     model_id: i32,
     race: Race,
@@ -89,15 +142,147 @@ pub fn build_equipment_path(
     gender: Gender,
     slot: Slot,
 ) -> String {
+    build_equipment_path_for_race_id(model_id, get_race_id(race, subrace, gender).unwrap(), slot)
+}
+
+/// Same as [build_equipment_path], but for the race id `race_id` such as 101.
+fn build_equipment_path_for_race_id(model_id: i32, race_id: i32, slot: Slot) -> String {
+    if slot.is_accessory() {
+        return format!(
+            "chara/accessory/a{:04}/model/c{:04}a{:04}_{}.mdl",
+            model_id,
+            race_id,
+            model_id,
+            get_slot_abbreviation(slot)
+        );
+    }
+
     format!(
         "chara/equipment/e{:04}/model/c{:04}e{:04}_{}.mdl",
         model_id,
-        get_race_id(race, subrace, gender).unwrap(),
+        race_id,
         model_id,
         get_slot_abbreviation(slot)
     )
 }
 
+/// The suffixes of the materials an equipment model can use, such as "_a" in "mt_c0101e0000_top_a.mtrl".
+const MATERIAL_SUFFIXES: [&str; 3] = ["a", "b", "c"];
+
+/// The bodies a race can have, such as "b0001" in "chara/human/c0101/obj/body/b0001". Slots that show skin use the
+/// models and materials of these as well.
+const BODY_VARIANTS: [i32; 2] = [1, 101];
+
+/// Builds every plausible game path for the equipment specified, starting with its model and followed by the
+/// materials of `variant` with each suffix (such as "_a" and "_b"). Slots that show skin also include the models and
+/// skin materials of each body variant (such as "b0001").
+///
+/// Not every path exists, so they should be checked with [GameData::exists] before being used. Returns None if the
+/// race, subrace and gender don't make up a valid race.
+pub fn build_equipment_path_variants(
+    model_id: i32,
+    race: Race,
+    subrace: Subrace,
+    gender: Gender,
+    slot: Slot,
+    variant: i32,
+) -> Option<Vec<String>> {
+    let race_id = get_race_id(race, subrace, gender)?;
+    let abbreviation = get_slot_abbreviation(slot.clone());
+    let (directory, prefix) = if slot.is_accessory() {
+        ("accessory", 'a')
+    } else {
+        ("equipment", 'e')
+    };
+
+    let mut paths = vec![build_equipment_path_for_race_id(
+        model_id,
+        race_id,
+        slot.clone(),
+    )];
+
+    for suffix in MATERIAL_SUFFIXES {
+        paths.push(format!(
+            "chara/{directory}/{prefix}{model_id:04}/material/v{variant:04}/mt_c{race_id:04}{prefix}{model_id:04}_{abbreviation}_{suffix}.mtrl"
+        ));
+    }
+
+    if matches!(slot, Slot::Body | Slot::Hands | Slot::Legs | Slot::Feet) {
+        for body in BODY_VARIANTS {
+            paths.push(format!(
+                "chara/human/c{race_id:04}/obj/body/b{body:04}/model/c{race_id:04}b{body:04}_{abbreviation}.mdl"
+            ));
+
+            for suffix in MATERIAL_SUFFIXES {
+                paths.push(build_skin_material_path(
+                    race_id,
+                    body,
+                    &format!("/mt_c{race_id:04}b{body:04}_{suffix}.mtrl"),
+                ));
+            }
+        }
+    }
+
+    Some(paths)
+}
+
+/// Builds a game path to the equipment deformer parameters (EQDP) of `race_code`, for either the equipment or the
+/// accessories.
+pub fn build_eqdp_path(race_code: i32, accessory: bool) -> String {
+    let kind = if accessory { "accessory" } else { "equipment" };
+
+    format!("chara/xls/charadb/{kind}deformerparameter/c{race_code:04}.eqdp")
+}
+
+/// Finds the model that the game uses for the equipment specified. When the race doesn't have its own model, according
+/// to its EQDP file, or the model doesn't exist, the fallback races from [get_fallback_race_id] are tried in order. For
+/// example, a Hrothgar male without a unique model uses the Hyur Midlander male one.
+///
+/// Returns None if the race is invalid, or none of the races have the model.
+pub fn resolve_equipment_model_path(
+    game_data: &mut GameData,
+    model_id: i32,
+    race: Race,
+    subrace: Subrace,
+    gender: Gender,
+    slot: Slot,
+) -> Option<String> {
+    let mut race_id = get_race_id(race, subrace, gender)?;
+
+    loop {
+        // races without an EQDP file only go by whether the model exists
+        let has_model = game_data
+            .extract(&build_eqdp_path(race_id, slot.is_accessory()))
+            .and_then(|eqdp| EquipmentDeformerParameters::from_existing(&eqdp))
+            .is_none_or(|eqdp| eqdp.has_model(model_id as u16, slot.clone()));
+
+        if has_model {
+            let path = build_equipment_path_for_race_id(model_id, race_id, slot.clone());
+            if game_data.exists(&path) {
+                return Some(path);
+            }
+        }
+
+        race_id = get_fallback_race_id(race_id)?;
+    }
+}
+
+/// Builds a game path to the accessory specified. Returns None if the race, subrace and gender don't make up a
+/// valid race.
+pub fn build_accessory_path(
+    model_id: i32,
+    race: Race,
+    subrace: Subrace,
+    gender: Gender,
+    slot: Slot,
+) -> Option<String> {
+    Some(build_equipment_path_for_race_id(
+        model_id,
+        get_race_id(race, subrace, gender)?,
+        slot,
+    ))
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 pub enum CharacterCategory {
@@ -160,6 +345,17 @@ pub fn build_gear_material_path(gear_id: i32, gear_version: i32, material_name:
     format!("chara/equipment/e{gear_id:04}/material/v{gear_version:04}{material_name}")
 }
 
+/// Resolves a material name from `MDL::material_names` into a full game path, for an equipment model.
+/// Short names such as "/mt_c0101e0000_top_a.mtrl" are placed in the material directory for `variant`,
+/// while full paths such as "chara/equipment/e0000/material/v0001/mt_c0101e0000_top_a.mtrl" are returned as-is.
+pub fn build_material_path(material_name: &str, variant: i32, model_id: i32) -> String {
+    if material_name.starts_with('/') {
+        build_gear_material_path(model_id, variant, material_name)
+    } else {
+        material_name.to_string()
+    }
+}
+
 /// Builds a skin material path for a character
 pub fn build_skin_material_path(race_code: i32, body_code: i32, material_name: &str) -> String {
     format!("chara/human/c{race_code:04}/obj/body/b{body_code:04}/material/v0001{material_name}")
@@ -185,6 +381,29 @@ pub fn build_tail_material_path(race_code: i32, tail_code: i32, material_name: &
     format!("chara/human/c{race_code:04}/obj/tail/t{tail_code:04}/material/v0001{material_name}")
 }
 
+/// Builds a skin texture path for a character, such as "base", "norm" or "mask" for `texture_type`. Unlike the
+/// textures shared by every race under "chara/common/texture", each race and body has their own skin textures.
+pub fn build_skin_texture_path(race_code: i32, body_code: i32, texture_type: &str) -> String {
+    format!(
+        "chara/human/c{race_code:04}/obj/body/b{body_code:04}/texture/c{race_code:04}b{body_code:04}_{texture_type}.tex"
+    )
+}
+
+/// Builds the path to the catchlight texture shared by every race, which is reflected in the eyes.
+pub fn build_catchlight_path() -> String {
+    "chara/common/texture/sphere_d_array.tex".to_string()
+}
+
+/// Builds a face paint (decal) texture path, as picked in the character creator.
+pub fn build_decal_path(decal_id: i32) -> String {
+    format!("chara/common/texture/decal_face/_decal_{decal_id}.tex")
+}
+
+/// Builds an equipment decal texture path, such as the emblems on some gear.
+pub fn build_equipment_decal_path(decal_id: i32) -> String {
+    format!("chara/common/texture/decal_equip/-decal_{decal_id:03}.tex")
+}
+
 pub fn deconstruct_equipment_path(path: &str) -> Option<(i32, Slot)> {
     let model_id = &path[6..10];
     let slot_name = &path[11..14];
@@ -207,6 +426,237 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_accessory_path() {
+        assert_eq!(
+            build_equipment_path(
+                1,
+                Race::Hyur,
+                Subrace::Midlander,
+                Gender::Male,
+                Slot::RingRight
+            ),
+            "chara/accessory/a0001/model/c0101a0001_rir.mdl"
+        );
+        assert_eq!(
+            build_equipment_path(
+                54,
+                Race::Elezen,
+                Subrace::Wildwood,
+                Gender::Female,
+                Slot::Earring
+            ),
+            "chara/accessory/a0054/model/c0601a0054_ear.mdl"
+        );
+
+        assert_eq!(
+            build_accessory_path(
+                1,
+                Race::Hyur,
+                Subrace::Midlander,
+                Gender::Male,
+                Slot::RingRight
+            )
+            .as_deref(),
+            Some("chara/accessory/a0001/model/c0101a0001_rir.mdl")
+        );
+        assert_eq!(
+            build_accessory_path(
+                1,
+                Race::Hyur,
+                Subrace::Wildwood,
+                Gender::Male,
+                Slot::RingRight
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_path_variants() {
+        assert_eq!(
+            build_equipment_path_variants(
+                1,
+                Race::Hyur,
+                Subrace::Midlander,
+                Gender::Male,
+                Slot::Body,
+                2
+            )
+            .unwrap(),
+            [
+                "chara/equipment/e0001/model/c0101e0001_top.mdl",
+                "chara/equipment/e0001/material/v0002/mt_c0101e0001_top_a.mtrl",
+                "chara/equipment/e0001/material/v0002/mt_c0101e0001_top_b.mtrl",
+                "chara/equipment/e0001/material/v0002/mt_c0101e0001_top_c.mtrl",
+                "chara/human/c0101/obj/body/b0001/model/c0101b0001_top.mdl",
+                "chara/human/c0101/obj/body/b0001/material/v0001/mt_c0101b0001_a.mtrl",
+                "chara/human/c0101/obj/body/b0001/material/v0001/mt_c0101b0001_b.mtrl",
+                "chara/human/c0101/obj/body/b0001/material/v0001/mt_c0101b0001_c.mtrl",
+                "chara/human/c0101/obj/body/b0101/model/c0101b0101_top.mdl",
+                "chara/human/c0101/obj/body/b0101/material/v0001/mt_c0101b0101_a.mtrl",
+                "chara/human/c0101/obj/body/b0101/material/v0001/mt_c0101b0101_b.mtrl",
+                "chara/human/c0101/obj/body/b0101/material/v0001/mt_c0101b0101_c.mtrl",
+            ]
+        );
+
+        // accessories don't show skin
+        assert_eq!(
+            build_equipment_path_variants(
+                54,
+                Race::Elezen,
+                Subrace::Wildwood,
+                Gender::Female,
+                Slot::Earring,
+                1
+            )
+            .unwrap(),
+            [
+                "chara/accessory/a0054/model/c0601a0054_ear.mdl",
+                "chara/accessory/a0054/material/v0001/mt_c0601a0054_ear_a.mtrl",
+                "chara/accessory/a0054/material/v0001/mt_c0601a0054_ear_b.mtrl",
+                "chara/accessory/a0054/material/v0001/mt_c0601a0054_ear_c.mtrl",
+            ]
+        );
+
+        // Wildwood isn't a Hyur subrace
+        assert!(build_equipment_path_variants(
+            1,
+            Race::Hyur,
+            Subrace::Wildwood,
+            Gender::Male,
+            Slot::Body,
+            1
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_resolve_model_path() {
+        let mut d = std::env::temp_dir();
+        d.push("physis-equipment-resolve");
+        if d.exists() {
+            std::fs::remove_dir_all(&d).unwrap();
+        }
+
+        let write = |path: &str, data: &[u8]| {
+            let path = d.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, data).unwrap();
+        };
+
+        write("chara/equipment/e0001/model/c0101e0001_top.mdl", b"");
+        write("chara/equipment/e0001/model/c0201e0001_top.mdl", b"");
+        write("chara/equipment/e0001/model/c0801e0001_top.mdl", b"");
+
+        // Miqo'te females have a model on disk, but their EQDP says they don't use it
+        let mut eqdp = vec![];
+        for value in [0u16, 4, 1, 0, 0, 0, 0, 0] {
+            eqdp.extend_from_slice(&value.to_le_bytes());
+        }
+        write(&build_eqdp_path(801, false), &eqdp);
+
+        let mut game_data = GameData::from_loose_files(d.to_str().unwrap()).unwrap();
+
+        let mut resolve = |race, subrace, gender| {
+            resolve_equipment_model_path(&mut game_data, 1, race, subrace, gender, Slot::Body)
+        };
+
+        // the unique model is used if there is one
+        assert_eq!(
+            resolve(Race::Hyur, Subrace::Midlander, Gender::Female).unwrap(),
+            "chara/equipment/e0001/model/c0201e0001_top.mdl"
+        );
+        // otherwise it falls back to the Hyur Midlander ones
+        assert_eq!(
+            resolve(Race::Hrothgar, Subrace::Hellion, Gender::Male).unwrap(),
+            "chara/equipment/e0001/model/c0101e0001_top.mdl"
+        );
+        assert_eq!(
+            resolve(Race::Miqote, Subrace::Seeker, Gender::Female).unwrap(),
+            "chara/equipment/e0001/model/c0201e0001_top.mdl"
+        );
+        assert!(resolve(Race::Hyur, Subrace::Wildwood, Gender::Male).is_none());
+
+        assert!(resolve_equipment_model_path(
+            &mut game_data,
+            2,
+            Race::Hyur,
+            Subrace::Midlander,
+            Gender::Male,
+            Slot::Body
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_material_path() {
+        assert_eq!(
+            build_material_path("/mt_c0101e0000_top_a.mtrl", 1, 0),
+            "chara/equipment/e0000/material/v0001/mt_c0101e0000_top_a.mtrl"
+        );
+        assert_eq!(
+            build_material_path(
+                "chara/equipment/e0005/material/v0002/mt_c0101e0005_top_a.mtrl",
+                1,
+                0
+            ),
+            "chara/equipment/e0005/material/v0002/mt_c0101e0005_top_a.mtrl"
+        );
+    }
+
+    #[test]
+    fn test_texture_paths() {
+        assert_eq!(
+            build_skin_texture_path(101, 1, "base"),
+            "chara/human/c0101/obj/body/b0001/texture/c0101b0001_base.tex"
+        );
+        assert_eq!(
+            build_catchlight_path(),
+            "chara/common/texture/sphere_d_array.tex"
+        );
+        assert_eq!(
+            build_decal_path(5),
+            "chara/common/texture/decal_face/_decal_5.tex"
+        );
+        assert_eq!(
+            build_equipment_decal_path(1),
+            "chara/common/texture/decal_equip/-decal_001.tex"
+        );
+    }
+
+    #[test]
+    fn test_slots() {
+        assert_eq!(get_slot_from_abbreviation("top"), Some(Slot::Body));
+        assert_eq!(get_slot_from_abbreviation("abc"), None);
+        assert_eq!(slot_from_abbreviation("top"), Some(Slot::Body));
+        assert_eq!(slot_from_abbreviation("abc"), None);
+
+        for (abbreviation, slot) in [
+            ("met", Slot::Head),
+            ("glv", Slot::Hands),
+            ("dwn", Slot::Legs),
+            ("sho", Slot::Feet),
+            ("top", Slot::Body),
+            ("ear", Slot::Earring),
+            ("nek", Slot::Neck),
+            ("ril", Slot::RingLeft),
+            ("rir", Slot::RingRight),
+            ("wrs", Slot::Wrists),
+        ] {
+            assert_eq!(slot_from_abbreviation(abbreviation), Some(slot));
+        }
+
+        assert_eq!(Slot::all().len(), 10);
+        for slot in Slot::all() {
+            assert_eq!(get_slot_from_id(slot.id()).as_ref(), Some(slot));
+            assert_eq!(
+                get_slot_from_abbreviation(get_slot_abbreviation(slot.clone())).as_ref(),
+                Some(slot)
+            );
+        }
+    }
+
     #[test]
     fn test_deconstruct() {
         assert_eq!(
@@ -0,0 +1,85 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Builders for the EXH and EXD files used by the Excel tests.
+
+use crate::common::Language;
+use crate::exh::ColumnDataType;
+
+/// Builds an EXH with `columns` given as (type, offset) pairs, `pages` given as (first row id, row count) pairs and
+/// `languages`. `data_offset` is the size of the fixed-size column data of each row.
+pub(crate) fn build_exh(
+    data_offset: u16,
+    columns: &[(ColumnDataType, u16)],
+    pages: &[(u32, u32)],
+    languages: &[Language],
+) -> Vec<u8> {
+    // rows are looked up by id, so this has to go past the last one
+    let row_count = pages
+        .iter()
+        .map(|(start, count)| start + count)
+        .max()
+        .unwrap_or(0);
+
+    let mut data = vec![];
+    data.extend_from_slice(b"EXHF");
+    for value in [
+        3, // version
+        data_offset,
+        columns.len() as u16,
+        pages.len() as u16,
+        languages.len() as u16,
+    ] {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+    data.extend_from_slice(&[0; 6]);
+    data.extend_from_slice(&row_count.to_be_bytes());
+    data.extend_from_slice(&[0; 8]);
+
+    for (data_type, offset) in columns {
+        data.extend_from_slice(&(data_type.clone() as u16).to_be_bytes());
+        data.extend_from_slice(&offset.to_be_bytes());
+    }
+
+    for (start, count) in pages {
+        data.extend_from_slice(&start.to_be_bytes());
+        data.extend_from_slice(&count.to_be_bytes());
+    }
+
+    for language in languages {
+        data.push(*language as u8);
+    }
+
+    data
+}
+
+/// Builds an EXD page with `rows` given as (row id, data) pairs, where the data is the fixed-size column data of the
+/// row followed by its strings.
+pub(crate) fn build_exd(rows: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let index_size = rows.len() as u32 * 8;
+    // each row has a 6 byte header
+    let data_size: u32 = rows.iter().map(|(_, row)| 6 + row.len() as u32).sum();
+
+    let mut data = vec![];
+    data.extend_from_slice(b"EXDF");
+    data.extend_from_slice(&2u16.to_be_bytes()); // version
+    data.extend_from_slice(&[0; 2]);
+    data.extend_from_slice(&index_size.to_be_bytes());
+    data.extend_from_slice(&data_size.to_be_bytes());
+    data.extend_from_slice(&[0; 16]);
+
+    let mut offset = 32 + index_size;
+    for (row_id, row) in rows {
+        data.extend_from_slice(&row_id.to_be_bytes());
+        data.extend_from_slice(&offset.to_be_bytes());
+        offset += 6 + row.len() as u32;
+    }
+
+    for (_, row) in rows {
+        data.extend_from_slice(&(row.len() as u32).to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes()); // row count
+        data.extend_from_slice(row);
+    }
+
+    data
+}
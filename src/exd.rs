@@ -1,14 +1,15 @@
 // SPDX-FileCopyrightText: 2023 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::io::{Cursor, Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 
 use binrw::binrw;
-use binrw::{BinRead, Endian};
+use binrw::{BinRead, BinWrite, Endian};
 
 use crate::common::Language;
 use crate::exh::{ColumnDataType, ExcelColumnDefinition, ExcelDataPagination, EXH};
-use crate::ByteSpan;
+use crate::sestring::SeString;
+use crate::{ByteBuffer, ByteSpan};
 
 #[binrw]
 #[brw(magic = b"EXDF")]
@@ -18,11 +19,15 @@ use crate::ByteSpan;
 struct EXDHeader {
     version: u16,
 
-    #[br(pad_before = 2)]
-    #[br(pad_after = 20)]
+    #[brw(pad_before = 2)]
     index_size: u32,
+    #[brw(pad_after = 16)]
+    data_size: u32,
 }
 
+/// The size of the EXD header, the row offsets come right after it.
+const EXD_HEADER_SIZE: u32 = 32;
+
 #[binrw]
 #[brw(big)]
 #[derive(Debug)]
@@ -53,9 +58,12 @@ pub struct EXD {
     pub rows: Vec<ExcelRow>,
 }
 
-#[derive(Debug, Clone)]
+/// A single decoded column value, typed according to the column's [ColumnDataType] in the EXH.
+/// The packed bool types are decoded into [ColumnData::Bool], using the bit for that column.
+#[derive(Debug, Clone, PartialEq)]
 pub enum ColumnData {
-    String(String),
+    /// Game strings can contain macros, use [SeString::as_text] for the plain text.
+    String(SeString),
     Bool(bool),
     Int8(i8),
     UInt8(u8),
@@ -68,73 +76,161 @@ pub enum ColumnData {
     UInt64(u64),
 }
 
-#[derive(Debug)]
+/// Formats the value for display, such as in a table. Bools are written as "True" or "False", floats with as many
+/// digits as needed to read them back and strings as plain text.
+impl std::fmt::Display for ColumnData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnData::String(value) => write!(f, "{value}"),
+            ColumnData::Bool(true) => write!(f, "True"),
+            ColumnData::Bool(false) => write!(f, "False"),
+            ColumnData::Int8(value) => write!(f, "{value}"),
+            ColumnData::UInt8(value) => write!(f, "{value}"),
+            ColumnData::Int16(value) => write!(f, "{value}"),
+            ColumnData::UInt16(value) => write!(f, "{value}"),
+            ColumnData::Int32(value) => write!(f, "{value}"),
+            ColumnData::UInt32(value) => write!(f, "{value}"),
+            ColumnData::Float32(value) => write!(f, "{value}"),
+            ColumnData::Int64(value) => write!(f, "{value}"),
+            ColumnData::UInt64(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub struct ExcelRow {
+    /// The ID of the row. Subrows share the ID of their parent row.
+    pub row_id: u32,
+    /// The index of the subrow in its parent row, always 0 for sheets without subrows.
+    pub subrow_id: u16,
     pub data: Vec<ColumnData>,
 }
 
 impl EXD {
     pub fn from_existing(exh: &EXH, buffer: ByteSpan) -> Option<EXD> {
-        let mut cursor = Cursor::new(buffer);
-        let mut exd = EXD::read(&mut cursor).ok()?;
+        EXD::from_reader(exh, &mut Cursor::new(buffer))
+    }
+
+    /// Same as [EXD::from_existing], but reads from `reader`, such as a file handle, instead of a buffer holding
+    /// all of it. The page has to start at the beginning of `reader`.
+    pub fn from_reader<R: Read + Seek>(exh: &EXH, reader: &mut R) -> Option<EXD> {
+        let mut exd = EXD::read(reader).ok()?;
 
         for i in 0..exh.header.row_count {
             for offset in &exd.data_offsets {
                 if offset.row_id == i {
-                    cursor.seek(SeekFrom::Start(offset.offset.into())).ok()?;
+                    let mut rows = Self::read_row_entry(reader, exh, offset)?;
+                    exd.rows.append(&mut rows);
+                }
+            }
+        }
 
-                    let row_header = ExcelDataRowHeader::read(&mut cursor).ok()?;
+        Some(exd)
+    }
 
-                    let header_offset = offset.offset + 6; // std::mem::size_of::<ExcelDataRowHeader>() as u32;
+    /// Same as [EXD::from_existing], but only decodes rows with an id between `start_row` (inclusive) and
+    /// `end_row` (exclusive). The rest of the page is skipped, use [EXH::pages] to find which pages
+    /// contain the range.
+    pub fn from_existing_range(
+        exh: &EXH,
+        buffer: ByteSpan,
+        start_row: u32,
+        end_row: u32,
+    ) -> Option<EXD> {
+        let mut cursor = Cursor::new(buffer);
+        let mut exd = EXD::read(&mut cursor).ok()?;
 
-                    let mut read_row = |row_offset: u32| -> Option<ExcelRow> {
-                        let mut subrow = ExcelRow {
-                            data: Vec::with_capacity(exh.column_definitions.len()),
-                        };
+        exd.data_offsets
+            .retain(|offset| (start_row..end_row).contains(&offset.row_id));
+        exd.data_offsets.sort_by_key(|offset| offset.row_id);
 
-                        for column in &exh.column_definitions {
-                            cursor
-                                .seek(SeekFrom::Start((row_offset + column.offset as u32).into()))
-                                .ok()?;
+        for offset in &exd.data_offsets {
+            let mut rows = Self::read_row_entry(&mut cursor, exh, offset)?;
+            exd.rows.append(&mut rows);
+        }
 
-                            subrow.data.push(
-                                Self::read_column(&mut cursor, exh, row_offset, column).unwrap(),
-                            );
-                        }
+        Some(exd)
+    }
 
-                        Some(subrow)
-                    };
+    /// Returns every row alongside its ID. Subrows are returned in order, and share the ID of their parent row.
+    pub fn rows_with_ids(&self) -> impl Iterator<Item = (u32, &ExcelRow)> {
+        self.rows.iter().map(|row| (row.row_id, row))
+    }
 
-                    if row_header.row_count > 1 {
-                        for i in 0..row_header.row_count {
-                            let subrow_offset =
-                                header_offset + (i * exh.header.data_offset + 2 * (i + 1)) as u32;
+    /// Returns the column at index `column` of row `row_id` as a display string, see the [std::fmt::Display]
+    /// implementation of [ColumnData]. For sheets with subrows, this is the column of the first subrow.
+    ///
+    /// Returns None if the row isn't in this page, or `exh` doesn't have that column.
+    pub fn get_cell_string(&self, exh: &EXH, row_id: u32, column: usize) -> Option<String> {
+        if column >= exh.column_definitions.len() {
+            return None;
+        }
 
-                            exd.rows.push(read_row(subrow_offset).unwrap());
-                        }
-                    } else {
-                        exd.rows.push(read_row(header_offset).unwrap());
-                    }
-                }
+        let row = self.rows.iter().find(|row| row.row_id == row_id)?;
+
+        Some(row.data.get(column)?.to_string())
+    }
+
+    /// Reads the row (or all of the subrows) that `offset` points to.
+    fn read_row_entry<R: Read + Seek>(
+        cursor: &mut R,
+        exh: &EXH,
+        offset: &ExcelDataOffset,
+    ) -> Option<Vec<ExcelRow>> {
+        cursor.seek(SeekFrom::Start(offset.offset.into())).ok()?;
+
+        let row_header = ExcelDataRowHeader::read(cursor).ok()?;
+
+        let header_offset = offset.offset + 6; // std::mem::size_of::<ExcelDataRowHeader>() as u32;
+
+        let mut read_row = |row_offset: u32, subrow_id: u16| -> Option<ExcelRow> {
+            let mut subrow = ExcelRow {
+                row_id: offset.row_id,
+                subrow_id,
+                data: Vec::with_capacity(exh.column_definitions.len()),
+            };
+
+            for column in &exh.column_definitions {
+                cursor
+                    .seek(SeekFrom::Start((row_offset + column.offset as u32).into()))
+                    .ok()?;
+
+                subrow
+                    .data
+                    .push(Self::read_column(cursor, exh, row_offset, column)?);
             }
+
+            Some(subrow)
+        };
+
+        let mut rows = vec![];
+        if row_header.row_count > 1 {
+            for i in 0..row_header.row_count {
+                let subrow_offset =
+                    header_offset + (i * exh.header.data_offset + 2 * (i + 1)) as u32;
+
+                rows.push(read_row(subrow_offset, i)?);
+            }
+        } else {
+            rows.push(read_row(header_offset, 0)?);
         }
 
-        Some(exd)
+        Some(rows)
     }
 
-    fn read_data_raw<Z: BinRead<Args<'static> = ()>>(cursor: &mut Cursor<ByteSpan>) -> Option<Z> {
+    fn read_data_raw<R: Read + Seek, Z: BinRead<Args<'static> = ()>>(cursor: &mut R) -> Option<Z> {
         Z::read_options(cursor, Endian::Big, ()).ok()
     }
 
-    fn read_column(
-        cursor: &mut Cursor<ByteSpan>,
+    fn read_column<R: Read + Seek>(
+        cursor: &mut R,
         exh: &EXH,
         row_offset: u32,
         column: &ExcelColumnDefinition,
     ) -> Option<ColumnData> {
         let mut read_packed_bool = |shift: i32| -> bool {
             let bit = 1 << shift;
-            let bool_data: i32 = Self::read_data_raw(cursor).unwrap_or(0);
+            let bool_data: u8 = Self::read_data_raw(cursor).unwrap_or(0);
 
             (bool_data & bit) == bit
         };
@@ -149,21 +245,20 @@ impl EXD {
                     ))
                     .ok()?;
 
-                let mut string = String::new();
+                let mut bytes = vec![];
 
                 let mut byte: u8 = Self::read_data_raw(cursor).unwrap();
                 while byte != 0 {
-                    string.push(byte as char);
+                    bytes.push(byte);
                     byte = Self::read_data_raw(cursor).unwrap();
                 }
 
-                Some(ColumnData::String(string))
+                Some(ColumnData::String(SeString::from_bytes(&bytes)))
             }
             ColumnDataType::Bool => {
-                // FIXME: i believe Bool is int8?
-                let bool_data: i32 = Self::read_data_raw(cursor).unwrap();
+                let bool_data: u8 = Self::read_data_raw(cursor).unwrap();
 
-                Some(ColumnData::Bool(bool_data == 1))
+                Some(ColumnData::Bool(bool_data != 0))
             }
             ColumnDataType::Int8 => Some(ColumnData::Int8(Self::read_data_raw(cursor).unwrap())),
             ColumnDataType::UInt8 => Some(ColumnData::UInt8(Self::read_data_raw(cursor).unwrap())),
@@ -193,6 +288,129 @@ impl EXD {
         }
     }
 
+    /// Writes the rows of this page into a new EXD file, with each column laid out according to `exh`.
+    /// Each row is written with its [ExcelRow::row_id], sorted by ID, so rows can be freely added or removed.
+    /// Sheets with subrows or rows sharing an ID are not supported, and will return None.
+    pub fn write_to_buffer(&self, exh: &EXH) -> Option<ByteBuffer> {
+        let mut rows: Vec<&ExcelRow> = self.rows.iter().collect();
+        rows.sort_by_key(|row| row.row_id);
+
+        if rows.iter().any(|row| row.subrow_id != 0)
+            || rows.windows(2).any(|pair| pair[0].row_id == pair[1].row_id)
+        {
+            return None;
+        }
+
+        let index_size = (rows.len() * core::mem::size_of::<ExcelDataOffset>()) as u32;
+
+        let mut data_offsets = Vec::with_capacity(rows.len());
+        let mut row_cursor = Cursor::new(ByteBuffer::new());
+        for row in rows {
+            data_offsets.push(ExcelDataOffset {
+                row_id: row.row_id,
+                offset: EXD_HEADER_SIZE + index_size + row_cursor.position() as u32,
+            });
+
+            let data = Self::write_row(exh, row)?;
+
+            ExcelDataRowHeader {
+                data_size: data.len() as u32,
+                row_count: 1,
+            }
+            .write(&mut row_cursor)
+            .ok()?;
+            row_cursor.get_mut().extend_from_slice(&data);
+            row_cursor.seek(SeekFrom::End(0)).ok()?;
+        }
+
+        let row_data = row_cursor.into_inner();
+
+        let mut buffer = ByteBuffer::new();
+        {
+            let mut cursor = Cursor::new(&mut buffer);
+
+            EXDHeader {
+                version: self.header.version,
+                index_size,
+                data_size: row_data.len() as u32,
+            }
+            .write(&mut cursor)
+            .ok()?;
+            data_offsets.write(&mut cursor).ok()?;
+        }
+        buffer.extend_from_slice(&row_data);
+
+        Some(buffer)
+    }
+
+    /// Writes the fixed-size column data of a row, followed by its strings.
+    fn write_row(exh: &EXH, row: &ExcelRow) -> Option<ByteBuffer> {
+        let mut data = vec![0u8; exh.header.data_offset as usize];
+        let mut strings = ByteBuffer::new();
+
+        if row.data.len() != exh.column_definitions.len() {
+            return None;
+        }
+
+        for (column, value) in exh.column_definitions.iter().zip(&row.data) {
+            let offset = column.offset as usize;
+
+            let bytes = match (&column.data_type, value) {
+                (ColumnDataType::String, ColumnData::String(value)) => {
+                    // strings are offset from the end of the column data
+                    let string_offset = strings.len() as u32;
+                    strings.extend_from_slice(value.as_bytes());
+                    strings.push(0);
+
+                    string_offset.to_be_bytes().to_vec()
+                }
+                (ColumnDataType::Bool, ColumnData::Bool(value)) => vec![*value as u8],
+                (ColumnDataType::Int8, ColumnData::Int8(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::UInt8, ColumnData::UInt8(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::Int16, ColumnData::Int16(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::UInt16, ColumnData::UInt16(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::Int32, ColumnData::Int32(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::UInt32, ColumnData::UInt32(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::Float32, ColumnData::Float32(value)) => {
+                    value.to_be_bytes().to_vec()
+                }
+                (ColumnDataType::Int64, ColumnData::Int64(value)) => value.to_be_bytes().to_vec(),
+                (ColumnDataType::UInt64, ColumnData::UInt64(value)) => value.to_be_bytes().to_vec(),
+                (data_type, ColumnData::Bool(value)) => {
+                    // several packed bools share the same byte
+                    let shift = Self::packed_bool_shift(data_type)?;
+                    *data.get_mut(offset)? |= (*value as u8) << shift;
+                    continue;
+                }
+                _ => return None,
+            };
+
+            data.get_mut(offset..offset + bytes.len())?
+                .copy_from_slice(&bytes);
+        }
+
+        data.extend_from_slice(&strings);
+
+        // rows are padded to 4 bytes
+        data.resize(data.len().next_multiple_of(4), 0);
+
+        Some(data)
+    }
+
+    fn packed_bool_shift(data_type: &ColumnDataType) -> Option<u8> {
+        match data_type {
+            ColumnDataType::PackedBool0 => Some(0),
+            ColumnDataType::PackedBool1 => Some(1),
+            ColumnDataType::PackedBool2 => Some(2),
+            ColumnDataType::PackedBool3 => Some(3),
+            ColumnDataType::PackedBool4 => Some(4),
+            ColumnDataType::PackedBool5 => Some(5),
+            ColumnDataType::PackedBool6 => Some(6),
+            ColumnDataType::PackedBool7 => Some(7),
+            _ => None,
+        }
+    }
+
     pub fn calculate_filename(
         name: &str,
         language: Language,
@@ -213,12 +431,195 @@ impl EXD {
 
 #[cfg(test)]
 mod tests {
+    use crate::excel_fixtures::{build_exd, build_exh};
     use crate::exh::EXHHeader;
     use std::fs::read;
     use std::path::PathBuf;
 
     use super::*;
 
+    #[test]
+    fn test_write() {
+        let columns = [
+            (ColumnDataType::String, 0),
+            (ColumnDataType::Bool, 4),
+            (ColumnDataType::Int8, 5),
+            (ColumnDataType::UInt8, 6),
+            (ColumnDataType::PackedBool0, 7),
+            (ColumnDataType::PackedBool3, 7),
+            (ColumnDataType::Int16, 8),
+            (ColumnDataType::UInt16, 10),
+            (ColumnDataType::Int32, 12),
+            (ColumnDataType::UInt32, 16),
+            (ColumnDataType::Float32, 20),
+            (ColumnDataType::Int64, 24),
+            (ColumnDataType::UInt64, 32),
+            (ColumnDataType::String, 40),
+        ];
+
+        let exh =
+            EXH::from_existing(&build_exh(44, &columns, &[(0, 3)], &[Language::None])).unwrap();
+
+        let row = |i: u8| ExcelRow {
+            row_id: i as u32,
+            subrow_id: 0,
+            data: vec![
+                ColumnData::String(format!("Row {i}").into()),
+                ColumnData::Bool(i.is_multiple_of(2)),
+                ColumnData::Int8(-(i as i8)),
+                ColumnData::UInt8(i),
+                ColumnData::Bool(i == 1),
+                ColumnData::Bool(i != 1),
+                ColumnData::Int16(-300),
+                ColumnData::UInt16(300 + i as u16),
+                ColumnData::Int32(-70000),
+                ColumnData::UInt32(70000 + i as u32),
+                ColumnData::Float32(1.5 * i as f32),
+                ColumnData::Int64(-5_000_000_000),
+                ColumnData::UInt64(5_000_000_000 + i as u64),
+                ColumnData::String("x".repeat(i as usize).into()),
+            ],
+        };
+
+        let exd = EXD {
+            header: EXDHeader {
+                version: 2,
+                index_size: 0,
+                data_size: 0,
+            },
+            data_offsets: vec![],
+            rows: (0..3).map(row).collect(),
+        };
+
+        let buffer = exd.write_to_buffer(&exh).unwrap();
+        let new_exd = EXD::from_existing(&exh, &buffer).unwrap();
+
+        assert_eq!(new_exd.rows, exd.rows);
+        assert_eq!(
+            new_exd
+                .rows_with_ids()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        // writing it again should produce the same file
+        assert_eq!(new_exd.write_to_buffer(&exh).unwrap(), buffer);
+
+        // rows can be removed, and added back in any order as they are written sorted by their ID
+        let mut edited = EXD::from_existing(&exh, &buffer).unwrap();
+        edited.rows.remove(1);
+        let mut edited = EXD::from_existing(&exh, &edited.write_to_buffer(&exh).unwrap()).unwrap();
+        assert_eq!(
+            edited.rows_with_ids().map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+
+        edited.rows.insert(0, row(1));
+        assert_eq!(edited.write_to_buffer(&exh).unwrap(), buffer);
+
+        // subrows can't be written
+        edited.rows[0].subrow_id = 1;
+        assert!(edited.write_to_buffer(&exh).is_none());
+
+        // values have to match their column's type
+        let mut exd = new_exd;
+        exd.rows[0].data[2] = ColumnData::UInt64(0);
+        assert!(exd.write_to_buffer(&exh).is_none());
+    }
+
+    #[test]
+    fn test_from_existing_range() {
+        let exh = EXH::from_existing(&build_exh(
+            4,
+            &[(ColumnDataType::UInt32, 0)],
+            &[(0, 3)],
+            &[Language::None],
+        ))
+        .unwrap();
+        let rows: Vec<(u32, Vec<u8>)> = (0..3u32)
+            .map(|row_id| (row_id, (row_id * 10).to_be_bytes().to_vec()))
+            .collect();
+        let buffer = build_exd(&rows);
+
+        // only the requested rows should be decoded
+        let range = EXD::from_existing_range(&exh, &buffer, 1, 3).unwrap();
+        assert_eq!(range.data_offsets.len(), 2);
+        assert_eq!(
+            range
+                .rows_with_ids()
+                .map(|(id, row)| (id, row.data.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, vec![ColumnData::UInt32(10)]),
+                (2, vec![ColumnData::UInt32(20)])
+            ]
+        );
+
+        let range = EXD::from_existing_range(&exh, &buffer, 5, 10).unwrap();
+        assert!(range.rows.is_empty());
+    }
+
+    #[test]
+    fn test_decode_mixed() {
+        let columns = [
+            (ColumnDataType::UInt32, 0),
+            (ColumnDataType::PackedBool0, 4),
+            (ColumnDataType::PackedBool1, 4),
+            (ColumnDataType::PackedBool7, 4),
+            (ColumnDataType::Bool, 5),
+            (ColumnDataType::Int16, 6),
+            (ColumnDataType::Float32, 8),
+            (ColumnDataType::String, 12),
+        ];
+
+        let exh =
+            EXH::from_existing(&build_exh(16, &columns, &[(0, 1)], &[Language::None])).unwrap();
+
+        let mut row = vec![];
+        row.extend_from_slice(&1234u32.to_be_bytes());
+        row.push(0b1000_0001); // bits 0 and 7 are set, but not 1
+        row.push(1);
+        row.extend_from_slice(&(-2i16).to_be_bytes());
+        row.extend_from_slice(&0.5f32.to_be_bytes());
+        row.extend_from_slice(&0u32.to_be_bytes());
+        row.extend_from_slice(b"Test\0");
+
+        let buffer = build_exd(&[(0, row)]);
+
+        let exd = EXD::from_existing(&exh, &buffer).unwrap();
+        assert_eq!(
+            exd.rows,
+            vec![ExcelRow {
+                row_id: 0,
+                subrow_id: 0,
+                data: vec![
+                    ColumnData::UInt32(1234),
+                    ColumnData::Bool(true),
+                    ColumnData::Bool(false),
+                    ColumnData::Bool(true),
+                    ColumnData::Bool(true),
+                    ColumnData::Int16(-2),
+                    ColumnData::Float32(0.5),
+                    ColumnData::String("Test".into()),
+                ]
+            }]
+        );
+
+        assert_eq!(exd.get_cell_string(&exh, 0, 0).unwrap(), "1234");
+        assert_eq!(exd.get_cell_string(&exh, 0, 2).unwrap(), "False");
+        assert_eq!(exd.get_cell_string(&exh, 0, 4).unwrap(), "True");
+        assert_eq!(exd.get_cell_string(&exh, 0, 5).unwrap(), "-2");
+        assert_eq!(exd.get_cell_string(&exh, 0, 6).unwrap(), "0.5");
+        assert_eq!(exd.get_cell_string(&exh, 0, 7).unwrap(), "Test");
+        assert!(exd.get_cell_string(&exh, 0, 8).is_none());
+        assert!(exd.get_cell_string(&exh, 1, 0).is_none());
+
+        // reading from an owned buffer instead of a slice gives the same rows
+        let from_reader = EXD::from_reader(&exh, &mut Cursor::new(buffer)).unwrap();
+        assert_eq!(from_reader.rows, exd.rows);
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -93,6 +93,17 @@ impl EXH {
     pub fn from_existing(buffer: ByteSpan) -> Option<EXH> {
         EXH::read(&mut Cursor::new(&buffer)).ok()
     }
+
+    /// Returns the languages the sheet is available in, which are the ones accepted by
+    /// [GameData::read_excel_sheet](crate::gamedata::GameData::read_excel_sheet). Sheets that aren't localized only
+    /// have [Language::None].
+    pub fn languages(&self) -> &[Language] {
+        if self.languages.is_empty() {
+            return &[Language::None];
+        }
+
+        &self.languages
+    }
 }
 
 #[cfg(test)]
@@ -100,8 +111,34 @@ mod tests {
     use std::fs::read;
     use std::path::PathBuf;
 
+    use crate::excel_fixtures::build_exh;
+
     use super::*;
 
+    /// Builds a sheet with a single string column and a single page, available in `languages`.
+    fn build_localized_exh(languages: &[Language]) -> Vec<u8> {
+        build_exh(4, &[(ColumnDataType::String, 0)], &[(0, 1)], languages)
+    }
+
+    #[test]
+    fn test_languages() {
+        let localized = [
+            Language::Japanese,
+            Language::English,
+            Language::German,
+            Language::French,
+        ];
+        let exh = EXH::from_existing(&build_localized_exh(&localized)).unwrap();
+        assert_eq!(exh.languages(), localized);
+
+        let exh = EXH::from_existing(&build_localized_exh(&[Language::None])).unwrap();
+        assert_eq!(exh.languages(), [Language::None]);
+
+        // a sheet without any languages listed still has the language-agnostic one
+        let exh = EXH::from_existing(&build_localized_exh(&[])).unwrap();
+        assert_eq!(exh.languages(), [Language::None]);
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
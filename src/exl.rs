@@ -40,6 +40,8 @@ impl EXL {
         Some(exl)
     }
 
+    /// Writes the list into a new buffer, starting with the "EXLT,<version>" header and followed by one "name,id"
+    /// line for each entry.
     pub fn write_to_buffer(&self) -> Option<ByteBuffer> {
         let mut buffer = ByteBuffer::new();
 
@@ -129,6 +131,29 @@ mod tests {
         assert_eq!(existing_exl.write_to_buffer().unwrap(), exl);
     }
 
+    #[test]
+    fn test_write_new() {
+        let exl = EXL {
+            version: 2,
+            entries: vec![
+                ("Achievement".to_string(), 209),
+                ("Action".to_string(), 4),
+                ("quest/000/ClsHrv001_00003".to_string(), -1),
+            ],
+        };
+
+        let buffer = exl.write_to_buffer().unwrap();
+        assert_eq!(
+            buffer,
+            b"EXLT,2\nAchievement,209\nAction,4\nquest/000/ClsHrv001_00003,-1"
+        );
+
+        let new_exl = EXL::from_existing(&buffer).unwrap();
+        assert_eq!(new_exl.version, exl.version);
+        assert_eq!(new_exl.entries, exl.entries);
+        assert_eq!(new_exl.write_to_buffer().unwrap(), buffer);
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2023 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::fs::read;
+use std::fs::{read, read_dir};
 use std::io::Cursor;
 use std::path::Path;
 
@@ -52,6 +52,21 @@ pub struct FIINEntry {
     pub sha1: Vec<u8>,
 }
 
+/// A file that doesn't match its entry, see [FileInfo::verify].
+#[derive(Debug, PartialEq, Eq)]
+pub enum FiinMismatch {
+    /// The file could not be read.
+    Missing { file_name: String },
+    /// The file's size is different than recorded.
+    SizeMismatch {
+        file_name: String,
+        expected: i32,
+        actual: i32,
+    },
+    /// The file's size matches, but its SHA1 is different than recorded.
+    HashMismatch { file_name: String },
+}
+
 impl FileInfo {
     /// Parses an existing FIIN file.
     pub fn from_existing(buffer: ByteSpan) -> Option<FileInfo> {
@@ -93,6 +108,54 @@ impl FileInfo {
 
         Some(FileInfo { entries })
     }
+
+    /// Creates a new FileInfo structure from every file directly inside of `base_dir`, sorted by
+    /// their filenames. Subdirectories are ignored.
+    pub fn from_directory(base_dir: &str) -> Option<FileInfo> {
+        let mut paths: Vec<String> = read_dir(base_dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|x| x.is_file()))
+            .filter_map(|entry| entry.path().to_str().map(str::to_string))
+            .collect();
+        paths.sort();
+
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+        Self::new(&paths)
+    }
+
+    /// Checks each entry against the files in `base_dir`, returning every file that is missing or
+    /// doesn't match. If the list is empty, all of the files are intact.
+    pub fn verify(&self, base_dir: &str) -> Vec<FiinMismatch> {
+        let mut mismatches = vec![];
+
+        for entry in &self.entries {
+            let file_name = entry.file_name.clone();
+
+            let Ok(file) = read(Path::new(base_dir).join(&entry.file_name)) else {
+                mismatches.push(FiinMismatch::Missing { file_name });
+                continue;
+            };
+
+            if file.len() as i32 != entry.file_size {
+                mismatches.push(FiinMismatch::SizeMismatch {
+                    file_name,
+                    expected: entry.file_size,
+                    actual: file.len() as i32,
+                });
+                continue;
+            }
+
+            // the hash is padded out to 24 bytes
+            let hash = Sha1::from(&file).digest().bytes();
+            if entry.sha1.get(..hash.len()) != Some(hash.as_slice()) {
+                mismatches.push(FiinMismatch::HashMismatch { file_name });
+            }
+        }
+
+        mismatches
+    }
 }
 
 #[cfg(test)]
@@ -100,7 +163,7 @@ mod tests {
     use std::fs::read;
     use std::path::PathBuf;
 
-    use crate::fiin::FileInfo;
+    use crate::fiin::{FIINEntry, FiinMismatch, FileInfo};
 
     fn common_setup() -> FileInfo {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -140,6 +203,68 @@ mod tests {
         assert_eq!(*valid_fiin, testing_fiin.write_to_buffer().unwrap());
     }
 
+    #[test]
+    fn from_directory() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+
+        let mut dir = std::env::temp_dir();
+        dir.push("physis-fiin-tests");
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::create_dir_all(dir.join("subdirectory")).unwrap();
+
+        std::fs::copy(d.join("test.txt"), dir.join("test.txt")).unwrap();
+        std::fs::copy(d.join("test.exl"), dir.join("test.exl")).unwrap();
+
+        let fiin = FileInfo::from_directory(dir.to_str().unwrap()).unwrap();
+
+        assert_eq!(fiin.entries.len(), 2);
+        assert_eq!(fiin.entries[0].file_name, "test.exl");
+        assert_eq!(fiin.entries[1].file_name, "test.txt");
+        assert!(fiin.verify(dir.to_str().unwrap()).is_empty());
+
+        // the entries should be identical to the ones in the retail-compatible test file
+        let valid_fiin = common_setup();
+        assert_eq!(fiin.entries[0].sha1, valid_fiin.entries[1].sha1[..20]);
+        assert_eq!(fiin.entries[1].sha1, valid_fiin.entries[0].sha1[..20]);
+    }
+
+    #[test]
+    fn verify() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+
+        let mut fiin = common_setup();
+        assert!(fiin.verify(d.to_str().unwrap()).is_empty());
+
+        fiin.entries[0].sha1[0] ^= 0xFF;
+        fiin.entries[1].file_size += 1;
+        fiin.entries.push(FIINEntry {
+            file_size: 0,
+            file_name: "missing.txt".to_string(),
+            sha1: vec![0; 24],
+        });
+
+        assert_eq!(
+            fiin.verify(d.to_str().unwrap()),
+            vec![
+                FiinMismatch::HashMismatch {
+                    file_name: "test.txt".to_string()
+                },
+                FiinMismatch::SizeMismatch {
+                    file_name: "test.exl".to_string(),
+                    expected: fiin.entries[1].file_size,
+                    actual: fiin.entries[1].file_size - 1,
+                },
+                FiinMismatch::Missing {
+                    file_name: "missing.txt".to_string()
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -4,18 +4,27 @@
 use std::collections::HashMap;
 use std::fs;
 use std::fs::{DirEntry, ReadDir};
-use std::path::PathBuf;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
 use tracing::{debug, warn};
 
 use crate::common::{read_version, Language, Platform};
-use crate::dat::DatFile;
+use crate::dat::{update_data_header, DatFile};
 use crate::exd::EXD;
 use crate::exh::EXH;
 use crate::exl::EXL;
-use crate::index::{Index2File, IndexEntry, IndexFile};
-use crate::patch::{PatchError, ZiPatch};
-use crate::repository::{string_to_category, Category, Repository};
+use crate::index::{patch_index_entry, Index2File, IndexEntry, IndexFile};
+#[cfg(feature = "visual_data")]
+use crate::model::MDL;
+#[cfg(feature = "visual_data")]
+use crate::mtrl::Material;
+use crate::patch::{PatchApplyOptions, PatchError, PatchOperation, ZiPatch};
+use crate::repository::{string_to_category, Category, Repository, RepositoryType};
+use crate::sha1::Sha1;
+use crate::sqpack::SqPackWriter;
+#[cfg(feature = "visual_data")]
+use crate::tex::Texture;
 use crate::ByteBuffer;
 
 /// Framework for operating on game data.
@@ -28,8 +37,19 @@ pub struct GameData {
 
     index_files: HashMap<String, IndexFile>,
     index2_files: HashMap<String, Index2File>,
+
+    /// Known paths, keyed by their index hash. See [GameData::load_path_list].
+    known_paths: HashMap<u64, String>,
+    /// Known paths, keyed by their index2 hash.
+    known_paths2: HashMap<u32, String>,
+
+    /// If set, files are read from this directory instead of the dat files. See [GameData::from_loose_files].
+    loose_directory: Option<PathBuf>,
 }
 
+/// The filenames of the index and index2 files that can contain a path, alongside their chunk.
+type IndexFilenames = (Vec<(String, u8)>, Vec<(String, u8)>);
+
 fn is_valid(path: &str) -> bool {
     let d = PathBuf::from(path);
 
@@ -41,6 +61,56 @@ fn is_valid(path: &str) -> bool {
     true
 }
 
+/// The languages tried by [GameData::read_excel_sheet_with_fallback] when a sheet isn't available in
+/// the requested language: English, then Japanese, then sheets that aren't localized at all.
+pub const LANGUAGE_FALLBACK: [Language; 3] =
+    [Language::English, Language::Japanese, Language::None];
+
+/// Where a game path is stored, see [GameData::locate].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLocation {
+    /// The name of the repository, such as "ffxiv" or "ex1".
+    pub repository: String,
+    /// The category, which is the first directory of the path.
+    pub category: Category,
+    /// The filename of the first index file for the category, like _"020100.win32.index"_. Large categories are split into
+    /// several chunks, which use the following index files.
+    pub index_filename: String,
+}
+
+/// A read-only view of a [GameData], which can be shared between threads. See [GameData::view].
+#[derive(Clone, Copy)]
+pub struct GameDataView<'a> {
+    game_data: &'a GameData,
+}
+
+impl GameDataView<'_> {
+    /// Same as [GameData::exists], but only the index files that are already loaded are searched.
+    pub fn exists(&self, path: &str) -> bool {
+        if self.game_data.loose_directory.is_some() {
+            return self
+                .game_data
+                .loose_file_path(path)
+                .is_some_and(|file_path| file_path.is_file());
+        }
+
+        self.game_data.find_cached_entry(path).is_some()
+    }
+
+    /// Same as [GameData::extract], but only the index files that are already loaded are searched. Each call opens
+    /// its own handle to the dat file, so several threads can extract at once.
+    pub fn extract(&self, path: &str) -> Option<ByteBuffer> {
+        debug!(file = path, "Extracting file");
+
+        if self.game_data.loose_directory.is_some() {
+            return fs::read(self.game_data.loose_file_path(path)?).ok();
+        }
+
+        let (entry, chunk) = self.game_data.find_cached_entry(path)?;
+        self.game_data.read_entry(path, entry, chunk)
+    }
+}
+
 /// Possible actions to repair game files
 #[derive(Debug)]
 pub enum RepairAction {
@@ -50,6 +120,42 @@ pub enum RepairAction {
     VersionFileCanRestore,
 }
 
+/// Errors that can occur while modifying game files, see [GameData::replace_file].
+#[derive(Debug)]
+pub enum GameDataError {
+    /// The path isn't in any of the index files, or is invalid.
+    FileNotFound,
+    /// The new data couldn't be written as a dat file entry.
+    InvalidData,
+    /// Writing to the dat or index file failed.
+    WriteFailed(std::io::Error),
+}
+
+impl std::fmt::Display for GameDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameDataError::FileNotFound => write!(f, "file not found"),
+            GameDataError::InvalidData => write!(f, "invalid data"),
+            GameDataError::WriteFailed(error) => write!(f, "failed to write game files: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GameDataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameDataError::WriteFailed(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GameDataError {
+    fn from(error: std::io::Error) -> Self {
+        GameDataError::WriteFailed(error)
+    }
+}
+
 #[derive(Debug)]
 /// Possible errors emitted through the repair process
 pub enum RepairError<'a> {
@@ -80,6 +186,9 @@ impl GameData {
                     repositories: vec![],
                     index_files: HashMap::new(),
                     index2_files: HashMap::new(),
+                    known_paths: HashMap::new(),
+                    known_paths2: HashMap::new(),
+                    loose_directory: None,
                 };
                 data.reload_repositories(platform);
                 Some(data)
@@ -91,6 +200,45 @@ impl GameData {
         }
     }
 
+    /// Read game data from a directory of loose files, laid out by their game path. For example,
+    /// "exd/root.exl" is read from `root/exd/root.exl`. No index or dat files are used, which is
+    /// useful for tests and previewing mods.
+    ///
+    /// This will return _None_ if the directory does not exist.
+    pub fn from_loose_files(root: &str) -> Option<GameData> {
+        debug!(root, "Loading loose file directory");
+
+        if !is_valid(root) {
+            return None;
+        }
+
+        Some(Self {
+            game_directory: String::from(root),
+            repositories: vec![],
+            index_files: HashMap::new(),
+            index2_files: HashMap::new(),
+            known_paths: HashMap::new(),
+            known_paths2: HashMap::new(),
+            loose_directory: Some(PathBuf::from(root)),
+        })
+    }
+
+    /// Returns where `path` would be on disk, if reading from loose files. Paths that try to escape the
+    /// directory are rejected.
+    fn loose_file_path(&self, path: &str) -> Option<PathBuf> {
+        let directory = self.loose_directory.as_ref()?;
+
+        let path = Path::new(path);
+        if !path
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+        {
+            return None;
+        }
+
+        Some(directory.join(path))
+    }
+
     fn reload_repositories(&mut self, platform: Platform) {
         self.repositories.clear();
 
@@ -127,21 +275,47 @@ impl GameData {
         self.repositories.sort();
     }
 
-    fn get_dat_file(&self, path: &str, chunk: u8, data_file_id: u32) -> Option<DatFile> {
-        let (repository, category) = self.parse_repository_category(path).unwrap();
+    fn get_dat_path(&self, path: &str, chunk: u8, data_file_id: u32) -> Option<PathBuf> {
+        let (repository, category) = self.parse_repository_category(path)?;
+
+        Some(
+            [
+                self.game_directory.clone(),
+                "sqpack".to_string(),
+                repository.name.clone(),
+                repository.dat_filename(chunk, category, data_file_id),
+            ]
+            .iter()
+            .collect(),
+        )
+    }
 
-        let dat_path: PathBuf = [
-            self.game_directory.clone(),
-            "sqpack".to_string(),
-            repository.name.clone(),
-            repository.dat_filename(chunk, category, data_file_id),
-        ]
-        .iter()
-        .collect();
+    fn get_dat_file(&self, path: &str, chunk: u8, data_file_id: u32) -> Option<DatFile> {
+        let dat_path = self.get_dat_path(path, chunk, data_file_id)?;
 
         DatFile::from_existing(dat_path.to_str()?)
     }
 
+    /// Returns the version of the base game, which is read from the "ffxivgame.ver" file in the game
+    /// directory. Returns None if it's missing.
+    pub fn game_version(&self) -> Option<String> {
+        self.repositories
+            .iter()
+            .find(|repository| repository.repo_type == RepositoryType::Base)?
+            .version
+            .clone()
+    }
+
+    /// Returns the name and version of each installed expansion, such as ("ex1", "2023.07.26.0000.0000"),
+    /// in order of their expansion number. The version is None if the expansion's version file is missing.
+    pub fn expansion_versions(&self) -> Vec<(String, Option<String>)> {
+        self.repositories
+            .iter()
+            .filter(|repository| matches!(repository.repo_type, RepositoryType::Expansion { .. }))
+            .map(|repository| (repository.name.clone(), repository.version.clone()))
+            .collect()
+    }
+
     /// Checks if a file located at `path` exists.
     ///
     /// # Example
@@ -157,6 +331,12 @@ impl GameData {
     /// }
     /// ```
     pub fn exists(&mut self, path: &str) -> bool {
+        if self.loose_directory.is_some() {
+            return self
+                .loose_file_path(path)
+                .is_some_and(|file_path| file_path.is_file());
+        }
+
         let Some((_, _)) = self.get_index_filenames(path) else {
             return false;
         };
@@ -182,45 +362,388 @@ impl GameData {
     pub fn extract(&mut self, path: &str) -> Option<ByteBuffer> {
         debug!(file = path, "Extracting file");
 
-        let slice = self.find_entry(path);
-        match slice {
-            Some((entry, chunk)) => {
-                let mut dat_file = self.get_dat_file(path, chunk, entry.data_file_id.into())?;
+        if self.loose_directory.is_some() {
+            return fs::read(self.loose_file_path(path)?).ok();
+        }
+
+        let (entry, chunk) = self.find_entry(path)?;
+        self.read_entry(path, entry, chunk)
+    }
+
+    /// Reads the file at `path` from the dat file that `entry` points to.
+    fn read_entry(&self, path: &str, entry: IndexEntry, chunk: u8) -> Option<ByteBuffer> {
+        let mut dat_file = self.get_dat_file(path, chunk, entry.data_file_id.into())?;
+
+        match dat_file.read_from_offset(entry.offset) {
+            Ok(data) => Some(data),
+            Err(err) => {
+                warn!(file = path, ?err, "Failed to read file from dat");
+                None
+            }
+        }
+    }
+
+    /// Loads the index files of every category in every repository up front, instead of when a file in them is first
+    /// looked up. This is needed before using [GameData::view], which can't load them itself.
+    pub fn preload_all_indices(&mut self) {
+        let mut filenames = vec![];
+        for repository in &self.repositories {
+            for category in repository.categories() {
+                filenames.push(self.index_filenames(repository, category));
+            }
+        }
+
+        for (index_paths, index2_paths) in filenames {
+            for (index_path, _) in index_paths {
+                self.cache_index_file(&index_path);
+            }
+
+            for (index2_path, _) in index2_paths {
+                self.cache_index2_file(&index2_path);
+            }
+        }
+    }
+
+    /// Returns a view which extracts files through a shared reference, so several threads can extract from the same
+    /// game data at once. It never loads index files, so call [GameData::preload_all_indices] first, otherwise only
+    /// files in index files that were loaded by previous lookups are found.
+    ///
+    /// # Example
+    ///
+    /// ```should_panic
+    /// # use physis::common::Platform;
+    /// # use physis::gamedata::GameData;
+    /// # let mut game = GameData::from_existing(Platform::Win32, "SquareEnix/Final Fantasy XIV - A Realm Reborn/game").unwrap();
+    /// game.preload_all_indices();
+    ///
+    /// let view = game.view();
+    /// std::thread::scope(|scope| {
+    ///     scope.spawn(|| view.extract("exd/root.exl").unwrap());
+    ///     scope.spawn(|| view.extract("exd/cid.exl").unwrap());
+    /// });
+    /// ```
+    pub fn view(&self) -> GameDataView<'_> {
+        GameDataView { game_data: self }
+    }
+
+    /// Extracts and parses the model located at `path`. Returns None if the file doesn't exist, or isn't a valid
+    /// model.
+    ///
+    /// # Example
+    ///
+    /// ```should_panic
+    /// # use physis::common::Platform;
+    /// # use physis::gamedata::GameData;
+    /// # let mut game = GameData::from_existing(Platform::Win32, "SquareEnix/Final Fantasy XIV - A Realm Reborn/game").unwrap();
+    /// let model = game.read_model("chara/equipment/e0000/model/c0201e0000_top.mdl").unwrap();
+    /// ```
+    #[cfg(feature = "visual_data")]
+    pub fn read_model(&mut self, path: &str) -> Option<MDL> {
+        MDL::from_existing(&self.extract(path)?)
+    }
+
+    /// Extracts and decodes the texture located at `path`. Returns None if the file doesn't exist, or isn't a valid
+    /// texture.
+    ///
+    /// # Example
+    ///
+    /// ```should_panic
+    /// # use physis::common::Platform;
+    /// # use physis::gamedata::GameData;
+    /// # let mut game = GameData::from_existing(Platform::Win32, "SquareEnix/Final Fantasy XIV - A Realm Reborn/game").unwrap();
+    /// let texture = game.read_texture("ui/icon/051000/051474.tex").unwrap();
+    /// ```
+    #[cfg(feature = "visual_data")]
+    pub fn read_texture(&mut self, path: &str) -> Option<Texture> {
+        Texture::from_existing(&self.extract(path)?)
+    }
 
-                dat_file.read_from_offset(entry.offset)
+    /// Extracts and parses the material located at `path`. Returns None if the file doesn't exist, or isn't a valid
+    /// material.
+    ///
+    /// # Example
+    ///
+    /// ```should_panic
+    /// # use physis::common::Platform;
+    /// # use physis::gamedata::GameData;
+    /// # let mut game = GameData::from_existing(Platform::Win32, "SquareEnix/Final Fantasy XIV - A Realm Reborn/game").unwrap();
+    /// let material = game.read_material("chara/equipment/e0000/material/v0001/mt_c0201e0000_top_a.mtrl").unwrap();
+    /// ```
+    #[cfg(feature = "visual_data")]
+    pub fn read_material(&mut self, path: &str) -> Option<Material> {
+        Material::from_existing(&self.extract(path)?)
+    }
+
+    /// Replaces the file located at `path` in the game's own dat files, like TexTools does when installing a mod.
+    ///
+    /// The new data is appended to the end of the dat file holding the original, which is left where it is, and the
+    /// entries in the index and index2 files are pointed to it. Models are stored as model entries and everything else
+    /// as standard entries. The file has to be listed in an index file, files only listed in index2 files can't be
+    /// replaced. Files whose hash is shared with another (synonyms) can't be replaced either.
+    ///
+    /// **This modifies the game installation**, which can't be undone short of repairing the game. Before the index
+    /// files are first modified, they're copied next to the originals with a ".bak" extension added, such as
+    /// "0a0000.win32.index.bak". Only the entry of the file is changed, the rest of the index files are kept as-is.
+    ///
+    /// With [GameData::from_loose_files], the loose file is overwritten instead.
+    pub fn replace_file(&mut self, path: &str, data: &[u8]) -> Result<(), GameDataError> {
+        if self.loose_directory.is_some() {
+            let file_path = self
+                .loose_file_path(path)
+                .ok_or(GameDataError::FileNotFound)?;
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(file_path, data)?;
+            return Ok(());
+        }
+
+        let (index_paths, index2_paths) = self
+            .get_index_filenames(path)
+            .ok_or(GameDataError::FileNotFound)?;
+        let (index_path, chunk, entry) = index_paths
+            .into_iter()
+            .find_map(|(index_path, chunk)| {
+                self.cache_index_file(&index_path);
+
+                let entry = self.get_index_file(&index_path)?.find_entry(path)?;
+                Some((index_path, chunk, entry))
+            })
+            .ok_or(GameDataError::FileNotFound)?;
+        let index2_path = index2_paths
+            .into_iter()
+            .find(|(_, index2_chunk)| *index2_chunk == chunk)
+            .map(|(index2_path, _)| index2_path)
+            .filter(|index2_path| Path::new(index2_path).exists());
+
+        let file_entry = SqPackWriter::write_entry(path, data).ok_or(GameDataError::InvalidData)?;
+
+        // entries are aligned to 128 bytes
+        let dat_path = self
+            .get_dat_path(path, chunk, entry.data_file_id.into())
+            .ok_or(GameDataError::FileNotFound)?;
+        let mut dat_file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(dat_path)?;
+        let end = dat_file.seek(SeekFrom::End(0))?;
+        let offset = end.next_multiple_of(128);
+
+        // patch the index files before touching anything, so nothing is modified if they can't be
+        let mut index = fs::read(&index_path)?;
+        if patch_index_entry(&mut index, false, entry.hash, offset, entry.data_file_id)
+            != Some(true)
+        {
+            return Err(GameDataError::InvalidData);
+        }
+
+        let mut index2 = None;
+        if let Some(index2_path) = &index2_path {
+            let mut buffer = fs::read(index2_path)?;
+            let hash = Index2File::calculate_hash(path) as u64;
+            match patch_index_entry(&mut buffer, true, hash, offset, entry.data_file_id) {
+                Some(true) => index2 = Some((index2_path, buffer)),
+                Some(false) => {}
+                None => return Err(GameDataError::InvalidData),
+            }
+        }
+
+        for index_path in std::iter::once(&index_path).chain(index2.as_ref().map(|(x, _)| *x)) {
+            let backup_path = format!("{index_path}.bak");
+            if !Path::new(&backup_path).exists() {
+                fs::copy(index_path, &backup_path)?;
+            }
+        }
+
+        dat_file.write_all(&vec![0; (offset - end) as usize])?;
+        dat_file.write_all(&file_entry)?;
+        update_data_header(&mut dat_file)?;
+
+        fs::write(&index_path, index)?;
+        if let Some((index2_path, buffer)) = index2 {
+            fs::write(index2_path, buffer)?;
+            self.index2_files.remove(index2_path);
+        }
+
+        if let Some(index_file) = self.index_files.get_mut(&index_path) {
+            index_file.update_entry(entry.hash, offset, entry.data_file_id);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a hash of the contents of the file at `path`, once decompressed. This can be stored to find out if a
+    /// file changed between game updates, without keeping the file around.
+    ///
+    /// This is a content hash, made from the first 8 bytes of its SHA1, and it's unrelated to the path hashes used in
+    /// the index files. Returns None if the file can't be extracted.
+    pub fn file_hash(&mut self, path: &str) -> Option<u64> {
+        let data = self.extract(path)?;
+        let digest = Sha1::from(&data).digest().bytes();
+
+        Some(u64::from_le_bytes(digest[..8].try_into().ok()?))
+    }
+
+    /// Extracts the file matching `folder_hash` and `file_hash` from the index file at `index_path`, such as
+    /// "sqpack/ffxiv/0a0000.win32.index" in the game directory. This is useful when the path of a file isn't known,
+    /// only its hashes. See [IndexFile::find].
+    pub fn extract_by_hashes(
+        &mut self,
+        index_path: &str,
+        folder_hash: u32,
+        file_hash: u32,
+    ) -> Option<ByteBuffer> {
+        debug!(
+            index = index_path,
+            folder_hash, file_hash, "Extracting file by hashes"
+        );
+
+        self.cache_index_file(index_path);
+        let entry = self
+            .get_index_file(index_path)?
+            .find(folder_hash, file_hash)?;
+
+        // the dat files sit right next to their index
+        let dat_path = format!(
+            "{}.dat{}",
+            index_path.strip_suffix(".index")?,
+            entry.data_file_id
+        );
+
+        match DatFile::from_existing(&dat_path)?.read_from_offset(entry.offset) {
+            Ok(data) => Some(data),
+            Err(err) => {
+                warn!(index = index_path, ?err, "Failed to read file from dat");
+                None
             }
-            None => None,
         }
     }
 
+    /// Opens the file located at `path` for reading, without decompressing all of it up front. This is
+    /// useful for large files where only parts of it are needed, such as headers.
+    ///
+    /// This isn't supported when reading from loose files, use [GameData::extract] instead.
+    pub fn extract_reader(&mut self, path: &str) -> Option<impl Read + Seek> {
+        debug!(file = path, "Opening file for reading");
+
+        let (entry, chunk) = self.find_entry(path)?;
+        let dat_file = self.get_dat_file(path, chunk, entry.data_file_id.into())?;
+
+        dat_file.reader_from_offset(entry.offset)
+    }
+
+    /// Extracts several files at once, returning them in the same order as `paths`. Files that
+    /// don't exist or fail to extract are `None`.
+    ///
+    /// This is faster than calling `extract` in a loop, as every index lookup is done up front and
+    /// each dat file is only opened once. With the `parallel` feature, dat files are also read
+    /// concurrently.
+    pub fn extract_many(&mut self, paths: &[&str]) -> Vec<Option<ByteBuffer>> {
+        debug!(count = paths.len(), "Extracting files");
+
+        if self.loose_directory.is_some() {
+            return paths.iter().map(|path| self.extract(path)).collect();
+        }
+
+        // group the offsets we need by which dat file they're in
+        let mut dat_offsets: HashMap<PathBuf, Vec<(usize, u64)>> = HashMap::new();
+        for (i, path) in paths.iter().enumerate() {
+            let Some((entry, chunk)) = self.find_entry(path) else {
+                continue;
+            };
+
+            if let Some(dat_path) = self.get_dat_path(path, chunk, entry.data_file_id.into()) {
+                dat_offsets
+                    .entry(dat_path)
+                    .or_default()
+                    .push((i, entry.offset));
+            }
+        }
+
+        let read_dat_file = |(dat_path, mut offsets): (PathBuf, Vec<(usize, u64)>)| {
+            // reading in order keeps the seeks short
+            offsets.sort_by_key(|(_, offset)| *offset);
+
+            let mut dat_file = dat_path.to_str().and_then(DatFile::from_existing);
+
+            offsets
+                .into_iter()
+                .map(|(i, offset)| {
+                    (
+                        i,
+                        dat_file
+                            .as_mut()
+                            .and_then(|dat_file| dat_file.read_from_offset(offset).ok()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        #[cfg(feature = "parallel")]
+        let extracted: Vec<_> = {
+            use rayon::prelude::*;
+
+            dat_offsets
+                .into_par_iter()
+                .flat_map_iter(read_dat_file)
+                .collect()
+        };
+
+        #[cfg(not(feature = "parallel"))]
+        let extracted: Vec<_> = dat_offsets.into_iter().flat_map(read_dat_file).collect();
+
+        let mut files = vec![None; paths.len()];
+        for (i, data) in extracted {
+            files[i] = data;
+        }
+
+        files
+    }
+
     /// Finds the offset inside of the DAT file for `path`.
     pub fn find_offset(&mut self, path: &str) -> Option<u64> {
         let slice = self.find_entry(path);
         slice.map(|(entry, _)| entry.offset)
     }
 
+    /// Returns which repository and category `path` belongs to, and so which index and dat files it's read from.
+    /// This doesn't check if the file exists, see [GameData::exists] for that.
+    pub fn locate(&self, path: &str) -> Option<FileLocation> {
+        let (repository, category) = self.parse_repository_category(path)?;
+
+        Some(FileLocation {
+            repository: repository.name.clone(),
+            category,
+            index_filename: repository.index_filename(0, category),
+        })
+    }
+
     /// Parses a path structure and spits out the corresponding category and repository.
     fn parse_repository_category(&self, path: &str) -> Option<(&Repository, Category)> {
-        let tokens: Vec<&str> = path.split('/').collect(); // TODO: use split_once here
+        let (category_token, rest) = path.split_once('/')?;
+        let category = string_to_category(category_token)?;
 
-        if tokens.len() < 2 {
-            return None;
-        }
-
-        let repository_token = tokens[1];
+        // files from expansions are in a folder named after them, such as "bg/ex1/..."
+        let repository_token = rest.split_once('/').map_or(rest, |(token, _)| token);
 
-        for repository in &self.repositories {
-            if repository.name == repository_token {
-                return Some((repository, string_to_category(tokens[0])?));
-            }
-        }
+        let repository = self
+            .repositories
+            .iter()
+            .find(|repository| repository.name == repository_token)
+            .unwrap_or(self.repositories.first()?);
 
-        Some((&self.repositories[0], string_to_category(tokens[0])?))
+        Some((repository, category))
     }
 
-    fn get_index_filenames(&self, path: &str) -> Option<(Vec<(String, u8)>, Vec<(String, u8)>)> {
+    fn get_index_filenames(&self, path: &str) -> Option<IndexFilenames> {
         let (repository, category) = self.parse_repository_category(path)?;
 
+        Some(self.index_filenames(repository, category))
+    }
+
+    /// Returns the filenames of every possible index and index2 file of `category` in `repository`.
+    fn index_filenames(&self, repository: &Repository, category: Category) -> IndexFilenames {
         let mut index1_filenames = vec![];
         let mut index2_filenames = vec![];
 
@@ -248,7 +771,96 @@ impl GameData {
             index2_filenames.push((index2_path.into_os_string().into_string().unwrap(), chunk));
         }
 
-        Some((index1_filenames, index2_filenames))
+        (index1_filenames, index2_filenames)
+    }
+
+    /// Adds a list of known game paths, such as "exd/root.exl", so their hashes can be turned back into
+    /// paths with [GameData::resolve_hash]. Since hashes are one-way, files not included in any list
+    /// stay anonymous.
+    pub fn load_path_list(&mut self, paths: &[String]) {
+        for path in paths {
+            self.known_paths
+                .insert(IndexFile::calculate_hash(path), path.clone());
+            self.known_paths2
+                .insert(Index2File::calculate_hash(path), path.clone());
+        }
+    }
+
+    /// Finds the known path of an entry `hash` from the index file at `index_path`, which can be
+    /// either an index or index2 file. Returns None if the path isn't in any list loaded with
+    /// [GameData::load_path_list].
+    pub fn resolve_hash(&self, index_path: &str, hash: u64) -> Option<&str> {
+        let index_filename = Path::new(index_path).file_name()?.to_str()?;
+
+        let path = if index_filename.ends_with(".index2") {
+            self.known_paths2.get(&u32::try_from(hash).ok()?)?
+        } else {
+            self.known_paths.get(&hash)?
+        };
+
+        // make sure the path actually belongs in this index, the first four characters are its category and expansion
+        let (repository, category) = self.parse_repository_category(path)?;
+        let expected_filename = repository.index_filename(0, category);
+        if index_filename.get(..4)? != expected_filename.get(..4)? {
+            return None;
+        }
+
+        Some(path)
+    }
+
+    /// Extracts every known file under `prefix`, such as "ui/icon/051000/", returning their paths and
+    /// contents sorted by path. Only paths loaded with [GameData::load_path_list] can be found, use
+    /// [GameData::unresolved_folder_hashes] to list the files that were missed.
+    pub fn extract_folder(&mut self, prefix: &str) -> Vec<(String, ByteBuffer)> {
+        let prefix = prefix.to_lowercase();
+
+        let mut paths: Vec<String> = self
+            .known_paths
+            .values()
+            .filter(|path| path.to_lowercase().starts_with(&prefix))
+            .cloned()
+            .collect();
+        paths.sort();
+
+        let files = self.extract_many(&paths.iter().map(String::as_str).collect::<Vec<_>>());
+
+        paths
+            .into_iter()
+            .zip(files)
+            .filter_map(|(path, file)| Some((path, file?)))
+            .collect()
+    }
+
+    /// Returns the index hashes of files directly inside `folder` that don't match any path loaded
+    /// with [GameData::load_path_list]. Subfolders aren't included, as index files only keep the hash
+    /// of the folder a file is in.
+    pub fn unresolved_folder_hashes(&mut self, folder: &str) -> Vec<u64> {
+        let folder = folder.trim_end_matches('/');
+        let folder_hash = IndexFile::calculate_partial_hash(folder);
+
+        let Some((index_paths, _)) = self.get_index_filenames(folder) else {
+            return Vec::new();
+        };
+
+        let mut hashes = Vec::new();
+        for (index_path, _) in index_paths {
+            self.cache_index_file(&index_path);
+
+            if let Some(index_file) = self.get_index_file(&index_path) {
+                hashes.extend(
+                    index_file
+                        .entries
+                        .iter()
+                        .map(|entry| entry.hash)
+                        .filter(|hash| {
+                            (hash >> 32) as u32 == folder_hash
+                                && !self.known_paths.contains_key(hash)
+                        }),
+                );
+            }
+        }
+
+        hashes
     }
 
     /// Read an excel sheet by name (e.g. "Achievement")
@@ -302,11 +914,95 @@ impl GameData {
         EXD::from_existing(exh, &exd_file)
     }
 
+    /// Same as [GameData::read_excel_sheet], but if the page doesn't exist for `language`, each
+    /// language in `fallback` is tried in order. Returns the page and the language it was read in.
+    ///
+    /// [LANGUAGE_FALLBACK] is a good default, as many sheets are only shipped in some languages.
+    pub fn read_excel_sheet_with_fallback(
+        &mut self,
+        name: &str,
+        exh: &EXH,
+        language: Language,
+        page: usize,
+        fallback: &[Language],
+    ) -> Option<(EXD, Language)> {
+        std::iter::once(&language)
+            .chain(fallback)
+            .find_map(|language| {
+                self.read_excel_sheet(name, exh, *language, page)
+                    .map(|exd| (exd, *language))
+            })
+    }
+
+    /// Reads every page of an excel sheet, in order. Sheets that aren't localized are always read
+    /// with [Language::None], regardless of `language`.
+    ///
+    /// Use [EXD::rows_with_ids] to go through the rows of every page:
+    ///
+    /// ```should_panic
+    /// # use physis::common::{Language, Platform};
+    /// # use physis::gamedata::GameData;
+    /// # let mut game = GameData::from_existing(Platform::Win32, "SquareEnix/Final Fantasy XIV - A Realm Reborn/game").unwrap();
+    /// let pages = game.read_excel_sheet_all("Item", Language::English).unwrap();
+    /// for (row_id, row) in pages.iter().flat_map(|page| page.rows_with_ids()) {
+    ///     println!("{row_id}: {:?}", row.data);
+    /// }
+    /// ```
+    pub fn read_excel_sheet_all(&mut self, name: &str, language: Language) -> Option<Vec<EXD>> {
+        self.read_sheet(name, language).map(|(_, pages)| pages)
+    }
+
+    /// Same as [GameData::read_excel_sheet_all], but also returns the sheet's header, which describes its columns.
+    ///
+    /// # Example
+    ///
+    /// ```should_panic
+    /// # use physis::common::{Language, Platform};
+    /// # use physis::gamedata::GameData;
+    /// # let mut game = GameData::from_existing(Platform::Win32, "SquareEnix/Final Fantasy XIV - A Realm Reborn/game").unwrap();
+    /// let (exh, pages) = game.read_sheet("Item", Language::English).unwrap();
+    /// ```
+    pub fn read_sheet(&mut self, name: &str, language: Language) -> Option<(EXH, Vec<EXD>)> {
+        let exh = self.read_excel_sheet_header(name)?;
+
+        let language = if exh
+            .languages
+            .iter()
+            .all(|sheet_language| matches!(sheet_language, Language::None))
+        {
+            Language::None
+        } else {
+            language
+        };
+
+        let pages = (0..exh.pages.len())
+            .map(|page| self.read_excel_sheet(name, &exh, language, page))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some((exh, pages))
+    }
+
     /// Applies the patch to game data and returns any errors it encounters. This function will not update the version in the GameData struct.
     pub fn apply_patch(&self, patch_path: &str) -> Result<(), PatchError> {
         ZiPatch::apply(&self.game_directory, patch_path)
     }
 
+    /// Same as [GameData::apply_patch], but with extra `options`. Set [PatchApplyOptions::dry_run] to get the
+    /// operations the patch would perform without modifying anything.
+    pub fn apply_patch_with_options(
+        &self,
+        patch_path: &str,
+        options: &PatchApplyOptions,
+    ) -> Result<Vec<PatchOperation>, PatchError> {
+        ZiPatch::apply_with_options(&self.game_directory, patch_path, options)
+    }
+
+    /// Performs a dry run of `apply_patch`, returning the operations the patch would perform without modifying anything.
+    /// Paths in the returned operations are relative to the game directory.
+    pub fn inspect_patch(&self, patch_path: &str) -> Result<Vec<PatchOperation>, PatchError> {
+        ZiPatch::inspect(patch_path)
+    }
+
     /// Detects whether or not the game files need a repair, right now it only checks for invalid
     /// version files.
     /// If the repair is needed, a list of invalid repositories is given.
@@ -424,6 +1120,31 @@ impl GameData {
         self.index2_files.get(filename)
     }
 
+    /// Same as [GameData::find_entry], but only searches the index files that are already loaded.
+    fn find_cached_entry(&self, path: &str) -> Option<(IndexEntry, u8)> {
+        let (index_paths, index2_paths) = self.get_index_filenames(path)?;
+
+        for (index_path, chunk) in index_paths {
+            if let Some(entry) = self
+                .get_index_file(&index_path)
+                .and_then(|index_file| index_file.find_entry(path))
+            {
+                return Some((entry, chunk));
+            }
+        }
+
+        for (index2_path, chunk) in index2_paths {
+            if let Some(entry) = self
+                .get_index2_file(&index2_path)
+                .and_then(|index_file| index_file.find_entry(path))
+            {
+                return Some((entry, chunk));
+            }
+        }
+
+        None
+    }
+
     fn find_entry(&mut self, path: &str) -> Option<(IndexEntry, u8)> {
         let (index_paths, index2_paths) = self.get_index_filenames(path)?;
 
@@ -453,7 +1174,11 @@ impl GameData {
 
 #[cfg(test)]
 mod tests {
+    use crate::excel_fixtures::{build_exd, build_exh};
+    use crate::exd::ColumnData;
+    use crate::exh::ColumnDataType;
     use crate::repository::Category::EXD;
+    use crate::sqpack_fixtures::packed_setup_data;
 
     use super::*;
 
@@ -466,6 +1191,314 @@ mod tests {
         GameData::from_existing(Platform::Win32, d.to_str().unwrap()).unwrap()
     }
 
+    #[test]
+    fn replace_file() {
+        use crate::dat::SqPackDataHeader;
+        use binrw::BinRead;
+        use std::io::Cursor;
+
+        let mut data = packed_setup_data(
+            "physis-gamedata-replace-file",
+            &[
+                ("common/replaced.txt", b"original"),
+                ("common/untouched.txt", b"untouched"),
+            ],
+        );
+
+        let new_content = b"a longer replacement, which won't fit where the original was";
+        data.replace_file("common/replaced.txt", new_content)
+            .unwrap();
+
+        assert_eq!(data.extract("common/replaced.txt").unwrap(), new_content);
+        assert_eq!(data.extract("common/untouched.txt").unwrap(), b"untouched");
+
+        // the changes are on disk, and the original index is backed up
+        let mut reloaded = GameData::from_existing(Platform::Win32, &data.game_directory).unwrap();
+        assert_eq!(
+            reloaded.extract("common/replaced.txt").unwrap(),
+            new_content
+        );
+
+        let backup_path = PathBuf::from(&data.game_directory)
+            .join("sqpack/ffxiv/000000.win32.index.bak")
+            .to_str()
+            .unwrap()
+            .to_string();
+        let backup = IndexFile::from_existing(&backup_path).unwrap();
+        assert!(backup.exists("common/replaced.txt"));
+
+        // the dat header matches the entries after the new one was appended
+        let dat =
+            fs::read(PathBuf::from(&data.game_directory).join("sqpack/ffxiv/000000.win32.dat0"))
+                .unwrap();
+        let data_header = SqPackDataHeader::read(&mut Cursor::new(&dat[0x400..])).unwrap();
+        assert_eq!(data_header.data_size as usize, dat.len() - 0x800);
+        assert_eq!(
+            data_header.data_hash,
+            Sha1::from(&dat[0x800..]).digest().bytes()
+        );
+
+        assert!(matches!(
+            data.replace_file("common/missing.txt", b""),
+            Err(GameDataError::FileNotFound)
+        ));
+    }
+
+    #[test]
+    fn replace_file_keeps_index_segments() {
+        use crate::index::{
+            write_index2, write_index_with_synonyms, Index2HashTableEntry, IndexHashTableEntry,
+            SQPACK_HEADER_SIZE,
+        };
+
+        let mut data = packed_setup_data(
+            "physis-gamedata-replace-file-segments",
+            &[
+                ("common/replaced.txt", b"original"),
+                ("common/untouched.txt", b"untouched"),
+            ],
+        );
+
+        let sqpack_dir = PathBuf::from(&data.game_directory).join("sqpack/ffxiv");
+        let index_path = sqpack_dir.join("000000.win32.index");
+        let index2_path = sqpack_dir.join("000000.win32.index2");
+
+        // add a synonym, and an index2 file pointing to the same entries
+        let mut entries = IndexFile::from_existing(index_path.to_str().unwrap())
+            .unwrap()
+            .entries;
+        let paths = ["common/replaced.txt", "common/untouched.txt"];
+        let mut index2_entries: Vec<Index2HashTableEntry> = paths
+            .iter()
+            .map(|path| {
+                let entry = entries
+                    .iter()
+                    .find(|entry| entry.hash == IndexFile::calculate_hash(path))
+                    .unwrap();
+
+                Index2HashTableEntry {
+                    hash: Index2File::calculate_hash(path),
+                    is_synonym: false,
+                    data_file_id: entry.data_file_id,
+                    offset: entry.offset,
+                }
+            })
+            .collect();
+        index2_entries.sort_by_key(|entry| entry.hash);
+
+        entries.push(IndexHashTableEntry {
+            hash: IndexFile::calculate_hash("common/synonym.txt"),
+            is_synonym: true,
+            data_file_id: 0,
+            offset: 0,
+        });
+        entries.sort_by_key(|entry| entry.hash);
+
+        let synonyms = vec![0xAB; 0x100];
+        fs::write(
+            &index_path,
+            write_index_with_synonyms(Platform::Win32, &entries, &synonyms).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            &index2_path,
+            write_index2(Platform::Win32, &index2_entries, &[], &[], 1).unwrap(),
+        )
+        .unwrap();
+
+        let original_index = fs::read(&index_path).unwrap();
+        let original_index2 = fs::read(&index2_path).unwrap();
+
+        // synonyms point to the synonym table, so they can't be replaced
+        assert!(matches!(
+            data.replace_file("common/synonym.txt", b""),
+            Err(GameDataError::InvalidData)
+        ));
+        assert_eq!(fs::read(&index_path).unwrap(), original_index);
+
+        data.replace_file("common/replaced.txt", b"replaced")
+            .unwrap();
+
+        // only the hash table and header changed, and the synonym is still there
+        let index = fs::read(&index_path).unwrap();
+        assert_eq!(index.len(), original_index.len());
+        let table_end = SQPACK_HEADER_SIZE as usize * 2 + entries.len() * 16;
+        assert_eq!(index[table_end..], original_index[table_end..]);
+        assert_eq!(index[table_end..table_end + 0x100], synonyms);
+
+        let index = IndexFile::from_existing(index_path.to_str().unwrap()).unwrap();
+        assert_eq!(index.entries.len(), 3);
+        assert!(index.entries.iter().any(|entry| entry.is_synonym
+            && entry.hash == IndexFile::calculate_hash("common/synonym.txt")));
+
+        // both indices agree about where the file is
+        let index2 = Index2File::from_existing(index2_path.to_str().unwrap()).unwrap();
+        let entry = index.find_entry("common/replaced.txt").unwrap();
+        let entry2 = index2.find_entry("common/replaced.txt").unwrap();
+        assert_eq!(entry.offset, entry2.offset);
+        assert_eq!(entry.data_file_id, entry2.data_file_id);
+        assert_eq!(
+            index2.find_entry("common/untouched.txt").unwrap().offset,
+            index.find_entry("common/untouched.txt").unwrap().offset
+        );
+
+        assert!(sqpack_dir.join("000000.win32.index2.bak").exists());
+        assert_eq!(
+            fs::read(sqpack_dir.join("000000.win32.index2.bak")).unwrap(),
+            original_index2
+        );
+
+        let mut reloaded = GameData::from_existing(Platform::Win32, &data.game_directory).unwrap();
+        assert_eq!(
+            reloaded.extract("common/replaced.txt").unwrap(),
+            b"replaced"
+        );
+        assert_eq!(
+            reloaded.extract("common/untouched.txt").unwrap(),
+            b"untouched"
+        );
+    }
+
+    #[test]
+    fn platform_filenames() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("valid_sqpack");
+        d.push("game");
+
+        for (platform, name) in [
+            (Platform::Win32, "win32"),
+            (Platform::PS3, "ps3"),
+            (Platform::PS4, "ps4"),
+        ] {
+            let data = GameData::from_existing(platform, d.to_str().unwrap()).unwrap();
+            let (index1, index2) = data.get_index_filenames("exd/root.exl").unwrap();
+
+            assert!(index1[0].0.ends_with(&format!("0a0000.{name}.index")));
+            assert!(index2[0].0.ends_with(&format!("0a0000.{name}.index2")));
+            assert!(data
+                .get_dat_path("exd/root.exl", 0, 1)
+                .unwrap()
+                .ends_with(format!("0a0000.{name}.dat1")));
+        }
+    }
+
+    #[test]
+    fn versions() {
+        let data = common_setup_data();
+
+        assert_eq!(data.game_version().unwrap(), "1.0.1");
+        assert_eq!(
+            data.expansion_versions(),
+            vec![
+                ("ex1".to_string(), Some("1.0.2".to_string())),
+                ("ex2".to_string(), Some("1.0.3".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_hash() {
+        let mut data = common_setup_data();
+        data.load_path_list(&["exd/root.exl".to_string(), "bg/ex1/test.mdl".to_string()]);
+
+        let hash = IndexFile::calculate_hash("EXD/root.exl");
+        assert_eq!(
+            data.resolve_hash("sqpack/ffxiv/0a0000.win32.index", hash),
+            Some("exd/root.exl")
+        );
+
+        let hash2 = Index2File::calculate_hash("exd/root.exl") as u64;
+        assert_eq!(
+            data.resolve_hash("sqpack/ffxiv/0a0000.win32.index2", hash2),
+            Some("exd/root.exl")
+        );
+
+        // wrong category
+        assert!(data
+            .resolve_hash("sqpack/ffxiv/040000.win32.index", hash)
+            .is_none());
+
+        // wrong expansion
+        let hash = IndexFile::calculate_hash("bg/ex1/test.mdl");
+        assert!(data
+            .resolve_hash("sqpack/ex1/020100.win32.index", hash)
+            .is_some());
+        assert!(data
+            .resolve_hash("sqpack/ffxiv/020000.win32.index", hash)
+            .is_none());
+
+        // unknown path
+        let hash = IndexFile::calculate_hash("exd/unknown.exh");
+        assert!(data
+            .resolve_hash("sqpack/ffxiv/0a0000.win32.index", hash)
+            .is_none());
+    }
+
+    #[test]
+    fn extract_by_hashes() {
+        let mut data = packed_setup_data(
+            "physis-gamedata-extract-by-hashes",
+            &[("exd/root.exl", b"root")],
+        );
+
+        let index_path = Path::new(&data.game_directory)
+            .join("sqpack")
+            .join("ffxiv")
+            .join("0a0000.win32.index");
+        let index_path = index_path.to_str().unwrap();
+
+        // "exd" and "root.exl"
+        assert_eq!(
+            data.extract_by_hashes(index_path, 0xE39B7999, 0x51B57EBC),
+            Some(b"root".to_vec())
+        );
+        assert_eq!(data.extract_by_hashes(index_path, 0xE39B7999, 0), None);
+    }
+
+    #[test]
+    fn extract_folder() {
+        let mut data = packed_setup_data(
+            "physis-gamedata-extract-folder",
+            &[
+                ("common/icon/051000/051001.tex", b"first"),
+                ("common/icon/051000/051002.tex", b"second"),
+                ("common/icon/051000/unknown.tex", b"unknown"),
+                ("common/icon/052000/052001.tex", b"other"),
+            ],
+        );
+        data.load_path_list(&[
+            "common/icon/051000/051001.tex".to_string(),
+            "common/icon/051000/051002.tex".to_string(),
+            "common/icon/051000/051003.tex".to_string(),
+            "common/icon/052000/052001.tex".to_string(),
+        ]);
+
+        // 051003 is known but doesn't exist, so it's skipped
+        assert_eq!(
+            data.extract_folder("common/icon/051000/"),
+            vec![
+                (
+                    "common/icon/051000/051001.tex".to_string(),
+                    b"first".to_vec()
+                ),
+                (
+                    "common/icon/051000/051002.tex".to_string(),
+                    b"second".to_vec()
+                ),
+            ]
+        );
+        assert_eq!(data.extract_folder("common/icon/").len(), 3);
+
+        assert_eq!(
+            data.unresolved_folder_hashes("common/icon/051000/"),
+            vec![IndexFile::calculate_hash("common/icon/051000/unknown.tex")]
+        );
+        assert!(data
+            .unresolved_folder_hashes("common/icon/052000")
+            .is_empty());
+    }
+
     #[test]
     fn repository_ordering() {
         let data = common_setup_data();
@@ -487,4 +1520,259 @@ mod tests {
             .parse_repository_category("what/some_font.dat")
             .is_none());
     }
+
+    #[test]
+    fn locate() {
+        let data = common_setup_data();
+
+        assert_eq!(
+            data.locate("bg/ex1/01_roc_r2/twn/r2t1/level/planevent.lgb"),
+            Some(FileLocation {
+                repository: "ex1".to_string(),
+                category: Category::Background,
+                index_filename: "020100.win32.index".to_string(),
+            })
+        );
+        assert_eq!(
+            data.locate("exd/root.exl"),
+            Some(FileLocation {
+                repository: "ffxiv".to_string(),
+                category: EXD,
+                index_filename: "0a0000.win32.index".to_string(),
+            })
+        );
+        assert!(data.locate("what/some_font.dat").is_none());
+        assert!(data.locate("exd").is_none());
+    }
+
+    #[test]
+    fn file_hash() {
+        let mut data = packed_setup_data(
+            "physis-gamedata-file-hash",
+            &[
+                ("common/a.txt", b"foo"),
+                ("common/b.txt", b"foo"),
+                ("common/c.txt", b"bar"),
+            ],
+        );
+
+        let hash = data.file_hash("common/a.txt").unwrap();
+        assert_eq!(hash, 0xdb0f3feab5c7ee0b);
+        assert_eq!(data.file_hash("common/b.txt"), Some(hash));
+        assert_ne!(data.file_hash("common/c.txt"), Some(hash));
+        assert_eq!(data.file_hash("common/missing.txt"), None);
+    }
+
+    #[test]
+    fn extract_many() {
+        let mut data = packed_setup_data(
+            "physis-gamedata-extract-many",
+            &[("common/a.txt", b"foo"), ("common/b/c.txt", b"bar")],
+        );
+
+        assert_eq!(
+            data.extract_many(&["common/b/c.txt", "common/missing.txt", "common/a.txt"]),
+            vec![Some(b"bar".to_vec()), None, Some(b"foo".to_vec())]
+        );
+    }
+
+    #[test]
+    fn shared_view() {
+        let files: Vec<(String, Vec<u8>)> = (0..8)
+            .map(|i| (format!("common/{i}.txt"), format!("file {i}").into_bytes()))
+            .collect();
+        let file_refs: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|(path, data)| (path.as_str(), data.as_slice()))
+            .collect();
+
+        let mut data = packed_setup_data("physis-gamedata-shared-view", &file_refs);
+
+        // nothing is loaded yet, so the view can't find anything
+        assert!(!data.view().exists("common/0.txt"));
+
+        data.preload_all_indices();
+
+        let view = data.view();
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = files
+                .iter()
+                .map(|(path, expected)| {
+                    scope.spawn(move || assert_eq!(view.extract(path).as_ref(), Some(expected)))
+                })
+                .collect();
+
+            for thread in threads {
+                thread.join().unwrap();
+            }
+        });
+
+        assert!(view.exists("common/7.txt"));
+        assert!(view.extract("common/missing.txt").is_none());
+    }
+
+    #[test]
+    fn extract_reader() {
+        use std::io::SeekFrom;
+
+        // large enough to be split into several blocks
+        let large_file: Vec<u8> = (0..40000u32).map(|x| (x % 251) as u8).collect();
+
+        let mut data = packed_setup_data(
+            "physis-gamedata-extract-reader",
+            &[("common/large.bin", &large_file)],
+        );
+
+        let mut reader = data.extract_reader("common/large.bin").unwrap();
+
+        // read across a block boundary
+        let mut buffer = [0u8; 100];
+        reader.seek(SeekFrom::Start(15950)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, large_file[15950..16050]);
+
+        reader.seek(SeekFrom::Current(-200)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, large_file[15850..15950]);
+
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, large_file);
+
+        assert!(data.extract_reader("common/missing.bin").is_none());
+    }
+
+    #[test]
+    fn loose_files() {
+        let mut d = std::env::temp_dir();
+        d.push("physis-gamedata-loose-files");
+        if d.exists() {
+            fs::remove_dir_all(&d).unwrap();
+        }
+
+        fs::create_dir_all(d.join("exd")).unwrap();
+        fs::write(d.join("exd").join("root.exl"), b"EXLT,2").unwrap();
+
+        assert!(GameData::from_loose_files(d.join("missing").to_str().unwrap()).is_none());
+
+        let mut data = GameData::from_loose_files(d.to_str().unwrap()).unwrap();
+
+        assert!(data.exists("exd/root.exl"));
+        assert!(!data.exists("exd/missing.exl"));
+        assert!(!data.exists("exd"));
+        assert!(!data.exists("../physis-gamedata-loose-files/exd/root.exl"));
+
+        assert_eq!(data.extract("exd/root.exl").unwrap(), b"EXLT,2");
+        assert!(data.extract("exd/missing.exl").is_none());
+        assert_eq!(
+            data.extract_many(&["exd/missing.exl", "exd/root.exl"]),
+            vec![None, Some(b"EXLT,2".to_vec())]
+        );
+    }
+
+    /// Builds the header of a sheet with a single u32 column, and a page for each of `pages`.
+    fn build_test_exh(pages: &[&[u32]], languages: &[Language]) -> Vec<u8> {
+        let pages: Vec<(u32, u32)> = pages
+            .iter()
+            .map(|page| (page[0], page.len() as u32))
+            .collect();
+
+        build_exh(4, &[(ColumnDataType::UInt32, 0)], &pages, languages)
+    }
+
+    /// Builds a page for [build_test_exh], where each row stores its id times `multiplier`.
+    fn build_test_exd(page: &[u32], multiplier: u32) -> Vec<u8> {
+        let rows: Vec<(u32, Vec<u8>)> = page
+            .iter()
+            .map(|row_id| (*row_id, (row_id * multiplier).to_be_bytes().to_vec()))
+            .collect();
+
+        build_exd(&rows)
+    }
+
+    #[test]
+    fn read_excel_sheet_all() {
+        // a sheet with no localization, split over two pages
+        let pages: [&[u32]; 2] = [&[0, 1], &[10, 11]];
+        let exh = build_test_exh(&pages, &[Language::None]);
+        let exds: Vec<Vec<u8>> = pages.iter().map(|page| build_test_exd(page, 100)).collect();
+
+        let mut data = packed_setup_data(
+            "physis-gamedata-read-excel-sheet-all",
+            &[
+                ("exd/root.exl", b"EXLT,2\nTest,0"),
+                ("exd/test.exh", &exh),
+                ("exd/test_0.exd", &exds[0]),
+                ("exd/test_10.exd", &exds[1]),
+            ],
+        );
+
+        let sheet = data
+            .read_excel_sheet_all("Test", Language::English)
+            .unwrap();
+        assert_eq!(sheet.len(), 2);
+
+        let rows: Vec<(u32, ColumnData)> = sheet
+            .iter()
+            .flat_map(|page| page.rows_with_ids())
+            .map(|(row_id, row)| (row_id, row.data[0].clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, ColumnData::UInt32(0)),
+                (1, ColumnData::UInt32(100)),
+                (10, ColumnData::UInt32(1000)),
+                (11, ColumnData::UInt32(1100)),
+            ]
+        );
+
+        assert!(data
+            .read_excel_sheet_all("Missing", Language::English)
+            .is_none());
+    }
+
+    #[test]
+    fn read_excel_sheet_fallback() {
+        let pages: [&[u32]; 1] = [&[0, 1]];
+        let exh = build_test_exh(&pages, &[Language::Japanese, Language::English]);
+
+        // only the English page exists
+        let mut data = packed_setup_data(
+            "physis-gamedata-read-excel-sheet-fallback",
+            &[
+                ("exd/root.exl", b"EXLT,2\nTest,0"),
+                ("exd/test.exh", &exh),
+                ("exd/test_0_en.exd", &build_test_exd(pages[0], 2)),
+            ],
+        );
+
+        let exh = data.read_excel_sheet_header("Test").unwrap();
+
+        let (exd, language) = data
+            .read_excel_sheet_with_fallback("Test", &exh, Language::German, 0, &LANGUAGE_FALLBACK)
+            .unwrap();
+        assert!(matches!(language, Language::English));
+        assert_eq!(exd.rows[1].data, vec![ColumnData::UInt32(2)]);
+
+        let (_, language) = data
+            .read_excel_sheet_with_fallback("Test", &exh, Language::English, 0, &[])
+            .unwrap();
+        assert!(matches!(language, Language::English));
+
+        // without a fallback, a missing language still fails
+        assert!(data
+            .read_excel_sheet_with_fallback("Test", &exh, Language::German, 0, &[])
+            .is_none());
+        assert!(data
+            .read_excel_sheet_with_fallback(
+                "Test",
+                &exh,
+                Language::German,
+                0,
+                &[Language::Japanese]
+            )
+            .is_none());
+    }
 }
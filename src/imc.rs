@@ -0,0 +1,136 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::Cursor;
+
+use crate::ByteSpan;
+use binrw::binread;
+use binrw::BinRead;
+
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+struct ImcHeader {
+    /// Number of variants, not counting the default variant.
+    count: u16,
+    part_mask: u16,
+}
+
+/// A single variant entry for a part.
+#[binread]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[br(little)]
+pub struct ImcEntry {
+    /// The material variant to use, e.g. 1 for "v0001".
+    pub material_id: u8,
+    /// The decal to use, or 0 for none.
+    pub decal_id: u8,
+
+    #[br(temp)]
+    attribute_and_sound: u16,
+
+    /// Which attributes (such as "atr_a") are enabled.
+    #[br(calc = attribute_and_sound & 0x3FF)]
+    pub attribute_mask: u16,
+    /// The sound to use, or 0 for none.
+    #[br(calc = attribute_and_sound >> 10)]
+    pub sound_id: u16,
+
+    /// The VFX to use, or 0 for none.
+    pub vfx_id: u8,
+    /// The material animation to use, or 0 for none.
+    pub material_animation_id: u8,
+}
+
+/// All of the variants for a single part, such as the "top" in equipment.
+#[derive(Debug)]
+pub struct ImcPart {
+    /// Indexed by variant number, where index 0 is the default variant.
+    pub variants: Vec<ImcEntry>,
+}
+
+/// Image change data, which describes the material, decal and vfx of each variant.
+#[derive(Debug)]
+pub struct ImcFile {
+    /// Bitmask of which parts are present.
+    pub part_mask: u16,
+    /// The parts present in this file, in bit order of `part_mask`.
+    pub parts: Vec<ImcPart>,
+}
+
+impl ImcFile {
+    /// Reads an existing IMC file
+    pub fn from_existing(buffer: ByteSpan) -> Option<ImcFile> {
+        let mut cursor = Cursor::new(buffer);
+        let header = ImcHeader::read(&mut cursor).ok()?;
+
+        let part_count = header.part_mask.count_ones() as usize;
+        let variant_count = header.count as usize + 1; // includes the default variant
+
+        let mut parts: Vec<ImcPart> = (0..part_count)
+            .map(|_| ImcPart {
+                variants: Vec::with_capacity(variant_count),
+            })
+            .collect();
+
+        // entries are stored variant-major, so every part for variant 0 comes first
+        for _ in 0..variant_count {
+            for part in &mut parts {
+                part.variants.push(ImcEntry::read(&mut cursor).ok()?);
+            }
+        }
+
+        Some(ImcFile {
+            part_mask: header.part_mask,
+            parts,
+        })
+    }
+
+    /// Returns the entry for `variant` of the `part` index, or None if either is out of range.
+    /// The material id of this entry can be passed to `build_gear_material_path`.
+    pub fn get_variant(&self, part: usize, variant: usize) -> Option<&ImcEntry> {
+        self.parts.get(part)?.variants.get(variant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("test.imc");
+
+        let imc = ImcFile::from_existing(&read(d).unwrap()).unwrap();
+
+        assert_eq!(imc.part_mask, 0b11);
+        assert_eq!(imc.parts.len(), 2);
+        assert_eq!(imc.parts[0].variants.len(), 2);
+
+        let entry = imc.get_variant(1, 1).unwrap();
+        assert_eq!(entry.material_id, 4);
+        assert_eq!(entry.decal_id, 2);
+        assert_eq!(entry.attribute_mask, 0x5);
+        assert_eq!(entry.sound_id, 3);
+        assert_eq!(entry.vfx_id, 1);
+        assert_eq!(entry.material_animation_id, 0);
+
+        assert!(imc.get_variant(2, 0).is_none());
+        assert!(imc.get_variant(0, 2).is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
+
+        // Feeding it invalid data should not panic
+        ImcFile::from_existing(&read(d).unwrap());
+    }
+}
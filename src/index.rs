@@ -4,21 +4,77 @@
 #![allow(clippy::identity_op)]
 #![allow(unused_variables)] // for br(temp), meh
 
-use std::io::SeekFrom;
+use std::io::{Cursor, Seek, SeekFrom};
 
 use crate::common::Platform;
 use crate::crc::Jamcrc;
+use crate::sha1::Sha1;
+use crate::ByteBuffer;
 use binrw::binrw;
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 
 #[binrw]
-#[br(magic = b"SqPack\0\0")]
+#[brw(magic = b"SqPack\0\0")]
 pub struct SqPackHeader {
-    platform_id: Platform,
-    #[br(pad_before = 3)]
-    size: u32,
-    version: u32,
-    file_type: u32,
+    pub(crate) platform_id: Platform,
+    #[brw(pad_before = 3)]
+    #[br(is_big = platform_id == Platform::PS3)]
+    #[bw(is_big = *platform_id == Platform::PS3)]
+    pub(crate) size: u32,
+    #[br(is_big = platform_id == Platform::PS3)]
+    #[bw(is_big = *platform_id == Platform::PS3)]
+    pub(crate) version: u32,
+    #[br(is_big = platform_id == Platform::PS3)]
+    #[bw(is_big = *platform_id == Platform::PS3)]
+    pub(crate) file_type: u32,
+}
+
+/// The size of the SqPack header, and the index or data header that follows it.
+pub(crate) const SQPACK_HEADER_SIZE: u32 = 0x400;
+
+/// Where the SHA1 of the preceding header bytes is stored.
+const HEADER_HASH_OFFSET: usize = 0x3C0;
+
+/// The SqPack header file type for dat files.
+pub(crate) const SQPACK_FILE_TYPE_DATA: u32 = 1;
+
+/// The SqPack header file type for index files.
+pub(crate) const SQPACK_FILE_TYPE_INDEX: u32 = 2;
+
+impl SqPackHeader {
+    pub(crate) fn new(platform: Platform, file_type: u32) -> Self {
+        SqPackHeader {
+            platform_id: platform,
+            size: SQPACK_HEADER_SIZE,
+            version: 1,
+            file_type,
+        }
+    }
+}
+
+/// Serializes a header, padding it out to the full header size and storing the hash of its contents.
+pub(crate) fn write_header<T>(header: &T) -> Option<ByteBuffer>
+where
+    T: for<'a> BinWrite<Args<'a> = ()>,
+{
+    let mut buffer = ByteBuffer::new();
+    header.write_le(&mut Cursor::new(&mut buffer)).ok()?;
+
+    buffer.resize(HEADER_HASH_OFFSET, 0);
+    let hash = Sha1::from(&buffer).digest().bytes();
+    buffer.extend_from_slice(&hash);
+    buffer.resize(SQPACK_HEADER_SIZE as usize, 0);
+
+    Some(buffer)
+}
+
+/// Segment hashes are stored in 64 byte fields, and empty segments are left zeroed.
+fn segment_hash(data: &[u8]) -> [u8; 64] {
+    let mut hash = [0; 64];
+    if !data.is_empty() {
+        hash[..20].copy_from_slice(&Sha1::from(data).digest().bytes());
+    }
+    hash
 }
 
 #[binrw]
@@ -39,20 +95,21 @@ pub struct SqPackIndexHeader {
     dir_index_data_size: u32,
     dir_index_data_hash: [u8; 64],
     index_type: u32,
-    #[br(pad_before = 656)]
+    #[brw(pad_before = 656)]
     self_hash: [u8; 64],
 }
 
 #[binrw]
+#[derive(Clone)]
 pub struct IndexHashTableEntry {
     pub hash: u64,
 
     #[br(temp)]
-    #[bw(ignore)]
+    #[bw(calc = (*offset / 0x08) as u32 | (*data_file_id as u32) << 1 | *is_synonym as u32)]
     data: u32,
 
     #[br(temp)]
-    #[bw(ignore)]
+    #[bw(calc = 0)]
     padding: u32,
 
     #[br(calc = (data & 0b1) == 0b1)]
@@ -72,12 +129,12 @@ pub struct IndexHashTableEntry {
 // The folder name and the filename are split in index1 (hence why it's 64-bits and not 32-bit)
 // But in index2, its both the file and folder name in one single CRC hash.
 #[binrw]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Index2HashTableEntry {
     pub hash: u32,
 
     #[br(temp)]
-    #[bw(ignore)]
+    #[bw(calc = (*offset / 0x08) as u32 | (*data_file_id as u32) << 1 | *is_synonym as u32)]
     data: u32,
 
     #[br(calc = (data & 0b1) == 0b1)]
@@ -93,6 +150,16 @@ pub struct Index2HashTableEntry {
     pub offset: u64,
 }
 
+/// Describes which range of the hash table belongs to a folder.
+#[binrw]
+#[brw(little)]
+struct IndexFolderEntry {
+    folder_hash: u32,
+    files_offset: u32,
+    #[brw(pad_after = 4)]
+    files_size: u32,
+}
+
 #[derive(Debug)]
 pub struct IndexEntry {
     pub hash: u64,
@@ -109,8 +176,19 @@ pub struct IndexFile {
     index_header: SqPackIndexHeader,
 
     #[br(seek_before = SeekFrom::Start(index_header.index_data_offset.into()))]
+    /// The entries of this index, sorted by their hash.
     #[br(count = index_header.index_data_size / 16)]
     pub entries: Vec<IndexHashTableEntry>,
+
+    #[br(seek_before = SeekFrom::Start(index_header.synonym_data_offset.into()))]
+    #[br(count = index_header.synonym_data_size)]
+    #[bw(ignore)]
+    synonym_data: Vec<u8>,
+
+    #[br(seek_before = SeekFrom::Start(index_header.empty_block_data_offset.into()))]
+    #[br(count = index_header.empty_block_data_size)]
+    #[bw(ignore)]
+    empty_block_data: Vec<u8>,
 }
 
 #[binrw]
@@ -122,61 +200,337 @@ pub struct Index2File {
     index_header: SqPackIndexHeader,
 
     #[br(seek_before = SeekFrom::Start(index_header.index_data_offset.into()))]
+    /// The entries of this index, sorted by their hash.
     #[br(count = index_header.index_data_size / 8)]
     pub entries: Vec<Index2HashTableEntry>,
+
+    #[br(seek_before = SeekFrom::Start(index_header.synonym_data_offset.into()))]
+    #[br(count = index_header.synonym_data_size)]
+    #[bw(ignore)]
+    synonym_data: Vec<u8>,
+
+    #[br(seek_before = SeekFrom::Start(index_header.empty_block_data_offset.into()))]
+    #[br(count = index_header.empty_block_data_size)]
+    #[bw(ignore)]
+    empty_block_data: Vec<u8>,
 }
 
 const CRC: Jamcrc = Jamcrc::new();
 
+/// Game paths are hashed lowercase, and always with forward slashes.
+fn normalize_path(path: &str) -> String {
+    path.to_lowercase().replace('\\', "/")
+}
+
+/// Serializes a complete `index` file for `entries`, which must already be sorted by hash.
+pub(crate) fn write_index(
+    platform: Platform,
+    entries: &[IndexHashTableEntry],
+) -> Option<ByteBuffer> {
+    write_index_with_synonyms(platform, entries, &[])
+}
+
+/// Same as [write_index], but with `synonym_data` stored as-is in the synonym segment.
+pub(crate) fn write_index_with_synonyms(
+    platform: Platform,
+    entries: &[IndexHashTableEntry],
+    synonym_data: &[u8],
+) -> Option<ByteBuffer> {
+    write_index_with_segments(platform, entries, synonym_data, &[], 1)
+}
+
+/// Same as [write_index], but with the synonym and empty block segments stored as-is. The number of dat files is
+/// at least `number_of_data_file`, or more if an entry points past it.
+fn write_index_with_segments(
+    platform: Platform,
+    entries: &[IndexHashTableEntry],
+    synonym_data: &[u8],
+    empty_block_data: &[u8],
+    number_of_data_file: u32,
+) -> Option<ByteBuffer> {
+    let index_data_offset = SQPACK_HEADER_SIZE * 2;
+
+    let mut index_data = ByteBuffer::new();
+    {
+        let mut cursor = Cursor::new(&mut index_data);
+        for entry in entries {
+            entry.write_le(&mut cursor).ok()?;
+        }
+    }
+
+    // Files in the same folder share the upper half of their hash, so they are contiguous in the hash table
+    let mut dir_index_data = ByteBuffer::new();
+    {
+        let mut cursor = Cursor::new(&mut dir_index_data);
+        let mut files_offset = index_data_offset;
+        for folder in entries.chunk_by(|a, b| a.hash >> 32 == b.hash >> 32) {
+            let files_size = (folder.len() * 16) as u32;
+
+            IndexFolderEntry {
+                folder_hash: (folder[0].hash >> 32) as u32,
+                files_offset,
+                files_size,
+            }
+            .write(&mut cursor)
+            .ok()?;
+
+            files_offset += files_size;
+        }
+    }
+
+    let number_of_data_file = entries
+        .iter()
+        .map(|entry| entry.data_file_id as u32 + 1)
+        .fold(number_of_data_file, u32::max);
+
+    write_index_segments(
+        platform,
+        &index_data,
+        synonym_data,
+        empty_block_data,
+        &dir_index_data,
+        number_of_data_file,
+    )
+}
+
+/// Serializes a complete `index2` file for `entries`, which must already be sorted by hash. These don't have a folder
+/// table, and the synonym and empty block segments are stored as-is.
+pub(crate) fn write_index2(
+    platform: Platform,
+    entries: &[Index2HashTableEntry],
+    synonym_data: &[u8],
+    empty_block_data: &[u8],
+    number_of_data_file: u32,
+) -> Option<ByteBuffer> {
+    let mut index_data = ByteBuffer::new();
+    {
+        let mut cursor = Cursor::new(&mut index_data);
+        for entry in entries {
+            entry.write_le(&mut cursor).ok()?;
+        }
+    }
+
+    let number_of_data_file = entries
+        .iter()
+        .map(|entry| entry.data_file_id as u32 + 1)
+        .fold(number_of_data_file, u32::max);
+
+    write_index_segments(
+        platform,
+        &index_data,
+        synonym_data,
+        empty_block_data,
+        &[],
+        number_of_data_file,
+    )
+}
+
+/// Writes the headers of an index file, followed by each of its segments.
+fn write_index_segments(
+    platform: Platform,
+    index_data: &[u8],
+    synonym_data: &[u8],
+    empty_block_data: &[u8],
+    dir_index_data: &[u8],
+    number_of_data_file: u32,
+) -> Option<ByteBuffer> {
+    let index_data_offset = SQPACK_HEADER_SIZE * 2;
+    let synonym_data_offset = index_data_offset + index_data.len() as u32;
+    let empty_block_data_offset = synonym_data_offset + synonym_data.len() as u32;
+
+    let index_header = SqPackIndexHeader {
+        size: SQPACK_HEADER_SIZE,
+        version: 1,
+        index_data_offset,
+        index_data_size: index_data.len() as u32,
+        index_data_hash: segment_hash(index_data),
+        number_of_data_file,
+        synonym_data_offset,
+        synonym_data_size: synonym_data.len() as u32,
+        synonym_data_hash: segment_hash(synonym_data),
+        empty_block_data_offset,
+        empty_block_data_size: empty_block_data.len() as u32,
+        empty_block_data_hash: segment_hash(empty_block_data),
+        dir_index_data_offset: empty_block_data_offset + empty_block_data.len() as u32,
+        dir_index_data_size: dir_index_data.len() as u32,
+        dir_index_data_hash: segment_hash(dir_index_data),
+        index_type: 0,
+        self_hash: [0; 64],
+    };
+
+    let mut buffer = write_header(&SqPackHeader::new(platform, SQPACK_FILE_TYPE_INDEX))?;
+    buffer.append(&mut write_header(&index_header)?);
+    buffer.extend_from_slice(index_data);
+    buffer.extend_from_slice(synonym_data);
+    buffer.extend_from_slice(empty_block_data);
+    buffer.extend_from_slice(dir_index_data);
+
+    Some(buffer)
+}
+
+/// Points the entry for `hash` to `offset` in the dat file `data_file_id`, by patching the index or index2 file in
+/// `buffer` in place. Unlike [IndexFile::write_to_buffer], the rest of the file is kept as-is, including the synonym,
+/// empty block and folder segments. Only the hash of the hash table and the index header are updated to match.
+///
+/// Returns Some(false) if there's no entry for `hash`, and None if the file can't be read or the entry is a synonym,
+/// as those point to the synonym table instead of a dat file.
+pub(crate) fn patch_index_entry(
+    buffer: &mut [u8],
+    index2: bool,
+    hash: u64,
+    offset: u64,
+    data_file_id: u8,
+) -> Option<bool> {
+    let mut cursor = Cursor::new(&*buffer);
+    let sqpack_header = SqPackHeader::read_le(&mut cursor).ok()?;
+
+    let header_start = sqpack_header.size as usize;
+    cursor.seek(SeekFrom::Start(header_start as u64)).ok()?;
+    let mut index_header = SqPackIndexHeader::read_le(&mut cursor).ok()?;
+
+    let hash_size = if index2 { 4 } else { 8 };
+    let table_start = index_header.index_data_offset as usize;
+    let table = buffer.get_mut(table_start..table_start + index_header.index_data_size as usize)?;
+
+    let Some(entry) = table
+        .chunks_exact_mut(hash_size * 2)
+        .find(|entry| match index2 {
+            true => u32::from_le_bytes(entry[..4].try_into().unwrap()) as u64 == hash,
+            false => u64::from_le_bytes(entry[..8].try_into().unwrap()) == hash,
+        })
+    else {
+        return Some(false);
+    };
+
+    let data = &mut entry[hash_size..hash_size + 4];
+    if u32::from_le_bytes((&*data).try_into().unwrap()) & 0b1 == 0b1 {
+        return None;
+    }
+    data.copy_from_slice(&((offset / 0x08) as u32 | (data_file_id as u32) << 1).to_le_bytes());
+
+    index_header.index_data_hash = segment_hash(table);
+    buffer
+        .get_mut(header_start..header_start + SQPACK_HEADER_SIZE as usize)?
+        .copy_from_slice(&write_header(&index_header)?);
+
+    Some(true)
+}
+
 impl IndexFile {
     /// Creates a new reference to an existing index file.
     pub fn from_existing(path: &str) -> Option<Self> {
         let mut index_file = std::fs::File::open(path).ok()?;
 
-        Self::read(&mut index_file).ok()
+        let mut index = Self::read(&mut index_file).ok()?;
+        // the game writes them sorted, but lookups depend on it so don't trust that
+        if !index.entries.is_sorted_by_key(|entry| entry.hash) {
+            index.entries.sort_by_key(|entry| entry.hash);
+        }
+
+        Some(index)
     }
 
     /// Calculates a partial hash for a given path
     pub fn calculate_partial_hash(path: &str) -> u32 {
-        let lowercase = path.to_lowercase();
+        let lowercase = normalize_path(path);
 
         CRC.checksum(lowercase.as_bytes())
     }
 
-    /// Calculates a hash for `index` files from a game path.
-    pub fn calculate_hash(path: &str) -> u64 {
-        let lowercase = path.to_lowercase();
+    /// Calculates the hash of the folder portion of a game path, everything before the last `/`.
+    /// Paths without a folder hash to zero.
+    pub fn calculate_folder_hash(path: &str) -> u32 {
+        let lowercase = normalize_path(path);
 
-        if let Some(pos) = lowercase.rfind('/') {
-            let (directory, filename) = lowercase.split_at(pos);
+        match lowercase.rfind('/') {
+            Some(pos) => CRC.checksum(&lowercase.as_bytes()[..pos]),
+            None => 0,
+        }
+    }
 
-            let directory_crc = CRC.checksum(directory.as_bytes());
-            let filename_crc = CRC.checksum(filename[1..filename.len()].as_bytes());
+    /// Calculates the hash of the filename portion of a game path, everything after the last `/`.
+    pub fn calculate_filename_hash(path: &str) -> u32 {
+        let lowercase = normalize_path(path);
 
-            (directory_crc as u64) << 32 | (filename_crc as u64)
-        } else {
-            CRC.checksum(lowercase.as_bytes()) as u64
+        match lowercase.rfind('/') {
+            Some(pos) => CRC.checksum(&lowercase.as_bytes()[pos + 1..]),
+            None => CRC.checksum(lowercase.as_bytes()),
         }
     }
 
+    /// Calculates a hash for `index` files from a game path.
+    pub fn calculate_hash(path: &str) -> u64 {
+        (IndexFile::calculate_folder_hash(path) as u64) << 32
+            | (IndexFile::calculate_filename_hash(path) as u64)
+    }
+
+    /// Points the entry for `hash` to `offset` in the dat file `data_file_id`, adding a new entry if
+    /// there isn't one already. The hash table is kept sorted, as the game binary searches it.
+    pub fn update_entry(&mut self, hash: u64, offset: u64, data_file_id: u8) {
+        match self.entries.binary_search_by_key(&hash, |entry| entry.hash) {
+            Ok(index) => {
+                let entry = &mut self.entries[index];
+                entry.offset = offset;
+                entry.data_file_id = data_file_id;
+            }
+            Err(index) => self.entries.insert(
+                index,
+                IndexHashTableEntry {
+                    hash,
+                    is_synonym: false,
+                    data_file_id,
+                    offset,
+                },
+            ),
+        }
+    }
+
+    /// Writes the index back into a new buffer, regenerating its headers and folder table. The synonym and empty
+    /// block segments are kept as they were read.
+    pub fn write_to_buffer(&self) -> Option<ByteBuffer> {
+        let mut entries = self.entries.clone();
+        entries.sort_by_key(|entry| entry.hash);
+
+        write_index_with_segments(
+            self.sqpack_header.platform_id.clone(),
+            &entries,
+            &self.synonym_data,
+            &self.empty_block_data,
+            self.index_header.number_of_data_file,
+        )
+    }
+
     // TODO: turn into traits?
     pub fn exists(&self, path: &str) -> bool {
         let hash = IndexFile::calculate_hash(path);
-        self.entries.iter().any(|s| s.hash == hash)
+        self.entries
+            .binary_search_by_key(&hash, |entry| entry.hash)
+            .is_ok()
     }
 
     pub fn find_entry(&self, path: &str) -> Option<IndexEntry> {
-        let hash = IndexFile::calculate_hash(path);
-
-        if let Some(entry) = self.entries.iter().find(|s| s.hash == hash) {
-            return Some(IndexEntry {
-                hash: entry.hash,
-                data_file_id: entry.data_file_id,
-                offset: entry.offset,
-            });
-        }
+        self.find(
+            IndexFile::calculate_folder_hash(path),
+            IndexFile::calculate_filename_hash(path),
+        )
+    }
 
-        None
+    /// Finds the entry matching a folder and filename hash, see [IndexFile::calculate_folder_hash] and
+    /// [IndexFile::calculate_filename_hash].
+    pub fn find(&self, folder_hash: u32, file_hash: u32) -> Option<IndexEntry> {
+        let hash = (folder_hash as u64) << 32 | file_hash as u64;
+
+        let index = self
+            .entries
+            .binary_search_by_key(&hash, |entry| entry.hash)
+            .ok()?;
+        let entry = &self.entries[index];
+
+        Some(IndexEntry {
+            hash: entry.hash,
+            data_file_id: entry.data_file_id,
+            offset: entry.offset,
+        })
     }
 }
 
@@ -185,33 +539,58 @@ impl Index2File {
     pub fn from_existing(path: &str) -> Option<Self> {
         let mut index_file = std::fs::File::open(path).ok()?;
 
-        Self::read(&mut index_file).ok()
+        let mut index = Self::read(&mut index_file).ok()?;
+        // the game writes them sorted, but lookups depend on it so don't trust that
+        if !index.entries.is_sorted_by_key(|entry| entry.hash) {
+            index.entries.sort_by_key(|entry| entry.hash);
+        }
+
+        Some(index)
     }
 
     /// Calculates a hash for `index2` files from a game path.
     pub fn calculate_hash(path: &str) -> u32 {
-        let lowercase = path.to_lowercase();
+        let lowercase = normalize_path(path);
 
         CRC.checksum(lowercase.as_bytes())
     }
 
+    /// Writes the index2 back into a new buffer, regenerating its headers. The synonym and empty block segments are
+    /// kept as they were read.
+    pub fn write_to_buffer(&self) -> Option<ByteBuffer> {
+        let mut entries = self.entries.clone();
+        entries.sort_by_key(|entry| entry.hash);
+
+        write_index2(
+            self.sqpack_header.platform_id.clone(),
+            &entries,
+            &self.synonym_data,
+            &self.empty_block_data,
+            self.index_header.number_of_data_file,
+        )
+    }
+
     pub fn exists(&self, path: &str) -> bool {
         let hash = Index2File::calculate_hash(path);
-        self.entries.iter().any(|s| s.hash == hash)
+        self.entries
+            .binary_search_by_key(&hash, |entry| entry.hash)
+            .is_ok()
     }
 
     pub fn find_entry(&self, path: &str) -> Option<IndexEntry> {
         let hash = Index2File::calculate_hash(path);
 
-        if let Some(entry) = self.entries.iter().find(|s| s.hash == hash) {
-            return Some(IndexEntry {
-                hash: entry.hash as u64,
-                data_file_id: entry.data_file_id,
-                offset: entry.offset,
-            });
-        }
-
-        None
+        let index = self
+            .entries
+            .binary_search_by_key(&hash, |entry| entry.hash)
+            .ok()?;
+        let entry = &self.entries[index];
+
+        Some(IndexEntry {
+            hash: entry.hash as u64,
+            data_file_id: entry.data_file_id,
+            offset: entry.offset,
+        })
     }
 }
 
@@ -219,8 +598,238 @@ impl Index2File {
 mod tests {
     use std::path::PathBuf;
 
+    use binrw::Endian;
+
     use super::*;
 
+    #[test]
+    fn test_header_endianness() {
+        for platform in [Platform::Win32, Platform::PS3, Platform::PS4] {
+            let buffer =
+                write_header(&SqPackHeader::new(platform.clone(), SQPACK_FILE_TYPE_INDEX)).unwrap();
+
+            let size = &buffer[12..16];
+            match platform.endianness() {
+                Endian::Big => assert_eq!(size, SQPACK_HEADER_SIZE.to_be_bytes()),
+                Endian::Little => assert_eq!(size, SQPACK_HEADER_SIZE.to_le_bytes()),
+            }
+
+            let header = SqPackHeader::read_le(&mut Cursor::new(&buffer)).unwrap();
+            assert_eq!(header.platform_id, platform);
+            assert_eq!(header.size, SQPACK_HEADER_SIZE);
+            assert_eq!(header.file_type, SQPACK_FILE_TYPE_INDEX);
+        }
+    }
+
+    #[test]
+    fn test_split_hashes() {
+        let path = "exd/root.exl";
+        assert_eq!(
+            IndexFile::calculate_folder_hash(path),
+            IndexFile::calculate_partial_hash("exd")
+        );
+        assert_eq!(
+            IndexFile::calculate_filename_hash(path),
+            IndexFile::calculate_partial_hash("root.exl")
+        );
+        assert_eq!(
+            IndexFile::calculate_hash(path),
+            (IndexFile::calculate_folder_hash(path) as u64) << 32
+                | IndexFile::calculate_filename_hash(path) as u64
+        );
+
+        // only the last slash separates the folder
+        assert_eq!(
+            IndexFile::calculate_folder_hash("Common/Font/font1.tex"),
+            IndexFile::calculate_partial_hash("common/font")
+        );
+
+        let path = "root.exl";
+        assert_eq!(IndexFile::calculate_folder_hash(path), 0);
+        assert_eq!(
+            IndexFile::calculate_filename_hash(path),
+            IndexFile::calculate_partial_hash(path)
+        );
+        assert_eq!(
+            IndexFile::calculate_hash(path),
+            IndexFile::calculate_partial_hash(path) as u64
+        );
+    }
+
+    #[test]
+    fn test_index2_hash() {
+        assert_eq!(Index2File::calculate_hash("exd/root.exl"), 0x3E16266C);
+        assert_eq!(
+            Index2File::calculate_hash("chara/equipment/e0000/model/c0101e0000_top.mdl"),
+            0x1687C4E1
+        );
+
+        // the whole path is hashed, unlike index files
+        assert_eq!(
+            Index2File::calculate_hash("exd/root.exl"),
+            IndexFile::calculate_partial_hash("exd/root.exl")
+        );
+
+        // casing and backslashes don't matter
+        assert_eq!(
+            Index2File::calculate_hash("EXD\\Root.exl"),
+            Index2File::calculate_hash("exd/root.exl")
+        );
+        assert_eq!(
+            IndexFile::calculate_hash("EXD\\Root.exl"),
+            IndexFile::calculate_hash("exd/root.exl")
+        );
+    }
+
+    #[test]
+    fn test_unsorted_entries() {
+        let paths = ["exd/root.exl", "exd/item.exh", "bg/test.lgb", "ui/icon.tex"];
+        let mut entries: Vec<IndexHashTableEntry> = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| IndexHashTableEntry {
+                hash: IndexFile::calculate_hash(path),
+                is_synonym: false,
+                data_file_id: 0,
+                offset: 0x800 + 0x80 * i as u64,
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.hash));
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-index-unsorted-entries");
+        std::fs::create_dir_all(&d).unwrap();
+        let path = d.join("0a0000.win32.index");
+
+        std::fs::write(&path, write_index(Platform::Win32, &entries).unwrap()).unwrap();
+        let index = IndexFile::from_existing(path.to_str().unwrap()).unwrap();
+
+        assert!(index.entries.is_sorted_by_key(|entry| entry.hash));
+        for (i, path) in paths.iter().enumerate() {
+            assert!(index.exists(path));
+            assert_eq!(
+                index.find_entry(path).unwrap().offset,
+                0x800 + 0x80 * i as u64
+            );
+        }
+        assert!(!index.exists("exd/missing.exh"));
+    }
+
+    #[test]
+    fn test_update_entry() {
+        let mut entries: Vec<IndexHashTableEntry> = ["exd/root.exl", "exd/item.exh", "bg/test.lgb"]
+            .iter()
+            .enumerate()
+            .map(|(i, path)| IndexHashTableEntry {
+                hash: IndexFile::calculate_hash(path),
+                is_synonym: false,
+                data_file_id: 0,
+                offset: 0x800 + 0x80 * i as u64,
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.hash);
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-index-update-entry");
+        std::fs::create_dir_all(&d).unwrap();
+        let path = d.join("0a0000.win32.index");
+
+        std::fs::write(&path, write_index(Platform::Win32, &entries).unwrap()).unwrap();
+        let mut index = IndexFile::from_existing(path.to_str().unwrap()).unwrap();
+
+        index.update_entry(IndexFile::calculate_hash("exd/item.exh"), 0x1000, 2);
+        index.update_entry(IndexFile::calculate_hash("exd/new.exh"), 0x1080, 1);
+
+        std::fs::write(&path, index.write_to_buffer().unwrap()).unwrap();
+        let index = IndexFile::from_existing(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(index.entries.len(), 4);
+        assert!(index.entries.is_sorted_by_key(|entry| entry.hash));
+
+        let entry = index.find_entry("exd/item.exh").unwrap();
+        assert_eq!(entry.offset, 0x1000);
+        assert_eq!(entry.data_file_id, 2);
+
+        let entry = index.find_entry("exd/new.exh").unwrap();
+        assert_eq!(entry.offset, 0x1080);
+        assert_eq!(entry.data_file_id, 1);
+
+        // the others are untouched
+        let entry = index.find_entry("exd/root.exl").unwrap();
+        assert_eq!(entry.offset, 0x800);
+        assert_eq!(entry.data_file_id, 0);
+
+        let entry = index.find_entry("bg/test.lgb").unwrap();
+        assert_eq!(entry.offset, 0x900);
+        assert_eq!(entry.data_file_id, 0);
+    }
+
+    #[test]
+    fn test_round_trip_segments() {
+        let mut entries: Vec<IndexHashTableEntry> = ["exd/root.exl", "exd/item.exh"]
+            .iter()
+            .map(|path| IndexHashTableEntry {
+                hash: IndexFile::calculate_hash(path),
+                is_synonym: false,
+                data_file_id: 0,
+                offset: 0x800,
+            })
+            .collect();
+        entries.push(IndexHashTableEntry {
+            hash: IndexFile::calculate_hash("exd/synonym.exh"),
+            is_synonym: true,
+            data_file_id: 0,
+            offset: 0,
+        });
+        entries.sort_by_key(|entry| entry.hash);
+
+        let index2_entries = vec![Index2HashTableEntry {
+            hash: Index2File::calculate_hash("exd/synonym.exh"),
+            is_synonym: true,
+            data_file_id: 0,
+            offset: 0,
+        }];
+
+        let synonym_data = vec![0xAB; 0x100];
+        let empty_block_data = vec![0xCD; 0x10];
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-index-round-trip-segments");
+        std::fs::create_dir_all(&d).unwrap();
+
+        let index_path = d.join("0a0000.win32.index");
+        let original = write_index_with_segments(
+            Platform::Win32,
+            &entries,
+            &synonym_data,
+            &empty_block_data,
+            3,
+        )
+        .unwrap();
+        std::fs::write(&index_path, &original).unwrap();
+
+        let index = IndexFile::from_existing(index_path.to_str().unwrap()).unwrap();
+        assert!(index.entries.iter().any(|entry| entry.is_synonym));
+        assert_eq!(index.write_to_buffer().unwrap(), original);
+
+        let index2_path = d.join("0a0000.win32.index2");
+        let original = write_index2(
+            Platform::Win32,
+            &index2_entries,
+            &synonym_data,
+            &empty_block_data,
+            3,
+        )
+        .unwrap();
+        std::fs::write(&index2_path, &original).unwrap();
+
+        let index2 = Index2File::from_existing(index2_path.to_str().unwrap()).unwrap();
+        assert!(index2.entries[0].is_synonym);
+        assert_eq!(index2.write_to_buffer().unwrap(), original);
+
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
     #[test]
     fn test_index_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -9,37 +9,19 @@ use binrw::{binread, BinRead, BinReaderExt};
 
 // From https://github.com/NotAdam/Lumina/tree/40dab50183eb7ddc28344378baccc2d63ae71d35/src/Lumina/Data/Parsing/Layer
 
-// TODO: convert these all to magic
+/// The type of an object placed in a layer.
 #[binrw]
+#[brw(repr = i32)]
 #[repr(i32)]
-#[derive(Debug, PartialEq)]
-enum LayerEntryType {
-    #[brw(magic = 0x0i32)]
-    AssetNone,
-    #[brw(magic = 0x1i32)]
-    BG {
-        asset_path_offset: u32,
-        collision_asset_path_offset: i32,
-        collision_type: ModelCollisionType,
-        attribute_mask: u32,
-        attribute: u32,
-        collision_config: u32,
-        is_visible: u8,
-        render_shadow_enabled: u8,
-        render_light_shadow_enabled: u8,
-        padding: u8,
-        render_model_clip_range: f32,
-    },
-    #[brw(magic = 0x2i32)]
-    Attribute,
-    #[brw(magic = 0x3i32)]
-    LayLight,
-    #[brw(magic = 0x4i32)]
-    Vfx,
-    #[brw(magic = 0x5i32)]
-    PositionMarker,
-    #[brw(magic = 0x6i32)]
-    SharedGroup,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerEntryType {
+    AssetNone = 0x0,
+    BG = 0x1,
+    Attribute = 0x2,
+    LayLight = 0x3,
+    Vfx = 0x4,
+    PositionMarker = 0x5,
+    SharedGroup = 0x6,
     Sound = 0x7,     //  //
     EventNPC = 0x8,  //  //
     BattleNPC = 0x9, //  //
@@ -165,19 +147,21 @@ enum TriggerBoxShape {
     BoardBothSides = 0x6,
 }
 
+/// How a BG model collides with the world.
 #[binrw]
 #[brw(repr = i32)]
-#[derive(Debug, PartialEq)]
-enum ModelCollisionType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelCollisionType {
     None = 0x0,
     Replace = 0x1,
     Box = 0x2,
 }
 
+/// The shape of a light.
 #[binrw]
-#[brw(repr = u8)]
-#[derive(Debug, PartialEq)]
-enum LightType {
+#[brw(repr = i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightType {
     None = 0x0,
     Directional = 0x1,
     Point = 0x2,
@@ -187,10 +171,11 @@ enum LightType {
     Specular = 0x6,
 }
 
+/// The shape of a point light.
 #[binrw]
-#[brw(repr = u8)]
-#[derive(Debug, PartialEq)]
-enum PointLightType {
+#[brw(repr = i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointLightType {
     Sphere = 0x0,
     Hemisphere = 0x1,
 }
@@ -441,91 +426,510 @@ struct LayerChunk {
     layer_count: i32,
 }
 
+/// The position, rotation and scale of an object.
+#[binread]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[br(little)]
+pub struct Transformation {
+    pub translation: [f32; 3],
+    /// Euler angles in radians.
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
 #[binread]
 #[derive(Debug)]
 #[br(little)]
-#[allow(dead_code)] // most of the fields are unused at the moment
-struct InstanceObject {
+struct InstanceObjectHeader {
     asset_type: LayerEntryType,
     instance_id: u32,
     name_offset: u32,
+    transform: Transformation,
 }
 
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+struct BGInstanceObjectData {
+    asset_path_offset: u32,
+    collision_asset_path_offset: u32,
+    collision_type: ModelCollisionType,
+    attribute_mask: u32,
+    attribute: u32,
+    collision_config: u32,
+    is_visible: u8,
+    render_shadow_enabled: u8,
+    #[br(pad_after = 1)]
+    render_light_shadow_enabled: u8,
+    render_model_clip_range: f32,
+}
+
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+struct LightInstanceObjectData {
+    light_type: LightType,
+    attenuation: f32,
+    range_rate: f32,
+    point_light_type: PointLightType,
+    attenuation_cone_coefficient: f32,
+    cone_degree: f32,
+    texture_path_offset: u32,
+    diffuse_color: [u8; 4],
+    diffuse_intensity: f32,
+}
+
+#[binread]
 #[derive(Debug)]
-pub struct Layer {}
+#[br(little)]
+struct VfxInstanceObjectData {
+    asset_path_offset: u32,
+    #[br(pad_after = 4)]
+    soft_particle_fade_range: f32,
+    color: [u8; 4],
+    auto_play: u8,
+    #[br(pad_after = 2)]
+    no_far_clip: u8,
+}
+
+/// A placed BG model.
+#[derive(Debug)]
+pub struct BGInstanceObject {
+    /// Path to the model, such as "bg/ffxiv/sea_s1/twn/s1t1/bgparts/s1t1_t1_bbd1.mdl".
+    pub asset_path: String,
+    /// Path to the collision mesh (PCB), which may be empty.
+    pub collision_asset_path: String,
+    pub collision_type: ModelCollisionType,
+    pub attribute_mask: u32,
+    pub attribute: u32,
+    pub collision_config: u32,
+    pub is_visible: bool,
+    pub render_shadow_enabled: bool,
+    pub render_light_shadow_enabled: bool,
+    pub render_model_clip_range: f32,
+}
+
+/// A placed light.
+#[derive(Debug)]
+pub struct LightInstanceObject {
+    pub light_type: LightType,
+    pub attenuation: f32,
+    pub range_rate: f32,
+    pub point_light_type: PointLightType,
+    pub attenuation_cone_coefficient: f32,
+    pub cone_degree: f32,
+    /// Path to the light's texture, which may be empty.
+    pub texture_path: String,
+    /// In RGBA order.
+    pub diffuse_color: [u8; 4],
+    pub diffuse_intensity: f32,
+}
+
+/// A placed visual effect.
+#[derive(Debug)]
+pub struct VfxInstanceObject {
+    /// Path to the effect (AVFX).
+    pub asset_path: String,
+    pub soft_particle_fade_range: f32,
+    /// In RGBA order.
+    pub color: [u8; 4],
+    pub auto_play: bool,
+    pub no_far_clip: bool,
+}
+
+/// A placed shared group, whose objects are in a separate SGB file.
+#[derive(Debug)]
+pub struct SharedGroupInstanceObject {
+    /// Path to the shared group, such as "bg/ffxiv/sea_s1/twn/common/sgb/s1t0_a0_plnt1.sgb".
+    pub asset_path: String,
+}
+
+/// A placed event object, such as a door or a quest item.
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+pub struct EventInstanceObject {
+    /// Row of the EObj sheet.
+    pub base_id: u32,
+    pub bound_instance_id: u32,
+    pub linked_instance_id: u32,
+}
+
+/// The type-specific data of an object.
+#[derive(Debug)]
+pub enum LayerEntryData {
+    BG(BGInstanceObject),
+    LayLight(LightInstanceObject),
+    Vfx(VfxInstanceObject),
+    SharedGroup(SharedGroupInstanceObject),
+    EventObject(EventInstanceObject),
+    /// An object type that isn't parsed yet, see [InstanceObject::asset_type].
+    Unknown,
+}
+
+/// An object placed in a layer.
+#[derive(Debug)]
+pub struct InstanceObject {
+    pub asset_type: LayerEntryType,
+    pub instance_id: u32,
+    pub name: String,
+    pub transform: Transformation,
+    pub data: LayerEntryData,
+}
+
+/// A layer, which is a named collection of objects.
+#[derive(Debug)]
+pub struct Layer {
+    pub id: u32,
+    pub name: String,
+    pub objects: Vec<InstanceObject>,
+}
+
+/// Layer group (LGB) files, which place objects in a zone.
+#[derive(Debug)]
+pub struct LayerGroup {
+    pub layers: Vec<Layer>,
+}
+
+/// Reads a null-terminated string.
+pub(crate) fn read_string(buffer: ByteSpan, offset: u64) -> Option<String> {
+    let bytes = buffer.get(offset as usize..)?;
+    let length = bytes.iter().position(|x| *x == 0)?;
+
+    Some(String::from_utf8_lossy(&bytes[..length]).to_string())
+}
 
-impl Layer {
-    /// Reads an existing PBD file
-    pub fn from_existing(buffer: ByteSpan) -> Option<Layer> {
+impl LayerGroup {
+    /// Reads an existing LGB file
+    pub fn from_existing(buffer: ByteSpan) -> Option<LayerGroup> {
         let mut cursor = Cursor::new(buffer);
 
-        let file_header = LgbHeader::read(&mut cursor).unwrap();
+        let file_header = LgbHeader::read(&mut cursor).ok()?;
         if file_header.file_size < 0 || file_header.total_chunk_count < 0 {
             return None;
         }
 
-        let chunk_header = LayerChunk::read(&mut cursor).unwrap();
+        let chunk_header = LayerChunk::read(&mut cursor).ok()?;
 
         let old_pos = cursor.position();
 
-        let mut layer_offsets = vec![0i32; chunk_header.layer_count as usize];
-        for i in 0..chunk_header.layer_count {
-            layer_offsets[i as usize] = cursor.read_le::<i32>().unwrap();
+        let mut layer_offsets = vec![];
+        for _ in 0..chunk_header.layer_count {
+            layer_offsets.push(cursor.read_le::<i32>().ok()?);
         }
 
-        for i in 0..chunk_header.layer_count {
-            cursor
-                .seek(SeekFrom::Start(old_pos + layer_offsets[i as usize] as u64))
-                .unwrap();
-
-            let old_pos = cursor.position();
-
-            let header = LayerHeader::read(&mut cursor).unwrap();
+        let mut layers = vec![];
 
+        for layer_offset in layer_offsets {
             cursor
                 .seek(SeekFrom::Start(
-                    old_pos + header.instance_object_offset as u64,
+                    old_pos.checked_add_signed(layer_offset as i64)?,
                 ))
-                .unwrap();
+                .ok()?;
 
-            let mut instance_offsets = vec![0i32; header.instance_object_count as usize];
-            for i in 0..header.instance_object_count {
-                instance_offsets[i as usize] = cursor.read_le::<i32>().unwrap();
-            }
+            layers.push(Self::read_layer(buffer, &mut cursor)?);
+        }
+
+        Some(LayerGroup { layers })
+    }
+
+    pub(crate) fn read_layer(buffer: ByteSpan, cursor: &mut Cursor<ByteSpan>) -> Option<Layer> {
+        let old_pos = cursor.position();
+
+        let header = LayerHeader::read(cursor).ok()?;
+
+        let instances_start = old_pos.checked_add_signed(header.instance_object_offset as i64)?;
+        cursor.seek(SeekFrom::Start(instances_start)).ok()?;
+
+        let mut instance_offsets = vec![];
+        for _ in 0..header.instance_object_count {
+            instance_offsets.push(cursor.read_le::<i32>().ok()?);
+        }
+
+        cursor
+            .seek(SeekFrom::Start(old_pos.checked_add_signed(
+                header.layer_set_referenced_list_offset as i64,
+            )?))
+            .ok()?;
+        LayerSetReferencedList::read(cursor).ok()?;
 
+        let mut objects = vec![];
+
+        for instance_offset in instance_offsets {
             cursor
                 .seek(SeekFrom::Start(
-                    old_pos + header.layer_set_referenced_list_offset as u64,
+                    instances_start.checked_add_signed(instance_offset as i64)?,
                 ))
-                .unwrap();
-            LayerSetReferencedList::read(&mut cursor).unwrap();
-
-            for i in 0..header.instance_object_count {
-                cursor
-                    .seek(SeekFrom::Start(
-                        old_pos
-                            + header.instance_object_offset as u64
-                            + instance_offsets[i as usize] as u64,
-                    ))
-                    .unwrap();
-
-                InstanceObject::read(&mut cursor).unwrap();
-            }
+                .ok()?;
+
+            objects.push(Self::read_instance_object(buffer, cursor)?);
         }
 
         Some(Layer {
-            //header
+            id: header.layer_id,
+            name: read_string(buffer, old_pos + header.name_offset as u64)?,
+            objects,
+        })
+    }
+
+    fn read_instance_object(
+        buffer: ByteSpan,
+        cursor: &mut Cursor<ByteSpan>,
+    ) -> Option<InstanceObject> {
+        // offsets to strings are relative to the start of the object
+        let old_pos = cursor.position();
+
+        let header = InstanceObjectHeader::read(cursor).ok()?;
+
+        let data = match header.asset_type {
+            LayerEntryType::BG => {
+                let data = BGInstanceObjectData::read(cursor).ok()?;
+
+                LayerEntryData::BG(BGInstanceObject {
+                    asset_path: read_string(buffer, old_pos + data.asset_path_offset as u64)?,
+                    collision_asset_path: read_string(
+                        buffer,
+                        old_pos + data.collision_asset_path_offset as u64,
+                    )?,
+                    collision_type: data.collision_type,
+                    attribute_mask: data.attribute_mask,
+                    attribute: data.attribute,
+                    collision_config: data.collision_config,
+                    is_visible: data.is_visible != 0,
+                    render_shadow_enabled: data.render_shadow_enabled != 0,
+                    render_light_shadow_enabled: data.render_light_shadow_enabled != 0,
+                    render_model_clip_range: data.render_model_clip_range,
+                })
+            }
+            LayerEntryType::LayLight => {
+                let data = LightInstanceObjectData::read(cursor).ok()?;
+
+                LayerEntryData::LayLight(LightInstanceObject {
+                    light_type: data.light_type,
+                    attenuation: data.attenuation,
+                    range_rate: data.range_rate,
+                    point_light_type: data.point_light_type,
+                    attenuation_cone_coefficient: data.attenuation_cone_coefficient,
+                    cone_degree: data.cone_degree,
+                    texture_path: read_string(buffer, old_pos + data.texture_path_offset as u64)?,
+                    diffuse_color: data.diffuse_color,
+                    diffuse_intensity: data.diffuse_intensity,
+                })
+            }
+            LayerEntryType::Vfx => {
+                let data = VfxInstanceObjectData::read(cursor).ok()?;
+
+                LayerEntryData::Vfx(VfxInstanceObject {
+                    asset_path: read_string(buffer, old_pos + data.asset_path_offset as u64)?,
+                    soft_particle_fade_range: data.soft_particle_fade_range,
+                    color: data.color,
+                    auto_play: data.auto_play != 0,
+                    no_far_clip: data.no_far_clip != 0,
+                })
+            }
+            LayerEntryType::SharedGroup => {
+                let asset_path_offset = cursor.read_le::<u32>().ok()?;
+
+                LayerEntryData::SharedGroup(SharedGroupInstanceObject {
+                    asset_path: read_string(buffer, old_pos + asset_path_offset as u64)?,
+                })
+            }
+            LayerEntryType::EventObject => {
+                LayerEntryData::EventObject(EventInstanceObject::read(cursor).ok()?)
+            }
+            _ => LayerEntryData::Unknown,
+        };
+
+        Some(InstanceObject {
+            asset_type: header.asset_type,
+            instance_id: header.instance_id,
+            name: read_string(buffer, old_pos + header.name_offset as u64)?,
+            transform: header.transform,
+            data,
         })
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use std::fs::read;
     use std::path::PathBuf;
 
     use super::*;
 
+    pub(crate) fn push_u32s(buffer: &mut Vec<u8>, values: &[u32]) {
+        for value in values {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    pub(crate) fn push_f32s(buffer: &mut Vec<u8>, values: &[f32]) {
+        for value in values {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Builds an instance object, with its name followed by `strings` after the type-specific `data`.
+    pub(crate) fn build_instance(
+        asset_type: u32,
+        instance_id: u32,
+        name: &str,
+        data: impl Fn(&[u32]) -> Vec<u8>,
+        strings: &[&str],
+    ) -> Vec<u8> {
+        // the header and transform are 48 bytes, and the data is the same size regardless of the offsets
+        let data_size = data(&vec![0; strings.len()]).len();
+
+        let mut string_offsets = vec![];
+        let mut string_data = vec![];
+        for string in std::iter::once(&name).chain(strings) {
+            string_offsets.push((48 + data_size + string_data.len()) as u32);
+            string_data.extend_from_slice(string.as_bytes());
+            string_data.push(0);
+        }
+
+        let mut buffer = vec![];
+        push_u32s(&mut buffer, &[asset_type, instance_id, string_offsets[0]]);
+        push_f32s(&mut buffer, &[1.0, 2.0, 3.0, 0.0, 1.5, 0.0, 1.0, 1.0, 1.0]);
+        buffer.extend_from_slice(&data(&string_offsets[1..]));
+        buffer.extend_from_slice(&string_data);
+
+        buffer
+    }
+
+    /// Builds a layer named "Layer" containing `instances`.
+    pub(crate) fn build_layer(layer_id: u32, instances: &[Vec<u8>]) -> Vec<u8> {
+        let instance_offsets_size = instances.len() as u32 * 4;
+        let instances_size: u32 = instances.iter().map(|x| x.len() as u32).sum();
+
+        // layer header, followed by the layer set list and the instance offsets
+        let mut buffer = vec![];
+        push_u32s(
+            &mut buffer,
+            &[layer_id, 64 + instance_offsets_size + instances_size, 64],
+        );
+        push_u32s(&mut buffer, &[instances.len() as u32, 0, 52]);
+        buffer.resize(64, 0);
+
+        let mut instance_offset = instance_offsets_size;
+        for instance in instances {
+            push_u32s(&mut buffer, &[instance_offset]);
+            instance_offset += instance.len() as u32;
+        }
+        for instance in instances {
+            buffer.extend_from_slice(instance);
+        }
+        buffer.extend_from_slice(b"Layer\0");
+
+        buffer
+    }
+
+    /// Where the layer starts in a file from [build_lgb].
+    const LAYER_START: usize = 40;
+
+    /// Builds an LGB file containing a single `layer`.
+    fn build_lgb(layer: &[u8]) -> Vec<u8> {
+        let mut buffer = vec![];
+        buffer.extend_from_slice(b"LGB1");
+        push_u32s(&mut buffer, &[0, 1]);
+        buffer.extend_from_slice(b"LGP1");
+        push_u32s(&mut buffer, &[0, 0, 0, 0, 1]);
+
+        // the only layer comes right after its offset
+        push_u32s(&mut buffer, &[4]);
+        buffer.extend_from_slice(layer);
+
+        buffer
+    }
+
+    #[test]
+    fn test_parsing() {
+        let bg = build_instance(
+            0x1,
+            100,
+            "bg_object",
+            |offsets| {
+                let mut data = vec![];
+                push_u32s(&mut data, &[offsets[0], offsets[1], 0x2, 0, 0, 0]);
+                data.extend_from_slice(&[1, 1, 0, 0]);
+                push_f32s(&mut data, &[50.0]);
+                data
+            },
+            &["bg/ffxiv/test/bgparts/test.mdl", ""],
+        );
+        let event_object = build_instance(
+            0x2D,
+            101,
+            "door",
+            |_| {
+                let mut data = vec![];
+                push_u32s(&mut data, &[2000, 5, 6]);
+                data
+            },
+            &[],
+        );
+        let sound = build_instance(0x7, 102, "sound", |_| vec![], &[]);
+
+        let buffer = build_lgb(&build_layer(7, &[bg, event_object, sound]));
+
+        let lgb = LayerGroup::from_existing(&buffer).unwrap();
+        assert_eq!(lgb.layers.len(), 1);
+
+        let layer = &lgb.layers[0];
+        assert_eq!(layer.id, 7);
+        assert_eq!(layer.name, "Layer");
+        assert_eq!(layer.objects.len(), 3);
+
+        let object = &layer.objects[0];
+        assert_eq!(object.asset_type, LayerEntryType::BG);
+        assert_eq!(object.instance_id, 100);
+        assert_eq!(object.name, "bg_object");
+        assert_eq!(object.transform.translation, [1.0, 2.0, 3.0]);
+        assert_eq!(object.transform.rotation, [0.0, 1.5, 0.0]);
+        assert_eq!(object.transform.scale, [1.0, 1.0, 1.0]);
+        let LayerEntryData::BG(bg) = &object.data else {
+            panic!("Expected a BG object");
+        };
+        assert_eq!(bg.asset_path, "bg/ffxiv/test/bgparts/test.mdl");
+        assert_eq!(bg.collision_asset_path, "");
+        assert_eq!(bg.collision_type, ModelCollisionType::Box);
+        assert!(bg.is_visible);
+        assert!(bg.render_shadow_enabled);
+        assert!(!bg.render_light_shadow_enabled);
+        assert_eq!(bg.render_model_clip_range, 50.0);
+
+        let object = &layer.objects[1];
+        assert_eq!(object.name, "door");
+        let LayerEntryData::EventObject(event_object) = &object.data else {
+            panic!("Expected an event object");
+        };
+        assert_eq!(event_object.base_id, 2000);
+        assert_eq!(event_object.bound_instance_id, 5);
+        assert_eq!(event_object.linked_instance_id, 6);
+
+        let object = &layer.objects[2];
+        assert_eq!(object.asset_type, LayerEntryType::Sound);
+        assert!(matches!(object.data, LayerEntryData::Unknown));
+    }
+
+    #[test]
+    fn test_negative_offsets() {
+        let sound = build_instance(0x7, 102, "sound", |_| vec![], &[]);
+        let lgb = build_lgb(&build_layer(7, &[sound]));
+        assert!(LayerGroup::from_existing(&lgb).is_some());
+
+        let with_offset = |position: usize| {
+            let mut lgb = lgb.clone();
+            lgb[position..position + 4].copy_from_slice(&(-1000i32).to_le_bytes());
+            lgb
+        };
+
+        // the layer, the list of instances and the instance itself point before the start of the file
+        assert!(LayerGroup::from_existing(&with_offset(LAYER_START - 4)).is_none());
+        assert!(LayerGroup::from_existing(&with_offset(LAYER_START + 8)).is_none());
+        assert!(LayerGroup::from_existing(&with_offset(LAYER_START + 64)).is_none());
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -533,6 +937,6 @@ mod tests {
         d.push("random");
 
         // Feeding it invalid data should not panic
-        Layer::from_existing(&read(d).unwrap());
+        LayerGroup::from_existing(&read(d).unwrap());
     }
 }
@@ -28,6 +28,7 @@ pub mod index;
 
 mod compression;
 mod dat;
+pub use dat::{DatError, DatFile, DatFileReader, DatHeaderInfo, RawBlock};
 
 /// Reading model (MDL) files.
 #[cfg(feature = "visual_data")]
@@ -63,6 +64,18 @@ pub mod exh;
 /// Reading Excel data files (EXD).
 pub mod exd;
 
+/// Decoding the rich text strings (SeString) used by Excel sheets.
+pub mod sestring;
+
+/// Typed accessors for commonly used Excel sheets, built on top of [gamedata::GameData::read_excel_sheet].
+pub mod sheets;
+
+#[cfg(test)]
+mod excel_fixtures;
+
+#[cfg(test)]
+mod sqpack_fixtures;
+
 /// Reading Havok XML sidecar files.
 #[cfg(feature = "visual_data")]
 pub mod skeleton;
@@ -130,7 +143,7 @@ pub const PHYSIS_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Reading ULD files
 pub mod uld;
 
-/// Reading SGB files
+/// Reading shared group (SGB) files
 pub mod sgb;
 
 /// Reading SCD files
@@ -168,3 +181,13 @@ pub mod existing_dirs;
 
 /// Reading patch lists
 pub mod patchlist;
+
+/// Reading image change files (IMC)
+pub mod imc;
+
+/// Reading equipment deformer parameter files (EQDP)
+pub mod eqdp;
+
+/// Reading the equipment parameter file (EQP)
+#[cfg(feature = "visual_data")]
+pub mod eqp;
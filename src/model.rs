@@ -3,26 +3,67 @@
 
 #![allow(clippy::unnecessary_fallible_conversions)] // This wrongly trips on binrw code
 
-use std::io::{Cursor, Seek, SeekFrom};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::mem::size_of;
 
 use binrw::BinRead;
 use binrw::BinReaderExt;
-use binrw::{binrw, BinWrite, BinWriterExt};
-use tracing::warn;
+use binrw::{binrw, BinWrite, BinWriterExt, Endian};
+use tracing::info_span;
 
+use crate::common::Platform;
 use crate::common_file_operations::{read_bool_from, write_bool_as};
 use crate::model_vertex_declarations::{
-    vertex_element_parser, vertex_element_writer, VertexDeclaration, VertexType, VertexUsage,
-    VERTEX_ELEMENT_SIZE,
+    get_vertex_type_size, vertex_element_parser, vertex_element_writer, VertexDeclaration,
+    VertexElement, VertexType, VertexUsage, VERTEX_ELEMENT_SIZE,
 };
 use crate::{ByteBuffer, ByteSpan};
 
 pub const NUM_VERTICES: u32 = 17;
 
+/// The model version used before Dawntrail.
+pub const MDL_VERSION_5: u32 = 0x1000005;
+/// The model version introduced in Dawntrail, which widened the submesh bone map.
+pub const MDL_VERSION_6: u32 = 0x1000006;
+
+/// The maximum number of LODs a model can have.
+pub const MAX_LODS: u8 = 3;
+
+#[derive(Debug, PartialEq)]
+/// Errors emitted while parsing a model
+pub enum ModelError {
+    /// The file header couldn't be read
+    InvalidHeader,
+    /// The model version isn't [MDL_VERSION_5] or [MDL_VERSION_6], such as older models from the 1.x or benchmark
+    /// clients which have a different layout. Contains the version that was read.
+    UnsupportedVersion(u32),
+    /// The model has more LODs than [MAX_LODS]
+    UnsupportedLodCount(u8),
+    /// Failed to parse the model data following the header
+    ParseError,
+    /// A mesh's vertex declaration refers to a stream that doesn't exist, or has no stride
+    InvalidVertexStream {
+        /// The index of the mesh in the model
+        mesh: u16,
+        /// The stream that was referenced
+        stream: u8,
+    },
+    /// A vertex element can't be written from [Vertex], because of its usage or type
+    UnsupportedVertexElement(VertexElement),
+    /// A submesh covers indices past the end of its part's index buffer
+    InvalidSubmesh {
+        /// The index of the submesh in the list that was passed in
+        submesh: usize,
+        /// The offset of its first index
+        index_offset: u32,
+        /// The number of indices it covers
+        index_count: u32,
+    },
+}
+
 #[binrw]
 #[derive(Debug, Clone, PartialEq)]
-#[brw(little)]
 pub struct ModelFileHeader {
     pub version: u32,
 
@@ -168,6 +209,49 @@ struct MeshLod {
     index_data_offset: u32,
 }
 
+impl MeshLod {
+    /// An LOD with `mesh_count` meshes starting at `mesh_index`, and nothing else. Its buffers are filled in by
+    /// [MDL::update_headers].
+    fn empty(mesh_index: u16, mesh_count: u16) -> MeshLod {
+        MeshLod {
+            mesh_index,
+            mesh_count,
+            model_lod_range: 0.0,
+            texture_lod_range: 0.0,
+            water_mesh_index: mesh_index + mesh_count,
+            water_mesh_count: 0,
+            shadow_mesh_index: mesh_index + mesh_count,
+            shadow_mesh_count: 0,
+            terrain_shadow_mesh_count: 0,
+            terrain_shadow_mesh_index: 0,
+            vertical_fog_mesh_index: mesh_index + mesh_count,
+            vertical_fog_mesh_count: 0,
+            edge_geometry_size: 0,
+            edge_geometry_data_offset: 0,
+            polygon_count: 0,
+            vertex_buffer_size: 0,
+            index_buffer_size: 0,
+            vertex_data_offset: 0,
+            index_data_offset: 0,
+        }
+    }
+
+    /// The end of every kind of mesh used by this LOD, ignoring the kinds it has none of.
+    fn mesh_end(&self) -> u16 {
+        [
+            (self.mesh_index, self.mesh_count),
+            (self.water_mesh_index, self.water_mesh_count),
+            (self.shadow_mesh_index, self.shadow_mesh_count),
+            (self.vertical_fog_mesh_index, self.vertical_fog_mesh_count),
+        ]
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(index, count)| index + count)
+        .max()
+        .unwrap_or(0)
+    }
+}
+
 #[binrw]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
@@ -290,7 +374,7 @@ struct ShapeValue {
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 #[br(import {file_header: &ModelFileHeader})]
-#[brw(little)]
+#[bw(import {file_header: &ModelFileHeader})]
 pub struct ModelData {
     #[br(args { vertex_declaration_count: file_header.vertex_declaration_count })]
     pub header: ModelHeader,
@@ -323,11 +407,11 @@ pub struct ModelData {
     bone_name_offsets: Vec<u32>,
 
     #[br(count = header.bone_table_count)]
-    #[br(if(file_header.version <= 0x1000005))]
+    #[br(if(file_header.version <= MDL_VERSION_5))]
     bone_tables: Vec<BoneTable>,
 
     #[br(count = header.bone_table_count)]
-    #[br(if(file_header.version >= 0x1000006))]
+    #[br(if(file_header.version >= MDL_VERSION_6))]
     bone_tables_v2: Vec<BoneTableV2>,
 
     #[br(count = header.shape_count)]
@@ -340,14 +424,14 @@ pub struct ModelData {
     shape_values: Vec<ShapeValue>,
 
     // TODO: try to unify these fields?
-    #[br(if(file_header.version <= 0x1000005))]
+    #[brw(if(file_header.version <= MDL_VERSION_5))]
     submesh_bone_map_size: u32,
 
     // hehe, Dawntrail made this u16 instead of u32. fun?
-    #[br(if(file_header.version >= 0x1000006))]
+    #[brw(if(file_header.version >= MDL_VERSION_6))]
     submesh_bone_map_size_v2: u16,
 
-    #[br(count = if file_header.version >= 0x1000006 { (submesh_bone_map_size_v2 / 2) as u32 } else { submesh_bone_map_size / 2 } )]
+    #[br(count = if file_header.version >= MDL_VERSION_6 { (submesh_bone_map_size_v2 / 2) as u32 } else { submesh_bone_map_size / 2 } )]
     submesh_bone_map: Vec<u16>,
 
     padding_amount: u8,
@@ -387,6 +471,55 @@ pub struct Vertex {
 
     pub bone_weight: [f32; 4],
     pub bone_id: [u8; 4],
+
+    /// Weights for the 5th to 8th bone influences, only used if the vertex declaration has a second blend weight stream.
+    pub bone_weight2: [f32; 4],
+    /// Bone IDs for the 5th to 8th bone influences, only used if the vertex declaration has a second blend index stream.
+    pub bone_id2: [u8; 4],
+}
+
+/// Reads the null-terminated string at `offset` in the string table. Returns None if the offset is past the end of the
+/// table, or the string isn't terminated before it ends.
+fn read_cstring(strings: &[u8], offset: usize) -> Option<String> {
+    let string = strings.get(offset..)?;
+    let length = string.iter().position(|x| *x == 0)?;
+
+    Some(String::from_utf8_lossy(&string[..length]).to_string())
+}
+
+/// How close two vertex attributes have to be for [MDL::optimize_part] to consider them the same.
+const WELD_EPSILON: f32 = 1e-5;
+
+impl Vertex {
+    /// Quantizes every attribute of this vertex, so vertices that only differ by less than [WELD_EPSILON] end up with the same key.
+    fn weld_key(&self) -> Vec<i64> {
+        let quantize = |x: &f32| (x / WELD_EPSILON).round() as i64;
+
+        self.position
+            .iter()
+            .chain(&self.uv0)
+            .chain(&self.uv1)
+            .chain(&self.normal)
+            .chain(&self.bitangent)
+            .chain(&self.color)
+            .chain(&self.bone_weight)
+            .chain(&self.bone_weight2)
+            .map(quantize)
+            .chain(self.bone_id.iter().chain(&self.bone_id2).map(|x| *x as i64))
+            .collect()
+    }
+
+    /// Rescales all eight bone weights so they add up to 1.0. Vertices without any weights are left untouched.
+    pub fn normalize_weights(&mut self) {
+        let total: f32 = self.bone_weight.iter().chain(&self.bone_weight2).sum();
+        if total <= 0.0 {
+            return;
+        }
+
+        for weight in self.bone_weight.iter_mut().chain(&mut self.bone_weight2) {
+            *weight /= total;
+        }
+    }
 }
 
 impl Default for Vertex {
@@ -400,10 +533,51 @@ impl Default for Vertex {
             color: [0.0; 4],
             bone_weight: [0.0; 4],
             bone_id: [0u8; 4],
+            bone_weight2: [0.0; 4],
+            bone_id2: [0u8; 4],
         }
     }
 }
 
+#[derive(Debug, Clone)]
+struct MeshBuilderPart {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    material_name: String,
+    declaration: VertexDeclaration,
+}
+
+/// Collects the parts of a brand-new model, see [MDL::new].
+#[derive(Debug, Clone, Default)]
+pub struct MeshBuilder {
+    parts: Vec<MeshBuilderPart>,
+}
+
+impl MeshBuilder {
+    /// Creates a new, empty builder.
+    pub fn new() -> Self {
+        MeshBuilder { parts: Vec::new() }
+    }
+
+    /// Adds a part (a mesh in the first LOD) using the material at `material_name`, such as
+    /// "/mt_c0201e0038_top_a.mtrl". Its vertices are stored as described by `declaration`, which
+    /// may only use element types that [MDL::write_to_buffer] supports.
+    pub fn add_part(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        material_name: &str,
+        declaration: VertexDeclaration,
+    ) {
+        self.parts.push(MeshBuilderPart {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+            material_name: material_name.to_string(),
+            declaration,
+        });
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct NewShapeValue {
@@ -412,13 +586,146 @@ pub struct NewShapeValue {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(C)]
 pub struct SubMesh {
+    #[cfg_attr(feature = "serde", serde(skip))]
     submesh_index: usize,
     pub index_count: u32,
     pub index_offset: u32,
 }
 
+/// Conversions to apply when loading a model, see [MDL::from_existing_with_options].
+///
+/// Models are stored in the game's left-handed, Y-up space, and their UVs have V increasing downwards
+/// like in Direct3D. The default leaves the data exactly as stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelLoadOptions {
+    /// Negates the V coordinate of every UV.
+    pub flip_v: bool,
+    /// Flips the Z axis of positions, normals and bitangents to convert to a right-handed space such as
+    /// glTF's, and reverses the winding order of every triangle to match.
+    pub convert_handedness: bool,
+}
+
+/// Options for [MDL::write_to_buffer_with_options]. The default writes the model with its current vertex
+/// declarations.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelWriteOptions {
+    /// Writes half-precision positions as `Single3` instead, growing the vertex streams to fit. Large meshes
+    /// lose enough precision as `Half4` that cracks appear between their triangles.
+    pub single_precision_positions: bool,
+}
+
+/// Totals and buffer locations for a single LOD, see [MDL::lod_info].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LodInfo {
+    /// Range of meshes belonging to this LOD, which are the `parts` of the corresponding [Lod].
+    pub mesh_range: std::ops::Range<u16>,
+    /// Number of vertices across all meshes.
+    pub vertex_count: u32,
+    /// Number of indices across all meshes.
+    pub index_count: u32,
+    /// Number of triangles across all meshes.
+    pub polygon_count: u32,
+    /// Size of the vertex buffer in bytes.
+    pub vertex_buffer_size: u32,
+    /// Size of the index buffer in bytes.
+    pub index_buffer_size: u32,
+    /// Offset of the vertex buffer in the file.
+    pub vertex_data_offset: u32,
+    /// Offset of the index buffer in the file.
+    pub index_data_offset: u32,
+}
+
+/// A point other models or effects can be attached to, such as where a weapon is held, see [MDL::element_ids].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ElementIdInfo {
+    pub element_id: u32,
+    /// Name of the bone this point is relative to.
+    pub parent_bone: String,
+    /// Offset from the parent bone.
+    pub translate: [f32; 3],
+    /// Rotation around the X, Y and Z axes in radians, relative to the parent bone.
+    pub rotate: [f32; 3],
+}
+
+impl ElementIdInfo {
+    /// Returns the transform from this point to its parent bone, as a column-major 4x4 matrix. The rotation is
+    /// applied around the X axis first, then Y and then Z.
+    pub fn transform(&self) -> [f32; 16] {
+        let (sx, cx) = self.rotate[0].sin_cos();
+        let (sy, cy) = self.rotate[1].sin_cos();
+        let (sz, cz) = self.rotate[2].sin_cos();
+        let [tx, ty, tz] = self.translate;
+
+        [
+            cz * cy,
+            sz * cy,
+            -sy,
+            0.0,
+            cz * sy * sx - sz * cx,
+            sz * sy * sx + cz * cx,
+            cy * sx,
+            0.0,
+            cz * sy * cx + sz * sx,
+            sz * sy * cx - cz * sx,
+            cy * cx,
+            0.0,
+            tx,
+            ty,
+            tz,
+            1.0,
+        ]
+    }
+}
+
+/// The metadata of a model, without any of its geometry. See [MDL::header_only].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModelInfo {
+    /// The version of the file, such as [MDL_VERSION_6].
+    pub version: u32,
+    /// Same as [MDL::material_names].
+    pub material_names: Vec<String>,
+    /// Same as [MDL::affected_bone_names].
+    pub affected_bone_names: Vec<String>,
+    /// Totals and buffer locations of each LOD, same as [MDL::lod_info].
+    pub lods: Vec<LodInfo>,
+    pub mesh_count: u16,
+    pub submesh_count: u16,
+    pub shape_count: u16,
+    pub bounding_box_min: [f32; 4],
+    pub bounding_box_max: [f32; 4],
+}
+
+impl std::fmt::Display for ModelInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Model version {:#x}", self.version)?;
+        writeln!(
+            f,
+            "{} meshes, {} submeshes, {} shapes",
+            self.mesh_count, self.submesh_count, self.shape_count
+        )?;
+        writeln!(
+            f,
+            "Bounding box: {:?} to {:?}",
+            self.bounding_box_min, self.bounding_box_max
+        )?;
+        for (i, lod) in self.lods.iter().enumerate() {
+            writeln!(
+                f,
+                "LOD {i}: {} vertices, {} triangles",
+                lod.vertex_count, lod.polygon_count
+            )?;
+        }
+        writeln!(f, "Materials: {}", self.material_names.join(", "))?;
+        write!(f, "Bones: {}", self.affected_bone_names.join(", "))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Shape {
     pub name: String,
@@ -439,9 +746,130 @@ pub struct Part {
     pub shapes: Vec<Shape>,
 }
 
+impl Part {
+    /// Returns the indices belonging to `submesh`, which should be one of this part's `submeshes`.
+    /// Returns an empty slice if the submesh lies outside of this part.
+    pub fn submesh_indices(&self, submesh: &SubMesh) -> &[u16] {
+        // submesh offsets are relative to the whole LOD, and the part starts at its first submesh
+        let Some(start_index) = self.submeshes.first().map(|first| first.index_offset) else {
+            return &[];
+        };
+
+        let Some(start) = submesh.index_offset.checked_sub(start_index) else {
+            return &[];
+        };
+        let start = start as usize;
+
+        self.indices
+            .get(start..start + submesh.index_count as usize)
+            .unwrap_or(&[])
+    }
+
+    /// Returns the vertex referenced by each of the indices of `submesh`, so the submesh can be
+    /// used without an index buffer. Returns an empty list if the submesh lies outside of this part,
+    /// or references a vertex that doesn't exist.
+    pub fn submesh_vertices(&self, submesh: &SubMesh) -> Vec<Vertex> {
+        self.submesh_indices(submesh)
+            .iter()
+            .map(|index| self.vertices.get(*index as usize).copied())
+            .collect::<Option<Vec<Vertex>>>()
+            .unwrap_or_default()
+    }
+
+    /// Returns how many triangles this part has, which is a third of its indices.
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// The differences between the same part of two models, see [MDL::diff]. A part missing from one of
+/// the models has no vertices, indices or material there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartDiff {
+    pub lod_index: usize,
+    pub part_index: usize,
+    /// The vertex count in both models, if it changed.
+    pub vertex_count: Option<(usize, usize)>,
+    /// The index count in both models, if it changed.
+    pub index_count: Option<(usize, usize)>,
+    /// The material name in both models, if it changed.
+    pub material: Option<(String, String)>,
+    /// Whether the bounds of the part's vertex positions changed.
+    pub bounding_box_changed: bool,
+}
+
+/// The differences between two models, see [MDL::diff].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ModelDiff {
+    /// Parts that differ, in LOD and part order. Unchanged parts aren't listed.
+    pub parts: Vec<PartDiff>,
+    /// Materials only used by the other model.
+    pub added_materials: Vec<String>,
+    /// Materials only used by this model.
+    pub removed_materials: Vec<String>,
+    /// Whether the bounding box stored for the whole model changed.
+    pub bounding_box_changed: bool,
+}
+
+impl ModelDiff {
+    /// Returns true if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+            && self.added_materials.is_empty()
+            && self.removed_materials.is_empty()
+            && !self.bounding_box_changed
+    }
+}
+
+/// The range of a merged index buffer covering one of the original parts, see [Lod::merged_mesh].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergedSubmesh {
+    /// Index of the part in [Lod::parts] these indices came from.
+    pub part_index: usize,
+    /// Same as [Part::material_index].
+    pub material_index: u16,
+    pub index_offset: u32,
+    pub index_count: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct Lod {
     pub parts: Vec<Part>,
+    /// The edge geometry of this LOD, used by the PS3 for culling. It isn't parsed, but it's kept
+    /// as-is so it isn't lost when writing the model back.
+    pub edge_geometry: Vec<u8>,
+}
+
+impl Lod {
+    /// Returns how many triangles this LOD has across all of its parts.
+    pub fn triangle_count(&self) -> usize {
+        self.parts.iter().map(Part::triangle_count).sum()
+    }
+
+    /// Merges every part into a single mesh, for formats that prefer one. The vertices of each part are appended in
+    /// order, and their indices are offset to match. Indices are widened to `u32`, as the merged mesh can have more
+    /// than 65535 vertices. Each part becomes a [MergedSubmesh], to keep track of its material.
+    pub fn merged_mesh(&self) -> (Vec<Vertex>, Vec<u32>, Vec<MergedSubmesh>) {
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut submeshes = Vec::with_capacity(self.parts.len());
+
+        for (part_index, part) in self.parts.iter().enumerate() {
+            let base_vertex = vertices.len() as u32;
+
+            submeshes.push(MergedSubmesh {
+                part_index,
+                material_index: part.material_index,
+                index_offset: indices.len() as u32,
+                index_count: part.indices.len() as u32,
+            });
+
+            vertices.extend_from_slice(&part.vertices);
+            indices.extend(part.indices.iter().map(|index| base_vertex + *index as u32));
+        }
+
+        (vertices, indices, submeshes)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -456,47 +884,293 @@ pub struct MDL {
 
 impl MDL {
     pub fn from_existing(buffer: ByteSpan) -> Option<MDL> {
-        let mut cursor = Cursor::new(buffer);
-        let model_file_header = ModelFileHeader::read(&mut cursor).ok()?;
+        MDL::parse(buffer).ok()
+    }
 
-        let model = ModelData::read_args(
-            &mut cursor,
-            binrw::args! { file_header: &model_file_header },
-        )
-        .ok()?;
+    /// Reads an existing MDL file built for `platform`. See [MDL::parse_platform] for what is supported on consoles.
+    pub fn from_existing_platform(buffer: ByteSpan, platform: Platform) -> Option<MDL> {
+        MDL::parse_platform(buffer, platform).ok()
+    }
+
+    /// Same as [MDL::from_existing], but converts the vertices and indices according to `options`.
+    /// Only the decoded `vertices`, `indices` and shapes are converted, the raw vertex streams are left as-is.
+    pub fn from_existing_with_options(buffer: ByteSpan, options: ModelLoadOptions) -> Option<MDL> {
+        let mut mdl = MDL::parse(buffer).ok()?;
 
-        let mut affected_bone_names = vec![];
+        let convert = |vertex: &mut Vertex| {
+            if options.flip_v {
+                vertex.uv0[1] = -vertex.uv0[1];
+                vertex.uv1[1] = -vertex.uv1[1];
+            }
+
+            if options.convert_handedness {
+                vertex.position[2] = -vertex.position[2];
+                vertex.normal[2] = -vertex.normal[2];
+                vertex.bitangent[2] = -vertex.bitangent[2];
+                // mirroring also flips which side the bitangent is on
+                vertex.bitangent[3] = -vertex.bitangent[3];
+            }
+        };
 
-        for offset in &model.bone_name_offsets {
-            let mut offset = *offset;
-            let mut string = String::new();
+        for part in mdl.lods.iter_mut().flat_map(|lod| lod.parts.iter_mut()) {
+            part.vertices.iter_mut().for_each(convert);
+            part.shapes
+                .iter_mut()
+                .flat_map(|shape| shape.morphed_vertices.iter_mut())
+                .for_each(convert);
 
-            let mut next_char = model.header.strings[offset as usize] as char;
-            while next_char != '\0' {
-                string.push(next_char);
-                offset += 1;
-                next_char = model.header.strings[offset as usize] as char;
+            if options.convert_handedness {
+                for triangle in part.indices.chunks_exact_mut(3) {
+                    triangle.swap(1, 2);
+                }
             }
+        }
+
+        Some(mdl)
+    }
 
-            affected_bone_names.push(string);
+    /// Creates a static model with a single LOD out of the parts in `builder`, without any bones, shapes
+    /// or attributes. The bounding boxes are calculated from the vertex positions.
+    ///
+    /// Returns None if there are no parts, a part has more than 65535 vertices, or an index points
+    /// outside of its part.
+    pub fn new(builder: MeshBuilder) -> Option<MDL> {
+        if builder.parts.is_empty() {
+            return None;
         }
 
-        let mut material_names = vec![];
+        let mut strings = vec![];
+        let mut material_names: Vec<&str> = vec![];
+        let mut material_name_offsets = vec![];
+        for part in &builder.parts {
+            if !material_names.contains(&part.material_name.as_str()) {
+                material_names.push(&part.material_name);
+                material_name_offsets.push(strings.len() as u32);
 
-        for offset in &model.material_name_offsets {
-            let mut offset = *offset;
-            let mut string = String::new();
+                strings.extend_from_slice(part.material_name.as_bytes());
+                strings.push(0);
+            }
+        }
+        // the string table is padded to 4 bytes
+        strings.resize(strings.len().next_multiple_of(4), 0);
+
+        let mut meshes = vec![];
+        let mut submeshes = vec![];
+        let mut lod_parts = vec![];
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        let mut radius: f32 = 0.0;
+        let mut index_offset = 0;
+
+        for (i, part) in builder.parts.iter().enumerate() {
+            let vertex_count = u16::try_from(part.vertices.len()).ok()?;
+            if part.indices.iter().any(|index| *index >= vertex_count) {
+                return None;
+            }
+
+            let mut vertex_buffer_strides = [0u8; 3];
+            for element in &part.declaration.elements {
+                let stride = vertex_buffer_strides.get_mut(element.stream as usize)?;
+                *stride =
+                    (*stride).max(element.offset + get_vertex_type_size(element.vertex_type) as u8);
+            }
 
-            let mut next_char = model.header.strings[offset as usize] as char;
-            while next_char != '\0' {
-                string.push(next_char);
-                offset += 1;
-                next_char = model.header.strings[offset as usize] as char;
+            for vertex in &part.vertices {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(vertex.position[axis]);
+                    max[axis] = max[axis].max(vertex.position[axis]);
+                }
+                radius = radius.max(vertex.position.iter().map(|x| x * x).sum::<f32>().sqrt());
             }
 
-            material_names.push(string);
+            meshes.push(Mesh {
+                vertex_count,
+                index_count: part.indices.len() as u32,
+                material_index: material_names
+                    .iter()
+                    .position(|name| *name == part.material_name)?
+                    as u16,
+                submesh_index: i as u16,
+                submesh_count: 1,
+                // no bones
+                bone_table_index: 255,
+                start_index: index_offset,
+                vertex_buffer_offsets: [0; 3],
+                vertex_buffer_strides,
+                vertex_stream_count: vertex_buffer_strides
+                    .iter()
+                    .filter(|stride| **stride != 0)
+                    .count() as u8,
+            });
+
+            submeshes.push(Submesh {
+                index_offset,
+                index_count: part.indices.len() as u32,
+                attribute_index_mask: 0,
+                bone_start_index: 0,
+                bone_count: 0,
+            });
+
+            lod_parts.push(Part {
+                mesh_index: i as u16,
+                vertices: part.vertices.clone(),
+                vertex_streams: vec![],
+                vertex_stream_strides: vec![],
+                indices: part.indices.clone(),
+                material_index: meshes[i].material_index,
+                submeshes: vec![SubMesh {
+                    submesh_index: i,
+                    index_count: part.indices.len() as u32,
+                    index_offset,
+                }],
+                shapes: vec![],
+            });
+
+            index_offset += part.indices.len() as u32;
         }
 
+        let mesh_count = meshes.len() as u16;
+
+        let bounding_box = BoundingBox {
+            min: [min[0], min[1], min[2], 1.0],
+            max: [max[0], max[1], max[2], 1.0],
+        };
+        let empty_bounding_box = BoundingBox {
+            min: [0.0; 4],
+            max: [0.0; 4],
+        };
+
+        let mut mdl = MDL {
+            file_header: ModelFileHeader {
+                version: MDL_VERSION_5,
+                stack_size: 0,
+                runtime_size: 0,
+                vertex_declaration_count: mesh_count,
+                material_count: material_names.len() as u16,
+                vertex_offsets: [0; 3],
+                index_offsets: [0; 3],
+                vertex_buffer_size: [0; 3],
+                index_buffer_size: [0; 3],
+                lod_count: 1,
+                index_buffer_streaming_enabled: false,
+                has_edge_geometry: false,
+            },
+            model_data: ModelData {
+                header: ModelHeader {
+                    vertex_declarations: builder
+                        .parts
+                        .iter()
+                        .map(|part| part.declaration.clone())
+                        .collect(),
+                    string_count: material_names.len() as u16,
+                    string_size: strings.len() as u32,
+                    strings,
+                    radius,
+                    mesh_count,
+                    attribute_count: 0,
+                    submesh_count: submeshes.len() as u16,
+                    material_count: material_names.len() as u16,
+                    bone_count: 0,
+                    bone_table_count: 0,
+                    shape_count: 0,
+                    shape_mesh_count: 0,
+                    shape_value_count: 0,
+                    lod_count: 1,
+                    flags1: ModelFlags1::WavingAnimationDisabled,
+                    element_id_count: 0,
+                    terrain_shadow_mesh_count: 0,
+                    flags2: ModelFlags2::None,
+                    model_clip_out_of_distance: 0.0,
+                    shadow_clip_out_of_distance: 0.0,
+                    unknown4: 0,
+                    terrain_shadow_submesh_count: 0,
+                    unknown5: 0,
+                    bg_change_material_index: 0,
+                    bg_crest_change_material_index: 0,
+                    unknown6: 0,
+                    unknown7: 0,
+                    unknown8: 0,
+                    unknown9: 0,
+                },
+                element_ids: vec![],
+                lods: vec![
+                    MeshLod::empty(0, mesh_count),
+                    MeshLod::empty(mesh_count, 0),
+                    MeshLod::empty(mesh_count, 0),
+                ],
+                meshes,
+                attribute_name_offsets: vec![],
+                terrain_shadow_meshes: vec![],
+                submeshes,
+                terrain_shadow_submeshes: vec![],
+                material_name_offsets,
+                bone_name_offsets: vec![],
+                bone_tables: vec![],
+                bone_tables_v2: vec![],
+                shapes: vec![],
+                shape_meshes: vec![],
+                shape_values: vec![],
+                submesh_bone_map_size: 0,
+                submesh_bone_map_size_v2: 0,
+                submesh_bone_map: vec![],
+                padding_amount: 0,
+                unknown_padding: vec![],
+                bounding_box: bounding_box.clone(),
+                model_bounding_box: bounding_box,
+                water_bounding_box: empty_bounding_box.clone(),
+                vertical_fog_bounding_box: empty_bounding_box,
+                bone_bounding_boxes: vec![],
+            },
+            lods: vec![Lod {
+                parts: lod_parts,
+                edge_geometry: vec![],
+            }],
+            affected_bone_names: vec![],
+            material_names: material_names.iter().map(|name| name.to_string()).collect(),
+        };
+
+        mdl.update_headers();
+
+        // reading it back fills in everything else derived from the file, like the raw vertex streams
+        MDL::from_existing(&mdl.write_to_buffer()?)
+    }
+
+    /// Same as [MDL::from_existing], but describes why the model couldn't be parsed. Unknown versions
+    /// and LOD counts are rejected before reading anything else.
+    pub fn parse(buffer: ByteSpan) -> Result<MDL, ModelError> {
+        MDL::parse_platform(buffer, Platform::Win32)
+    }
+
+    /// Same as [MDL::parse], but for a model built for `platform`. The headers, vertices and indices of big-endian
+    /// (PS3) models are byte-swapped as they are read.
+    ///
+    /// The raw vertex streams and edge geometry are kept in their original byte order, and models are always
+    /// written back in the PC layout, so a console model shouldn't be written back without replacing its vertices.
+    pub fn parse_platform(buffer: ByteSpan, platform: Platform) -> Result<MDL, ModelError> {
+        let _span = info_span!("parse_model", bytes = buffer.len()).entered();
+
+        MDL::parse_reader(&mut Cursor::new(buffer), platform)
+    }
+
+    /// Reads an existing MDL file from `reader`, such as a file handle, instead of a buffer holding all of it.
+    /// The model has to start at the beginning of `reader`. Wrapping files in a [std::io::BufReader] is recommended,
+    /// as the vertices are read a few bytes at a time.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Option<MDL> {
+        MDL::parse_reader(reader, Platform::Win32).ok()
+    }
+
+    /// Same as [MDL::parse_platform], but reads from `reader` like [MDL::from_reader].
+    pub fn parse_reader<R: Read + Seek>(
+        cursor: &mut R,
+        platform: Platform,
+    ) -> Result<MDL, ModelError> {
+        let endian = platform.endianness();
+
+        let (model_file_header, model) = MDL::read_headers(cursor, endian)?;
+
+        let affected_bone_names = MDL::read_names(&model.header.strings, &model.bone_name_offsets)?;
+        let material_names = MDL::read_names(&model.header.strings, &model.material_name_offsets)?;
+
         let mut lods = vec![];
 
         for i in 0..model.header.lod_count {
@@ -509,6 +1183,8 @@ impl MDL {
                 let vertex_count = model.meshes[j as usize].vertex_count;
                 let material_index = model.meshes[j as usize].material_index;
 
+                MDL::validate_vertex_streams(j, &model.meshes[j as usize], declaration)?;
+
                 let mut vertices: Vec<Vertex> = vec![Vertex::default(); vertex_count as usize];
 
                 for k in 0..vertex_count {
@@ -524,23 +1200,23 @@ impl MDL {
                                         as u32
                                         * k as u32) as u64,
                             ))
-                            .ok()?;
+                            .map_err(|_| ModelError::ParseError)?;
 
                         match element.vertex_usage {
                             VertexUsage::Position => match element.vertex_type {
                                 VertexType::Single4 => {
                                     vertices[k as usize].position.clone_from_slice(
-                                        &MDL::read_single4(&mut cursor).unwrap()[0..3],
+                                        &MDL::read_single4(cursor, endian).unwrap()[0..3],
                                     );
                                 }
                                 VertexType::Half4 => {
                                     vertices[k as usize].position.clone_from_slice(
-                                        &MDL::read_half4(&mut cursor).unwrap()[0..3],
+                                        &MDL::read_half4(cursor, endian).unwrap()[0..3],
                                     );
                                 }
                                 VertexType::Single3 => {
                                     vertices[k as usize].position =
-                                        MDL::read_single3(&mut cursor).unwrap();
+                                        MDL::read_single3(cursor, endian).unwrap();
                                 }
                                 _ => {
                                     panic!(
@@ -549,61 +1225,75 @@ impl MDL {
                                     );
                                 }
                             },
-                            VertexUsage::BlendWeights => match element.vertex_type {
-                                VertexType::ByteFloat4 => {
-                                    vertices[k as usize].bone_weight =
-                                        MDL::read_byte_float4(&mut cursor).unwrap();
-                                }
-                                VertexType::Byte4 => {
-                                    vertices[k as usize].bone_weight =
-                                        MDL::read_tangent(&mut cursor).unwrap();
-                                }
-                                VertexType::UnsignedShort4 => {
-                                    let bytes = MDL::read_unsigned_short4(&mut cursor).unwrap();
-                                    vertices[k as usize].bone_weight = [
-                                        f32::from(bytes[0]),
-                                        f32::from(bytes[1]),
-                                        f32::from(bytes[2]),
-                                        f32::from(bytes[3]),
-                                    ];
-                                }
-                                _ => {
-                                    panic!(
-                                        "Unexpected vertex type for blendweight: {:#?}",
-                                        element.vertex_type
-                                    );
-                                }
-                            },
-                            VertexUsage::BlendIndices => match element.vertex_type {
-                                VertexType::Byte4 => {
-                                    vertices[k as usize].bone_id =
-                                        MDL::read_byte4(&mut cursor).unwrap();
-                                }
-                                VertexType::UnsignedShort4 => {
-                                    let shorts = MDL::read_unsigned_short4(&mut cursor).unwrap();
-                                    vertices[k as usize].bone_id = [
-                                        shorts[0] as u8,
-                                        shorts[1] as u8,
-                                        shorts[2] as u8,
-                                        shorts[3] as u8,
-                                    ];
+                            VertexUsage::BlendWeights => {
+                                // the second set of weights is used for vertices with more than 4 influences
+                                let bone_weight = match element.usage_index {
+                                    0 => &mut vertices[k as usize].bone_weight,
+                                    _ => &mut vertices[k as usize].bone_weight2,
+                                };
+
+                                match element.vertex_type {
+                                    VertexType::ByteFloat4 => {
+                                        *bone_weight = MDL::read_byte_float4(cursor).unwrap();
+                                    }
+                                    VertexType::Byte4 => {
+                                        *bone_weight = MDL::read_tangent(cursor).unwrap();
+                                    }
+                                    VertexType::UnsignedShort4 => {
+                                        let bytes =
+                                            MDL::read_unsigned_short4(cursor, endian).unwrap();
+                                        *bone_weight = [
+                                            f32::from(bytes[0]),
+                                            f32::from(bytes[1]),
+                                            f32::from(bytes[2]),
+                                            f32::from(bytes[3]),
+                                        ];
+                                    }
+                                    _ => {
+                                        panic!(
+                                            "Unexpected vertex type for blendweight: {:#?}",
+                                            element.vertex_type
+                                        );
+                                    }
                                 }
-                                _ => {
-                                    panic!(
-                                        "Unexpected vertex type for blendindice: {:#?}",
-                                        element.vertex_type
-                                    );
+                            }
+                            VertexUsage::BlendIndices => {
+                                let bone_id = match element.usage_index {
+                                    0 => &mut vertices[k as usize].bone_id,
+                                    _ => &mut vertices[k as usize].bone_id2,
+                                };
+
+                                match element.vertex_type {
+                                    VertexType::Byte4 => {
+                                        *bone_id = MDL::read_byte4(cursor).unwrap();
+                                    }
+                                    VertexType::UnsignedShort4 => {
+                                        let shorts =
+                                            MDL::read_unsigned_short4(cursor, endian).unwrap();
+                                        *bone_id = [
+                                            shorts[0] as u8,
+                                            shorts[1] as u8,
+                                            shorts[2] as u8,
+                                            shorts[3] as u8,
+                                        ];
+                                    }
+                                    _ => {
+                                        panic!(
+                                            "Unexpected vertex type for blendindice: {:#?}",
+                                            element.vertex_type
+                                        );
+                                    }
                                 }
-                            },
+                            }
                             VertexUsage::Normal => match element.vertex_type {
                                 VertexType::Half4 => {
                                     vertices[k as usize].normal.clone_from_slice(
-                                        &MDL::read_half4(&mut cursor).unwrap()[0..3],
+                                        &MDL::read_half4(cursor, endian).unwrap()[0..3],
                                     );
                                 }
                                 VertexType::Single3 => {
                                     vertices[k as usize].normal =
-                                        MDL::read_single3(&mut cursor).unwrap();
+                                        MDL::read_single3(cursor, endian).unwrap();
                                 }
                                 _ => {
                                     panic!(
@@ -614,25 +1304,25 @@ impl MDL {
                             },
                             VertexUsage::UV => match element.vertex_type {
                                 VertexType::ByteFloat4 => {
-                                    let combined = MDL::read_byte_float4(&mut cursor).unwrap();
+                                    let combined = MDL::read_byte_float4(cursor).unwrap();
 
                                     vertices[k as usize].uv0.clone_from_slice(&combined[0..2]);
                                     vertices[k as usize].uv1.clone_from_slice(&combined[2..4]);
                                 }
                                 VertexType::Half4 => {
-                                    let combined = MDL::read_half4(&mut cursor).unwrap();
+                                    let combined = MDL::read_half4(cursor, endian).unwrap();
 
                                     vertices[k as usize].uv0.clone_from_slice(&combined[0..2]);
                                     vertices[k as usize].uv1.clone_from_slice(&combined[2..4]);
                                 }
                                 VertexType::Single4 => {
-                                    let combined = MDL::read_single4(&mut cursor).unwrap();
+                                    let combined = MDL::read_single4(cursor, endian).unwrap();
 
                                     vertices[k as usize].uv0.clone_from_slice(&combined[0..2]);
                                     vertices[k as usize].uv1.clone_from_slice(&combined[2..4]);
                                 }
                                 VertexType::Half2 => {
-                                    let combined = MDL::read_half2(&mut cursor).unwrap();
+                                    let combined = MDL::read_half2(cursor, endian).unwrap();
 
                                     vertices[k as usize].uv0.clone_from_slice(&combined[0..2]);
                                 }
@@ -646,7 +1336,7 @@ impl MDL {
                             VertexUsage::BiTangent => match element.vertex_type {
                                 VertexType::ByteFloat4 => {
                                     vertices[k as usize].bitangent =
-                                        MDL::read_tangent(&mut cursor).unwrap();
+                                        MDL::read_tangent(cursor).unwrap();
                                 }
                                 _ => {
                                     panic!(
@@ -670,7 +1360,7 @@ impl MDL {
                             VertexUsage::Color => match element.vertex_type {
                                 VertexType::ByteFloat4 => {
                                     vertices[k as usize].color =
-                                        MDL::read_byte_float4(&mut cursor).unwrap();
+                                        MDL::read_byte_float4(cursor).unwrap();
                                 }
                                 _ => {
                                     panic!(
@@ -689,13 +1379,17 @@ impl MDL {
                             + (model.meshes[j as usize].start_index * size_of::<u16>() as u32))
                             as u64,
                     ))
-                    .ok()?;
+                    .map_err(|_| ModelError::ParseError)?;
 
                 // TODO: optimize!
                 let mut indices: Vec<u16> =
                     Vec::with_capacity(model.meshes[j as usize].index_count as usize);
                 for _ in 0..model.meshes[j as usize].index_count {
-                    indices.push(cursor.read_le::<u16>().ok()?);
+                    indices.push(
+                        cursor
+                            .read_type::<u16>(endian)
+                            .map_err(|_| ModelError::ParseError)?,
+                    );
                 }
 
                 let mut submeshes: Vec<SubMesh> =
@@ -760,18 +1454,9 @@ impl MDL {
                             vertex.position[2] = new_vertex.position[2] - old_vertex.position[2];
                         }
 
-                        let mut offset = shape.string_offset;
-                        let mut string = String::new();
-
-                        let mut next_char = model.header.strings[offset as usize] as char;
-                        while next_char != '\0' {
-                            string.push(next_char);
-                            offset += 1;
-                            next_char = model.header.strings[offset as usize] as char;
-                        }
-
                         shapes.push(Shape {
-                            name: string,
+                            name: read_cstring(&model.header.strings, shape.string_offset as usize)
+                                .ok_or(ModelError::ParseError)?,
                             morphed_vertices,
                         });
                     }
@@ -781,13 +1466,6 @@ impl MDL {
                 let mut vertex_stream_strides = vec![];
                 let mesh = &model.meshes[j as usize];
                 for stream in 0..mesh.vertex_stream_count {
-                    if stream as usize >= mesh.vertex_buffer_offsets.len() {
-                        warn!(
-                            "Stream {} is greater than the number of vertex buffer offsets",
-                            stream
-                        );
-                        break;
-                    }
                     let mut vertex_data = vec![];
                     let stride = mesh.vertex_buffer_strides[stream as usize];
                     for z in 0..mesh.vertex_count {
@@ -801,10 +1479,11 @@ impl MDL {
                                     + (z as u32 * stride as u32))
                                     as u64,
                             ))
-                            .ok()?;
+                            .map_err(|_| ModelError::ParseError)?;
 
                         for _ in 0..stride {
-                            vertex_data.push(cursor.read_le::<u8>().ok()?);
+                            vertex_data
+                                .push(cursor.read_le::<u8>().map_err(|_| ModelError::ParseError)?);
                         }
                     }
 
@@ -825,10 +1504,28 @@ impl MDL {
                 });
             }
 
-            lods.push(Lod { parts });
+            let mesh_lod = &model.lods[i as usize];
+            cursor
+                .seek(SeekFrom::Start(mesh_lod.edge_geometry_data_offset as u64))
+                .map_err(|_| ModelError::ParseError)?;
+
+            let mut edge_geometry = vec![];
+            cursor
+                .by_ref()
+                .take(mesh_lod.edge_geometry_size as u64)
+                .read_to_end(&mut edge_geometry)
+                .map_err(|_| ModelError::ParseError)?;
+            if edge_geometry.len() != mesh_lod.edge_geometry_size as usize {
+                return Err(ModelError::ParseError);
+            }
+
+            lods.push(Lod {
+                parts,
+                edge_geometry,
+            });
         }
 
-        Some(MDL {
+        Ok(MDL {
             file_header: model_file_header,
             model_data: model,
             lods,
@@ -837,6 +1534,122 @@ impl MDL {
         })
     }
 
+    /// Reads only the headers of a model, which is much faster than [MDL::from_existing] as none of the vertices,
+    /// indices or shapes are decoded. Useful when only the metadata of a model is needed.
+    pub fn header_only(buffer: ByteSpan) -> Option<ModelInfo> {
+        let mut cursor = Cursor::new(buffer);
+        let (file_header, model) = MDL::read_headers(&mut cursor, Endian::Little).ok()?;
+
+        Some(ModelInfo {
+            version: file_header.version,
+            material_names: MDL::read_names(&model.header.strings, &model.material_name_offsets)
+                .ok()?,
+            affected_bone_names: MDL::read_names(&model.header.strings, &model.bone_name_offsets)
+                .ok()?,
+            lods: (0..model.header.lod_count as usize)
+                .map(|lod_index| model.lod_info(lod_index))
+                .collect::<Option<Vec<_>>>()?,
+            mesh_count: model.header.mesh_count,
+            submesh_count: model.header.submesh_count,
+            shape_count: model.header.shape_count,
+            bounding_box_min: model.bounding_box.min,
+            bounding_box_max: model.bounding_box.max,
+        })
+    }
+
+    /// Reads the file header and model data, which come before any of the vertex and index buffers.
+    fn read_headers<R: Read + Seek>(
+        cursor: &mut R,
+        endian: Endian,
+    ) -> Result<(ModelFileHeader, ModelData), ModelError> {
+        let model_file_header = ModelFileHeader::read_options(cursor, endian, ())
+            .map_err(|_| ModelError::InvalidHeader)?;
+
+        if model_file_header.version != MDL_VERSION_5 && model_file_header.version != MDL_VERSION_6
+        {
+            return Err(ModelError::UnsupportedVersion(model_file_header.version));
+        }
+
+        if model_file_header.lod_count > MAX_LODS {
+            return Err(ModelError::UnsupportedLodCount(model_file_header.lod_count));
+        }
+
+        let model = ModelData::read_options(
+            cursor,
+            endian,
+            binrw::args! { file_header: &model_file_header },
+        )
+        .map_err(|_| ModelError::ParseError)?;
+
+        if model.header.lod_count > MAX_LODS {
+            return Err(ModelError::UnsupportedLodCount(model.header.lod_count));
+        }
+
+        Ok((model_file_header, model))
+    }
+
+    /// Reads the null-terminated names at each of `offsets` in the string table.
+    fn read_names(strings: &[u8], offsets: &[u32]) -> Result<Vec<String>, ModelError> {
+        offsets
+            .iter()
+            .map(|offset| read_cstring(strings, *offset as usize).ok_or(ModelError::ParseError))
+            .collect()
+    }
+
+    /// Checks that every element of `declaration` reads from one of the mesh's streams, and that those streams have a stride.
+    fn validate_vertex_streams(
+        mesh_index: u16,
+        mesh: &Mesh,
+        declaration: &VertexDeclaration,
+    ) -> Result<(), ModelError> {
+        let invalid = |stream: u8| ModelError::InvalidVertexStream {
+            mesh: mesh_index,
+            stream,
+        };
+
+        if mesh.vertex_stream_count as usize > mesh.vertex_buffer_offsets.len() {
+            return Err(invalid(mesh.vertex_stream_count));
+        }
+
+        for element in &declaration.elements {
+            if element.stream >= mesh.vertex_stream_count
+                || mesh.vertex_buffer_strides[element.stream as usize] == 0
+            {
+                return Err(invalid(element.stream));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every submesh lies within `indices`. Like [Part::submesh_indices], the offsets are relative to
+    /// the whole LOD, and the part starts at the offset of its first submesh.
+    fn validate_submeshes(indices: &[u16], submeshes: &[SubMesh]) -> Result<(), ModelError> {
+        let start_index = submeshes.first().map_or(0, |first| first.index_offset);
+
+        for (i, submesh) in submeshes.iter().enumerate() {
+            let fits = submesh
+                .index_offset
+                .checked_sub(start_index)
+                .is_some_and(|start| {
+                    start as usize + submesh.index_count as usize <= indices.len()
+                });
+
+            if !fits {
+                return Err(ModelError::InvalidSubmesh {
+                    submesh: i,
+                    index_offset: submesh.index_offset,
+                    index_count: submesh.index_count,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the vertices and indices of a part, along with the offsets and counts of its submeshes.
+    ///
+    /// Returns an error and leaves the model untouched if one of `submeshes` doesn't fit within `indices`.
     pub fn replace_vertices(
         &mut self,
         lod_index: usize,
@@ -844,7 +1657,9 @@ impl MDL {
         vertices: &[Vertex],
         indices: &[u16],
         submeshes: &[SubMesh],
-    ) {
+    ) -> Result<(), ModelError> {
+        MDL::validate_submeshes(indices, submeshes)?;
+
         let part = &mut self.lods[lod_index].parts[part_index];
 
         part.vertices = Vec::from(vertices);
@@ -864,40 +1679,537 @@ impl MDL {
         self.model_data.meshes[part.mesh_index as usize].index_count = part.indices.len() as u32;
 
         self.update_headers();
+
+        Ok(())
     }
 
-    pub fn remove_shape_meshes(&mut self) {
-        self.model_data.shape_meshes.clear();
-        self.model_data.shape_values.clear();
+    /// Welds identical vertices of a part together, such as the duplicates left behind by importing a model from
+    /// another format, and rebuilds its index buffer to match. The triangles and submeshes stay the same. Returns how
+    /// many vertices were removed.
+    ///
+    /// Parts with shapes are left untouched, as the shapes refer to specific vertices.
+    pub fn optimize_part(&mut self, lod_index: usize, part_index: usize) -> usize {
+        let Some(part) = self
+            .lods
+            .get(lod_index)
+            .and_then(|lod| lod.parts.get(part_index))
+        else {
+            return 0;
+        };
 
-        for lod in 0..3 {
-            for shape in &mut self.model_data.shapes {
-                shape.shape_mesh_count[lod] = 0;
-                shape.shape_mesh_start_index[lod] = 0;
-            }
+        if !part.shapes.is_empty() {
+            return 0;
         }
 
-        self.update_headers();
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(part.vertices.len());
+        let mut welded: HashMap<Vec<i64>, u16> = HashMap::new();
+        let remap: Vec<u16> = part
+            .vertices
+            .iter()
+            .map(|vertex| {
+                *welded.entry(vertex.weld_key()).or_insert_with(|| {
+                    vertices.push(*vertex);
+                    (vertices.len() - 1) as u16
+                })
+            })
+            .collect();
+
+        let removed = part.vertices.len() - vertices.len();
+        if removed == 0 {
+            return 0;
+        }
+
+        let indices: Vec<u16> = part
+            .indices
+            .iter()
+            .map(|index| remap.get(*index as usize).copied().unwrap_or(*index))
+            .collect();
+        let submeshes = part.submeshes.clone();
+
+        if self
+            .replace_vertices(lod_index, part_index, &vertices, &indices, &submeshes)
+            .is_err()
+        {
+            return 0;
+        }
+
+        removed
     }
 
-    pub fn add_shape_mesh(
+    /// Same as [MDL::replace_vertices], but the part is written with `declaration` instead of its current vertex
+    /// declaration. The stream strides and offsets are updated to fit the new layout. This can be used to add or
+    /// remove vertex attributes, such as vertex colors.
+    ///
+    /// Every element in `declaration` has to be something [MDL::write_to_buffer] can write from a [Vertex], otherwise
+    /// the model is left untouched and an error is returned. The same goes for submeshes that don't fit within `indices`.
+    pub fn replace_vertices_with_declaration(
         &mut self,
         lod_index: usize,
-        shape_index: usize,
-        shape_mesh_index: usize,
         part_index: usize,
-        shape_values: &[NewShapeValue],
-    ) {
-        let part = &mut self.lods[lod_index].parts[part_index];
+        vertices: &[Vertex],
+        indices: &[u16],
+        submeshes: &[SubMesh],
+        declaration: VertexDeclaration,
+    ) -> Result<(), ModelError> {
+        MDL::validate_submeshes(indices, submeshes)?;
 
-        // TODO: this is assuming they are added in order
-        if shape_mesh_index == 0 {
-            self.model_data.shapes[shape_index].shape_mesh_start_index[lod_index] =
-                self.model_data.shape_meshes.len() as u16;
-        }
+        let mesh_index = self.lods[lod_index].parts[part_index].mesh_index;
 
-        self.model_data.shape_meshes.push(ShapeMesh {
-            mesh_index_offset: self.model_data.meshes[part.mesh_index as usize].start_index,
+        let mut strides = [0u8; 3];
+        for element in &declaration.elements {
+            if !MDL::is_writable_element(element) {
+                return Err(ModelError::UnsupportedVertexElement(*element));
+            }
+
+            let end = element.offset as usize + get_vertex_type_size(element.vertex_type);
+            let stride = strides.get_mut(element.stream as usize).ok_or(
+                ModelError::InvalidVertexStream {
+                    mesh: mesh_index,
+                    stream: element.stream,
+                },
+            )?;
+            *stride = (*stride as usize).max(end).try_into().map_err(|_| {
+                ModelError::InvalidVertexStream {
+                    mesh: mesh_index,
+                    stream: element.stream,
+                }
+            })?;
+        }
+
+        let mesh = &mut self.model_data.meshes[mesh_index as usize];
+        mesh.vertex_buffer_strides = strides;
+        mesh.vertex_stream_count = declaration
+            .elements
+            .iter()
+            .map(|element| element.stream + 1)
+            .max()
+            .unwrap_or(0);
+        self.model_data.header.vertex_declarations[mesh_index as usize] = declaration;
+
+        self.replace_vertices(lod_index, part_index, vertices, indices, submeshes)
+    }
+
+    /// Whether [MDL::write_to_buffer] is able to write `element`.
+    fn is_writable_element(element: &VertexElement) -> bool {
+        match element.vertex_usage {
+            VertexUsage::Position => matches!(
+                element.vertex_type,
+                VertexType::Single4 | VertexType::Half4 | VertexType::Single3
+            ),
+            VertexUsage::BlendWeights => matches!(
+                element.vertex_type,
+                VertexType::ByteFloat4 | VertexType::Byte4
+            ),
+            VertexUsage::BlendIndices => element.vertex_type == VertexType::Byte4,
+            VertexUsage::Normal => {
+                matches!(element.vertex_type, VertexType::Half4 | VertexType::Single3)
+            }
+            VertexUsage::UV => {
+                matches!(element.vertex_type, VertexType::Half4 | VertexType::Single4)
+            }
+            VertexUsage::BiTangent => element.vertex_type == VertexType::ByteFloat4,
+            VertexUsage::Tangent => false,
+            VertexUsage::Color => element.vertex_type == VertexType::ByteFloat4,
+        }
+    }
+
+    /// Returns the names of the attributes (such as "atr_leg") enabled for a submesh, in bit order of
+    /// its attribute mask. Returns an empty list if the submesh doesn't exist.
+    pub fn submesh_attributes(&self, lod: usize, part: usize, submesh: usize) -> Vec<String> {
+        let Some(submesh) = self
+            .lods
+            .get(lod)
+            .and_then(|lod| lod.parts.get(part))
+            .and_then(|part| part.submeshes.get(submesh))
+        else {
+            return vec![];
+        };
+
+        let mask = self.model_data.submeshes[submesh.submesh_index].attribute_index_mask;
+        let strings = &self.model_data.header.strings;
+
+        self.model_data
+            .attribute_name_offsets
+            .iter()
+            .take(u32::BITS as usize)
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .filter_map(|(_, offset)| read_cstring(strings, *offset as usize))
+            .collect()
+    }
+
+    /// Returns the layout of the vertices of a part, which describes the usage, type, stream and offset of each of its
+    /// vertex attributes. This can be used along with [MDL::raw_lod_buffers] to use the vertices as-is in a renderer.
+    /// Returns None if the part doesn't exist.
+    pub fn vertex_declaration(&self, lod: usize, part: usize) -> Option<&VertexDeclaration> {
+        let part = self.lods.get(lod)?.parts.get(part)?;
+
+        self.model_data
+            .header
+            .vertex_declarations
+            .get(part.mesh_index as usize)
+    }
+
+    /// Returns the element ids of this model, with their parent bone names read from the string table. A name that
+    /// can't be read is left empty.
+    pub fn element_ids(&self) -> Vec<ElementIdInfo> {
+        self.model_data
+            .element_ids
+            .iter()
+            .map(|element_id| ElementIdInfo {
+                element_id: element_id.element_id,
+                parent_bone: read_cstring(
+                    &self.model_data.header.strings,
+                    element_id.parent_bone_name as usize,
+                )
+                .unwrap_or_default(),
+                translate: element_id.translate,
+                rotate: element_id.rotate,
+            })
+            .collect()
+    }
+
+    /// Returns the vertex and index totals of a LOD, and where its buffers are. Returns None if the
+    /// LOD doesn't exist.
+    pub fn lod_info(&self, lod_index: usize) -> Option<LodInfo> {
+        if lod_index >= self.lods.len() {
+            return None;
+        }
+
+        self.model_data.lod_info(lod_index)
+    }
+
+    /// Returns the vertex and index buffers of a LOD as they are stored in the file, for uploading them to the GPU
+    /// as-is. The vertex buffer has each mesh's streams interleaved according to its vertex declaration, at the
+    /// `vertex_buffer_offsets` of the mesh. The index buffer is made up of little-endian `u16` indices, with each mesh
+    /// starting at its `start_index`. The sizes match those of [MDL::lod_info].
+    ///
+    /// The buffers are encoded from the vertices and indices of each part, so any changes made to the model are
+    /// included.
+    pub fn raw_lod_buffers(&self, lod_index: usize) -> Option<(Vec<u8>, Vec<u8>)> {
+        let info = self.lod_info(lod_index)?;
+
+        let mut vertices = Cursor::new(vec![0u8; info.vertex_buffer_size as usize]);
+        let mut indices = Cursor::new(vec![0u8; info.index_buffer_size as usize]);
+
+        for part in &self.lods[lod_index].parts {
+            self.write_part_vertices(&mut vertices, part, 0)?;
+
+            let start_index = self
+                .model_data
+                .meshes
+                .get(part.mesh_index as usize)?
+                .start_index;
+            indices
+                .seek(SeekFrom::Start(
+                    start_index as u64 * size_of::<u16>() as u64,
+                ))
+                .ok()?;
+            indices.write_le(&part.indices).ok()?;
+        }
+
+        let (vertices, indices) = (vertices.into_inner(), indices.into_inner());
+
+        // writing past the sizes in the header means they are out of date
+        if vertices.len() != info.vertex_buffer_size as usize
+            || indices.len() != info.index_buffer_size as usize
+        {
+            return None;
+        }
+
+        Some((vertices, indices))
+    }
+
+    /// Compares this model to `other`, such as an edited copy of it, and returns what changed between
+    /// them. Neither model is modified.
+    pub fn diff(&self, other: &MDL) -> ModelDiff {
+        let mut diff = ModelDiff {
+            added_materials: other
+                .material_names
+                .iter()
+                .filter(|name| !self.material_names.contains(name))
+                .cloned()
+                .collect(),
+            removed_materials: self
+                .material_names
+                .iter()
+                .filter(|name| !other.material_names.contains(name))
+                .cloned()
+                .collect(),
+            bounding_box_changed: self.model_data.bounding_box != other.model_data.bounding_box,
+            ..Default::default()
+        };
+
+        for lod_index in 0..self.lods.len().max(other.lods.len()) {
+            let parts = |mdl: &MDL| mdl.lods.get(lod_index).map_or(0, |lod| lod.parts.len());
+
+            for part_index in 0..parts(self).max(parts(other)) {
+                let (vertices, indices, material, bounds) =
+                    self.part_summary(lod_index, part_index);
+                let (other_vertices, other_indices, other_material, other_bounds) =
+                    other.part_summary(lod_index, part_index);
+
+                let part_diff = PartDiff {
+                    lod_index,
+                    part_index,
+                    vertex_count: (vertices != other_vertices)
+                        .then_some((vertices, other_vertices)),
+                    index_count: (indices != other_indices).then_some((indices, other_indices)),
+                    material: (material != other_material).then_some((material, other_material)),
+                    bounding_box_changed: bounds != other_bounds,
+                };
+
+                if part_diff.vertex_count.is_some()
+                    || part_diff.index_count.is_some()
+                    || part_diff.material.is_some()
+                    || part_diff.bounding_box_changed
+                {
+                    diff.parts.push(part_diff);
+                }
+            }
+        }
+
+        diff
+    }
+
+    /// Returns the vertex count, index count, material name and the bounds of the vertex positions
+    /// of a part, which are all empty if it doesn't exist.
+    #[allow(clippy::type_complexity)]
+    fn part_summary(
+        &self,
+        lod_index: usize,
+        part_index: usize,
+    ) -> (usize, usize, String, Option<([f32; 3], [f32; 3])>) {
+        let Some(part) = self
+            .lods
+            .get(lod_index)
+            .and_then(|lod| lod.parts.get(part_index))
+        else {
+            return (0, 0, String::new(), None);
+        };
+
+        let material = self
+            .material_names
+            .get(part.material_index as usize)
+            .cloned()
+            .unwrap_or_default();
+
+        let bounds = part.vertices.iter().fold(None, |bounds, vertex| {
+            let (mut min, mut max) = bounds.unwrap_or((vertex.position, vertex.position));
+            for i in 0..3 {
+                min[i] = min[i].min(vertex.position[i]);
+                max[i] = max[i].max(vertex.position[i]);
+            }
+            Some((min, max))
+        });
+
+        (part.vertices.len(), part.indices.len(), material, bounds)
+    }
+
+    /// Renames the bones used by this model according to `mapping`, which maps old bone names to new
+    /// ones, such as when retargeting it to a different skeleton. Bones not in `mapping` keep their name.
+    ///
+    /// The string table is rebuilt, so the rest of the file is moved to make room for the new names.
+    pub fn remap_bones(&mut self, mapping: &HashMap<String, String>) {
+        for name in &mut self.affected_bone_names {
+            if let Some(new_name) = mapping.get(name) {
+                *name = new_name.clone();
+            }
+        }
+
+        self.rebuild_strings();
+    }
+
+    /// Renames the material `old_name` to `new_material`, such as when retexturing a model. Every part using it is
+    /// affected. Returns false if the model doesn't use `old_name`.
+    ///
+    /// The string table is rebuilt, so the rest of the file is moved to make room for the new name.
+    pub fn set_material(&mut self, old_name: &str, new_material: &str) -> bool {
+        let Some(name) = self
+            .material_names
+            .iter_mut()
+            .find(|name| *name == old_name)
+        else {
+            return false;
+        };
+        *name = new_material.to_string();
+
+        self.rebuild_strings();
+
+        true
+    }
+
+    /// Changes the material of a single part to `new_material`. If the model doesn't use that material yet, it's
+    /// added to `material_names`. Returns false if the part doesn't exist.
+    ///
+    /// The string table is rebuilt, so the rest of the file is moved to make room for the new name.
+    pub fn set_part_material(
+        &mut self,
+        lod_index: usize,
+        part_index: usize,
+        new_material: &str,
+    ) -> bool {
+        let Some(part) = self
+            .lods
+            .get_mut(lod_index)
+            .and_then(|lod| lod.parts.get_mut(part_index))
+        else {
+            return false;
+        };
+
+        let material_index = match self
+            .material_names
+            .iter()
+            .position(|name| name == new_material)
+        {
+            Some(index) => index,
+            None => {
+                self.material_names.push(new_material.to_string());
+                self.model_data.material_name_offsets.push(0);
+                self.model_data.header.material_count = self.material_names.len() as u16;
+                self.file_header.material_count = self.material_names.len() as u16;
+
+                self.material_names.len() - 1
+            }
+        } as u16;
+
+        part.material_index = material_index;
+        self.model_data.meshes[part.mesh_index as usize].material_index = material_index;
+
+        self.rebuild_strings();
+
+        true
+    }
+
+    /// Regenerates the string table from `affected_bone_names` and `material_names`, so renaming a bone
+    /// or material is reflected in [MDL::write_to_buffer]. Attribute and shape names are kept as-is.
+    ///
+    /// Only renaming is supported, adding or removing names from either list is ignored.
+    pub fn rebuild_strings(&mut self) {
+        let old_strings = std::mem::take(&mut self.model_data.header.strings);
+        let read_string =
+            |offset: u32| read_cstring(&old_strings, offset as usize).unwrap_or_default();
+
+        let mut strings = vec![];
+        let mut string_count = 0;
+        let mut push_string = |string: &str| -> u32 {
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(string.as_bytes());
+            strings.push(0);
+            string_count += 1;
+
+            offset
+        };
+
+        // keep the same order the game uses: attributes, bones, materials and then shapes
+        for offset in &mut self.model_data.attribute_name_offsets {
+            *offset = push_string(&read_string(*offset));
+        }
+
+        let old_bone_offsets = self.model_data.bone_name_offsets.clone();
+        for (offset, name) in self
+            .model_data
+            .bone_name_offsets
+            .iter_mut()
+            .zip(&self.affected_bone_names)
+        {
+            *offset = push_string(name);
+        }
+
+        for (offset, name) in self
+            .model_data
+            .material_name_offsets
+            .iter_mut()
+            .zip(&self.material_names)
+        {
+            *offset = push_string(name);
+        }
+
+        for shape in &mut self.model_data.shapes {
+            shape.string_offset = push_string(&read_string(shape.string_offset));
+        }
+
+        // element ids point to one of the bone names
+        for element_id in &mut self.model_data.element_ids {
+            element_id.parent_bone_name = match old_bone_offsets
+                .iter()
+                .position(|offset| *offset == element_id.parent_bone_name)
+            {
+                Some(bone) => self.model_data.bone_name_offsets[bone],
+                None => push_string(&read_string(element_id.parent_bone_name)),
+            };
+        }
+
+        // the string table is padded to 4 bytes
+        strings.resize(strings.len().next_multiple_of(4), 0);
+
+        self.model_data.header.string_count = string_count;
+        self.model_data.header.string_size = strings.len() as u32;
+        self.model_data.header.strings = strings;
+
+        self.update_headers();
+    }
+
+    /// Checks that every bone table only references bones that exist in this model. Returns the index
+    /// of each bone table with an invalid entry, along with the bone index it references.
+    pub fn validate_bone_tables(&self) -> Vec<(usize, u16)> {
+        let bone_count = self.affected_bone_names.len();
+
+        let tables = self
+            .model_data
+            .bone_tables
+            .iter()
+            .map(|table| &table.bone_indices[..(table.bone_count as usize).min(64)])
+            .chain(
+                self.model_data
+                    .bone_tables_v2
+                    .iter()
+                    .map(|table| table.bone_indices.as_slice()),
+            );
+
+        tables
+            .enumerate()
+            .flat_map(|(i, indices)| {
+                indices
+                    .iter()
+                    .filter(|index| **index as usize >= bone_count)
+                    .map(move |index| (i, *index))
+            })
+            .collect()
+    }
+
+    pub fn remove_shape_meshes(&mut self) {
+        self.model_data.shape_meshes.clear();
+        self.model_data.shape_values.clear();
+
+        for lod in 0..3 {
+            for shape in &mut self.model_data.shapes {
+                shape.shape_mesh_count[lod] = 0;
+                shape.shape_mesh_start_index[lod] = 0;
+            }
+        }
+
+        self.update_headers();
+    }
+
+    pub fn add_shape_mesh(
+        &mut self,
+        lod_index: usize,
+        shape_index: usize,
+        shape_mesh_index: usize,
+        part_index: usize,
+        shape_values: &[NewShapeValue],
+    ) {
+        let part = &mut self.lods[lod_index].parts[part_index];
+
+        // TODO: this is assuming they are added in order
+        if shape_mesh_index == 0 {
+            self.model_data.shapes[shape_index].shape_mesh_start_index[lod_index] =
+                self.model_data.shape_meshes.len() as u16;
+        }
+
+        self.model_data.shape_meshes.push(ShapeMesh {
+            mesh_index_offset: self.model_data.meshes[part.mesh_index as usize].start_index,
             shape_value_count: shape_values.len() as u32,
             shape_value_offset: self.model_data.shape_values.len() as u32,
         });
@@ -920,7 +2232,123 @@ impl MDL {
         self.update_headers();
     }
 
+    /// Removes every LOD after the first `keep` ones, along with their meshes, submeshes and shape meshes, such as
+    /// keeping only LOD 0 to make a mod smaller. Does nothing if `keep` is 0, or the model doesn't have more LODs than
+    /// that.
+    ///
+    /// The meshes of the removed LODs are expected to come after those of the kept ones, like they do in the game's
+    /// models.
+    pub fn truncate_lods(&mut self, keep: usize) {
+        if keep == 0 || keep >= self.lods.len() {
+            return;
+        }
+
+        let mesh_end = self.model_data.lods[..keep]
+            .iter()
+            .map(MeshLod::mesh_end)
+            .max()
+            .unwrap_or(0);
+        let meshes = mesh_end as usize;
+        let submeshes = self.model_data.meshes[..meshes]
+            .iter()
+            .map(|mesh| (mesh.submesh_index + mesh.submesh_count) as usize)
+            .max()
+            .unwrap_or(0);
+
+        self.model_data.meshes.truncate(meshes);
+        self.model_data.header.vertex_declarations.truncate(meshes);
+        self.model_data.submeshes.truncate(submeshes);
+        self.model_data.header.mesh_count = meshes as u16;
+        self.model_data.header.submesh_count = submeshes as u16;
+        self.file_header.vertex_declaration_count = meshes as u16;
+
+        // drop the shape meshes of the removed LODs, and the shape values only they used
+        let dropped_shape_meshes: Vec<usize> = self
+            .model_data
+            .shapes
+            .iter()
+            .flat_map(|shape| {
+                (keep..3).flat_map(|lod| {
+                    let start = shape.shape_mesh_start_index[lod] as usize;
+                    start..start + shape.shape_mesh_count[lod] as usize
+                })
+            })
+            .collect();
+
+        let mut shape_meshes = vec![];
+        let mut shape_values = vec![];
+        let mut new_shape_mesh_indices = vec![];
+        for (i, shape_mesh) in self.model_data.shape_meshes.iter().enumerate() {
+            new_shape_mesh_indices.push(shape_meshes.len() as u16);
+            if dropped_shape_meshes.contains(&i) {
+                continue;
+            }
+
+            let start = shape_mesh.shape_value_offset as usize;
+            let values = self
+                .model_data
+                .shape_values
+                .get(start..start + shape_mesh.shape_value_count as usize)
+                .unwrap_or_default();
+
+            shape_meshes.push(ShapeMesh {
+                shape_value_offset: shape_values.len() as u32,
+                ..shape_mesh.clone()
+            });
+            shape_values.extend_from_slice(values);
+        }
+
+        for shape in &mut self.model_data.shapes {
+            for lod in 0..3 {
+                if lod < keep {
+                    let start = shape.shape_mesh_start_index[lod] as usize;
+                    shape.shape_mesh_start_index[lod] = new_shape_mesh_indices
+                        .get(start)
+                        .copied()
+                        .unwrap_or(shape_meshes.len() as u16);
+                } else {
+                    shape.shape_mesh_start_index[lod] = 0;
+                    shape.shape_mesh_count[lod] = 0;
+                }
+            }
+        }
+
+        self.model_data.shape_meshes = shape_meshes;
+        self.model_data.shape_values = shape_values;
+
+        for lod in &mut self.model_data.lods[keep..] {
+            *lod = MeshLod::empty(mesh_end, 0);
+        }
+
+        self.lods.truncate(keep);
+        self.file_header.lod_count = keep as u8;
+        self.model_data.header.lod_count = keep as u8;
+
+        self.update_headers();
+
+        // update_headers only fills in the LODs that are left
+        for i in keep..3 {
+            self.file_header.vertex_offsets[i] = 0;
+            self.file_header.index_offsets[i] = 0;
+            self.file_header.vertex_buffer_size[i] = 0;
+            self.file_header.index_buffer_size[i] = 0;
+        }
+    }
+
+    fn update_polygon_counts(model_data: &mut ModelData, lods: &[Lod]) {
+        for (model_lod, lod) in model_data.lods.iter_mut().zip(lods) {
+            model_lod.polygon_count = lod.triangle_count() as u32;
+        }
+    }
+
     pub(crate) fn update_headers(&mut self) {
+        // these are needed for the runtime size below
+        self.model_data.header.shape_count = self.model_data.shapes.len() as u16;
+        self.model_data.header.shape_mesh_count = self.model_data.shape_meshes.len() as u16;
+        self.model_data.header.shape_value_count = self.model_data.shape_values.len() as u16;
+
+        MDL::update_polygon_counts(&mut self.model_data, &self.lods);
+
         // update values
         for i in 0..self.file_header.lod_count {
             let mut vertex_offset = 0;
@@ -934,10 +2362,15 @@ impl MDL {
                 mesh.start_index =
                     self.model_data.submeshes[mesh.submesh_index as usize].index_offset;
 
-                for i in 0..mesh.vertex_stream_count as usize {
-                    mesh.vertex_buffer_offsets[i] = vertex_offset;
-                    vertex_offset +=
-                        mesh.vertex_count as u32 * mesh.vertex_buffer_strides[i] as u32;
+                let stream_count = mesh.vertex_stream_count as usize;
+                for (offset, stride) in mesh
+                    .vertex_buffer_offsets
+                    .iter_mut()
+                    .zip(mesh.vertex_buffer_strides)
+                    .take(stream_count)
+                {
+                    *offset = vertex_offset;
+                    vertex_offset += mesh.vertex_count as u32 * stride as u32;
                 }
             }
         }
@@ -951,11 +2384,13 @@ impl MDL {
                 let vertex_count = self.model_data.meshes[j as usize].vertex_count;
                 let index_count = self.model_data.meshes[j as usize].index_count;
 
-                let mut total_vertex_stride: u32 = 0;
-                for i in 0..self.model_data.meshes[j as usize].vertex_stream_count as usize {
-                    total_vertex_stride +=
-                        self.model_data.meshes[j as usize].vertex_buffer_strides[i] as u32;
-                }
+                let mesh = &self.model_data.meshes[j as usize];
+                let total_vertex_stride: u32 = mesh
+                    .vertex_buffer_strides
+                    .iter()
+                    .take(mesh.vertex_stream_count as usize)
+                    .map(|stride| *stride as u32)
+                    .sum();
 
                 total_vertex_buffer_size += vertex_count as u32 * total_vertex_stride;
                 total_index_buffer_size += index_count * size_of::<u16>() as u32;
@@ -983,22 +2418,27 @@ impl MDL {
 
         let mut overall_offset: u32 = 0;
 
-        for lod in &mut self.model_data.lods {
+        for (i, lod) in self.model_data.lods.iter_mut().enumerate() {
             // vertex
             lod.vertex_data_offset = data_offset + overall_offset;
             overall_offset += lod.vertex_buffer_size;
 
+            // edge, which sits between the vertex and index data
+            lod.edge_geometry_size = self
+                .lods
+                .get(i)
+                .map_or(0, |lod| lod.edge_geometry.len() as u32);
+            lod.edge_geometry_data_offset = data_offset + overall_offset;
+            overall_offset += lod.edge_geometry_size;
+
             // index
             lod.index_data_offset = data_offset + overall_offset;
             overall_offset += lod.index_buffer_size;
-
-            // edge, but unused?
-            //lod.edge_geometry_data_offset = data_offset + overall_offset;
-            //overall_offset += lod.edge_geometry_size;
-
-            lod.edge_geometry_data_offset = lod.index_data_offset;
         }
 
+        self.file_header.has_edge_geometry =
+            self.lods.iter().any(|lod| !lod.edge_geometry.is_empty());
+
         for i in 0..self.lods.len() {
             self.file_header.vertex_buffer_size[i] = self.model_data.lods[i].vertex_buffer_size;
         }
@@ -1014,10 +2454,218 @@ impl MDL {
         for i in 0..self.lods.len() {
             self.file_header.index_offsets[i] = self.model_data.lods[i].index_data_offset;
         }
+    }
 
-        self.model_data.header.shape_count = self.model_data.shapes.len() as u16;
-        self.model_data.header.shape_mesh_count = self.model_data.shape_meshes.len() as u16;
-        self.model_data.header.shape_value_count = self.model_data.shape_values.len() as u16;
+    /// Returns how large the file from [MDL::write_to_buffer] will be, without writing it. This includes the headers,
+    /// and the vertex, edge geometry and index buffers of every LOD.
+    pub fn estimated_file_size(&self) -> usize {
+        let buffers_size: usize = self
+            .model_data
+            .lods
+            .iter()
+            .zip(&self.lods)
+            .map(|(lod_header, lod)| {
+                lod_header.vertex_buffer_size as usize
+                    + lod.edge_geometry.len()
+                    + lod_header.index_buffer_size as usize
+            })
+            .sum();
+
+        size_of::<ModelFileHeader>()
+            + self.file_header.calculate_stack_size() as usize
+            + self.model_data.calculate_runtime_size() as usize
+            + buffers_size
+    }
+
+    /// Same as [MDL::write_to_buffer], but the model is written according to `options`. The model itself is left as-is.
+    pub fn write_to_buffer_with_options(&self, options: ModelWriteOptions) -> Option<ByteBuffer> {
+        if !options.single_precision_positions {
+            return self.write_to_buffer();
+        }
+
+        let mut mdl = self.clone();
+        mdl.promote_positions()?;
+        mdl.write_to_buffer()
+    }
+
+    /// Changes every `Half4` position into a `Single3` one, moving the elements after it in the same stream.
+    /// Returns None if a stream would become larger than 255 bytes.
+    fn promote_positions(&mut self) -> Option<()> {
+        let growth = (get_vertex_type_size(VertexType::Single3)
+            - get_vertex_type_size(VertexType::Half4)) as u8;
+
+        for (declaration, mesh) in self
+            .model_data
+            .header
+            .vertex_declarations
+            .iter_mut()
+            .zip(self.model_data.meshes.iter_mut())
+        {
+            let Some(position) = declaration.elements.iter().copied().find(|element| {
+                element.vertex_usage == VertexUsage::Position
+                    && element.vertex_type == VertexType::Half4
+            }) else {
+                continue;
+            };
+
+            for element in &mut declaration.elements {
+                if element.stream == position.stream && element.offset > position.offset {
+                    element.offset = element.offset.checked_add(growth)?;
+                }
+            }
+
+            for element in &mut declaration.elements {
+                if element.vertex_usage == VertexUsage::Position
+                    && element.stream == position.stream
+                    && element.offset == position.offset
+                {
+                    element.vertex_type = VertexType::Single3;
+                }
+            }
+
+            let stride = mesh
+                .vertex_buffer_strides
+                .get_mut(position.stream as usize)?;
+            *stride = stride.checked_add(growth)?;
+        }
+
+        self.update_headers();
+
+        Some(())
+    }
+
+    /// Writes the vertices of `part` according to its vertex declaration, with the vertex buffer of its LOD starting
+    /// at `vertex_data_offset`.
+    fn write_part_vertices<W: BinWriterExt>(
+        &self,
+        cursor: &mut W,
+        part: &Part,
+        vertex_data_offset: u32,
+    ) -> Option<()> {
+        let declaration = &self.model_data.header.vertex_declarations[part.mesh_index as usize];
+
+        for (k, vert) in part.vertices.iter().enumerate() {
+            for element in &declaration.elements {
+                cursor
+                    .seek(SeekFrom::Start(
+                        (vertex_data_offset
+                            + self.model_data.meshes[part.mesh_index as usize]
+                                .vertex_buffer_offsets[element.stream as usize]
+                            + element.offset as u32
+                            + self.model_data.meshes[part.mesh_index as usize].vertex_buffer_strides
+                                [element.stream as usize] as u32
+                                * k as u32) as u64,
+                    ))
+                    .ok()?;
+
+                match element.vertex_usage {
+                    VertexUsage::Position => match element.vertex_type {
+                        VertexType::Single4 => {
+                            MDL::write_single4(cursor, &MDL::pad_slice(&vert.position, 1.0))
+                                .ok()?;
+                        }
+                        VertexType::Half4 => {
+                            MDL::write_half4(cursor, &MDL::pad_slice(&vert.position, 1.0)).ok()?;
+                        }
+                        VertexType::Single3 => {
+                            MDL::write_single3(cursor, &vert.position).ok()?;
+                        }
+                        _ => {
+                            return None;
+                        }
+                    },
+                    VertexUsage::BlendWeights => {
+                        let bone_weight = match element.usage_index {
+                            0 => &vert.bone_weight,
+                            _ => &vert.bone_weight2,
+                        };
+
+                        match element.vertex_type {
+                            VertexType::ByteFloat4 => {
+                                MDL::write_byte_float4(cursor, bone_weight).ok()?;
+                            }
+                            VertexType::Byte4 => {
+                                MDL::write_byte_float42(cursor, bone_weight).ok()?;
+                                // TODO: WRONG!
+                            }
+                            _ => {
+                                return None;
+                            }
+                        }
+                    }
+                    VertexUsage::BlendIndices => {
+                        let bone_id = match element.usage_index {
+                            0 => &vert.bone_id,
+                            _ => &vert.bone_id2,
+                        };
+
+                        match element.vertex_type {
+                            VertexType::Byte4 => {
+                                MDL::write_byte4(cursor, bone_id).ok()?;
+                            }
+                            _ => {
+                                return None;
+                            }
+                        }
+                    }
+                    VertexUsage::Normal => match element.vertex_type {
+                        VertexType::Half4 => {
+                            MDL::write_half4(cursor, &MDL::pad_slice(&vert.normal, 0.0)).ok()?;
+                        }
+                        VertexType::Single3 => {
+                            MDL::write_single3(cursor, &vert.normal).ok()?;
+                        }
+                        _ => {
+                            return None;
+                        }
+                    },
+                    VertexUsage::UV => match element.vertex_type {
+                        VertexType::Half4 => {
+                            let combined = [vert.uv0[0], vert.uv0[1], vert.uv1[0], vert.uv1[1]];
+
+                            MDL::write_half4(cursor, &combined).ok()?;
+                        }
+                        VertexType::Single4 => {
+                            let combined = [vert.uv0[0], vert.uv0[1], vert.uv1[0], vert.uv1[1]];
+
+                            MDL::write_single4(cursor, &combined).ok()?;
+                        }
+                        _ => {
+                            return None;
+                        }
+                    },
+                    VertexUsage::BiTangent => match element.vertex_type {
+                        VertexType::ByteFloat4 => {
+                            MDL::write_tangent(cursor, &vert.bitangent).ok()?;
+                        }
+                        _ => {
+                            return None;
+                        }
+                    },
+                    VertexUsage::Tangent => {
+                        #[allow(clippy::match_single_binding)] // TODO
+                        match element.vertex_type {
+                            /*VertexType::ByteFloat4 => {
+                                MDL::write_tangent(cursor, &vert.binormal).ok()?;
+                            }*/
+                            _ => {
+                                return None;
+                            }
+                        }
+                    }
+                    VertexUsage::Color => match element.vertex_type {
+                        VertexType::ByteFloat4 => {
+                            MDL::write_byte_float4(cursor, &vert.color).ok()?;
+                        }
+                        _ => {
+                            return None;
+                        }
+                    },
+                }
+            }
+        }
+
+        Some(())
     }
 
     pub fn write_to_buffer(&self) -> Option<ByteBuffer> {
@@ -1027,162 +2675,23 @@ impl MDL {
             let mut cursor = Cursor::new(&mut buffer);
 
             // write file header
-            self.file_header.write(&mut cursor).ok()?;
+            self.file_header.write_le(&mut cursor).ok()?;
+
+            // the indices can be changed directly, so make sure the polygon counts aren't stale
+            let mut model_data = self.model_data.clone();
+            MDL::update_polygon_counts(&mut model_data, &self.lods);
 
-            self.model_data.write(&mut cursor).ok()?;
+            model_data
+                .write_le_args(&mut cursor, binrw::args! { file_header: &self.file_header })
+                .ok()?;
 
             for (l, lod) in self.lods.iter().enumerate() {
                 for part in lod.parts.iter() {
-                    let declaration =
-                        &self.model_data.header.vertex_declarations[part.mesh_index as usize];
-
-                    for (k, vert) in part.vertices.iter().enumerate() {
-                        for element in &declaration.elements {
-                            cursor
-                                .seek(SeekFrom::Start(
-                                    (self.model_data.lods[l].vertex_data_offset
-                                        + self.model_data.meshes[part.mesh_index as usize]
-                                            .vertex_buffer_offsets
-                                            [element.stream as usize]
-                                        + element.offset as u32
-                                        + self.model_data.meshes[part.mesh_index as usize]
-                                            .vertex_buffer_strides
-                                            [element.stream as usize]
-                                            as u32
-                                            * k as u32) as u64,
-                                ))
-                                .ok()?;
-
-                            match element.vertex_usage {
-                                VertexUsage::Position => match element.vertex_type {
-                                    VertexType::Single4 => {
-                                        MDL::write_single4(
-                                            &mut cursor,
-                                            &MDL::pad_slice(&vert.position, 1.0),
-                                        )
-                                        .ok()?;
-                                    }
-                                    VertexType::Half4 => {
-                                        MDL::write_half4(
-                                            &mut cursor,
-                                            &MDL::pad_slice(&vert.position, 1.0),
-                                        )
-                                        .ok()?;
-                                    }
-                                    VertexType::Single3 => {
-                                        MDL::write_single3(&mut cursor, &vert.position).ok()?;
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for position: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                                VertexUsage::BlendWeights => match element.vertex_type {
-                                    VertexType::ByteFloat4 => {
-                                        MDL::write_byte_float4(&mut cursor, &vert.bone_weight)
-                                            .ok()?;
-                                    }
-                                    VertexType::Byte4 => {
-                                        MDL::write_byte_float42(&mut cursor, &vert.bone_weight)
-                                            .ok()?; // TODO: WRONG!
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for blendweight: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                                VertexUsage::BlendIndices => match element.vertex_type {
-                                    VertexType::Byte4 => {
-                                        MDL::write_byte4(&mut cursor, &vert.bone_id).ok()?;
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for blendindice: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                                VertexUsage::Normal => match element.vertex_type {
-                                    VertexType::Half4 => {
-                                        MDL::write_half4(
-                                            &mut cursor,
-                                            &MDL::pad_slice(&vert.normal, 0.0),
-                                        )
-                                        .ok()?;
-                                    }
-                                    VertexType::Single3 => {
-                                        MDL::write_single3(&mut cursor, &vert.normal).ok()?;
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for normal: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                                VertexUsage::UV => match element.vertex_type {
-                                    VertexType::Half4 => {
-                                        let combined =
-                                            [vert.uv0[0], vert.uv0[1], vert.uv1[0], vert.uv1[1]];
-
-                                        MDL::write_half4(&mut cursor, &combined).ok()?;
-                                    }
-                                    VertexType::Single4 => {
-                                        let combined =
-                                            [vert.uv0[0], vert.uv0[1], vert.uv1[0], vert.uv1[1]];
-
-                                        MDL::write_single4(&mut cursor, &combined).ok()?;
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for uv: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                                VertexUsage::BiTangent => match element.vertex_type {
-                                    VertexType::ByteFloat4 => {
-                                        MDL::write_tangent(&mut cursor, &vert.bitangent).ok()?;
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for bitangent: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                                VertexUsage::Tangent => {
-                                    #[allow(clippy::match_single_binding)] // TODO
-                                    match element.vertex_type {
-                                        /*VertexType::ByteFloat4 => {
-                                            MDL::write_tangent(&mut cursor, &vert.binormal).ok()?;
-                                        }*/
-                                        _ => {
-                                            panic!(
-                                                "Unexpected vertex type for tangent: {:#?}",
-                                                element.vertex_type
-                                            );
-                                        }
-                                    }
-                                }
-                                VertexUsage::Color => match element.vertex_type {
-                                    VertexType::ByteFloat4 => {
-                                        MDL::write_byte_float4(&mut cursor, &vert.color).ok()?;
-                                    }
-                                    _ => {
-                                        panic!(
-                                            "Unexpected vertex type for color: {:#?}",
-                                            element.vertex_type
-                                        );
-                                    }
-                                },
-                            }
-                        }
-                    }
+                    self.write_part_vertices(
+                        &mut cursor,
+                        part,
+                        self.model_data.lods[l].vertex_data_offset,
+                    )?;
 
                     cursor
                         .seek(SeekFrom::Start(
@@ -1194,14 +2703,38 @@ impl MDL {
 
                     cursor.write_le(&part.indices).ok()?;
                 }
+
+                if !lod.edge_geometry.is_empty() {
+                    cursor
+                        .seek(SeekFrom::Start(
+                            self.model_data.lods[l].edge_geometry_data_offset as u64,
+                        ))
+                        .ok()?;
+
+                    cursor.write_le(&lod.edge_geometry).ok()?;
+                }
             }
         }
 
+        // the index buffers are padded, which isn't covered by the indices themselves
+        let end = self
+            .model_data
+            .lods
+            .iter()
+            .take(self.lods.len())
+            .map(|lod| (lod.index_data_offset + lod.index_buffer_size) as usize)
+            .max()
+            .unwrap_or(0);
+        if buffer.len() < end {
+            buffer.resize(end, 0);
+        }
+
         Some(buffer)
     }
 }
 
 impl ModelFileHeader {
+    /// Calculates the size of the vertex declarations that follow this header.
     pub fn calculate_stack_size(&self) -> u32 {
         // From https://github.com/Ottermandias/Penumbra.GameData/blob/44021b93e6901c84b739bbf4d1c6350f4486cdbf/Files/MdlFile.cs#L11
         self.vertex_declaration_count as u32 * NUM_VERTICES * VERTEX_ELEMENT_SIZE as u32
@@ -1210,6 +2743,26 @@ impl ModelFileHeader {
 
 // TODO: From Xande, need to be cleaned up :)
 impl ModelData {
+    fn lod_info(&self, lod_index: usize) -> Option<LodInfo> {
+        let lod = self.lods.get(lod_index)?;
+        let mesh_range = lod.mesh_index..lod.mesh_index + lod.mesh_count;
+        let meshes = self
+            .meshes
+            .get(mesh_range.start as usize..mesh_range.end as usize)?;
+
+        Some(LodInfo {
+            vertex_count: meshes.iter().map(|mesh| mesh.vertex_count as u32).sum(),
+            index_count: meshes.iter().map(|mesh| mesh.index_count).sum(),
+            mesh_range,
+            polygon_count: lod.polygon_count,
+            vertex_buffer_size: lod.vertex_buffer_size,
+            index_buffer_size: lod.index_buffer_size,
+            vertex_data_offset: lod.vertex_data_offset,
+            index_data_offset: lod.index_data_offset,
+        })
+    }
+
+    /// Calculates the size of this model data once written, excluding the vertex declarations and buffers.
     pub fn calculate_runtime_size(&self) -> u32 {
         2   //StringCount
         + 2 // Unknown
@@ -1248,6 +2801,45 @@ mod tests {
 
     use super::*;
 
+    /// A declaration with positions of `position_type` followed by UVs in the same stream. The UVs are half-precision
+    /// if the positions are, and single-precision otherwise.
+    fn position_uv_declaration(position_type: VertexType) -> VertexDeclaration {
+        let uv_type = match position_type {
+            VertexType::Half4 => VertexType::Half4,
+            _ => VertexType::Single4,
+        };
+
+        VertexDeclaration {
+            elements: vec![
+                VertexElement {
+                    stream: 0,
+                    offset: 0,
+                    vertex_type: position_type,
+                    vertex_usage: VertexUsage::Position,
+                    usage_index: 0,
+                },
+                VertexElement {
+                    stream: 0,
+                    offset: get_vertex_type_size(position_type) as u8,
+                    vertex_type: uv_type,
+                    vertex_usage: VertexUsage::UV,
+                    usage_index: 0,
+                },
+            ],
+        }
+    }
+
+    /// The polygon counts are zeroed in the test model, so this returns a copy with the counts that are recalculated
+    /// when the headers are updated.
+    fn with_polygon_counts(mdl: &MDL) -> MDL {
+        let mut mdl = mdl.clone();
+        for (model_lod, lod) in mdl.model_data.lods.iter_mut().zip(&mdl.lods) {
+            model_lod.polygon_count = lod.triangle_count() as u32;
+        }
+
+        mdl
+    }
+
     #[test]
     fn test_file_header_size() {
         assert_eq!(0x44, size_of::<ModelFileHeader>());
@@ -1302,7 +2894,7 @@ mod tests {
         d.push("c0201e0038_top_zeroed.mdl");
 
         let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
-        let old_mdl = mdl.clone();
+        let old_mdl = with_polygon_counts(&mdl);
 
         mdl.update_headers();
 
@@ -1312,7 +2904,65 @@ mod tests {
     }
 
     #[test]
-    fn test_update_vertices() {
+    fn test_write_headers() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+        let mdl = MDL::from_existing(&mdl_file).unwrap();
+        let buffer = mdl.write_to_buffer().unwrap();
+
+        // the file header and vertex declarations should be written back as-is
+        let stack_end = size_of::<ModelFileHeader>() + mdl.file_header.stack_size as usize;
+        assert_eq!(buffer[..stack_end], mdl_file[..stack_end]);
+
+        // and so should the model data, except for the polygon counts which are zeroed in the test file
+        let mut expected = mdl.model_data.clone();
+        for (model_lod, lod) in expected.lods.iter_mut().zip(&mdl.lods) {
+            assert_eq!(model_lod.polygon_count, 0);
+            model_lod.polygon_count = lod.triangle_count() as u32;
+        }
+        assert_eq!(MDL::from_existing(&buffer).unwrap().model_data, expected);
+    }
+
+    #[test]
+    fn test_estimated_file_size() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+        let mut mdl = MDL::from_existing(&mdl_file).unwrap();
+        assert_eq!(
+            mdl.estimated_file_size(),
+            mdl.write_to_buffer().unwrap().len()
+        );
+
+        // still matches after the buffers change size
+        let part = &mdl.lods[0].parts[0];
+        let vertices = part.vertices[..3].to_vec();
+        // only the first submesh is kept
+        let submeshes: Vec<SubMesh> = part
+            .submeshes
+            .iter()
+            .enumerate()
+            .map(|(i, submesh)| SubMesh {
+                index_offset: part.submeshes[0].index_offset,
+                index_count: if i == 0 { 3 } else { 0 },
+                ..*submesh
+            })
+            .collect();
+        mdl.replace_vertices(0, 0, &vertices, &[0, 1, 2], &submeshes)
+            .unwrap();
+        assert_eq!(
+            mdl.estimated_file_size(),
+            mdl.write_to_buffer().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_invalid_submesh() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.push("resources/tests");
         d.push("c0201e0038_top_zeroed.mdl");
@@ -1320,6 +2970,34 @@ mod tests {
         let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
         let old_mdl = mdl.clone();
 
+        let part = &old_mdl.lods[0].parts[0];
+        let mut submeshes = part.submeshes.clone();
+        let last = submeshes.len() - 1;
+        submeshes[last].index_count += 3;
+
+        assert_eq!(
+            mdl.replace_vertices(0, 0, &part.vertices, &part.indices, &submeshes),
+            Err(ModelError::InvalidSubmesh {
+                submesh: last,
+                index_offset: submeshes[last].index_offset,
+                index_count: submeshes[last].index_count,
+            })
+        );
+
+        // nothing should have been changed
+        assert_eq!(mdl.model_data, old_mdl.model_data);
+        assert_eq!(mdl.lods[0].parts[0].indices, part.indices);
+    }
+
+    #[test]
+    fn test_update_vertices() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        let old_mdl = with_polygon_counts(&mdl);
+
         for l in 0..old_mdl.lods.len() {
             for p in 0..old_mdl.lods[l].parts.len() {
                 mdl.replace_vertices(
@@ -1328,7 +3006,8 @@ mod tests {
                     &old_mdl.lods[l].parts[p].vertices,
                     &old_mdl.lods[l].parts[p].indices,
                     &old_mdl.lods[l].parts[p].submeshes,
-                );
+                )
+                .unwrap();
             }
         }
 
@@ -1367,6 +3046,1056 @@ mod tests {
         assert_eq!(mdl.model_data.header.radius, 1.5340779);
     }
 
+    #[test]
+    fn test_load_options() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+        let mdl = MDL::from_existing(&mdl_file).unwrap();
+
+        // the defaults shouldn't change anything
+        let unchanged =
+            MDL::from_existing_with_options(&mdl_file, ModelLoadOptions::default()).unwrap();
+        assert_eq!(
+            unchanged.lods[0].parts[0].vertices,
+            mdl.lods[0].parts[0].vertices
+        );
+        assert_eq!(
+            unchanged.lods[0].parts[0].indices,
+            mdl.lods[0].parts[0].indices
+        );
+
+        let converted = MDL::from_existing_with_options(
+            &mdl_file,
+            ModelLoadOptions {
+                flip_v: true,
+                convert_handedness: true,
+            },
+        )
+        .unwrap();
+
+        let original = &mdl.lods[0].parts[0];
+        let part = &converted.lods[0].parts[0];
+        for (vertex, original) in part.vertices.iter().zip(&original.vertices) {
+            assert_eq!(vertex.uv0, [original.uv0[0], -original.uv0[1]]);
+            assert_eq!(
+                vertex.position,
+                [
+                    original.position[0],
+                    original.position[1],
+                    -original.position[2]
+                ]
+            );
+            assert_eq!(vertex.normal[2], -original.normal[2]);
+        }
+        assert_eq!(
+            part.indices[0..3],
+            [
+                original.indices[0],
+                original.indices[2],
+                original.indices[1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new() {
+        use crate::model_vertex_declarations::VertexElement;
+
+        let element = |stream, offset, vertex_type, vertex_usage| VertexElement {
+            stream,
+            offset,
+            vertex_type,
+            vertex_usage,
+            usage_index: 0,
+        };
+        let declaration = VertexDeclaration {
+            elements: vec![
+                element(0, 0, VertexType::Single3, VertexUsage::Position),
+                element(1, 0, VertexType::Half4, VertexUsage::Normal),
+                element(1, 8, VertexType::Half4, VertexUsage::UV),
+            ],
+        };
+
+        let vertex = |position: [f32; 3], uv0: [f32; 2]| Vertex {
+            position,
+            uv0,
+            normal: [0.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        let quad = [
+            vertex([-1.0, -1.0, 0.0], [0.0, 1.0]),
+            vertex([1.0, -1.0, 0.0], [1.0, 1.0]),
+            vertex([1.0, 1.0, 0.0], [1.0, 0.0]),
+            vertex([-1.0, 1.0, 0.0], [0.0, 0.0]),
+        ];
+        let triangle = [
+            vertex([0.0, 0.0, 2.0], [0.0, 0.0]),
+            vertex([1.0, 0.0, 2.0], [1.0, 0.0]),
+            vertex([0.0, 1.0, 2.0], [0.0, 1.0]),
+        ];
+
+        let mut builder = MeshBuilder::new();
+        builder.add_part(
+            &quad,
+            &[0, 1, 2, 0, 2, 3],
+            "/mt_test_a.mtrl",
+            declaration.clone(),
+        );
+        builder.add_part(
+            &triangle,
+            &[0, 1, 2],
+            "/mt_test_b.mtrl",
+            declaration.clone(),
+        );
+
+        let mdl = MDL::new(builder).unwrap();
+
+        // everything should survive being written and read again
+        let buffer = mdl.write_to_buffer().unwrap();
+        let mdl = MDL::parse(&buffer).unwrap();
+
+        assert_eq!(mdl.lods.len(), 1);
+        assert_eq!(
+            mdl.material_names,
+            vec!["/mt_test_a.mtrl", "/mt_test_b.mtrl"]
+        );
+        assert_eq!(
+            mdl.file_header.runtime_size,
+            mdl.model_data.calculate_runtime_size()
+        );
+        assert_eq!(
+            mdl.file_header.stack_size,
+            mdl.file_header.calculate_stack_size()
+        );
+
+        let parts = &mdl.lods[0].parts;
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].vertices, quad);
+        assert_eq!(parts[0].indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(parts[0].material_index, 0);
+        assert_eq!(parts[1].vertices, triangle);
+        assert_eq!(parts[1].indices, vec![0, 1, 2]);
+        assert_eq!(parts[1].material_index, 1);
+        assert_eq!(mdl.vertex_declaration(0, 1), Some(&declaration));
+        assert_eq!(mdl.vertex_declaration(0, 2), None);
+        assert_eq!(mdl.vertex_declaration(1, 0), None);
+
+        assert_eq!(mdl.model_data.bounding_box.min, [-1.0, -1.0, 0.0, 1.0]);
+        assert_eq!(mdl.model_data.bounding_box.max, [1.0, 1.0, 2.0, 1.0]);
+
+        // indices have to point to an existing vertex
+        let mut builder = MeshBuilder::new();
+        builder.add_part(
+            &triangle,
+            &[0, 1, 3],
+            "/mt_test_a.mtrl",
+            VertexDeclaration { elements: vec![] },
+        );
+        assert!(MDL::new(builder).is_none());
+        assert!(MDL::new(MeshBuilder::new()).is_none());
+    }
+
+    #[test]
+    fn test_remap_bones() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        assert!(mdl.validate_bone_tables().is_empty());
+
+        let attributes = mdl.submesh_attributes(0, 0, 4);
+        let old_bone_names = mdl.affected_bone_names.clone();
+        let old_vertices = mdl.lods[0].parts[0].vertices.clone();
+
+        let mapping = HashMap::from([
+            ("j_kosi".to_string(), "j_kosi_retargeted".to_string()),
+            ("n_hte_l".to_string(), "n_l".to_string()),
+        ]);
+        mdl.remap_bones(&mapping);
+
+        assert_eq!(
+            mdl.file_header.runtime_size,
+            mdl.model_data.calculate_runtime_size()
+        );
+
+        // the new names should be there after reading it back, and nothing else changed
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        for (old_name, new_name) in old_bone_names.iter().zip(&mdl.affected_bone_names) {
+            assert_eq!(mapping.get(old_name).unwrap_or(old_name), new_name);
+        }
+        assert!(mdl
+            .affected_bone_names
+            .contains(&"j_kosi_retargeted".to_string()));
+        assert_eq!(mdl.submesh_attributes(0, 0, 4), attributes);
+        assert_eq!(mdl.lods[0].parts[0].vertices, old_vertices);
+        assert_eq!(
+            mdl.material_names,
+            vec![
+                "/mt_c0201e0038_top_a.mtrl".to_string(),
+                "/mt_c0201b0001_a.mtrl".to_string()
+            ]
+        );
+        assert_eq!(mdl.model_data.header.string_count, 41);
+
+        // point the first bone table to a bone that doesn't exist
+        let mut mdl = mdl;
+        mdl.model_data.bone_tables[0].bone_indices[1] = 100;
+        assert_eq!(mdl.validate_bone_tables(), vec![(0, 100)]);
+    }
+
+    #[test]
+    fn test_rename_material() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        let bone_names = mdl.affected_bone_names.clone();
+
+        mdl.material_names[0] = "/mt_c0201e6180_top_b.mtrl".to_string();
+        mdl.rebuild_strings();
+
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        assert_eq!(
+            mdl.material_names,
+            vec![
+                "/mt_c0201e6180_top_b.mtrl".to_string(),
+                "/mt_c0201b0001_a.mtrl".to_string()
+            ]
+        );
+        assert_eq!(mdl.affected_bone_names, bone_names);
+    }
+
+    #[test]
+    fn test_set_material() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        assert!(!mdl.set_material("/mt_missing.mtrl", "/mt_c0201e6180_top_b.mtrl"));
+        assert!(mdl.set_material("/mt_c0201e0038_top_a.mtrl", "/mt_c0201e6180_top_b.mtrl"));
+
+        let mut mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        assert_eq!(
+            mdl.material_names,
+            vec![
+                "/mt_c0201e6180_top_b.mtrl".to_string(),
+                "/mt_c0201b0001_a.mtrl".to_string()
+            ]
+        );
+
+        // a material the model doesn't use yet is added
+        assert!(!mdl.set_part_material(0, 100, "/mt_c0201e6180_top_c.mtrl"));
+        assert!(mdl.set_part_material(0, 0, "/mt_c0201e6180_top_c.mtrl"));
+
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        assert_eq!(mdl.material_names.len(), 3);
+        assert_eq!(mdl.material_names[2], "/mt_c0201e6180_top_c.mtrl");
+        assert_eq!(mdl.lods[0].parts[0].material_index, 2);
+        assert_eq!(
+            mdl.estimated_file_size(),
+            mdl.write_to_buffer().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_edge_geometry() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        assert!(mdl.lods.iter().all(|lod| lod.edge_geometry.is_empty()));
+
+        let edge_geometry: Vec<u8> = (0..100).collect();
+        mdl.lods[1].edge_geometry = edge_geometry.clone();
+        mdl.update_headers();
+
+        let buffer = mdl.write_to_buffer().unwrap();
+        let new_mdl = MDL::from_existing(&buffer).unwrap();
+
+        assert!(new_mdl.file_header.has_edge_geometry);
+        assert!(new_mdl.lods[0].edge_geometry.is_empty());
+        assert_eq!(new_mdl.lods[1].edge_geometry, edge_geometry);
+        assert!(new_mdl.lods[2].edge_geometry.is_empty());
+
+        // it sits between the vertex and index data
+        let lod = &new_mdl.model_data.lods[1];
+        assert_eq!(
+            lod.edge_geometry_data_offset,
+            lod.vertex_data_offset + lod.vertex_buffer_size
+        );
+        assert_eq!(
+            lod.index_data_offset,
+            lod.edge_geometry_data_offset + edge_geometry.len() as u32
+        );
+
+        // the rest of the model is unaffected
+        for (lod, new_lod) in mdl.lods.iter().zip(&new_mdl.lods) {
+            for (part, new_part) in lod.parts.iter().zip(&new_lod.parts) {
+                assert_eq!(part.indices, new_part.indices);
+                assert_eq!(part.vertices.len(), new_part.vertices.len());
+            }
+        }
+
+        assert_eq!(new_mdl.write_to_buffer().unwrap(), buffer);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        assert!(mdl.diff(&mdl.clone()).is_empty());
+
+        // move a vertex and drop the last triangle of a single part
+        let mut edited = mdl.clone();
+        let part = &mdl.lods[0].parts[1];
+        let mut vertices = part.vertices.clone();
+        vertices[0].position[0] += 100.0;
+        let indices = &part.indices[..part.indices.len() - 3];
+        let mut submeshes = part.submeshes.clone();
+        submeshes.last_mut().unwrap().index_count -= 3;
+        edited
+            .replace_vertices(0, 1, &vertices, indices, &submeshes)
+            .unwrap();
+
+        let diff = mdl.diff(&edited);
+        assert_eq!(
+            diff.parts,
+            vec![PartDiff {
+                lod_index: 0,
+                part_index: 1,
+                vertex_count: None,
+                index_count: Some((part.indices.len(), indices.len())),
+                material: None,
+                bounding_box_changed: true,
+            }]
+        );
+        assert!(diff.added_materials.is_empty());
+        assert!(diff.removed_materials.is_empty());
+
+        // renaming a material affects every part using it
+        let mut edited = mdl.clone();
+        edited.material_names[0] = "/mt_c0201e6180_top_b.mtrl".to_string();
+
+        let diff = mdl.diff(&edited);
+        assert_eq!(diff.added_materials, vec!["/mt_c0201e6180_top_b.mtrl"]);
+        assert_eq!(diff.removed_materials, vec![mdl.material_names[0].clone()]);
+        assert!(diff.parts.iter().all(|part| part.material.is_some()));
+        assert_eq!(
+            diff.parts.len(),
+            mdl.lods
+                .iter()
+                .flat_map(|lod| &lod.parts)
+                .filter(|part| part.material_index == 0)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_submesh_slices() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        for part in mdl.lods.iter().flat_map(|lod| &lod.parts) {
+            // the submeshes cover the whole part
+            let indices: Vec<u16> = part
+                .submeshes
+                .iter()
+                .flat_map(|submesh| part.submesh_indices(submesh).to_vec())
+                .collect();
+            assert_eq!(indices, part.indices);
+
+            for submesh in &part.submeshes {
+                let indices = part.submesh_indices(submesh);
+                assert_eq!(indices.len(), submesh.index_count as usize);
+
+                let vertices = part.submesh_vertices(submesh);
+                assert_eq!(vertices.len(), indices.len());
+                for (vertex, index) in vertices.iter().zip(indices) {
+                    assert_eq!(vertex.position, part.vertices[*index as usize].position);
+                }
+            }
+        }
+
+        let part = &mdl.lods[0].parts[0];
+        let mut submesh = part.submeshes[0];
+        submesh.index_offset += part.indices.len() as u32;
+        assert!(part.submesh_indices(&submesh).is_empty());
+        assert!(part.submesh_vertices(&submesh).is_empty());
+
+        submesh.index_offset = 0;
+        submesh.index_count = u32::MAX;
+        assert!(part.submesh_indices(&submesh).is_empty());
+    }
+
+    #[test]
+    fn test_header_only() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+        let mdl = MDL::from_existing(&mdl_file).unwrap();
+        let info = MDL::header_only(&mdl_file).unwrap();
+
+        assert_eq!(info.version, mdl.file_header.version);
+        assert_eq!(info.material_names, mdl.material_names);
+        assert_eq!(info.affected_bone_names, mdl.affected_bone_names);
+        assert_eq!(info.lods.len(), mdl.lods.len());
+        assert_eq!(info.lods[0], mdl.lod_info(0).unwrap());
+        assert_eq!(
+            info.mesh_count as usize,
+            mdl.lods.iter().map(|lod| lod.parts.len()).sum::<usize>()
+        );
+        assert_eq!(info.bounding_box_min, mdl.model_data.bounding_box.min);
+        assert_eq!(info.bounding_box_max, mdl.model_data.bounding_box.max);
+
+        // the header is still validated
+        let mut random = mdl_file.clone();
+        random[0] = 0xFF;
+        assert!(MDL::header_only(&random).is_none());
+    }
+
+    #[test]
+    fn test_info_display() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let info = MDL::header_only(&read(d).unwrap()).unwrap();
+        let text = info.to_string();
+
+        assert!(text.starts_with("Model version 0x1000005\n"));
+        assert!(text.contains(&format!("{} meshes", info.mesh_count)));
+        assert!(text.contains(&info.material_names[0]));
+        assert!(!text.ends_with('\n'));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_info_json() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let info = MDL::header_only(&read(d).unwrap()).unwrap();
+        let json = serde_json::to_value(&info).unwrap();
+
+        assert_eq!(json["version"], info.version);
+        assert_eq!(json["material_names"][0], info.material_names[0]);
+        assert_eq!(
+            json["affected_bone_names"].as_array().unwrap().len(),
+            info.affected_bone_names.len()
+        );
+        assert_eq!(json["lods"][0]["vertex_count"], info.lods[0].vertex_count);
+        assert_eq!(
+            json["lods"][0]["mesh_range"]["end"],
+            info.lods[0].mesh_range.end
+        );
+        assert_eq!(json["bounding_box_min"].as_array().unwrap().len(), 4);
+
+        let submesh = serde_json::to_value(SubMesh {
+            submesh_index: 3,
+            index_count: 6,
+            index_offset: 12,
+        })
+        .unwrap();
+        assert_eq!(
+            submesh,
+            serde_json::json!({ "index_count": 6, "index_offset": 12 })
+        );
+    }
+
+    #[test]
+    fn test_optimize_part() {
+        let declaration = position_uv_declaration(VertexType::Single3);
+
+        let vertex = |position: [f32; 3], uv0: [f32; 2]| Vertex {
+            position,
+            uv0,
+            ..Default::default()
+        };
+
+        // a quad where each triangle has its own vertices, and one of them is very slightly off
+        let vertices = [
+            vertex([-1.0, -1.0, 0.0], [0.0, 1.0]),
+            vertex([1.0, -1.0, 0.0], [1.0, 1.0]),
+            vertex([1.0, 1.0, 0.0], [1.0, 0.0]),
+            vertex([-1.0, -1.0, 0.0], [0.0, 1.0]),
+            vertex([1.0, 1.0 + 1e-7, 0.0], [1.0, 0.0]),
+            vertex([-1.0, 1.0, 0.0], [0.0, 0.0]),
+        ];
+        let indices = [0, 1, 2, 3, 4, 5];
+
+        let mut builder = MeshBuilder::new();
+        builder.add_part(&vertices, &indices, "/mt_test_a.mtrl", declaration);
+        let mut mdl = MDL::new(builder).unwrap();
+
+        assert_eq!(mdl.optimize_part(0, 1), 0);
+        assert_eq!(mdl.optimize_part(0, 0), 2);
+        assert_eq!(mdl.optimize_part(0, 0), 0);
+
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        let part = &mdl.lods[0].parts[0];
+        assert_eq!(part.vertices.len(), 4);
+        assert_eq!(part.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(part.submeshes[0].index_count, 6);
+
+        // the triangles are still the same
+        for (index, original_index) in part.indices.iter().zip(indices) {
+            let position = part.vertices[*index as usize].position;
+            let original_position = vertices[original_index as usize].position;
+            for i in 0..3 {
+                assert!((position[i] - original_position[i]).abs() < WELD_EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let declaration = position_uv_declaration(VertexType::Single3);
+
+        let vertices = [
+            Vertex {
+                position: [0.0, 1.0, 2.0],
+                uv0: [0.25, 0.5],
+                ..Default::default()
+            },
+            Vertex {
+                position: [-1.0, 0.5, 3.0],
+                uv0: [1.0, 0.0],
+                ..Default::default()
+            },
+            Vertex {
+                position: [4.0, -2.0, 0.0],
+                uv0: [0.0, 1.0],
+                ..Default::default()
+            },
+        ];
+
+        let mut builder = MeshBuilder::new();
+        builder.add_part(&vertices, &[0, 2, 1], "/mt_test_a.mtrl", declaration);
+        let mdl = MDL::new(builder).unwrap();
+        let little_endian = mdl.write_to_buffer().unwrap();
+
+        // byte-swap the headers, and every float and index that follows
+        let mut big_endian = little_endian.clone();
+        {
+            let mut cursor = Cursor::new(&mut big_endian);
+            mdl.file_header.write_be(&mut cursor).unwrap();
+            mdl.model_data
+                .write_be_args(&mut cursor, binrw::args! { file_header: &mdl.file_header })
+                .unwrap();
+        }
+
+        let vertex_start = mdl.file_header.vertex_offsets[0] as usize;
+        let vertex_end = vertex_start + mdl.file_header.vertex_buffer_size[0] as usize;
+        big_endian[vertex_start..vertex_end]
+            .chunks_exact_mut(size_of::<f32>())
+            .for_each(|float| float.reverse());
+
+        let index_start = mdl.file_header.index_offsets[0] as usize;
+        let index_end = index_start + mdl.file_header.index_buffer_size[0] as usize;
+        big_endian[index_start..index_end]
+            .chunks_exact_mut(size_of::<u16>())
+            .for_each(|index| index.reverse());
+
+        assert_ne!(big_endian, little_endian);
+
+        let mdl = MDL::parse_platform(&big_endian, Platform::PS3).unwrap();
+        assert_eq!(mdl.material_names, vec!["/mt_test_a.mtrl".to_string()]);
+
+        let part = &mdl.lods[0].parts[0];
+        assert_eq!(part.indices, vec![0, 2, 1]);
+        for (vertex, expected) in part.vertices.iter().zip(&vertices) {
+            assert_eq!(vertex.position, expected.position);
+            assert_eq!(vertex.uv0, expected.uv0);
+        }
+
+        // reading it as a little-endian model shouldn't work
+        assert!(MDL::from_existing_platform(&big_endian, Platform::Win32).is_none());
+    }
+
+    #[test]
+    fn test_replace_vertex_declaration() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+        let mut mdl = MDL::from_existing(&mdl_file).unwrap();
+
+        let part = &mdl.lods[0].parts[0];
+        let vertices = part.vertices.clone();
+        let indices = part.indices.clone();
+        let submeshes = part.submeshes.clone();
+
+        // a tangent can't be written from a vertex
+        let mut declaration = mdl.model_data.header.vertex_declarations[0].clone();
+        declaration.elements.push(VertexElement {
+            stream: 1,
+            offset: 64,
+            vertex_type: VertexType::ByteFloat4,
+            vertex_usage: VertexUsage::Tangent,
+            usage_index: 0,
+        });
+        assert!(matches!(
+            mdl.replace_vertices_with_declaration(
+                0,
+                0,
+                &vertices,
+                &indices,
+                &submeshes,
+                declaration
+            ),
+            Err(ModelError::UnsupportedVertexElement(_))
+        ));
+
+        // everything in a single stream, with only positions and UVs
+        let declaration = VertexDeclaration {
+            elements: vec![
+                VertexElement {
+                    stream: 0,
+                    offset: 0,
+                    vertex_type: VertexType::Single3,
+                    vertex_usage: VertexUsage::Position,
+                    usage_index: 0,
+                },
+                VertexElement {
+                    stream: 0,
+                    offset: 12,
+                    vertex_type: VertexType::Single4,
+                    vertex_usage: VertexUsage::UV,
+                    usage_index: 0,
+                },
+            ],
+        };
+        mdl.replace_vertices_with_declaration(
+            0,
+            0,
+            &vertices,
+            &indices,
+            &submeshes,
+            declaration.clone(),
+        )
+        .unwrap();
+
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        assert_eq!(mdl.model_data.header.vertex_declarations[0], declaration);
+        assert_eq!(mdl.model_data.meshes[0].vertex_stream_count, 1);
+        assert_eq!(mdl.model_data.meshes[0].vertex_buffer_strides[0], 28);
+
+        let part = &mdl.lods[0].parts[0];
+        assert_eq!(part.vertex_stream_strides, vec![28]);
+        for (vertex, original) in part.vertices.iter().zip(&vertices) {
+            assert_eq!(vertex.position, original.position);
+            assert_eq!(vertex.uv0, original.uv0);
+            assert_eq!(vertex.normal, [0.0; 3]);
+        }
+        assert_eq!(part.indices, indices);
+    }
+
+    #[test]
+    fn test_write_options() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        // the test model already has single-precision positions, so make a half-precision one first
+        let half_declaration = position_uv_declaration(VertexType::Half4);
+        let part = mdl.lods[0].parts[0].clone();
+        mdl.replace_vertices_with_declaration(
+            0,
+            0,
+            &part.vertices,
+            &part.indices,
+            &part.submeshes,
+            half_declaration.clone(),
+        )
+        .unwrap();
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+
+        // the defaults shouldn't change anything
+        assert_eq!(
+            mdl.write_to_buffer_with_options(ModelWriteOptions::default()),
+            mdl.write_to_buffer()
+        );
+
+        let buffer = mdl
+            .write_to_buffer_with_options(ModelWriteOptions {
+                single_precision_positions: true,
+            })
+            .unwrap();
+        let promoted = MDL::from_existing(&buffer).unwrap();
+
+        // the UVs are moved to make room for the larger positions
+        let elements = &promoted.model_data.header.vertex_declarations[0].elements;
+        assert_eq!(elements[0].vertex_type, VertexType::Single3);
+        assert_eq!(elements[1].vertex_type, VertexType::Half4);
+        assert_eq!(elements[1].offset, 12);
+        assert_eq!(promoted.model_data.meshes[0].vertex_buffer_strides[0], 20);
+        assert_eq!(
+            mdl.model_data.header.vertex_declarations[0],
+            half_declaration
+        );
+
+        // every vertex reads back the same
+        for (part, original) in promoted.lods[0].parts.iter().zip(&mdl.lods[0].parts) {
+            assert_eq!(part.vertices, original.vertices);
+            assert_eq!(part.indices, original.indices);
+        }
+    }
+
+    #[test]
+    fn test_truncate_lods() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let original = MDL::from_existing(&read(d).unwrap()).unwrap();
+        assert_eq!(original.lods.len(), 3);
+
+        let mut mdl = original.clone();
+        mdl.truncate_lods(1);
+
+        let buffer = mdl.write_to_buffer().unwrap();
+        assert_eq!(buffer.len(), mdl.estimated_file_size());
+        assert!(buffer.len() < original.write_to_buffer().unwrap().len());
+
+        let reloaded = MDL::from_existing(&buffer).unwrap();
+        assert_eq!(reloaded.lods.len(), 1);
+        assert_eq!(reloaded.file_header.lod_count, 1);
+        assert_eq!(reloaded.file_header.vertex_offsets[1], 0);
+        assert_eq!(
+            reloaded.model_data.meshes.len(),
+            original.model_data.lods[0].mesh_count as usize
+        );
+
+        // LOD 0 is left as-is
+        assert_eq!(reloaded.lods[0].parts.len(), original.lods[0].parts.len());
+        for (part, original_part) in reloaded.lods[0].parts.iter().zip(&original.lods[0].parts) {
+            assert_eq!(part.vertices, original_part.vertices);
+            assert_eq!(part.indices, original_part.indices);
+            assert_eq!(part.submeshes.len(), original_part.submeshes.len());
+            assert_eq!(part.shapes.len(), original_part.shapes.len());
+        }
+
+        // nothing to remove
+        let mut unchanged = original.clone();
+        unchanged.truncate_lods(0);
+        unchanged.truncate_lods(3);
+        assert_eq!(unchanged.model_data, original.model_data);
+    }
+
+    #[test]
+    fn test_element_ids() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        assert!(mdl.element_ids().is_empty());
+
+        mdl.model_data.element_ids.push(ElementId {
+            element_id: 1,
+            parent_bone_name: mdl.model_data.bone_name_offsets[0],
+            translate: [1.0, 2.0, 3.0],
+            rotate: [0.0, std::f32::consts::FRAC_PI_2, 0.0],
+        });
+        mdl.model_data.header.element_id_count = 1;
+        mdl.update_headers();
+
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        let element_ids = mdl.element_ids();
+        assert_eq!(element_ids.len(), 1);
+        assert_eq!(element_ids[0].element_id, 1);
+        assert_eq!(element_ids[0].parent_bone, mdl.affected_bone_names[0]);
+
+        // rotating a quarter turn around Y moves +X to -Z, before translating
+        let transform = element_ids[0].transform();
+        let point = [1.0, 0.0, 0.0];
+        let transformed: Vec<f32> = (0..3)
+            .map(|row| {
+                (0..3)
+                    .map(|column| transform[column * 4 + row] * point[column])
+                    .sum::<f32>()
+                    + transform[12 + row]
+            })
+            .collect();
+        for (actual, expected) in transformed.iter().zip([1.0, 2.0, 2.0]) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let expected = MDL::from_existing(&read(&d).unwrap()).unwrap();
+
+        let mut file = std::io::BufReader::new(std::fs::File::open(&d).unwrap());
+        let mdl = MDL::from_reader(&mut file).unwrap();
+        assert_eq!(mdl.model_data, expected.model_data);
+        assert_eq!(mdl.write_to_buffer(), expected.write_to_buffer());
+    }
+
+    #[test]
+    fn test_lod_info() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        for (i, lod) in mdl.lods.iter().enumerate() {
+            let info = mdl.lod_info(i).unwrap();
+
+            assert_eq!(info.mesh_range.len(), lod.parts.len());
+            assert_eq!(
+                info.vertex_count as usize,
+                lod.parts
+                    .iter()
+                    .map(|part| part.vertices.len())
+                    .sum::<usize>()
+            );
+            assert_eq!(
+                info.index_count as usize,
+                lod.parts
+                    .iter()
+                    .map(|part| part.indices.len())
+                    .sum::<usize>()
+            );
+            // each mesh's indices are padded, so the buffer is a bit larger
+            assert!(
+                info.index_buffer_size as usize
+                    >= info.index_count as usize * std::mem::size_of::<u16>()
+            );
+        }
+
+        assert!(mdl.lod_info(mdl.lods.len()).is_none());
+    }
+
+    #[test]
+    fn test_triangle_count() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        let part = mdl.lods[0].parts[0].clone();
+        assert_eq!(part.triangle_count(), part.indices.len() / 3);
+
+        // drop every triangle but the first one
+        let submeshes = vec![SubMesh {
+            submesh_index: part.submeshes[0].submesh_index,
+            index_count: 3,
+            index_offset: part.submeshes[0].index_offset,
+        }];
+        mdl.replace_vertices(0, 0, &part.vertices, &part.indices[..3], &submeshes)
+            .unwrap();
+        assert_eq!(mdl.lods[0].parts[0].triangle_count(), 1);
+
+        let expected: usize = mdl.lods[0]
+            .parts
+            .iter()
+            .map(|part| part.indices.len() / 3)
+            .sum();
+        assert_eq!(mdl.lods[0].triangle_count(), expected);
+        assert_eq!(mdl.lod_info(0).unwrap().polygon_count as usize, expected);
+
+        // a stale count is fixed when writing
+        mdl.model_data.lods[0].polygon_count = 0;
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+        assert_eq!(mdl.lod_info(0).unwrap().polygon_count as usize, expected);
+    }
+
+    #[test]
+    fn test_merged_mesh() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        let lod = &mdl.lods[0];
+        let (vertices, indices, submeshes) = lod.merged_mesh();
+
+        assert_eq!(submeshes.len(), lod.parts.len());
+        assert_eq!(
+            vertices.len(),
+            lod.parts
+                .iter()
+                .map(|part| part.vertices.len())
+                .sum::<usize>()
+        );
+
+        let mut base_vertex = 0;
+        for (part, submesh) in lod.parts.iter().zip(&submeshes) {
+            assert_eq!(submesh.material_index, part.material_index);
+
+            // every index still points to the same vertex
+            let start = submesh.index_offset as usize;
+            let merged = &indices[start..start + submesh.index_count as usize];
+            for (index, merged_index) in part.indices.iter().zip(merged) {
+                assert_eq!(*merged_index, base_vertex + *index as u32);
+                assert_eq!(
+                    vertices[*merged_index as usize],
+                    part.vertices[*index as usize]
+                );
+            }
+
+            base_vertex += part.vertices.len() as u32;
+        }
+
+        // parts past 65535 vertices in total don't wrap around
+        let mut part = lod.parts[0].clone();
+        part.vertices = vec![Vertex::default(); 40000];
+        part.indices = vec![0, 1, 39999];
+        let lod = Lod {
+            parts: vec![part.clone(), part],
+            edge_geometry: vec![],
+        };
+
+        let (vertices, indices, submeshes) = lod.merged_mesh();
+        assert_eq!(vertices.len(), 80000);
+        assert_eq!(indices, [0, 1, 39999, 40000, 40001, 79999]);
+        assert_eq!(submeshes[1].index_offset, 3);
+        assert_eq!(submeshes[1].part_index, 1);
+    }
+
+    #[test]
+    fn test_raw_lod_buffers() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let buffer = read(d).unwrap();
+        let mdl = MDL::from_existing(&buffer).unwrap();
+
+        let info = mdl.lod_info(0).unwrap();
+        let (vertices, indices) = mdl.raw_lod_buffers(0).unwrap();
+        assert_eq!(vertices.len(), info.vertex_buffer_size as usize);
+        assert_eq!(indices.len(), info.index_buffer_size as usize);
+
+        // each part's raw streams and indices should be found at the offsets in the mesh headers
+        for part in &mdl.lods[0].parts {
+            let mesh = &mdl.model_data.meshes[part.mesh_index as usize];
+            for (stream, data) in part.vertex_streams.iter().enumerate() {
+                let offset = mesh.vertex_buffer_offsets[stream] as usize;
+                assert_eq!(&vertices[offset..offset + data.len()], data.as_slice());
+            }
+
+            let offset = mesh.start_index as usize * size_of::<u16>();
+            let part_indices: Vec<u16> = indices
+                [offset..offset + part.indices.len() * size_of::<u16>()]
+                .chunks_exact(size_of::<u16>())
+                .map(|index| u16::from_le_bytes([index[0], index[1]]))
+                .collect();
+            assert_eq!(part_indices, part.indices);
+        }
+
+        assert!(mdl.raw_lod_buffers(mdl.lods.len()).is_none());
+    }
+
+    #[test]
+    fn test_submesh_attributes() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        assert!(mdl.submesh_attributes(0, 0, 0).is_empty());
+        assert_eq!(mdl.submesh_attributes(0, 0, 1), vec!["atr_tv_b"]);
+        // mask 0b1100
+        assert_eq!(mdl.submesh_attributes(0, 0, 4), vec!["atr_ude", "atr_tv_e"]);
+        // mask 0b100000100
+        assert_eq!(
+            mdl.submesh_attributes(0, 0, 10),
+            vec!["atr_ude", "atr_tv_d"]
+        );
+
+        assert!(mdl.submesh_attributes(0, 0, 1000).is_empty());
+        assert!(mdl.submesh_attributes(10, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_eight_bone_influences() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+
+        // Repurpose the bitangent and color elements of the first mesh as a second set of blend weights and indices
+        for element in &mut mdl.model_data.header.vertex_declarations[0].elements {
+            match element.vertex_usage {
+                VertexUsage::BiTangent => {
+                    element.vertex_usage = VertexUsage::BlendWeights;
+                    element.usage_index = 1;
+                }
+                VertexUsage::Color => {
+                    element.vertex_usage = VertexUsage::BlendIndices;
+                    element.vertex_type = VertexType::Byte4;
+                    element.usage_index = 1;
+                }
+                _ => {}
+            }
+        }
+
+        let weights = [51.0 / 255.0, 51.0 / 255.0, 34.0 / 255.0, 34.0 / 255.0];
+        for vertex in &mut mdl.lods[0].parts[0].vertices {
+            vertex.bone_weight = weights;
+            vertex.bone_id = [0, 1, 2, 3];
+            vertex.bone_weight2 = weights;
+            vertex.bone_id2 = [4, 5, 6, 7];
+        }
+
+        let mdl = MDL::from_existing(&mdl.write_to_buffer().unwrap()).unwrap();
+
+        for vertex in &mdl.lods[0].parts[0].vertices {
+            assert_eq!(vertex.bone_weight, weights);
+            assert_eq!(vertex.bone_id, [0, 1, 2, 3]);
+            assert_eq!(vertex.bone_weight2, weights);
+            assert_eq!(vertex.bone_id2, [4, 5, 6, 7]);
+        }
+    }
+
+    #[test]
+    fn test_normalize_weights() {
+        let mut vertex = Vertex {
+            bone_weight: [1.0, 1.0, 0.5, 0.5],
+            bone_weight2: [0.5, 0.5, 0.0, 0.0],
+            ..Default::default()
+        };
+        vertex.normalize_weights();
+
+        assert_eq!(vertex.bone_weight, [0.25, 0.25, 0.125, 0.125]);
+        assert_eq!(vertex.bone_weight2, [0.125, 0.125, 0.0, 0.0]);
+
+        // no weights shouldn't produce NaNs
+        let mut vertex = Vertex::default();
+        vertex.normalize_weights();
+        assert_eq!(vertex.bone_weight, [0.0; 4]);
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -1374,6 +4103,106 @@ mod tests {
         d.push("random");
 
         // Feeding it invalid data should not panic
-        MDL::from_existing(&read(d).unwrap());
+        MDL::from_existing(&read(d.clone()).unwrap());
+
+        let random = read(d).unwrap();
+        let version = u32::from_le_bytes(random[0..4].try_into().unwrap());
+        assert_eq!(
+            MDL::parse(&random).unwrap_err(),
+            ModelError::UnsupportedVersion(version)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl_file = read(d).unwrap();
+
+        // the rest of the model is fine, but it would be read with the wrong layout
+        for version in [0x1000004u32, 0x1000007, 0x100] {
+            mdl_file[0..4].copy_from_slice(&version.to_le_bytes());
+            assert_eq!(
+                MDL::parse(&mdl_file).unwrap_err(),
+                ModelError::UnsupportedVersion(version)
+            );
+            assert!(MDL::header_only(&mdl_file).is_none());
+        }
+    }
+
+    #[test]
+    fn test_unsupported_lod_count() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl_file = read(d).unwrap();
+        assert!(MDL::parse(&mdl_file).is_ok());
+
+        // lod_count in the file header
+        mdl_file[0x44 - 4] = 4;
+        assert_eq!(
+            MDL::parse(&mdl_file).unwrap_err(),
+            ModelError::UnsupportedLodCount(4)
+        );
+    }
+
+    #[test]
+    fn test_read_cstring() {
+        let strings = b"j_kosi\0mt_a\0unterminated";
+
+        assert_eq!(read_cstring(strings, 0), Some("j_kosi".to_string()));
+        assert_eq!(read_cstring(strings, 7), Some("mt_a".to_string()));
+        assert_eq!(read_cstring(strings, 6), Some(String::new()));
+        assert_eq!(read_cstring(strings, 12), None);
+        assert_eq!(read_cstring(strings, strings.len() + 10), None);
+    }
+
+    #[test]
+    fn test_string_offset_out_of_bounds() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        mdl.model_data.material_name_offsets[0] = mdl.model_data.header.strings.len() as u32 + 100;
+
+        assert_eq!(
+            MDL::parse(&mdl.write_to_buffer().unwrap()).unwrap_err(),
+            ModelError::ParseError
+        );
+    }
+
+    #[test]
+    fn test_invalid_vertex_stream() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+        let mdl = MDL::from_existing(&mdl_file).unwrap();
+
+        // point an element past the mesh's streams
+        let mut out_of_range = mdl.clone();
+        let stream_count = out_of_range.model_data.meshes[0].vertex_stream_count;
+        out_of_range.model_data.header.vertex_declarations[0].elements[0].stream = stream_count;
+        assert_eq!(
+            MDL::parse(&out_of_range.write_to_buffer().unwrap()).unwrap_err(),
+            ModelError::InvalidVertexStream {
+                mesh: 0,
+                stream: stream_count
+            }
+        );
+
+        // a stream without a stride
+        let mut no_stride = mdl.clone();
+        let stream = no_stride.model_data.header.vertex_declarations[0].elements[0].stream;
+        no_stride.model_data.meshes[0].vertex_buffer_strides[stream as usize] = 0;
+        assert_eq!(
+            MDL::parse(&no_stride.write_to_buffer().unwrap()).unwrap_err(),
+            ModelError::InvalidVertexStream { mesh: 0, stream }
+        );
     }
 }
@@ -1,17 +1,15 @@
 // SPDX-FileCopyrightText: 2023 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::common_file_operations::{decode_half, encode_half};
 use crate::model::MDL;
-use crate::ByteSpan;
-use binrw::{BinReaderExt, BinResult, BinWriterExt};
-use half::f16;
-use std::io::Cursor;
+use binrw::{BinReaderExt, BinResult, BinWriterExt, Endian};
 
 /// Maximum value of byte, used to divide and multiply floats in that space [0.0..1.0] to [0..255]
 const MAX_BYTE_FLOAT: f32 = u8::MAX as f32;
 
 impl MDL {
-    pub(crate) fn read_byte_float4(cursor: &mut Cursor<ByteSpan>) -> Option<[f32; 4]> {
+    pub(crate) fn read_byte_float4<T: BinReaderExt>(cursor: &mut T) -> Option<[f32; 4]> {
         Some([
             (f32::from(cursor.read_le::<u8>().ok()?) / MAX_BYTE_FLOAT),
             (f32::from(cursor.read_le::<u8>().ok()?) / MAX_BYTE_FLOAT),
@@ -44,7 +42,7 @@ impl MDL {
         ])
     }
 
-    pub(crate) fn read_tangent(cursor: &mut Cursor<ByteSpan>) -> Option<[f32; 4]> {
+    pub(crate) fn read_tangent<T: BinReaderExt>(cursor: &mut T) -> Option<[f32; 4]> {
         Some([
             (f32::from(cursor.read_le::<u8>().ok()?) * 2.0 / MAX_BYTE_FLOAT - 1.0),
             (f32::from(cursor.read_le::<u8>().ok()?) * 2.0 / MAX_BYTE_FLOAT - 1.0),
@@ -66,40 +64,37 @@ impl MDL {
         ]) // SqEx uses 0 as -1, not 1
     }
 
-    pub(crate) fn read_half4(cursor: &mut Cursor<ByteSpan>) -> Option<[f32; 4]> {
+    pub(crate) fn read_half4<T: BinReaderExt>(cursor: &mut T, endian: Endian) -> Option<[f32; 4]> {
         Some([
-            f16::from_bits(cursor.read_le::<u16>().ok()?).to_f32(),
-            f16::from_bits(cursor.read_le::<u16>().ok()?).to_f32(),
-            f16::from_bits(cursor.read_le::<u16>().ok()?).to_f32(),
-            f16::from_bits(cursor.read_le::<u16>().ok()?).to_f32(),
+            decode_half(cursor.read_type::<u16>(endian).ok()?),
+            decode_half(cursor.read_type::<u16>(endian).ok()?),
+            decode_half(cursor.read_type::<u16>(endian).ok()?),
+            decode_half(cursor.read_type::<u16>(endian).ok()?),
         ])
     }
 
     pub(crate) fn write_half4<T: BinWriterExt>(cursor: &mut T, vec: &[f32; 4]) -> BinResult<()> {
         cursor.write_le::<[u16; 4]>(&[
-            f16::from_f32(vec[0]).to_bits(),
-            f16::from_f32(vec[1]).to_bits(),
-            f16::from_f32(vec[2]).to_bits(),
-            f16::from_f32(vec[3]).to_bits(),
+            encode_half(vec[0]),
+            encode_half(vec[1]),
+            encode_half(vec[2]),
+            encode_half(vec[3]),
         ])
     }
 
-    pub(crate) fn read_half2(cursor: &mut Cursor<ByteSpan>) -> Option<[f32; 2]> {
+    pub(crate) fn read_half2<T: BinReaderExt>(cursor: &mut T, endian: Endian) -> Option<[f32; 2]> {
         Some([
-            f16::from_bits(cursor.read_le::<u16>().ok()?).to_f32(),
-            f16::from_bits(cursor.read_le::<u16>().ok()?).to_f32(),
+            decode_half(cursor.read_type::<u16>(endian).ok()?),
+            decode_half(cursor.read_type::<u16>(endian).ok()?),
         ])
     }
 
     #[allow(dead_code)] // We will eventually use this
     pub(crate) fn write_half2<T: BinWriterExt>(cursor: &mut T, vec: &[f32; 2]) -> BinResult<()> {
-        cursor.write_le::<[u16; 2]>(&[
-            f16::from_f32(vec[0]).to_bits(),
-            f16::from_f32(vec[1]).to_bits(),
-        ])
+        cursor.write_le::<[u16; 2]>(&[encode_half(vec[0]), encode_half(vec[1])])
     }
 
-    pub(crate) fn read_byte4(cursor: &mut Cursor<ByteSpan>) -> BinResult<[u8; 4]> {
+    pub(crate) fn read_byte4<T: BinReaderExt>(cursor: &mut T) -> BinResult<[u8; 4]> {
         cursor.read_le::<[u8; 4]>()
     }
 
@@ -107,24 +102,33 @@ impl MDL {
         cursor.write_le::<[u8; 4]>(vec)
     }
 
-    pub(crate) fn read_single3(cursor: &mut Cursor<ByteSpan>) -> BinResult<[f32; 3]> {
-        cursor.read_le::<[f32; 3]>()
+    pub(crate) fn read_single3<T: BinReaderExt>(
+        cursor: &mut T,
+        endian: Endian,
+    ) -> BinResult<[f32; 3]> {
+        cursor.read_type::<[f32; 3]>(endian)
     }
 
     pub(crate) fn write_single3<T: BinWriterExt>(cursor: &mut T, vec: &[f32; 3]) -> BinResult<()> {
         cursor.write_le::<[f32; 3]>(vec)
     }
 
-    pub(crate) fn read_single4(cursor: &mut Cursor<ByteSpan>) -> BinResult<[f32; 4]> {
-        cursor.read_le::<[f32; 4]>()
+    pub(crate) fn read_single4<T: BinReaderExt>(
+        cursor: &mut T,
+        endian: Endian,
+    ) -> BinResult<[f32; 4]> {
+        cursor.read_type::<[f32; 4]>(endian)
     }
 
     pub(crate) fn write_single4<T: BinWriterExt>(cursor: &mut T, vec: &[f32; 4]) -> BinResult<()> {
         cursor.write_le::<[f32; 4]>(vec)
     }
 
-    pub(crate) fn read_unsigned_short4(cursor: &mut Cursor<ByteSpan>) -> BinResult<[u16; 4]> {
-        cursor.read_le::<[u16; 4]>()
+    pub(crate) fn read_unsigned_short4<T: BinReaderExt>(
+        cursor: &mut T,
+        endian: Endian,
+    ) -> BinResult<[u16; 4]> {
+        cursor.read_type::<[u16; 4]>(endian)
     }
 
     pub(crate) fn pad_slice<const N: usize>(small_slice: &[f32; N], fill: f32) -> [f32; 4] {
@@ -137,6 +141,7 @@ impl MDL {
 #[cfg(test)]
 mod tests {
     use crate::model::MDL;
+    use binrw::Endian;
     use std::io::Cursor;
 
     macro_rules! assert_delta {
@@ -174,7 +179,10 @@ mod tests {
         MDL::write_half4(&mut cursor, &a).unwrap();
 
         let mut read_cursor = Cursor::new(v.as_slice());
-        assert_eq!(MDL::read_half4(&mut read_cursor).unwrap(), a);
+        assert_eq!(
+            MDL::read_half4(&mut read_cursor, Endian::Little).unwrap(),
+            a
+        );
     }
 
     #[test]
@@ -187,7 +195,10 @@ mod tests {
         MDL::write_half2(&mut cursor, &a).unwrap();
 
         let mut read_cursor = Cursor::new(v.as_slice());
-        assert_eq!(MDL::read_half2(&mut read_cursor).unwrap(), a);
+        assert_eq!(
+            MDL::read_half2(&mut read_cursor, Endian::Little).unwrap(),
+            a
+        );
     }
 
     #[test]
@@ -213,7 +224,10 @@ mod tests {
         MDL::write_single3(&mut cursor, &a).unwrap();
 
         let mut read_cursor = Cursor::new(v.as_slice());
-        assert_eq!(MDL::read_single3(&mut read_cursor).unwrap(), a);
+        assert_eq!(
+            MDL::read_single3(&mut read_cursor, Endian::Little).unwrap(),
+            a
+        );
     }
 
     #[test]
@@ -226,7 +240,10 @@ mod tests {
         MDL::write_single4(&mut cursor, &a).unwrap();
 
         let mut read_cursor = Cursor::new(v.as_slice());
-        assert_eq!(MDL::read_single4(&mut read_cursor).unwrap(), a);
+        assert_eq!(
+            MDL::read_single4(&mut read_cursor, Endian::Little).unwrap(),
+            a
+        );
     }
 
     #[test]
@@ -94,10 +94,13 @@ pub enum VertexUsage {
 #[repr(C)]
 #[brw(little)]
 pub struct VertexElement {
+    /// Which of the mesh's vertex streams this element is stored in.
     pub stream: u8,
+    /// Offset of this element within a vertex of its stream, in bytes.
     pub offset: u8,
     pub vertex_type: VertexType,
     pub vertex_usage: VertexUsage,
+    /// Distinguishes elements with the same usage, such as a second set of UVs.
     #[brw(pad_after = 3)]
     pub usage_index: u8,
 }
@@ -106,6 +109,7 @@ pub struct VertexElement {
 // 3 extra bytes to account for the padding that doesn't appear in the struct itself
 pub const VERTEX_ELEMENT_SIZE: usize = std::mem::size_of::<VertexElement>() + 3;
 
+/// The layout of the vertices of a mesh, see [crate::model::MDL::vertex_declaration].
 #[derive(Clone, Debug, PartialEq)]
 pub struct VertexDeclaration {
     pub elements: Vec<VertexElement>,
@@ -5,7 +5,7 @@
 
 use std::io::Cursor;
 
-use crate::common_file_operations::{Half1, Half2, Half3};
+use crate::common_file_operations::{decode_half, Half1, Half2, Half3};
 use crate::mtrl::ColorDyeTable::{
     DawntrailColorDyeTable, LegacyColorDyeTable, OpaqueColorDyeTable,
 };
@@ -183,7 +183,7 @@ pub enum ColorTable {
 }
 
 #[binread]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[allow(dead_code)]
 pub struct LegacyColorDyeTableRow {
     #[br(temp)]
@@ -290,6 +290,32 @@ pub enum ColorDyeTable {
     OpaqueColorDyeTable(OpaqueColorDyeTableData),
 }
 
+/// A row of a pre-Dawntrail color set, see [Material::color_set].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorSetRow {
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub emissive: [f32; 3],
+    pub specular_power: f32,
+    pub gloss: f32,
+    /// Index into the tiling texture array used for this row.
+    pub tile_index: u8,
+}
+
+impl From<&LegacyColorTableRow> for ColorSetRow {
+    fn from(row: &LegacyColorTableRow) -> Self {
+        ColorSetRow {
+            diffuse: row.diffuse_color,
+            specular: row.specular_color,
+            emissive: row.emissive_color,
+            specular_power: row.specular_strength,
+            gloss: row.gloss_strength,
+            // the tile index is stored as a half-float, divided by 64
+            tile_index: (decode_half(row.tile_set) * 64.0) as u8,
+        }
+    }
+}
+
 #[binrw]
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
@@ -486,6 +512,10 @@ pub struct Material {
     pub samplers: Vec<Sampler>,
     pub color_table: Option<ColorTable>,
     pub color_dye_table: Option<ColorDyeTable>,
+    /// The 16 rows of the color set, only available for pre-Dawntrail materials.
+    pub color_set: Option<[ColorSetRow; 16]>,
+    /// Which parts of each color set row can be dyed, only available for pre-Dawntrail materials.
+    pub color_set_dye: Option<[LegacyColorDyeTableRow; 16]>,
 }
 
 impl Material {
@@ -540,6 +570,22 @@ impl Material {
             });
         }
 
+        let color_set = match &mat_data.color_table {
+            Some(LegacyColorTable(table)) => table
+                .rows
+                .iter()
+                .map(ColorSetRow::from)
+                .collect::<Vec<_>>()
+                .try_into()
+                .ok(),
+            _ => None,
+        };
+
+        let color_set_dye = match &mat_data.color_dye_table {
+            Some(LegacyColorDyeTable(table)) => table.rows.clone().try_into().ok(),
+            _ => None,
+        };
+
         Some(Material {
             shader_package_name,
             texture_paths,
@@ -548,6 +594,8 @@ impl Material {
             samplers: mat_data.samplers,
             color_table: mat_data.color_table,
             color_dye_table: mat_data.color_dye_table,
+            color_set,
+            color_set_dye,
         })
     }
 }
@@ -568,4 +616,55 @@ mod tests {
         // Feeding it invalid data should not panic
         Material::from_existing(&read(d).unwrap());
     }
+
+    #[test]
+    fn test_color_set() {
+        let mut data = vec![];
+        // file header, with an empty string table and the table flags as additional data
+        data.extend_from_slice(&0x1030000u32.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 4]);
+        data.extend_from_slice(&[0; 4]);
+        // has a color table and a dye table, in the legacy format
+        data.extend_from_slice(&0xCu32.to_le_bytes());
+
+        // color table
+        for i in 0..16u16 {
+            // diffuse of 1.0, 0.5, 0.25
+            for value in [0x3C00u16, 0x3800, 0x3400] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            // specular strength of 2.0
+            data.extend_from_slice(&0x4000u16.to_le_bytes());
+            // specular, gloss and emissive
+            data.extend_from_slice(&[0; 14]);
+            // tile index 3 (3 / 64 as a half)
+            data.extend_from_slice(&0x2A00u16.to_le_bytes());
+            data.extend_from_slice(&[0; 8]);
+
+            assert_eq!(data.len(), 24 + (i as usize + 1) * 32);
+        }
+
+        // dye table, row 0 has a dyeable diffuse with template 1
+        data.extend_from_slice(&0x21u16.to_le_bytes());
+        data.extend_from_slice(&[0; 30]);
+
+        // material header
+        data.extend_from_slice(&[0; 12]);
+
+        let material = Material::from_existing(&data).unwrap();
+
+        let color_set = material.color_set.unwrap();
+        assert_eq!(color_set[0].diffuse, [1.0, 0.5, 0.25]);
+        assert_eq!(color_set[0].specular_power, 2.0);
+        assert_eq!(color_set[0].tile_index, 3);
+
+        let color_set_dye = material.color_set_dye.unwrap();
+        assert!(color_set_dye[0].diffuse);
+        assert!(!color_set_dye[0].specular);
+        assert_eq!(color_set_dye[0].template, 1);
+        assert!(!color_set_dye[1].diffuse);
+    }
 }
@@ -1,15 +1,16 @@
 // SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::io::Cursor;
+use std::io::{Cursor, Seek, SeekFrom};
 
-use crate::ByteSpan;
+use crate::{ByteBuffer, ByteSpan};
 use binrw::binrw;
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 
+/// The kind of skeleton the animations in a PAP file are bound to.
 #[binrw]
-#[derive(Debug)]
-enum SkeletonType {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkeletonType {
     #[brw(magic = 0u8)]
     Human,
     #[brw(magic = 1u8)]
@@ -30,22 +31,253 @@ struct PapHeader {
     num_animations: i16,
     model_id: u16,
     model_type: SkeletonType,
-    variant: i32,
+    variant: u8,
 
     info_offset: i32,
     havok_position: i32,
     footer_position: i32,
 }
 
+/// The size of [PapHeader], the animation info comes right after it.
+const PAP_HEADER_SIZE: u32 = 26;
+
+/// The size of [PapAnimationInfo].
+const PAP_ANIMATION_INFO_SIZE: u32 = 40;
+
+#[binrw]
+#[derive(Debug)]
+#[brw(little)]
+struct PapAnimationInfo {
+    name: [u8; 32],
+    animation_type: u16,
+    havok_index: i16,
+    is_face: u32,
+}
+
+/// An animation stored in a PAP file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PapAnimation {
+    /// The name of the animation, such as "cbbm_id0".
+    pub name: String,
+    /// The type of this animation, what each value means is still unknown.
+    pub animation_type: u16,
+    /// The index of this animation in the Havok container.
+    pub havok_index: i16,
+    /// Whether this is a facial animation.
+    pub is_face: bool,
+}
+
+/// An animation (PAP) file. Only the metadata is parsed, the animations themselves are stored in an embedded Havok
+/// container which is exposed as-is.
 #[derive(Debug)]
-pub struct Pap {}
+pub struct Pap {
+    /// The type of skeleton these animations are for.
+    pub skeleton_type: SkeletonType,
+    /// The id of the model the skeleton belongs to, for example 101 for "c0101".
+    pub model_id: u16,
+    /// The variant of the model.
+    pub variant: u8,
+    /// The animations in this file. They all share the same Havok container.
+    pub animations: Vec<PapAnimation>,
+    /// Where the Havok container starts in the file.
+    pub havok_offset: u32,
+    /// The size of the Havok container in bytes.
+    pub havok_size: u32,
+
+    version: i32,
+    magic: i32,
+    havok: ByteBuffer,
+    timeline: ByteBuffer,
+}
 
 impl Pap {
-    /// Reads an existing ULD file
+    /// Reads an existing PAP file
     pub fn from_existing(buffer: ByteSpan) -> Option<Self> {
         let mut cursor = Cursor::new(buffer);
-        PapHeader::read(&mut cursor).ok()?;
+        let header = PapHeader::read(&mut cursor).ok()?;
+
+        cursor
+            .seek(SeekFrom::Start(header.info_offset as u64))
+            .ok()?;
+
+        let mut animations = Vec::with_capacity(header.num_animations.max(0) as usize);
+        for _ in 0..header.num_animations {
+            let info = PapAnimationInfo::read(&mut cursor).ok()?;
+            let name_length = info.name.iter().position(|x| *x == 0).unwrap_or(32);
+
+            animations.push(PapAnimation {
+                name: String::from_utf8_lossy(&info.name[..name_length]).to_string(),
+                animation_type: info.animation_type,
+                havok_index: info.havok_index,
+                is_face: info.is_face != 0,
+            });
+        }
+
+        // the timeline data follows the Havok container
+        let havok_start = usize::try_from(header.havok_position).ok()?;
+        let havok_end = usize::try_from(header.footer_position).ok()?;
+
+        Some(Pap {
+            skeleton_type: header.model_type,
+            model_id: header.model_id,
+            variant: header.variant,
+            animations,
+            havok_offset: havok_start as u32,
+            havok_size: havok_end.checked_sub(havok_start)? as u32,
+            version: header.version,
+            magic: header.magic,
+            havok: buffer.get(havok_start..havok_end)?.to_vec(),
+            timeline: buffer.get(havok_end..)?.to_vec(),
+        })
+    }
+
+    /// Returns the embedded Havok container, which has the animations themselves.
+    pub fn havok(&self) -> &[u8] {
+        &self.havok
+    }
+
+    /// Replaces the embedded Havok container, for example with one taken from another PAP file.
+    /// [Pap::havok_offset] and [Pap::havok_size] are updated to where it will be written.
+    pub fn replace_havok(&mut self, havok: &[u8]) {
+        self.havok = havok.to_vec();
+        self.havok_offset =
+            PAP_HEADER_SIZE + self.animations.len() as u32 * PAP_ANIMATION_INFO_SIZE;
+        self.havok_size = havok.len() as u32;
+    }
+
+    /// Writes this PAP file into a new buffer, with the animation info, Havok container and timeline data laid out
+    /// one after another.
+    pub fn write_to_buffer(&self) -> Option<ByteBuffer> {
+        let mut buffer = ByteBuffer::new();
+
+        {
+            let mut cursor = Cursor::new(&mut buffer);
+
+            let havok_position =
+                PAP_HEADER_SIZE + self.animations.len() as u32 * PAP_ANIMATION_INFO_SIZE;
+            let header = PapHeader {
+                magic: self.magic,
+                version: self.version,
+                num_animations: self.animations.len() as i16,
+                model_id: self.model_id,
+                model_type: self.skeleton_type,
+                variant: self.variant,
+                info_offset: PAP_HEADER_SIZE as i32,
+                havok_position: havok_position as i32,
+                footer_position: (havok_position + self.havok.len() as u32) as i32,
+            };
+            header.write(&mut cursor).ok()?;
+
+            for animation in &self.animations {
+                let mut name = [0u8; 32];
+                let name_bytes = animation.name.as_bytes();
+                // leave room for the nul terminator
+                let name_length = name_bytes.len().min(31);
+                name[..name_length].copy_from_slice(&name_bytes[..name_length]);
+
+                PapAnimationInfo {
+                    name,
+                    animation_type: animation.animation_type,
+                    havok_index: animation.havok_index,
+                    is_face: animation.is_face as u32,
+                }
+                .write(&mut cursor)
+                .ok()?;
+            }
+        }
+
+        buffer.extend_from_slice(&self.havok);
+        buffer.extend_from_slice(&self.timeline);
+
+        Some(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn build_pap() -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(b"pap ");
+        data.extend_from_slice(&0x00020001i32.to_le_bytes()); // version
+        data.extend_from_slice(&2i16.to_le_bytes()); // animation count
+        data.extend_from_slice(&101u16.to_le_bytes()); // model id
+        data.push(0); // human
+        data.push(1); // variant
+        data.extend_from_slice(&26i32.to_le_bytes()); // info offset
+        data.extend_from_slice(&106i32.to_le_bytes()); // havok position
+        data.extend_from_slice(&114i32.to_le_bytes()); // footer position
+
+        for (name, havok_index, is_face) in [("cbbm_id0", 0i16, 0u32), ("cbbm_fc0", 1, 1)] {
+            let mut name_bytes = [0u8; 32];
+            name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+            data.extend_from_slice(&name_bytes);
+            data.extend_from_slice(&3u16.to_le_bytes());
+            data.extend_from_slice(&havok_index.to_le_bytes());
+            data.extend_from_slice(&is_face.to_le_bytes());
+        }
+
+        data.extend_from_slice(b"HAVOKHKX");
+        data.extend_from_slice(b"TMLB");
+
+        data
+    }
+
+    #[test]
+    fn test_read() {
+        let pap = Pap::from_existing(&build_pap()).unwrap();
+
+        assert_eq!(pap.skeleton_type, SkeletonType::Human);
+        assert_eq!(pap.model_id, 101);
+        assert_eq!(pap.variant, 1);
+        assert_eq!(
+            pap.animations,
+            vec![
+                PapAnimation {
+                    name: "cbbm_id0".to_string(),
+                    animation_type: 3,
+                    havok_index: 0,
+                    is_face: false,
+                },
+                PapAnimation {
+                    name: "cbbm_fc0".to_string(),
+                    animation_type: 3,
+                    havok_index: 1,
+                    is_face: true,
+                }
+            ]
+        );
+        assert_eq!(pap.havok_offset, 106);
+        assert_eq!(pap.havok_size, 8);
+        assert_eq!(pap.havok(), b"HAVOKHKX");
+    }
+
+    #[test]
+    fn test_write() {
+        let buffer = build_pap();
+        let mut pap = Pap::from_existing(&buffer).unwrap();
+        assert_eq!(pap.write_to_buffer().unwrap(), buffer);
+
+        // swapping the Havok container should move the timeline data along with it
+        pap.replace_havok(b"OTHERHAVOKHKX");
+        let new_pap = Pap::from_existing(&pap.write_to_buffer().unwrap()).unwrap();
+        assert_eq!(new_pap.havok(), b"OTHERHAVOKHKX");
+        assert_eq!(new_pap.havok_size, 13);
+        assert_eq!(new_pap.animations, pap.animations);
+        assert_eq!(new_pap.timeline, b"TMLB");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
 
-        Some(Pap {})
+        // Feeding it invalid data should not panic
+        Pap::from_existing(&read(d).unwrap());
     }
 }
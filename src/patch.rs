@@ -4,7 +4,7 @@
 use core::cmp::min;
 use std::fs;
 use std::fs::{File, OpenOptions, read, read_dir};
-use std::io::{BufWriter, Cursor, Seek, SeekFrom, Write};
+use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use binrw::{binrw, BinWrite};
@@ -14,6 +14,7 @@ use crate::ByteBuffer;
 
 use crate::common::{get_platform_string, Platform, Region};
 use crate::common_file_operations::{get_string_len, read_bool_from, read_string, write_bool_as, write_string};
+use crate::crc::{crc32_checksum, XivCrc32};
 use crate::sqpack::{read_data_block_patch, write_data_block_patch};
 
 #[binrw]
@@ -391,6 +392,63 @@ fn get_expansion_folder(id: u16) -> String {
     }
 }
 
+fn get_dat_path(
+    data_dir: &str,
+    target_info: &SqpkTargetInfo,
+    main_id: u16,
+    sub_id: u16,
+    file_id: u32,
+) -> String {
+    let filename = format!(
+        "{:02x}{:04x}.{}.dat{}",
+        main_id,
+        sub_id,
+        get_platform_string(&target_info.platform),
+        file_id
+    );
+    let path: PathBuf = [
+        data_dir,
+        "sqpack",
+        &get_expansion_folder_sub(sub_id),
+        &filename,
+    ]
+        .iter()
+        .collect();
+
+    path.to_str().unwrap().to_string()
+}
+
+fn get_index_path(
+    data_dir: &str,
+    target_info: &SqpkTargetInfo,
+    main_id: u16,
+    sub_id: u16,
+    file_id: u32,
+) -> String {
+    let mut filename = format!(
+        "{:02x}{:04x}.{}.index",
+        main_id,
+        sub_id,
+        get_platform_string(&target_info.platform)
+    );
+
+    // index files have no special ending if it's file_id == 0
+    if file_id != 0 {
+        filename += &*format!("{}", file_id);
+    }
+
+    let path: PathBuf = [
+        data_dir,
+        "sqpack",
+        &get_expansion_folder_sub(sub_id),
+        &filename,
+    ]
+        .iter()
+        .collect();
+
+    path.to_str().unwrap().to_string()
+}
+
 #[derive(Debug)]
 /// Errors emitted in the patching process
 pub enum PatchError {
@@ -398,6 +456,161 @@ pub enum PatchError {
     InvalidPatchFile,
     /// Failed to parse the patch format
     ParseError,
+    /// The data read back from `file` after patching didn't match what the patch wrote
+    HashMismatch { file: String },
+    /// Applying `chunk` (counting from zero) of the `patch` file failed, see [ZiPatch::apply_patches]
+    ApplyFailed {
+        patch: String,
+        chunk: usize,
+        error: Box<PatchError>,
+    },
+}
+
+/// Options for [ZiPatch::apply_with_options] and [ZiPatch::apply_patches_with_options].
+#[derive(Debug, Clone, Default)]
+pub struct PatchApplyOptions {
+    /// Checks the CRC32 of every chunk in the patch, and reads back every region written to a file to check it against
+    /// the data in the patch.
+    pub verify_after_apply: bool,
+    /// If set, every file is copied into this directory before it's first modified. If patching fails, the files are
+    /// restored and files created by the patches are removed. The copies are deleted once patching is finished.
+    pub backup_directory: Option<String>,
+    /// Doesn't modify anything, and instead returns the operations the patches would perform, see
+    /// [ZiPatch::inspect].
+    pub dry_run: bool,
+}
+
+/// Keeps a copy of every file touched while patching, so they can be restored.
+struct PatchBackup {
+    data_dir: PathBuf,
+    backup_dir: PathBuf,
+    /// Every file touched so far, and whether it existed before patching.
+    touched_files: Vec<(PathBuf, bool)>,
+}
+
+impl PatchBackup {
+    fn new(data_dir: &str, backup_dir: &str) -> Self {
+        Self {
+            data_dir: PathBuf::from(data_dir),
+            backup_dir: PathBuf::from(backup_dir),
+            touched_files: Vec::new(),
+        }
+    }
+
+    fn backup_path(&self, path: &Path) -> PathBuf {
+        self.backup_dir
+            .join(path.strip_prefix(&self.data_dir).unwrap_or(path))
+    }
+
+    /// Copies `path` into the backup directory, if it wasn't already.
+    fn snapshot(&mut self, path: &str) -> Result<(), PatchError> {
+        let path = PathBuf::from(path);
+        if self
+            .touched_files
+            .iter()
+            .any(|(touched, _)| *touched == path)
+        {
+            return Ok(());
+        }
+
+        let existed = path.is_file();
+        if existed {
+            let backup_path = self.backup_path(&path);
+            if let Some(parent) = backup_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&path, backup_path)?;
+        }
+
+        self.touched_files.push((path, existed));
+
+        Ok(())
+    }
+
+    /// Puts back every touched file the way it was before patching.
+    fn restore(self) {
+        for (path, existed) in &self.touched_files {
+            let result = if *existed {
+                if let Some(parent) = path.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                fs::copy(self.backup_path(path), path).map(|_| ())
+            } else if path.exists() {
+                fs::remove_file(path)
+            } else {
+                Ok(())
+            };
+
+            if result.is_err() {
+                warn!("Failed to restore {path:?}");
+            }
+        }
+
+        self.discard();
+    }
+
+    /// Removes the copies from the backup directory.
+    fn discard(self) {
+        for (path, existed) in &self.touched_files {
+            if *existed {
+                let _ = fs::remove_file(self.backup_path(path));
+            }
+        }
+    }
+}
+
+/// An operation a patch will perform on the game directory, see [ZiPatch::inspect].
+/// All paths are relative to the data directory the patch would be applied to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    /// Writes `size` bytes of data at `offset` in a dat file.
+    AddData {
+        path: String,
+        offset: u64,
+        size: u64,
+    },
+    /// Replaces `block_count` blocks at `offset` in a dat file with empty ones.
+    DeleteData {
+        path: String,
+        offset: u64,
+        block_count: u32,
+    },
+    /// Writes `block_count` empty blocks at `offset` in a dat file.
+    ExpandData {
+        path: String,
+        offset: u64,
+        block_count: u32,
+    },
+    /// Overwrites the header of a dat or index file.
+    HeaderUpdate { path: String },
+    /// Writes `size` bytes at `offset` in a regular file, creating it if needed.
+    AddFile {
+        path: String,
+        offset: u64,
+        size: u64,
+    },
+    /// Deletes a regular file.
+    DeleteFile { path: String },
+    /// Deletes a whole expansion folder.
+    RemoveAll { path: String },
+    /// Creates a directory and its parents.
+    MakeDirTree { path: String },
+}
+
+impl PatchOperation {
+    /// The path of the file or directory this operation modifies.
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOperation::AddData { path, .. }
+            | PatchOperation::DeleteData { path, .. }
+            | PatchOperation::ExpandData { path, .. }
+            | PatchOperation::HeaderUpdate { path }
+            | PatchOperation::AddFile { path, .. }
+            | PatchOperation::DeleteFile { path }
+            | PatchOperation::RemoveAll { path }
+            | PatchOperation::MakeDirTree { path } => path,
+        }
+    }
 }
 
 impl From<std::io::Error> for PatchError {
@@ -413,6 +626,64 @@ impl From<binrw::Error> for PatchError {
     }
 }
 
+/// Checks that `data` was written to `file` at `offset`, by comparing the CRC32 of both.
+fn verify_written(
+    mut file: &File,
+    file_path: &str,
+    offset: u64,
+    data: &[u8],
+) -> Result<(), PatchError> {
+    let mismatch = || PatchError::HashMismatch {
+        file: file_path.to_string(),
+    };
+
+    file.flush()?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut written = vec![0; data.len()];
+    file.read_exact(&mut written).map_err(|_| mismatch())?;
+
+    if XivCrc32::from(written.as_slice()) != XivCrc32::from(data) {
+        return Err(mismatch());
+    }
+
+    Ok(())
+}
+
+/// Checks the CRC32 of the chunk starting at the current position of `file`, which covers its type and data. The
+/// position is moved back to the start of the chunk afterwards.
+fn verify_chunk(file: &mut File, patch_path: &str) -> Result<(), PatchError> {
+    let mismatch = || PatchError::HashMismatch {
+        file: patch_path.to_string(),
+    };
+
+    let start = file.stream_position()?;
+
+    let mut size = [0; 4];
+    file.read_exact(&mut size)?;
+
+    let size = 4 + u32::from_be_bytes(size) as u64;
+    let mut data = vec![];
+    Read::by_ref(file).take(size).read_to_end(&mut data)?;
+    if data.len() as u64 != size {
+        return Err(mismatch());
+    }
+
+    // the end of file chunk doesn't have one
+    if !data.starts_with(b"EOF_") {
+        let mut crc32 = [0; 4];
+        file.read_exact(&mut crc32).map_err(|_| mismatch())?;
+
+        if crc32_checksum(&data) != u32::from_be_bytes(crc32) {
+            return Err(mismatch());
+        }
+    }
+
+    file.seek(SeekFrom::Start(start))?;
+
+    Ok(())
+}
+
 fn recurse(path: impl AsRef<Path>) -> Vec<PathBuf> {
     let Ok(entries) = read_dir(path) else {
         return vec![];
@@ -439,60 +710,112 @@ pub struct ZiPatch;
 impl ZiPatch {
     /// Applies a boot or a game patch to the specified _data_dir_.
     pub fn apply(data_dir: &str, patch_path: &str) -> Result<(), PatchError> {
-        let mut file = File::open(patch_path)?;
+        Self::apply_with_options(data_dir, patch_path, &PatchApplyOptions::default()).map(|_| ())
+    }
 
-        PatchHeader::read(&mut file)?;
+    /// Same as [ZiPatch::apply], but with extra `options`. Returns the operations of the patch for dry runs, and an
+    /// empty list otherwise.
+    ///
+    /// ZiPatch files don't store the hashes of the files they produce, so verification checks the CRC32 of each chunk
+    /// before applying it, and each write against the data in the patch. Any mismatch stops patching with
+    /// [PatchError::HashMismatch].
+    pub fn apply_with_options(
+        data_dir: &str,
+        patch_path: &str,
+        options: &PatchApplyOptions,
+    ) -> Result<Vec<PatchOperation>, PatchError> {
+        Self::apply_stack(data_dir, &[patch_path], options).map_err(|(_, _, error)| error)
+    }
 
-        let mut target_info: Option<SqpkTargetInfo> = None;
+    /// Applies several boot or game patches to the specified _data_dir_, in order. If one of them fails, this stops
+    /// and returns [PatchError::ApplyFailed], with the patch and chunk that caused it.
+    pub fn apply_patches(data_dir: &str, patch_paths: &[&str]) -> Result<(), PatchError> {
+        Self::apply_patches_with_options(data_dir, patch_paths, &PatchApplyOptions::default())
+            .map(|_| ())
+    }
 
-        let get_dat_path =
-            |target_info: &SqpkTargetInfo, main_id: u16, sub_id: u16, file_id: u32| -> String {
-                let filename = format!(
-                    "{:02x}{:04x}.{}.dat{}",
-                    main_id,
-                    sub_id,
-                    get_platform_string(&target_info.platform),
-                    file_id
-                );
-                let path: PathBuf = [
-                    data_dir,
-                    "sqpack",
-                    &get_expansion_folder_sub(sub_id),
-                    &filename,
-                ]
-                    .iter()
-                    .collect();
-
-                path.to_str().unwrap().to_string()
-            };
+    /// Same as [ZiPatch::apply_patches], but with extra `options`. Returns the operations of every patch in order for
+    /// dry runs, and an empty list otherwise.
+    pub fn apply_patches_with_options(
+        data_dir: &str,
+        patch_paths: &[&str],
+        options: &PatchApplyOptions,
+    ) -> Result<Vec<PatchOperation>, PatchError> {
+        Self::apply_stack(data_dir, patch_paths, options).map_err(|(patch, chunk, error)| {
+            PatchError::ApplyFailed {
+                patch: patch_paths[patch].to_string(),
+                chunk,
+                error: Box::new(error),
+            }
+        })
+    }
 
-        let get_index_path =
-            |target_info: &SqpkTargetInfo, main_id: u16, sub_id: u16, file_id: u32| -> String {
-                let mut filename = format!(
-                    "{:02x}{:04x}.{}.index",
-                    main_id,
-                    sub_id,
-                    get_platform_string(&target_info.platform)
-                );
-
-                // index files have no special ending if it's file_id == 0
-                if file_id != 0 {
-                    filename += &*format!("{}", file_id);
+    /// Applies every patch in order, rolling back if there's a backup directory. On failure, returns the index of the
+    /// patch and chunk that failed.
+    fn apply_stack(
+        data_dir: &str,
+        patch_paths: &[&str],
+        options: &PatchApplyOptions,
+    ) -> Result<Vec<PatchOperation>, (usize, usize, PatchError)> {
+        if options.dry_run {
+            let mut operations = Vec::new();
+            for (i, patch_path) in patch_paths.iter().enumerate() {
+                let mut chunk_index = 0;
+
+                match Self::inspect_chunks(patch_path, &mut chunk_index) {
+                    Ok(mut patch_operations) => operations.append(&mut patch_operations),
+                    Err(error) => return Err((i, chunk_index, error)),
                 }
+            }
+
+            return Ok(operations);
+        }
 
-                let path: PathBuf = [
-                    data_dir,
-                    "sqpack",
-                    &get_expansion_folder_sub(sub_id),
-                    &filename,
-                ]
-                    .iter()
-                    .collect();
+        let mut backup = options
+            .backup_directory
+            .as_ref()
+            .map(|backup_directory| PatchBackup::new(data_dir, backup_directory));
 
-                path.to_str().unwrap().to_string()
-            };
+        for (i, patch_path) in patch_paths.iter().enumerate() {
+            let mut chunk_index = 0;
+
+            if let Err(error) =
+                Self::apply_chunks(data_dir, patch_path, options, &mut backup, &mut chunk_index)
+            {
+                if let Some(backup) = backup {
+                    backup.restore();
+                }
+
+                return Err((i, chunk_index, error));
+            }
+        }
+
+        if let Some(backup) = backup {
+            backup.discard();
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Applies a single patch, keeping track of which chunk is being applied in `chunk_index`.
+    fn apply_chunks(
+        data_dir: &str,
+        patch_path: &str,
+        options: &PatchApplyOptions,
+        backup: &mut Option<PatchBackup>,
+        chunk_index: &mut usize,
+    ) -> Result<(), PatchError> {
+        let mut file = File::open(patch_path)?;
+
+        PatchHeader::read(&mut file)?;
+
+        let mut target_info: Option<SqpkTargetInfo> = None;
 
         loop {
+            if options.verify_after_apply {
+                verify_chunk(&mut file, patch_path)?;
+            }
+
             let chunk = PatchChunk::read(&mut file)?;
 
             match chunk.chunk_type {
@@ -500,6 +823,7 @@ impl ZiPatch {
                     match pchunk.operation {
                         SqpkOperation::AddData(add) => {
                             let filename = get_dat_path(
+                                data_dir,
                                 target_info.as_ref().unwrap(),
                                 add.main_id,
                                 add.sub_id,
@@ -509,26 +833,45 @@ impl ZiPatch {
                             let (left, _) = filename.rsplit_once('/').unwrap();
                             fs::create_dir_all(left)?;
 
+                            if let Some(backup) = backup.as_mut() {
+                                backup.snapshot(&filename)?;
+                            }
+
                             let mut new_file = OpenOptions::new()
+                                .read(true)
                                 .write(true)
                                 .create(true)
                                 .truncate(false)
-                                .open(filename)?;
+                                .open(&filename)?;
 
                             new_file.seek(SeekFrom::Start(add.block_offset))?;
 
                             new_file.write_all(&add.block_data)?;
 
                             wipe(&new_file, add.block_delete_number as usize)?;
+
+                            if options.verify_after_apply {
+                                verify_written(
+                                    &new_file,
+                                    &filename,
+                                    add.block_offset,
+                                    &add.block_data,
+                                )?;
+                            }
                         }
                         SqpkOperation::DeleteData(delete) => {
                             let filename = get_dat_path(
+                                data_dir,
                                 target_info.as_ref().unwrap(),
                                 delete.main_id,
                                 delete.sub_id,
                                 delete.file_id,
                             );
 
+                            if let Some(backup) = backup.as_mut() {
+                                backup.snapshot(&filename)?;
+                            }
+
                             let new_file = OpenOptions::new()
                                 .write(true)
                                 .create(true)
@@ -543,6 +886,7 @@ impl ZiPatch {
                         }
                         SqpkOperation::ExpandData(expand) => {
                             let filename = get_dat_path(
+                                data_dir,
                                 target_info.as_ref().unwrap(),
                                 expand.main_id,
                                 expand.sub_id,
@@ -552,6 +896,10 @@ impl ZiPatch {
                             let (left, _) = filename.rsplit_once('/').unwrap();
                             fs::create_dir_all(left)?;
 
+                            if let Some(backup) = backup.as_mut() {
+                                backup.snapshot(&filename)?;
+                            }
+
                             let new_file = OpenOptions::new()
                                 .write(true)
                                 .create(true)
@@ -567,12 +915,14 @@ impl ZiPatch {
                         SqpkOperation::HeaderUpdate(header) => {
                             let file_path = match header.file_kind {
                                 TargetFileKind::Dat => get_dat_path(
+                                    data_dir,
                                     target_info.as_ref().unwrap(),
                                     header.main_id,
                                     header.sub_id,
                                     header.file_id,
                                 ),
                                 TargetFileKind::Index => get_index_path(
+                                    data_dir,
                                     target_info.as_ref().unwrap(),
                                     header.main_id,
                                     header.sub_id,
@@ -583,17 +933,33 @@ impl ZiPatch {
                             let (left, _) = file_path.rsplit_once('/').ok_or(PatchError::ParseError)?;
                             fs::create_dir_all(left)?;
 
+                            if let Some(backup) = backup.as_mut() {
+                                backup.snapshot(&file_path)?;
+                            }
+
                             let mut new_file = OpenOptions::new()
+                                .read(true)
                                 .write(true)
                                 .create(true)
                                 .truncate(false)
-                                .open(file_path)?;
+                                .open(&file_path)?;
 
-                            if header.header_kind != TargetHeaderKind::Version {
-                                new_file.seek(SeekFrom::Start(1024))?;
-                            }
+                            let header_offset = match header.header_kind {
+                                TargetHeaderKind::Version => 0,
+                                _ => 1024,
+                            };
+                            new_file.seek(SeekFrom::Start(header_offset))?;
 
                             new_file.write_all(&header.header_data)?;
+
+                            if options.verify_after_apply {
+                                verify_written(
+                                    &new_file,
+                                    &file_path,
+                                    header_offset,
+                                    &header.header_data,
+                                )?;
+                            }
                         }
                         SqpkOperation::FileOperation(fop) => {
                             let file_path = format!("{}/{}", data_dir, fop.path);
@@ -615,8 +981,13 @@ impl ZiPatch {
                                     // re-apply crc32
                                     file.seek(SeekFrom::Current(4))?;
 
+                                    if let Some(backup) = backup.as_mut() {
+                                        backup.snapshot(&file_path)?;
+                                    }
+
                                     // now apply the file!
                                     let new_file = OpenOptions::new()
+                                        .read(true)
                                         .write(true)
                                         .create(true)
                                         .truncate(false)
@@ -629,11 +1000,19 @@ impl ZiPatch {
 
                                         file.seek(SeekFrom::Start(fop.offset))?;
                                         file.write_all(&data)?;
+
+                                        if options.verify_after_apply {
+                                            verify_written(&file, &file_path, fop.offset, &data)?;
+                                        }
                                     } else {
                                         warn!("{file_path} does not exist, skipping.");
                                     }
                                 }
                                 SqpkFileOperation::DeleteFile => {
+                                    if let Some(backup) = backup.as_mut() {
+                                        backup.snapshot(&file_path)?;
+                                    }
+
                                     if fs::remove_file(file_path.as_str()).is_err() {
                                         warn!("Failed to remove {file_path}");
                                     }
@@ -645,6 +1024,12 @@ impl ZiPatch {
                                             .collect();
 
                                     if fs::read_dir(&path).is_ok() {
+                                        if let Some(backup) = backup.as_mut() {
+                                            for file_path in recurse(&path) {
+                                                backup.snapshot(file_path.to_str().unwrap())?;
+                                            }
+                                        }
+
                                         fs::remove_dir_all(&path)?;
                                     }
                                 }
@@ -684,12 +1069,148 @@ impl ZiPatch {
                     return Ok(());
                 }
             }
+
+            *chunk_index += 1;
+        }
+    }
+
+    /// Reads a boot or a game patch without applying it, and returns every operation it would perform in order.
+    /// Chunks that don't modify the game directory (such as the file header) are skipped.
+    pub fn inspect(patch_path: &str) -> Result<Vec<PatchOperation>, PatchError> {
+        Self::inspect_chunks(patch_path, &mut 0)
+    }
+
+    /// Reads the operations of a single patch, keeping track of which chunk is being read in `chunk_index`.
+    fn inspect_chunks(
+        patch_path: &str,
+        chunk_index: &mut usize,
+    ) -> Result<Vec<PatchOperation>, PatchError> {
+        let mut file = File::open(patch_path)?;
+
+        PatchHeader::read(&mut file)?;
+
+        let mut target_info: Option<SqpkTargetInfo> = None;
+        let mut operations = Vec::new();
+
+        loop {
+            let chunk = PatchChunk::read(&mut file)?;
+
+            let pchunk = match chunk.chunk_type {
+                ChunkType::Sqpk(pchunk) => pchunk,
+                ChunkType::EndOfFile => return Ok(operations),
+                // the other chunks don't touch the game directory
+                _ => {
+                    *chunk_index += 1;
+                    continue;
+                }
+            };
+
+            match pchunk.operation {
+                SqpkOperation::AddData(add) => {
+                    let target_info = target_info.as_ref().ok_or(PatchError::ParseError)?;
+                    operations.push(PatchOperation::AddData {
+                        path: get_dat_path("", target_info, add.main_id, add.sub_id, add.file_id),
+                        offset: add.block_offset,
+                        size: add.block_number,
+                    });
+                }
+                SqpkOperation::DeleteData(delete) => {
+                    let target_info = target_info.as_ref().ok_or(PatchError::ParseError)?;
+                    operations.push(PatchOperation::DeleteData {
+                        path: get_dat_path(
+                            "",
+                            target_info,
+                            delete.main_id,
+                            delete.sub_id,
+                            delete.file_id,
+                        ),
+                        offset: delete.block_offset,
+                        block_count: delete.block_number,
+                    });
+                }
+                SqpkOperation::ExpandData(expand) => {
+                    let target_info = target_info.as_ref().ok_or(PatchError::ParseError)?;
+                    operations.push(PatchOperation::ExpandData {
+                        path: get_dat_path(
+                            "",
+                            target_info,
+                            expand.main_id,
+                            expand.sub_id,
+                            expand.file_id,
+                        ),
+                        offset: expand.block_offset,
+                        block_count: expand.block_number,
+                    });
+                }
+                SqpkOperation::HeaderUpdate(header) => {
+                    let target_info = target_info.as_ref().ok_or(PatchError::ParseError)?;
+                    let path = match header.file_kind {
+                        TargetFileKind::Dat => get_dat_path(
+                            "",
+                            target_info,
+                            header.main_id,
+                            header.sub_id,
+                            header.file_id,
+                        ),
+                        TargetFileKind::Index => get_index_path(
+                            "",
+                            target_info,
+                            header.main_id,
+                            header.sub_id,
+                            header.file_id,
+                        ),
+                    };
+                    operations.push(PatchOperation::HeaderUpdate { path });
+                }
+                SqpkOperation::FileOperation(fop) => match fop.operation {
+                    SqpkFileOperation::AddFile => {
+                        // skip over the file data, which is stored in blocks after the chunk
+                        file.seek(SeekFrom::Current(-4))?;
+
+                        let mut read_size = 0;
+                        while read_size < fop.file_size as usize {
+                            read_size += read_data_block_patch(&mut file)
+                                .ok_or(PatchError::ParseError)?
+                                .len();
+                        }
+
+                        file.seek(SeekFrom::Current(4))?;
+
+                        operations.push(PatchOperation::AddFile {
+                            path: fop.path,
+                            offset: fop.offset,
+                            size: fop.file_size,
+                        });
+                    }
+                    SqpkFileOperation::DeleteFile => {
+                        operations.push(PatchOperation::DeleteFile { path: fop.path });
+                    }
+                    SqpkFileOperation::RemoveAll => {
+                        let path: PathBuf = ["sqpack", &get_expansion_folder(fop.expansion_id)]
+                            .iter()
+                            .collect();
+                        operations.push(PatchOperation::RemoveAll {
+                            path: path.to_str().unwrap().to_string(),
+                        });
+                    }
+                    SqpkFileOperation::MakeDirTree => {
+                        operations.push(PatchOperation::MakeDirTree { path: fop.path });
+                    }
+                },
+                SqpkOperation::TargetInfo(new_target_info) => {
+                    target_info = Some(new_target_info);
+                }
+                SqpkOperation::PatchInfo(_) | SqpkOperation::Index(_) => {}
+            }
+
+            *chunk_index += 1;
         }
     }
 
     /// Creates a new ZiPatch describing the diff between `base_directory` and `new_directory`.
     pub fn create(base_directory: &str, new_directory: &str) -> Option<ByteBuffer> {
         let mut buffer = ByteBuffer::new();
+        let mut chunk_starts = vec![];
 
         {
             let cursor = Cursor::new(&mut buffer);
@@ -698,6 +1219,9 @@ impl ZiPatch {
             let header = PatchHeader {};
             header.write(&mut writer).ok()?;
 
+            // the size and CRC32 of each chunk are filled in once everything is written
+            chunk_starts.push(writer.stream_position().ok()?);
+
             let base_files = crate::patch::recurse(base_directory);
             let new_files = crate::patch::recurse(new_directory);
 
@@ -740,6 +1264,8 @@ impl ZiPatch {
 
                 // re-apply crc32
                 writer.seek(SeekFrom::Current(4)).ok()?;
+
+                chunk_starts.push(writer.stream_position().ok()?);
             }
 
             // Process deleted files
@@ -762,6 +1288,8 @@ impl ZiPatch {
                 };
 
                 remove_file_chunk.write(&mut writer).ok()?;
+
+                chunk_starts.push(writer.stream_position().ok()?);
             }
 
             let eof_chunk = PatchChunk {
@@ -772,6 +1300,17 @@ impl ZiPatch {
             eof_chunk.write(&mut writer).ok()?;
         }
 
+        // every chunk ends where the next one starts, and the last one is the end of file chunk which doesn't have these
+        for chunk in chunk_starts.windows(2) {
+            let (start, end) = (chunk[0] as usize, chunk[1] as usize);
+
+            let size = (end - start - 12) as u32;
+            buffer[start..start + 4].copy_from_slice(&size.to_be_bytes());
+
+            let crc32 = crc32_checksum(&buffer[start + 4..end - 4]);
+            buffer[end - 4..end].copy_from_slice(&crc32.to_be_bytes());
+        }
+
         Some(buffer)
     }
 }
@@ -813,6 +1352,269 @@ mod tests {
         ZiPatch::apply(&data_dir.clone(), &(data_dir + "/test.patch"));
     }
 
+    #[test]
+    fn test_inspect_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
+
+        // Feeding it invalid data should not panic
+        assert!(ZiPatch::inspect(d.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut test_dir = std::env::temp_dir();
+        test_dir.push("physis-patch-inspect-tests");
+        if test_dir.exists() {
+            fs::remove_dir_all(&test_dir).unwrap();
+        }
+
+        let base_dir = test_dir.join("base");
+        let new_dir = test_dir.join("new");
+        fs::create_dir_all(&base_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        write(new_dir.join("a.txt"), b"Hello, world!").unwrap();
+        write(new_dir.join("b.txt"), b"Goodbye, world!").unwrap();
+
+        let patch = ZiPatch::create(base_dir.to_str().unwrap(), new_dir.to_str().unwrap()).unwrap();
+
+        let patch_path = test_dir.join("test.patch");
+        write(&patch_path, &patch).unwrap();
+
+        let mut operations = ZiPatch::inspect(patch_path.to_str().unwrap()).unwrap();
+        operations.sort_by(|a, b| a.path().cmp(b.path()));
+
+        assert_eq!(
+            operations,
+            vec![
+                PatchOperation::AddFile {
+                    path: "a.txt".to_string(),
+                    offset: 0,
+                    size: 13,
+                },
+                PatchOperation::AddFile {
+                    path: "b.txt".to_string(),
+                    offset: 0,
+                    size: 15,
+                },
+            ]
+        );
+
+        // Nothing should have been written
+        assert!(!base_dir.join("a.txt").exists());
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_dry_run() {
+        let mut test_dir = std::env::temp_dir();
+        test_dir.push("physis-patch-dry-run-tests");
+        if test_dir.exists() {
+            fs::remove_dir_all(&test_dir).unwrap();
+        }
+
+        let base_dir = test_dir.join("base");
+        let new_dir = test_dir.join("new");
+        let data_dir = test_dir.join("data");
+        fs::create_dir_all(&base_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::create_dir_all(&data_dir).unwrap();
+        write(new_dir.join("a.txt"), b"Hello, world!").unwrap();
+        write(new_dir.join("b.txt"), b"Goodbye, world!").unwrap();
+        write(data_dir.join("a.txt"), b"old").unwrap();
+
+        let patch = ZiPatch::create(base_dir.to_str().unwrap(), new_dir.to_str().unwrap()).unwrap();
+
+        let patch_path = test_dir.join("test.patch");
+        write(&patch_path, &patch).unwrap();
+
+        let options = PatchApplyOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let operations = ZiPatch::apply_with_options(
+            data_dir.to_str().unwrap(),
+            patch_path.to_str().unwrap(),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            operations,
+            ZiPatch::inspect(patch_path.to_str().unwrap()).unwrap()
+        );
+
+        // Nothing should have changed
+        assert_eq!(read(data_dir.join("a.txt")).unwrap(), b"old");
+        assert!(!data_dir.join("b.txt").exists());
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_verified() {
+        let mut test_dir = std::env::temp_dir();
+        test_dir.push("physis-patch-verify-tests");
+        if test_dir.exists() {
+            fs::remove_dir_all(&test_dir).unwrap();
+        }
+
+        let base_dir = test_dir.join("base");
+        let new_dir = test_dir.join("new");
+        let data_dir = test_dir.join("data");
+        fs::create_dir_all(&base_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::create_dir_all(&data_dir).unwrap();
+        write(new_dir.join("a.txt"), b"Hello, world!").unwrap();
+        write(
+            data_dir.join("a.txt"),
+            b"A much longer file that gets replaced",
+        )
+        .unwrap();
+
+        let patch = ZiPatch::create(base_dir.to_str().unwrap(), new_dir.to_str().unwrap()).unwrap();
+
+        let patch_path = test_dir.join("test.patch");
+        write(&patch_path, &patch).unwrap();
+
+        let options = PatchApplyOptions {
+            verify_after_apply: true,
+            ..Default::default()
+        };
+        ZiPatch::apply_with_options(
+            data_dir.to_str().unwrap(),
+            patch_path.to_str().unwrap(),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(read(data_dir.join("a.txt")).unwrap(), b"Hello, world!");
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_corrupted() {
+        let mut test_dir = std::env::temp_dir();
+        test_dir.push("physis-patch-corrupted-tests");
+        if test_dir.exists() {
+            fs::remove_dir_all(&test_dir).unwrap();
+        }
+
+        let base_dir = test_dir.join("base");
+        let new_dir = test_dir.join("new");
+        let data_dir = test_dir.join("data");
+        fs::create_dir_all(&base_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::create_dir_all(&data_dir).unwrap();
+        write(new_dir.join("a.txt"), b"Hello, world!").unwrap();
+
+        let mut patch =
+            ZiPatch::create(base_dir.to_str().unwrap(), new_dir.to_str().unwrap()).unwrap();
+
+        // Flip a bit of the file data, after the chunk's CRC32 was calculated
+        let position = patch
+            .windows(5)
+            .position(|window| window == b"Hello")
+            .unwrap();
+        patch[position] ^= 1;
+
+        let patch_path = test_dir.join("test.patch");
+        write(&patch_path, &patch).unwrap();
+
+        let options = PatchApplyOptions {
+            verify_after_apply: true,
+            ..Default::default()
+        };
+        let result = ZiPatch::apply_with_options(
+            data_dir.to_str().unwrap(),
+            patch_path.to_str().unwrap(),
+            &options,
+        );
+        match result {
+            Err(PatchError::HashMismatch { file }) => {
+                assert_eq!(file, patch_path.to_str().unwrap())
+            }
+            _ => panic!("Expected the corrupted chunk to be caught"),
+        }
+        assert!(!data_dir.join("a.txt").exists());
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_patches() {
+        let mut test_dir = std::env::temp_dir();
+        test_dir.push("physis-patch-stack-tests");
+        if test_dir.exists() {
+            fs::remove_dir_all(&test_dir).unwrap();
+        }
+
+        let base_dir = test_dir.join("base");
+        let data_dir = test_dir.join("data");
+        let backup_dir = test_dir.join("backup");
+        fs::create_dir_all(&base_dir).unwrap();
+        fs::create_dir_all(&data_dir).unwrap();
+        write(data_dir.join("a.txt"), b"original").unwrap();
+
+        // Creates a patch adding `files`, relative to an empty directory
+        let create_patch = |name: &str, files: &[(&str, &[u8])]| {
+            let new_dir = test_dir.join(name);
+            fs::create_dir_all(&new_dir).unwrap();
+            for (path, data) in files {
+                write(new_dir.join(path), data).unwrap();
+            }
+
+            let patch =
+                ZiPatch::create(base_dir.to_str().unwrap(), new_dir.to_str().unwrap()).unwrap();
+
+            let patch_path = test_dir.join(format!("{name}.patch"));
+            write(&patch_path, &patch).unwrap();
+            patch_path.to_str().unwrap().to_string()
+        };
+
+        let first = create_patch("first", &[("a.txt", b"first"), ("b.txt", b"new file")]);
+        let second = create_patch("second", &[("a.txt", b"second")]);
+
+        let mut invalid = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        invalid.push("resources/tests");
+        invalid.push("random");
+        let invalid = invalid.to_str().unwrap();
+
+        let options = PatchApplyOptions {
+            backup_directory: Some(backup_dir.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        // The second patch fails, so the first one is rolled back
+        let result = ZiPatch::apply_patches_with_options(
+            data_dir.to_str().unwrap(),
+            &[&first, invalid],
+            &options,
+        );
+        match result {
+            Err(PatchError::ApplyFailed { patch, chunk, .. }) => {
+                assert_eq!(patch, invalid);
+                assert_eq!(chunk, 0);
+            }
+            _ => panic!("Expected the second patch to fail"),
+        }
+        assert_eq!(read(data_dir.join("a.txt")).unwrap(), b"original");
+        assert!(!data_dir.join("b.txt").exists());
+        assert!(!backup_dir.join("a.txt").exists());
+
+        ZiPatch::apply_patches_with_options(
+            data_dir.to_str().unwrap(),
+            &[&first, &second],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(read(data_dir.join("a.txt")).unwrap(), b"second");
+        assert_eq!(read(data_dir.join("b.txt")).unwrap(), b"new file");
+    }
+
     #[test]
     fn test_add_file_op() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -95,6 +95,20 @@ pub fn get_race_id(race: Race, subrace: Subrace, gender: Gender) -> Option<i32>
     }
 }
 
+/// Returns the race id whose equipment models are used when the race `race_id` doesn't have its own. For example,
+/// Hrothgar males (1501) use the models of Hyur Midlander males (101). Every race ends up at 101, which returns None.
+pub fn get_fallback_race_id(race_id: i32) -> Option<i32> {
+    match race_id {
+        101 => None,
+        // Lalafell females use the models of Lalafell males before the Hyur ones
+        1201 => Some(1101),
+        201 | 1101 => Some(101),
+        // female race ids have an even hundreds digit
+        _ if (race_id / 100) % 2 == 0 => Some(201),
+        _ => Some(101),
+    }
+}
+
 /// Builds the path to the skeleton (sklb) file for a given `race`, `subrace` and `gender`.
 pub fn build_skeleton_path(race: Race, subrace: Subrace, gender: Gender) -> String {
     format!(
@@ -128,4 +142,14 @@ mod tests {
             Some(901)
         );
     }
+
+    #[test]
+    fn test_fallback_race_id() {
+        assert_eq!(get_fallback_race_id(101), None);
+        assert_eq!(get_fallback_race_id(1501), Some(101));
+        assert_eq!(get_fallback_race_id(1801), Some(201));
+        assert_eq!(get_fallback_race_id(201), Some(101));
+        assert_eq!(get_fallback_race_id(1201), Some(1101));
+        assert_eq!(get_fallback_race_id(1101), Some(101));
+    }
 }
@@ -33,6 +33,8 @@ pub struct Repository {
     pub repo_type: RepositoryType,
     /// The version of the game data.
     pub version: Option<String>,
+    /// The folder containing the index and dat files, see [Repository::directory].
+    directory: PathBuf,
 }
 
 impl Eq for Repository {}
@@ -124,6 +126,35 @@ pub fn string_to_category(string: &str) -> Option<Category> {
     }
 }
 
+/// Returns the numeric id of `category`, which is used as the first byte of the index and dat filenames.
+pub fn category_to_id(category: Category) -> u8 {
+    category as u8
+}
+
+/// Returns the category with the numeric id `id`, the reverse of [category_to_id].
+pub fn id_to_category(id: u8) -> Option<Category> {
+    use crate::repository::Category::*;
+
+    match id {
+        0x00 => Some(Common),
+        0x01 => Some(BackgroundCommon),
+        0x02 => Some(Background),
+        0x03 => Some(Cutscene),
+        0x04 => Some(Character),
+        0x05 => Some(Shader),
+        0x06 => Some(UI),
+        0x07 => Some(Sound),
+        0x08 => Some(VFX),
+        0x09 => Some(UIScript),
+        0x0A => Some(EXD),
+        0x0B => Some(GameScript),
+        0x0C => Some(Music),
+        0x12 => Some(SqPackTest),
+        0x13 => Some(Debug),
+        _ => None,
+    }
+}
+
 impl Repository {
     /// Creates a new base `Repository`, from an existing directory. This may return `None` if
     /// the directory is invalid, e.g. a version file is missing.
@@ -142,6 +173,7 @@ impl Repository {
             platform,
             repo_type: Base,
             version,
+            directory: path.join("sqpack").join("ffxiv"),
         })
     }
 
@@ -166,14 +198,17 @@ impl Repository {
                 number: expansion_number,
             },
             version: read_version(d.as_path()),
+            directory: path.to_path_buf(),
         })
     }
 
     /// Calculate an index filename for a specific category, like _"0a0000.win32.index"_.
+    ///
+    /// The category, expansion and chunk are all written in hex, so chunk 10 of `bg` is _"02000a"_.
     pub fn index_filename(&self, chunk: u8, category: Category) -> String {
         format!(
-            "{:02x}{:02}{:02}.{}.index",
-            category as i32,
+            "{:02x}{:02x}{:02x}.{}.index",
+            category_to_id(category),
             self.expansion(),
             chunk,
             get_platform_string(&self.platform)
@@ -191,11 +226,58 @@ impl Repository {
         let platform = get_platform_string(&self.platform);
 
         format!(
-            "{:02x}{expansion:02}{chunk:02}.{platform}.dat{data_file_id}",
-            category as u32
+            "{:02x}{expansion:02x}{chunk:02x}.{platform}.dat{data_file_id}",
+            category_to_id(category)
         )
     }
 
+    /// The folder containing the index and dat files, such as "game/sqpack/ex1".
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The number of this repository's expansion, which is 0 for the base game, 1 for "ex1" and so on.
+    pub fn expansion_number(&self) -> u8 {
+        self.expansion() as u8
+    }
+
+    /// Scans the repository's folder for the categories that have index or dat files, for this repository's
+    /// platform and expansion. Returns them sorted by their id, and an empty list if the folder can't be read.
+    pub fn categories(&self) -> Vec<Category> {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return vec![];
+        };
+
+        let platform = get_platform_string(&self.platform);
+
+        let mut ids: Vec<u8> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                let (name, extension) = file_name.split_once('.')?;
+
+                let is_sqpack_file = extension.strip_prefix(platform).is_some_and(|kind| {
+                    kind == ".index" || kind == ".index2" || kind.starts_with(".dat")
+                });
+                if !is_sqpack_file || name.len() != 6 {
+                    return None;
+                }
+
+                // the name is the category, expansion and chunk in hex
+                let expansion = u8::from_str_radix(name.get(2..4)?, 16).ok()?;
+                if expansion != self.expansion_number() {
+                    return None;
+                }
+
+                u8::from_str_radix(name.get(0..2)?, 16).ok()
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+
+        ids.into_iter().filter_map(id_to_category).collect()
+    }
+
     fn expansion(&self) -> i32 {
         match self.repo_type {
             Base => 0,
@@ -233,6 +315,83 @@ mod tests {
         assert_eq!(repository.unwrap().version.unwrap(), "2012.01.01.0000.0000");
     }
 
+    #[test]
+    fn test_categories() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests/valid_sqpack/game/sqpack/ex1");
+
+        let repository =
+            Repository::from_existing_expansion(Platform::Win32, d.to_str().unwrap()).unwrap();
+        assert_eq!(repository.expansion_number(), 1);
+        // only has a dat file that doesn't belong to any category
+        assert!(repository.categories().is_empty());
+
+        // unique to this process, so concurrent test runs don't share it
+        let root = std::env::temp_dir().join(format!(
+            "physis-repository-categories-{}",
+            std::process::id()
+        ));
+        if root.exists() {
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+        let d = root.join("ex2");
+        std::fs::create_dir_all(&d).unwrap();
+        std::fs::write(d.join("ex2.ver"), "2012.01.01.0000.0000").unwrap();
+
+        for file_name in [
+            "0a0200.win32.index",
+            "0a0200.win32.dat0",
+            "020200.win32.index2",
+            "020201.win32.dat1",
+            // another expansion, another platform and not a sqpack file
+            "040100.win32.index",
+            "060200.ps3.index",
+            "readme.txt",
+        ] {
+            std::fs::write(d.join(file_name), []).unwrap();
+        }
+
+        let repository =
+            Repository::from_existing_expansion(Platform::Win32, d.to_str().unwrap()).unwrap();
+        assert_eq!(repository.expansion_number(), 2);
+        assert_eq!(repository.directory(), d);
+        assert_eq!(
+            repository.categories(),
+            vec![Category::Background, Category::EXD]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_category_ids() {
+        let categories = [
+            ("common", 0x00),
+            ("bgcommon", 0x01),
+            ("bg", 0x02),
+            ("cut", 0x03),
+            ("chara", 0x04),
+            ("shader", 0x05),
+            ("ui", 0x06),
+            ("sound", 0x07),
+            ("vfx", 0x08),
+            ("ui_script", 0x09),
+            ("exd", 0x0A),
+            ("game_script", 0x0B),
+            ("music", 0x0C),
+            ("sqpack_test", 0x12),
+            ("debug", 0x13),
+        ];
+
+        for (name, id) in categories {
+            assert_eq!(category_to_id(string_to_category(name).unwrap()), id);
+            assert_eq!(id_to_category(id), string_to_category(name));
+        }
+
+        assert!(string_to_category("unknown").is_none());
+        assert!(id_to_category(0x0D).is_none());
+    }
+
     #[test]
     fn test_win32_filenames() {
         let repo = Repository {
@@ -240,6 +399,7 @@ mod tests {
             platform: Platform::Win32,
             repo_type: RepositoryType::Base,
             version: None,
+            directory: PathBuf::new(),
         };
 
         assert_eq!(
@@ -254,6 +414,72 @@ mod tests {
             repo.dat_filename(0, Category::GameScript, 1),
             "0b0000.win32.dat1"
         );
+        assert_eq!(
+            repo.dat_filename(10, Category::Background, 2),
+            "02000a.win32.dat2"
+        );
+    }
+
+    #[test]
+    fn test_read_multiple_dats() {
+        use crate::gamedata::GameData;
+        use crate::index::{write_index, IndexFile, IndexHashTableEntry, SQPACK_HEADER_SIZE};
+        use crate::sqpack::SqPackWriter;
+        use std::fs;
+
+        let mut d = std::env::temp_dir();
+        d.push("physis-repository-multiple-dats");
+        if d.exists() {
+            fs::remove_dir_all(&d).unwrap();
+        }
+
+        let sqpack_dir = d.join("sqpack").join("ffxiv");
+        fs::create_dir_all(&sqpack_dir).unwrap();
+        fs::write(d.join("ffxivgame.ver"), "2012.01.01.0000.0000").unwrap();
+
+        // both files end up at the same offset, but in different dats of chunk 10, which is "0a" in the filenames
+        let files = [
+            ("common/first.txt", b"first"),
+            ("common/second.txt", b"other"),
+        ];
+
+        let mut entries = vec![];
+        for (data_file_id, (path, data)) in files.iter().enumerate() {
+            let mut writer = SqPackWriter::new();
+            writer.add_file(path, *data);
+
+            let (_, dat) = writer.finish().unwrap();
+            fs::write(
+                sqpack_dir.join(format!("00000a.win32.dat{data_file_id}")),
+                dat,
+            )
+            .unwrap();
+
+            entries.push(IndexHashTableEntry {
+                hash: IndexFile::calculate_hash(path),
+                is_synonym: false,
+                data_file_id: data_file_id as u8,
+                offset: SQPACK_HEADER_SIZE as u64 * 2,
+            });
+        }
+
+        entries.sort_by_key(|entry| entry.hash);
+        fs::write(
+            sqpack_dir.join("00000a.win32.index"),
+            write_index(Platform::Win32, &entries).unwrap(),
+        )
+        .unwrap();
+
+        let mut data = GameData::from_existing(Platform::Win32, d.to_str().unwrap()).unwrap();
+
+        assert_eq!(data.extract("common/first.txt").unwrap(), b"first");
+        assert_eq!(data.extract("common/second.txt").unwrap(), b"other");
+        assert_eq!(
+            data.extract_many(&["common/second.txt", "common/first.txt"]),
+            vec![Some(b"other".to_vec()), Some(b"first".to_vec())]
+        );
+
+        fs::remove_dir_all(&d).unwrap();
     }
 
     // TODO: We need to check if these console filenames are actually correct
@@ -264,6 +490,7 @@ mod tests {
             platform: Platform::PS3,
             repo_type: RepositoryType::Base,
             version: None,
+            directory: PathBuf::new(),
         };
 
         assert_eq!(repo.index_filename(0, Category::Music), "0c0000.ps3.index");
@@ -284,6 +511,7 @@ mod tests {
             platform: Platform::PS4,
             repo_type: RepositoryType::Base,
             version: None,
+            directory: PathBuf::new(),
         };
 
         assert_eq!(repo.index_filename(0, Category::Music), "0c0000.ps4.index");
@@ -1,35 +1,32 @@
 // SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::io::Cursor;
+use std::io::{Cursor, Seek, SeekFrom};
 
-use crate::ByteSpan;
+use crate::{ByteBuffer, ByteSpan};
 use binrw::binrw;
 use binrw::BinRead;
 
 #[binrw]
 #[derive(Debug)]
 #[brw(little)]
+#[brw(magic = b"SEDBSSCF")]
 struct ScdHeader {
-    #[br(count = 4)]
-    #[bw(pad_size_to = 4)]
-    #[bw(map = |x : &String | x.as_bytes())]
-    #[br(map = | x: Vec<u8> | String::from_utf8(x).unwrap().trim_matches(char::from(0)).to_string())]
-    pub file_type: String,
-
-    #[br(count = 4)]
-    #[bw(pad_size_to = 4)]
-    #[bw(map = |x : &String | x.as_bytes())]
-    #[br(map = | x: Vec<u8> | String::from_utf8(x).unwrap().trim_matches(char::from(0)).to_string())]
-    pub sub_type: String,
-
     version: u32,
-    endian_type: u32,
-    alignment_bits: u8,
-    offset: u16,
-    datetime: u64,
+    /// 0 for little-endian, 1 for big-endian.
+    endian_type: u8,
+    sscf_version: u8,
+    /// Where the offsets header is located.
+    offsets_offset: u16,
+
+    #[brw(pad_after = 28)]
+    file_size: u32,
+}
 
-    #[br(pad_before = 4)]
+#[binrw]
+#[derive(Debug)]
+#[brw(little)]
+struct ScdOffsets {
     sound_count: u16,
     track_count: u16,
     audio_count: u16,
@@ -41,19 +38,448 @@ struct ScdHeader {
     routing_offset: u32,
     attribute_offset: u32,
 
-    #[br(pad_after = 2)]
+    #[brw(pad_after = 2)]
     end_of_file_padding_size: u16,
 }
 
+#[binrw]
+#[derive(Debug)]
+#[brw(little)]
+struct ScdAudioHeader {
+    data_size: u32,
+    channel_count: u32,
+    sample_rate: u32,
+    codec: i32,
+    loop_start: i32,
+    loop_end: i32,
+    /// Size of the aux chunks and codec header, which come before the audio data.
+    extra_data_size: u32,
+    #[brw(pad_after = 2)]
+    aux_chunk_count: u16,
+}
+
+/// How the audio data of an entry is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScdCodec {
+    /// Uncompressed 16-bit PCM.
+    Pcm,
+    /// An Ogg Vorbis stream.
+    Ogg,
+    /// Microsoft ADPCM.
+    MsAdpcm,
+    /// A codec that isn't supported, or -1 for an empty entry.
+    Unknown(i32),
+}
+
+impl From<i32> for ScdCodec {
+    fn from(value: i32) -> Self {
+        match value {
+            0x01 => ScdCodec::Pcm,
+            0x06 => ScdCodec::Ogg,
+            0x0C => ScdCodec::MsAdpcm,
+            _ => ScdCodec::Unknown(value),
+        }
+    }
+}
+
+/// A single sound stream in a SCD file.
+#[derive(Debug)]
+pub struct ScdEntry {
+    /// How the audio data is encoded.
+    pub codec: ScdCodec,
+    /// Number of audio channels.
+    pub channel_count: u32,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Where the loop starts, in bytes of audio data.
+    pub loop_start: i32,
+    /// Where the loop ends, in bytes of audio data.
+    pub loop_end: i32,
+
+    /// The codec-specific header, without the aux chunks.
+    codec_header: ByteBuffer,
+    data: ByteBuffer,
+}
+
+/// Decoded audio samples, see [Scd::entry_pcm].
 #[derive(Debug)]
-pub struct Scd {}
+pub struct ScdPcm {
+    /// Number of audio channels.
+    pub channel_count: u32,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// 16-bit samples, interleaved by channel.
+    pub samples: Vec<i16>,
+}
+
+/// How much the step size changes for each ADPCM nibble.
+const ADPCM_ADAPTATION_TABLE: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+/// The standard predictor coefficients, used if the codec header doesn't include its own.
+const ADPCM_COEFFICIENTS: [(i32, i32); 7] = [
+    (256, 0),
+    (512, -256),
+    (0, 0),
+    (192, 64),
+    (240, 0),
+    (460, -208),
+    (392, -232),
+];
+
+struct AdpcmChannel {
+    coefficients: (i32, i32),
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+impl AdpcmChannel {
+    fn decode(&mut self, nibble: u8) -> i16 {
+        // sign extend the nibble
+        let signed = ((nibble << 4) as i8 >> 4) as i32;
+
+        // coefficients come from the file, so this can overflow an i32 with malformed ones
+        let predicted = (self.sample1 as i64 * self.coefficients.0 as i64
+            + self.sample2 as i64 * self.coefficients.1 as i64)
+            >> 8;
+        let sample = (predicted + signed as i64 * self.delta as i64)
+            .clamp(i16::MIN as i64, i16::MAX as i64) as i32;
+
+        self.sample2 = self.sample1;
+        self.sample1 = sample;
+        // malformed data can keep growing the step size, so it's capped before it would overflow
+        self.delta =
+            ((ADPCM_ADAPTATION_TABLE[nibble as usize] * self.delta) >> 8).clamp(16, i32::MAX / 768);
+
+        sample as i16
+    }
+}
+
+/// Sound container, which holds one or more sound entries.
+#[derive(Debug)]
+pub struct Scd {
+    /// The sound entries, which can be decoded with [Scd::entry_pcm] or [Scd::entry_ogg].
+    pub entries: Vec<ScdEntry>,
+}
 
 impl Scd {
-    /// Reads an existing ULD file
+    /// Reads an existing SCD file. Only little-endian files are supported.
     pub fn from_existing(buffer: ByteSpan) -> Option<Self> {
         let mut cursor = Cursor::new(buffer);
-        ScdHeader::read(&mut cursor).ok()?;
+        let header = ScdHeader::read(&mut cursor).ok()?;
+        if header.endian_type != 0 {
+            return None;
+        }
+
+        cursor
+            .seek(SeekFrom::Start(header.offsets_offset as u64))
+            .ok()?;
+        let offsets = ScdOffsets::read(&mut cursor).ok()?;
+
+        cursor
+            .seek(SeekFrom::Start(offsets.audio_offset as u64))
+            .ok()?;
+        let mut audio_offsets = vec![];
+        for _ in 0..offsets.audio_count {
+            audio_offsets.push(u32::read_le(&mut cursor).ok()?);
+        }
+
+        let entries = audio_offsets
+            .into_iter()
+            .map(|offset| Self::read_entry(buffer, offset))
+            .collect::<Option<Vec<ScdEntry>>>()?;
+
+        Some(Scd { entries })
+    }
+
+    fn read_entry(buffer: ByteSpan, offset: u32) -> Option<ScdEntry> {
+        let mut cursor = Cursor::new(buffer);
+        cursor.seek(SeekFrom::Start(offset as u64)).ok()?;
+        let header = ScdAudioHeader::read(&mut cursor).ok()?;
+
+        let extra_data_start = cursor.position() as usize;
+        let data_start = extra_data_start + header.extra_data_size as usize;
+        let extra_data = buffer.get(extra_data_start..data_start)?;
+
+        // aux chunks (such as "MARK") come before the codec header
+        let mut aux_size = 0;
+        for _ in 0..header.aux_chunk_count {
+            let chunk_size = extra_data.get(aux_size + 4..aux_size + 8)?;
+            aux_size += u32::from_le_bytes(chunk_size.try_into().ok()?) as usize;
+        }
+
+        Some(ScdEntry {
+            codec: header.codec.into(),
+            channel_count: header.channel_count,
+            sample_rate: header.sample_rate,
+            loop_start: header.loop_start,
+            loop_end: header.loop_end,
+            codec_header: extra_data.get(aux_size..)?.to_vec(),
+            data: buffer
+                .get(data_start..data_start + header.data_size as usize)?
+                .to_vec(),
+        })
+    }
+
+    /// Decodes the entry at `index` into PCM samples. Only PCM and MS-ADPCM entries can be
+    /// decoded, for Ogg Vorbis entries use [Scd::entry_ogg] instead.
+    pub fn entry_pcm(&self, index: usize) -> Option<ScdPcm> {
+        let entry = self.entries.get(index)?;
+
+        let samples = match entry.codec {
+            ScdCodec::Pcm => entry
+                .data
+                .chunks_exact(2)
+                .map(|x| i16::from_le_bytes([x[0], x[1]]))
+                .collect(),
+            ScdCodec::MsAdpcm => Self::decode_ms_adpcm(entry)?,
+            _ => return None,
+        };
+
+        Some(ScdPcm {
+            channel_count: entry.channel_count,
+            sample_rate: entry.sample_rate,
+            samples,
+        })
+    }
+
+    /// Extracts the Ogg Vorbis stream of the entry at `index`, which can be played or decoded
+    /// with any Ogg Vorbis decoder. Returns None if the entry isn't an Ogg Vorbis entry, or if it
+    /// uses the newer (version 3) encryption which isn't supported.
+    pub fn entry_ogg(&self, index: usize) -> Option<ByteBuffer> {
+        let entry = self.entries.get(index)?;
+        if entry.codec != ScdCodec::Ogg {
+            return None;
+        }
+
+        let header = &entry.codec_header;
+        let version = *header.first()?;
+        let xor_byte = *header.get(2)?;
+        let seek_table_size = u32::from_le_bytes(header.get(0x10..0x14)?.try_into().ok()?);
+        let vorbis_header_size = u32::from_le_bytes(header.get(0x14..0x18)?.try_into().ok()?);
+
+        if version == 3 {
+            return None;
+        }
+
+        // the vorbis header comes after the seek table, and is followed by the audio data
+        let vorbis_header_start = 0x20 + seek_table_size as usize;
+        let mut stream = header
+            .get(vorbis_header_start..vorbis_header_start + vorbis_header_size as usize)?
+            .to_vec();
+
+        // version 2 only encrypts the vorbis header
+        if version == 2 && xor_byte != 0 {
+            for byte in &mut stream {
+                *byte ^= xor_byte;
+            }
+        }
+
+        stream.extend_from_slice(&entry.data);
+
+        Some(stream)
+    }
+
+    fn decode_ms_adpcm(entry: &ScdEntry) -> Option<Vec<i16>> {
+        let channel_count = entry.channel_count as usize;
+        if !(1..=2).contains(&channel_count) {
+            return None;
+        }
+
+        // the codec header is a WAVEFORMATEX structure
+        let header = &entry.codec_header;
+        let block_align = u16::from_le_bytes(header.get(12..14)?.try_into().ok()?) as usize;
+
+        let coefficient_count = header
+            .get(20..22)
+            .map(|x| u16::from_le_bytes([x[0], x[1]]) as usize)
+            .unwrap_or_default();
+        let coefficients: Vec<(i32, i32)> = match coefficient_count {
+            0 => ADPCM_COEFFICIENTS.to_vec(),
+            count => header
+                .get(22..22 + count * 4)?
+                .chunks_exact(4)
+                .map(|x| {
+                    (
+                        i16::from_le_bytes([x[0], x[1]]) as i32,
+                        i16::from_le_bytes([x[2], x[3]]) as i32,
+                    )
+                })
+                .collect(),
+        };
+
+        let block_header_size = 7 * channel_count;
+        if block_align <= block_header_size {
+            return None;
+        }
+
+        let mut samples = vec![];
+
+        for block in entry.data.chunks(block_align) {
+            if block.len() < block_header_size {
+                break;
+            }
+
+            let read_i16 = |offset: usize| i16::from_le_bytes([block[offset], block[offset + 1]]);
+
+            // the block header stores each field for every channel, one after the other
+            let mut channels = (0..channel_count)
+                .map(|i| {
+                    Some(AdpcmChannel {
+                        coefficients: *coefficients.get(block[i] as usize)?,
+                        delta: read_i16(channel_count + i * 2) as i32,
+                        sample1: read_i16(channel_count * 3 + i * 2) as i32,
+                        sample2: read_i16(channel_count * 5 + i * 2) as i32,
+                    })
+                })
+                .collect::<Option<Vec<AdpcmChannel>>>()?;
+
+            // the first two samples are stored as-is, with the oldest one first
+            samples.extend(channels.iter().map(|x| x.sample2 as i16));
+            samples.extend(channels.iter().map(|x| x.sample1 as i16));
+
+            // the high nibble is decoded first, and they alternate between channels
+            for (i, nibble) in block[block_header_size..]
+                .iter()
+                .flat_map(|x| [x >> 4, x & 0xF])
+                .enumerate()
+            {
+                samples.push(channels[i % channel_count].decode(nibble));
+            }
+        }
+
+        Some(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Builds a SCD file out of (codec, channel count, codec header, data) entries.
+    fn build_scd(entries: &[(i32, u32, &[u8], &[u8])]) -> ByteBuffer {
+        let mut buffer = ByteBuffer::new();
+        buffer.extend_from_slice(b"SEDBSSCF");
+        buffer.extend_from_slice(&3u32.to_le_bytes());
+        buffer.extend_from_slice(&[0, 4]);
+        buffer.extend_from_slice(&0x30u16.to_le_bytes());
+        buffer.resize(0x30, 0);
+
+        // offsets header
+        let audio_offset = 0x50usize;
+        buffer.extend_from_slice(&[0, 0, 0, 0]);
+        buffer.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        buffer.extend_from_slice(&(audio_offset as u32).to_le_bytes());
+        buffer.resize(audio_offset + entries.len() * 4, 0);
+
+        for (i, (codec, channel_count, codec_header, data)) in entries.iter().enumerate() {
+            let entry_offset = buffer.len() as u32;
+            buffer[audio_offset + i * 4..audio_offset + i * 4 + 4]
+                .copy_from_slice(&entry_offset.to_le_bytes());
+
+            buffer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(&channel_count.to_le_bytes());
+            buffer.extend_from_slice(&44100u32.to_le_bytes());
+            buffer.extend_from_slice(&codec.to_le_bytes());
+            buffer.extend_from_slice(&[0; 8]);
+            buffer.extend_from_slice(&(codec_header.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(&[0; 4]);
+            buffer.extend_from_slice(codec_header);
+            buffer.extend_from_slice(data);
+        }
+
+        buffer
+    }
+
+    #[test]
+    fn test_entries() {
+        // mono, with a block align of 8
+        let mut adpcm_header = vec![0; 20];
+        adpcm_header[12] = 8;
+
+        // predictor 0, delta 16, sample1 100, sample2 50 and a single byte of nibbles
+        let adpcm_data = [0, 16, 0, 100, 0, 50, 0, 0x10];
+
+        let mut ogg_header = vec![0; 0x20];
+        ogg_header[0] = 2;
+        ogg_header[2] = 0xFF;
+        ogg_header[0x10] = 4; // seek table size
+        ogg_header[0x14] = 3; // vorbis header size
+        ogg_header.extend_from_slice(&[0; 4]);
+        ogg_header.extend_from_slice(&[!b'O', !b'g', !b'g']);
+
+        let buffer = build_scd(&[
+            (0x01, 2, &[], &[1, 0, 2, 0, 0xFF, 0xFF, 4, 0]),
+            (0x0C, 1, &adpcm_header, &adpcm_data),
+            (0x06, 2, &ogg_header, b"S"),
+            (-1, 0, &[], &[]),
+        ]);
+
+        let scd = Scd::from_existing(&buffer).unwrap();
+        assert_eq!(scd.entries.len(), 4);
+        assert_eq!(scd.entries[2].codec, ScdCodec::Ogg);
+        assert_eq!(scd.entries[3].codec, ScdCodec::Unknown(-1));
+
+        let pcm = scd.entry_pcm(0).unwrap();
+        assert_eq!(pcm.channel_count, 2);
+        assert_eq!(pcm.sample_rate, 44100);
+        assert_eq!(pcm.samples, [1, 2, -1, 4]);
+
+        let pcm = scd.entry_pcm(1).unwrap();
+        assert_eq!(pcm.channel_count, 1);
+        assert_eq!(pcm.samples, [50, 100, 116, 116]);
+
+        assert_eq!(scd.entry_ogg(2).unwrap(), b"OggS");
+
+        assert!(scd.entry_pcm(2).is_none());
+        assert!(scd.entry_ogg(0).is_none());
+        assert!(scd.entry_pcm(3).is_none());
+        assert!(scd.entry_pcm(4).is_none());
+    }
+
+    #[test]
+    fn test_adpcm_overflow() {
+        let mut channel = AdpcmChannel {
+            coefficients: ADPCM_COEFFICIENTS[1],
+            delta: i16::MAX as i32,
+            sample1: 0,
+            sample2: 0,
+        };
+
+        // the largest nibbles grow the step size the most, which shouldn't overflow
+        for nibble in std::iter::repeat_n([0x8, 0x7], 1000).flatten() {
+            channel.decode(nibble);
+        }
+        assert_eq!(channel.delta, i32::MAX / 768);
+
+        // neither should the most extreme coefficients a codec header can hold
+        for coefficients in [(-32768, -32768), (32767, 32767), (-32768, 32767)] {
+            let mut channel = AdpcmChannel {
+                coefficients,
+                delta: i32::MAX / 768,
+                sample1: i16::MIN as i32,
+                sample2: i16::MIN as i32,
+            };
+
+            for nibble in std::iter::repeat_n([0x7, 0x8], 1000).flatten() {
+                channel.decode(nibble);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
 
-        Some(Scd {})
+        // Feeding it invalid data should not panic
+        Scd::from_existing(&read(d).unwrap());
     }
 }
@@ -0,0 +1,321 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::ByteSpan;
+
+/// Marks the start of a macro payload.
+const START_BYTE: u8 = 0x02;
+
+/// Marks the end of a macro payload.
+const END_BYTE: u8 = 0x03;
+
+/// The kind of a macro embedded in a [SeString]. Only the common ones are named, the rest are kept as
+/// [MacroCode::Unknown].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroCode {
+    /// Picks between two strings based on a condition.
+    If,
+    /// Picks between several strings based on a value.
+    Switch,
+    /// The name of the player character.
+    PcName,
+    /// A line break.
+    NewLine,
+    /// An icon from the gfdata icon set.
+    Icon,
+    /// Changes the text color, or restores the previous one.
+    Color,
+    /// Changes the outline color of the text, or restores the previous one.
+    EdgeColor,
+    /// Changes the shadow color of the text, or restores the previous one.
+    ShadowColor,
+    /// A point where a word can be hyphenated if it doesn't fit on the line.
+    SoftHyphen,
+    /// Turns bold text on or off.
+    Bold,
+    /// Turns italic text on or off.
+    Italic,
+    /// A space that doesn't allow a line break.
+    NonBreakingSpace,
+    /// An icon like [MacroCode::Icon], which can be replaced depending on the input device.
+    Icon2,
+    /// A hyphen.
+    Hyphen,
+    /// A number, formatted as-is.
+    Num,
+    /// A link to an item, player, map location and so on.
+    Link,
+    /// A value from another Excel sheet.
+    Sheet,
+    /// A string, inserted as-is.
+    String,
+    /// Changes the text color to a row of the UIColor sheet.
+    ColorType,
+    /// Changes the outline color of the text to a row of the UIColor sheet.
+    EdgeColorType,
+    /// A macro that isn't named yet, with its raw code.
+    Unknown(u8),
+}
+
+impl From<u8> for MacroCode {
+    fn from(value: u8) -> Self {
+        match value {
+            0x08 => MacroCode::If,
+            0x09 => MacroCode::Switch,
+            0x0A => MacroCode::PcName,
+            0x10 => MacroCode::NewLine,
+            0x12 => MacroCode::Icon,
+            0x13 => MacroCode::Color,
+            0x14 => MacroCode::EdgeColor,
+            0x15 => MacroCode::ShadowColor,
+            0x16 => MacroCode::SoftHyphen,
+            0x19 => MacroCode::Bold,
+            0x1A => MacroCode::Italic,
+            0x1D => MacroCode::NonBreakingSpace,
+            0x1E => MacroCode::Icon2,
+            0x1F => MacroCode::Hyphen,
+            0x20 => MacroCode::Num,
+            0x27 => MacroCode::Link,
+            0x28 => MacroCode::Sheet,
+            0x29 => MacroCode::String,
+            0x48 => MacroCode::ColorType,
+            0x49 => MacroCode::EdgeColorType,
+            _ => MacroCode::Unknown(value),
+        }
+    }
+}
+
+/// A piece of a [SeString], see [SeString::tokens].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeStringToken {
+    /// Plain text.
+    Text(String),
+    /// A macro, with its expressions left encoded in `payload`.
+    Macro { code: MacroCode, payload: Vec<u8> },
+}
+
+/// A string from the game, which is UTF-8 text with macros embedded in it for formatting, links, and text that
+/// depends on the player. The raw bytes are kept as-is, so it can be written back without losing anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeString {
+    bytes: Vec<u8>,
+}
+
+impl SeString {
+    /// Creates a string out of its raw bytes, without the null terminator.
+    pub fn from_bytes(bytes: ByteSpan) -> SeString {
+        SeString {
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// The raw bytes of the string, including the macros.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Splits the string into text and macros. If a macro is malformed, the rest of the string is treated as text.
+    pub fn tokens(&self) -> Vec<SeStringToken> {
+        fn push_text(tokens: &mut Vec<SeStringToken>, bytes: ByteSpan) {
+            if !bytes.is_empty() {
+                tokens.push(SeStringToken::Text(
+                    String::from_utf8_lossy(bytes).to_string(),
+                ));
+            }
+        }
+
+        let mut tokens = vec![];
+        let mut text_start = 0;
+        let mut position = 0;
+
+        while position < self.bytes.len() {
+            if self.bytes[position] != START_BYTE {
+                position += 1;
+                continue;
+            }
+
+            let Some((code, payload, end)) = Self::read_macro(&self.bytes, position) else {
+                break;
+            };
+
+            push_text(&mut tokens, &self.bytes[text_start..position]);
+            tokens.push(SeStringToken::Macro {
+                code: MacroCode::from(code),
+                payload: payload.to_vec(),
+            });
+
+            position = end;
+            text_start = end;
+        }
+
+        push_text(&mut tokens, &self.bytes[text_start..]);
+
+        tokens
+    }
+
+    /// Renders the string as plain text. New lines, hyphens and non-breaking spaces are kept, but every other macro
+    /// is removed, including the text inside of conditionals such as [MacroCode::If] as it depends on the player.
+    pub fn as_text(&self) -> String {
+        let mut text = String::new();
+
+        for token in self.tokens() {
+            match token {
+                SeStringToken::Text(string) => text.push_str(&string),
+                SeStringToken::Macro { code, .. } => match code {
+                    MacroCode::NewLine => text.push('\n'),
+                    MacroCode::Hyphen => text.push('-'),
+                    MacroCode::NonBreakingSpace => text.push('\u{A0}'),
+                    _ => {}
+                },
+            }
+        }
+
+        text
+    }
+
+    /// Reads the macro starting at `start`, and returns its code, its payload and where it ends.
+    fn read_macro(bytes: ByteSpan, start: usize) -> Option<(u8, ByteSpan, usize)> {
+        let code = *bytes.get(start + 1)?;
+
+        let mut position = start + 2;
+        let length = read_integer(bytes, &mut position)? as usize;
+
+        let payload = bytes.get(position..position.checked_add(length)?)?;
+        position += length;
+
+        if *bytes.get(position)? != END_BYTE {
+            return None;
+        }
+
+        Some((code, payload, position + 1))
+    }
+}
+
+/// Reads an integer as encoded in macros. Small values are stored in a single byte, while larger ones have a
+/// marker byte saying which of their four bytes follow it.
+fn read_integer(bytes: ByteSpan, position: &mut usize) -> Option<u32> {
+    let marker = *bytes.get(*position)?;
+    *position += 1;
+
+    match marker {
+        0x01..=0xCF => Some(marker as u32 - 1),
+        0xF0..=0xFE => {
+            let present = (marker + 1) & 0b1111;
+
+            let mut value = 0;
+            for i in (0..4).rev() {
+                if present & (1 << i) != 0 {
+                    value |= (*bytes.get(*position)? as u32) << (i * 8);
+                    *position += 1;
+                }
+            }
+
+            Some(value)
+        }
+        // anything else is an expression, such as a parameter
+        _ => None,
+    }
+}
+
+impl std::fmt::Display for SeString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_text())
+    }
+}
+
+impl From<&str> for SeString {
+    fn from(value: &str) -> Self {
+        SeString::from_bytes(value.as_bytes())
+    }
+}
+
+impl From<String> for SeString {
+    fn from(value: String) -> Self {
+        SeString {
+            bytes: value.into_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integers() {
+        let bytes = [
+            0x01, 0x33, 0xF0, 0x12, 0xFE, 0x01, 0x02, 0x03, 0x04, 0xF3, 0x01, 0xD8,
+        ];
+        let mut position = 0;
+
+        assert_eq!(read_integer(&bytes, &mut position), Some(0));
+        assert_eq!(read_integer(&bytes, &mut position), Some(0x32));
+        assert_eq!(read_integer(&bytes, &mut position), Some(0x12));
+        assert_eq!(read_integer(&bytes, &mut position), Some(0x01020304));
+        // only the third byte is present
+        assert_eq!(read_integer(&bytes, &mut position), Some(0x010000));
+        // a parameter isn't an integer
+        assert_eq!(read_integer(&bytes, &mut position), None);
+    }
+
+    #[test]
+    fn test_formatted() {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(b"Use ");
+        // start coloring the text, with the color as a 4-byte integer
+        bytes.extend_from_slice(&[0x02, 0x13, 0x06, 0xFE, 0xFF, 0xF0, 0xB4, 0x00, 0x03]);
+        bytes.extend_from_slice("Ætherial".as_bytes());
+        // restore the previous color
+        bytes.extend_from_slice(&[0x02, 0x13, 0x02, 0xEC, 0x03]);
+        bytes.extend_from_slice(b" items.");
+        bytes.extend_from_slice(&[0x02, 0x10, 0x01, 0x03]);
+        bytes.extend_from_slice(b"Only ");
+        // a conditional on the player's gender
+        bytes.extend_from_slice(&[0x02, 0x08, 0x05, 0xE9, 0x05, 0xFF, 0x01, 0x03]);
+        bytes.extend_from_slice(b"once.");
+
+        let string = SeString::from_bytes(&bytes);
+        assert_eq!(string.as_bytes(), bytes);
+        assert_eq!(string.as_text(), "Use Ætherial items.\nOnly once.");
+        assert_eq!(string.to_string(), string.as_text());
+
+        let tokens = string.tokens();
+        assert_eq!(tokens.len(), 9);
+        assert_eq!(tokens[0], SeStringToken::Text("Use ".to_string()));
+        assert_eq!(
+            tokens[1],
+            SeStringToken::Macro {
+                code: MacroCode::Color,
+                payload: vec![0xFE, 0xFF, 0xF0, 0xB4, 0x00],
+            }
+        );
+        assert_eq!(tokens[2], SeStringToken::Text("Ætherial".to_string()));
+        assert!(matches!(
+            tokens[5],
+            SeStringToken::Macro {
+                code: MacroCode::NewLine,
+                ..
+            }
+        ));
+        assert!(matches!(
+            tokens[7],
+            SeStringToken::Macro {
+                code: MacroCode::If,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_malformed() {
+        // the payload is longer than the string, so it's kept as text
+        let string = SeString::from_bytes(&[b'a', 0x02, 0x13, 0x10, b'b']);
+        assert_eq!(
+            string.tokens(),
+            vec![SeStringToken::Text("a\u{2}\u{13}\u{10}b".to_string())]
+        );
+
+        assert!(SeString::from_bytes(&[]).tokens().is_empty());
+        assert_eq!(SeString::from("Plain").as_text(), "Plain");
+    }
+}
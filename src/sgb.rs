@@ -2,10 +2,19 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::io::Cursor;
+#[cfg(feature = "visual_data")]
+use std::io::{Seek, SeekFrom};
 
+#[cfg(feature = "visual_data")]
+use crate::lgb::{
+    read_string, BGInstanceObject, InstanceObject, Layer, LayerEntryData, LayerGroup,
+    LightInstanceObject,
+};
 use crate::ByteSpan;
 use binrw::binrw;
 use binrw::BinRead;
+#[cfg(feature = "visual_data")]
+use binrw::{binread, BinReaderExt};
 
 #[binrw]
 #[derive(Debug)]
@@ -14,22 +23,274 @@ struct SgbHeader {
     #[br(count = 4)]
     #[bw(pad_size_to = 4)]
     #[bw(map = |x : &String | x.as_bytes())]
-    #[br(map = | x: Vec<u8> | String::from_utf8(x).unwrap().trim_matches(char::from(0)).to_string())]
+    #[br(map = | x: Vec<u8> | String::from_utf8_lossy(&x).trim_matches(char::from(0)).to_string())]
     pub identifier: String,
 
     file_size: i32,
     total_chunk_count: i32,
 }
 
+#[cfg(feature = "visual_data")]
+#[binread]
 #[derive(Debug)]
-pub struct Sgb {}
+#[br(little)]
+#[allow(dead_code)] // most of the fields are unused at the moment
+struct SceneChunk {
+    #[br(count = 4)]
+    chunk_id: Vec<u8>,
+    chunk_size: i32,
+    // offsets in the scene are relative to here
+    layer_group_offset: i32,
+    layer_group_count: i32,
+}
+
+#[cfg(feature = "visual_data")]
+#[binread]
+#[derive(Debug)]
+#[br(little)]
+struct SgbLayerGroupHeader {
+    layer_group_id: u32,
+    name_offset: u32,
+    layer_offset: i32,
+    layer_count: i32,
+}
+
+/// A named collection of layers in a shared group.
+#[cfg(feature = "visual_data")]
+#[derive(Debug)]
+pub struct SgbLayerGroup {
+    pub id: u32,
+    pub name: String,
+    pub layers: Vec<Layer>,
+}
+
+/// Shared group (SGB) files, which bundle a reusable set of objects. They are placed in a zone by the
+/// `LayerEntryData::SharedGroup` objects of a layer, and their objects are relative to that placement.
+///
+/// The layers share their structures with LGB files, so they are only read with the `visual_data` feature. Otherwise,
+/// only the header is validated.
+#[derive(Debug)]
+pub struct Sgb {
+    #[cfg(feature = "visual_data")]
+    pub layer_groups: Vec<SgbLayerGroup>,
+}
 
 impl Sgb {
     /// Reads an existing SGB file
     pub fn from_existing(buffer: ByteSpan) -> Option<Self> {
         let mut cursor = Cursor::new(buffer);
-        SgbHeader::read(&mut cursor).ok()?;
 
+        let file_header = SgbHeader::read(&mut cursor).ok()?;
+        if file_header.file_size < 0 || file_header.total_chunk_count < 0 {
+            return None;
+        }
+
+        #[cfg(feature = "visual_data")]
+        {
+            Some(Sgb {
+                layer_groups: Self::read_layer_groups(buffer, &mut cursor)?,
+            })
+        }
+
+        #[cfg(not(feature = "visual_data"))]
         Some(Sgb {})
     }
+
+    /// Reads the scene chunk following the header, and the layer groups in it.
+    #[cfg(feature = "visual_data")]
+    fn read_layer_groups(
+        buffer: ByteSpan,
+        cursor: &mut Cursor<ByteSpan>,
+    ) -> Option<Vec<SgbLayerGroup>> {
+        let scene = SceneChunk::read(cursor).ok()?;
+        let scene_start = cursor.position() - 8;
+        let groups_start = scene_start.checked_add_signed(scene.layer_group_offset as i64)?;
+
+        let mut layer_groups = vec![];
+
+        for i in 0..scene.layer_group_count.max(0) as u64 {
+            let group_start = groups_start + i * 16;
+            cursor.seek(SeekFrom::Start(group_start)).ok()?;
+
+            let header = SgbLayerGroupHeader::read(cursor).ok()?;
+
+            // layer offsets are relative to the start of the list, like in LGB files
+            let layers_start = group_start.checked_add_signed(header.layer_offset as i64)?;
+            cursor.seek(SeekFrom::Start(layers_start)).ok()?;
+
+            let mut layer_offsets = vec![];
+            for _ in 0..header.layer_count {
+                layer_offsets.push(cursor.read_le::<i32>().ok()?);
+            }
+
+            let mut layers = vec![];
+            for layer_offset in layer_offsets {
+                cursor
+                    .seek(SeekFrom::Start(
+                        layers_start.checked_add_signed(layer_offset as i64)?,
+                    ))
+                    .ok()?;
+
+                layers.push(LayerGroup::read_layer(buffer, cursor)?);
+            }
+
+            layer_groups.push(SgbLayerGroup {
+                id: header.layer_group_id,
+                name: read_string(buffer, group_start + header.name_offset as u64)?,
+                layers,
+            });
+        }
+
+        Some(layer_groups)
+    }
+
+    /// Returns every object in the shared group, across all of its layers.
+    #[cfg(feature = "visual_data")]
+    pub fn objects(&self) -> impl Iterator<Item = &InstanceObject> {
+        self.layer_groups
+            .iter()
+            .flat_map(|group| &group.layers)
+            .flat_map(|layer| &layer.objects)
+    }
+
+    /// Returns the placed BG models, which also point to their collision meshes.
+    #[cfg(feature = "visual_data")]
+    pub fn models(&self) -> impl Iterator<Item = (&InstanceObject, &BGInstanceObject)> {
+        self.objects().filter_map(|object| match &object.data {
+            LayerEntryData::BG(bg) => Some((object, bg)),
+            _ => None,
+        })
+    }
+
+    /// Returns the placed lights.
+    #[cfg(feature = "visual_data")]
+    pub fn lights(&self) -> impl Iterator<Item = (&InstanceObject, &LightInstanceObject)> {
+        self.objects().filter_map(|object| match &object.data {
+            LayerEntryData::LayLight(light) => Some((object, light)),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Builds a shared group with a single layer, containing a BG model and a light.
+    #[cfg(feature = "visual_data")]
+    fn build_sgb() -> Vec<u8> {
+        use crate::lgb::tests::{build_instance, build_layer, push_f32s, push_u32s};
+
+        let bg = build_instance(
+            0x1,
+            10,
+            "tree",
+            |offsets| {
+                let mut data = vec![];
+                push_u32s(&mut data, &[offsets[0], offsets[1], 0x1, 0, 0, 0]);
+                data.extend_from_slice(&[1, 1, 1, 0]);
+                push_f32s(&mut data, &[0.0]);
+                data
+            },
+            &[
+                "bg/ffxiv/test/bgparts/tree.mdl",
+                "bg/ffxiv/test/collision/tree.pcb",
+            ],
+        );
+        let light = build_instance(
+            0x3,
+            11,
+            "lamp",
+            |offsets| {
+                let mut data = vec![];
+                push_u32s(&mut data, &[0x2]);
+                push_f32s(&mut data, &[1.0, 1.0]);
+                push_u32s(&mut data, &[0x0]);
+                push_f32s(&mut data, &[1.0, 45.0]);
+                push_u32s(&mut data, &[offsets[0]]);
+                data.extend_from_slice(&[255, 128, 0, 255]);
+                push_f32s(&mut data, &[2.0]);
+                data
+            },
+            &[""],
+        );
+
+        let mut buffer = vec![];
+        buffer.extend_from_slice(b"SGB1");
+        push_u32s(&mut buffer, &[0, 1]);
+        buffer.extend_from_slice(b"SCN1");
+        // the only layer group comes right after the scene offsets
+        push_u32s(&mut buffer, &[0, 8, 1]);
+
+        // layer group header, followed by its name and the offset of its only layer
+        push_u32s(&mut buffer, &[3, 16, 24, 1]);
+        buffer.extend_from_slice(b"Group\0\0\0");
+        push_u32s(&mut buffer, &[4]);
+        buffer.extend_from_slice(&build_layer(7, &[bg, light]));
+
+        buffer
+    }
+
+    #[test]
+    #[cfg(feature = "visual_data")]
+    fn test_parsing() {
+        use crate::lgb::{LayerEntryType, LightType};
+
+        let sgb = Sgb::from_existing(&build_sgb()).unwrap();
+
+        assert_eq!(sgb.layer_groups.len(), 1);
+        let group = &sgb.layer_groups[0];
+        assert_eq!(group.id, 3);
+        assert_eq!(group.name, "Group");
+        assert_eq!(group.layers.len(), 1);
+        assert_eq!(group.layers[0].name, "Layer");
+        assert_eq!(sgb.objects().count(), 2);
+
+        let models: Vec<_> = sgb.models().collect();
+        assert_eq!(models.len(), 1);
+        let (object, model) = models[0];
+        assert_eq!(object.asset_type, LayerEntryType::BG);
+        assert_eq!(object.name, "tree");
+        assert_eq!(object.transform.translation, [1.0, 2.0, 3.0]);
+        assert_eq!(model.asset_path, "bg/ffxiv/test/bgparts/tree.mdl");
+        assert_eq!(
+            model.collision_asset_path,
+            "bg/ffxiv/test/collision/tree.pcb"
+        );
+
+        let lights: Vec<_> = sgb.lights().collect();
+        assert_eq!(lights.len(), 1);
+        let (object, light) = lights[0];
+        assert_eq!(object.instance_id, 11);
+        assert_eq!(object.name, "lamp");
+        assert_eq!(light.light_type, LightType::Point);
+        assert_eq!(light.texture_path, "");
+        assert_eq!(light.diffuse_color, [255, 128, 0, 255]);
+    }
+
+    #[test]
+    #[cfg(feature = "visual_data")]
+    fn test_negative_offsets() {
+        let sgb = build_sgb();
+
+        // the layer groups, the list of layers and the layer itself point before the start of the file
+        for position in [20, 36, 52] {
+            let mut sgb = sgb.clone();
+            sgb[position..position + 4].copy_from_slice(&(-1000i32).to_le_bytes());
+            assert!(Sgb::from_existing(&sgb).is_none());
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("random");
+
+        // Feeding it invalid data should not panic
+        Sgb::from_existing(&read(d).unwrap());
+    }
 }
@@ -0,0 +1,129 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::common::Language;
+use crate::exd::ColumnData;
+use crate::exh::ColumnDataType;
+use crate::gamedata::GameData;
+
+/// Returns the display names of every expansion from the `ExVersion` sheet, in row order. For example, the first one is "A Realm Reborn".
+///
+/// If `language` isn't available for the sheet, this returns None. It also returns None if the sheet is missing or doesn't have the expected columns.
+pub fn read_expansion_names(game_data: &mut GameData, language: Language) -> Option<Vec<String>> {
+    let exh = game_data.read_excel_sheet_header("ExVersion")?;
+
+    // the name is always the first column
+    if !matches!(
+        exh.column_definitions.first()?.data_type,
+        ColumnDataType::String
+    ) {
+        return None;
+    }
+
+    if !exh
+        .languages
+        .iter()
+        .any(|sheet_language| *sheet_language as u8 == language as u8)
+    {
+        return None;
+    }
+
+    let mut names = vec![];
+    for page in 0..exh.pages.len() {
+        let exd = game_data.read_excel_sheet("ExVersion", &exh, language, page)?;
+
+        for row in exd.rows {
+            match row.data.first()? {
+                ColumnData::String(name) => names.push(name.as_text()),
+                _ => return None,
+            }
+        }
+    }
+
+    Some(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::excel_fixtures::{build_exd, build_exh};
+    use crate::exl::EXL;
+    use crate::sqpack_fixtures::packed_setup_data;
+
+    use super::*;
+
+    /// Builds an ExVersion sheet with a single column of `column_type`, and a page with `row_count` rows.
+    fn build_version_exh(column_type: ColumnDataType, row_count: u32) -> Vec<u8> {
+        build_exh(
+            4,
+            &[(column_type, 0)],
+            &[(0, row_count)],
+            &[Language::English],
+        )
+    }
+
+    fn build_version_exd(names: &[&str]) -> Vec<u8> {
+        let rows: Vec<(u32, Vec<u8>)> = names
+            .iter()
+            .enumerate()
+            .map(|(row_id, name)| {
+                let mut row = vec![];
+                row.extend_from_slice(&0u32.to_be_bytes()); // string offset
+                row.extend_from_slice(name.as_bytes());
+                row.push(0);
+
+                (row_id as u32, row)
+            })
+            .collect();
+
+        build_exd(&rows)
+    }
+
+    fn setup_data(name: &str, exh: &[u8], exd: &[u8]) -> GameData {
+        let exl = EXL {
+            version: 2,
+            entries: vec![("ExVersion".to_string(), 0)],
+        }
+        .write_to_buffer()
+        .unwrap();
+
+        packed_setup_data(
+            name,
+            &[
+                ("exd/root.exl", &exl),
+                ("exd/exversion.exh", exh),
+                ("exd/exversion_0_en.exd", exd),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_expansion_names() {
+        let names = ["A Realm Reborn", "Heavensward", "Stormblood"];
+
+        let mut data = setup_data(
+            "physis-sheets-expansion-names",
+            &build_version_exh(ColumnDataType::String, names.len() as u32),
+            &build_version_exd(&names),
+        );
+
+        assert_eq!(
+            read_expansion_names(&mut data, Language::English).unwrap(),
+            names
+        );
+
+        // not a language the sheet has
+        assert!(read_expansion_names(&mut data, Language::Japanese).is_none());
+    }
+
+    #[test]
+    fn test_unexpected_layout() {
+        // the first column is an integer instead of the name
+        let mut data = setup_data(
+            "physis-sheets-unexpected-layout",
+            &build_version_exh(ColumnDataType::UInt32, 1),
+            &build_version_exd(&["A Realm Reborn"]),
+        );
+
+        assert!(read_expansion_names(&mut data, Language::English).is_none());
+    }
+}
@@ -45,6 +45,7 @@ struct SKLB {
     sklb_v1: Option<SklbV1>,
 
     #[br(if(version == 0x3133_3030u32 || version == 0x3133_3031u32))]
+    #[br(assert(sklb_v1.is_some() || sklb_v2.is_some(), "unknown SKLB version {:#x}", version))]
     sklb_v2: Option<SklbV2>,
 
     #[br(seek_before(SeekFrom::Start(if (version == 0x3132_3030u32) { sklb_v1.as_ref().unwrap().havok_offset as u64 } else { sklb_v2.as_ref().unwrap().havok_offset as u64 })))]
@@ -69,6 +70,8 @@ pub struct Bone {
 
 #[derive(Debug)]
 pub struct Skeleton {
+    /// The body ID this skeleton belongs to, from the SKLB header
+    pub body_id: u32,
     /// Bones of this skeleton
     pub bones: Vec<Bone>,
 }
@@ -84,9 +87,18 @@ impl Skeleton {
         let raw_animation_container = root.find_object_by_type("hkaAnimationContainer");
         let animation_container = HavokAnimationContainer::new(raw_animation_container);
 
-        let havok_skeleton = &animation_container.skeletons[0];
+        let havok_skeleton = animation_container.skeletons.first()?;
 
-        let mut skeleton = Skeleton { bones: vec![] };
+        let body_id = match (&sklb.sklb_v1, &sklb.sklb_v2) {
+            (Some(sklb_v1), _) => sklb_v1.body_id,
+            (_, Some(sklb_v2)) => sklb_v2.body_id,
+            _ => 0,
+        };
+
+        let mut skeleton = Skeleton {
+            body_id,
+            bones: vec![],
+        };
 
         for (index, bone) in havok_skeleton.bone_names.iter().enumerate() {
             skeleton.bones.push(Bone {
@@ -108,6 +120,11 @@ impl Skeleton {
 
         Some(skeleton)
     }
+
+    /// Returns the index of the bone called `name`, for example to match bones from `MDL::affected_bone_names`.
+    pub fn find_bone(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|bone| bone.name == name)
+    }
 }
 
 #[cfg(test)]
@@ -126,4 +143,33 @@ mod tests {
         // Feeding it invalid data should not panic
         Skeleton::from_existing(&read(d).unwrap());
     }
+
+    #[test]
+    fn test_unknown_version() {
+        let mut data = b"blks".to_vec();
+        data.extend_from_slice(&0x3939_3939u32.to_le_bytes());
+        data.resize(64, 0);
+
+        // An unknown version should be rejected instead of panicking
+        assert!(Skeleton::from_existing(&data).is_none());
+    }
+
+    #[test]
+    fn test_find_bone() {
+        let bone = |name: &str, parent_index| Bone {
+            name: name.to_string(),
+            parent_index,
+            position: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+        };
+
+        let skeleton = Skeleton {
+            body_id: 0,
+            bones: vec![bone("n_root", -1), bone("n_hara", 0), bone("j_kosi", 1)],
+        };
+
+        assert_eq!(skeleton.find_bone("j_kosi"), Some(2));
+        assert_eq!(skeleton.find_bone("j_sebo_a"), None);
+    }
 }
@@ -1,23 +1,40 @@
 // SPDX-FileCopyrightText: 2023 Joshua Goins <josh@redstrate.com>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::collections::BTreeMap;
 use std::io::{Read, Seek, SeekFrom, Write};
 
 use binrw::{BinRead, BinWrite};
 
-use crate::compression::no_header_decompress;
-use crate::dat::{BlockHeader, CompressionMode};
+use crate::common::Platform;
+use crate::compression::{no_header_compress, no_header_decompress};
+use crate::dat::{BlockHeader, CompressionMode, DatFile, SqPackDataHeader};
+use crate::index::{
+    write_header, write_index, IndexFile, IndexHashTableEntry, SqPackHeader, SQPACK_FILE_TYPE_DATA,
+    SQPACK_HEADER_SIZE,
+};
+use crate::sha1::Sha1;
+use crate::ByteBuffer;
 
 pub fn read_data_block<T: Read + Seek>(mut buf: T, starting_position: u64) -> Option<Vec<u8>> {
     buf.seek(SeekFrom::Start(starting_position)).ok()?;
 
-    let block_header = BlockHeader::read(&mut buf).unwrap();
+    let block_header = BlockHeader::read(&mut buf).ok()?;
+
+    // every block written by the game has a fixed-size header, anything else is garbage
+    if block_header.size != BLOCK_HEADER_SIZE {
+        return None;
+    }
 
     match block_header.compression {
         CompressionMode::Compressed {
             compressed_length,
             decompressed_length,
         } => {
+            if compressed_length < 0 || decompressed_length < 0 {
+                return None;
+            }
+
             let mut compressed_data: Vec<u8> = vec![0; compressed_length as usize];
             buf.read_exact(&mut compressed_data).ok()?;
 
@@ -29,6 +46,10 @@ pub fn read_data_block<T: Read + Seek>(mut buf: T, starting_position: u64) -> Op
             Some(decompressed_data)
         }
         CompressionMode::Uncompressed { file_size } => {
+            if file_size < 0 {
+                return None;
+            }
+
             let mut local_data: Vec<u8> = vec![0; file_size as usize];
             buf.read_exact(&mut local_data).ok()?;
 
@@ -39,7 +60,7 @@ pub fn read_data_block<T: Read + Seek>(mut buf: T, starting_position: u64) -> Op
 
 /// A fixed version of read_data_block accounting for differing compressed block sizes in ZiPatch files.
 pub fn read_data_block_patch<T: Read + Seek>(mut buf: T) -> Option<Vec<u8>> {
-    let block_header = BlockHeader::read(&mut buf).unwrap();
+    let block_header = BlockHeader::read(&mut buf).ok()?;
 
     match block_header.compression {
         CompressionMode::Compressed {
@@ -91,3 +112,264 @@ pub fn write_data_block_patch<T: Write + Seek>(mut writer: T, data: Vec<u8>) {
     data.write(&mut writer).unwrap();
 }
 
+/// Size of the header preceding every data block.
+const BLOCK_HEADER_SIZE: u32 = 16;
+
+/// Writes `data` as a single data block, which is stored uncompressed if compression doesn't make it any smaller.
+/// Returns the size of the block, including its header and padding.
+pub fn write_data_block<T: Write + Seek>(mut writer: T, data: &[u8]) -> Option<u32> {
+    let compressed_data = no_header_compress(data).filter(|x| x.len() < data.len());
+
+    let (compression, block_data) = match &compressed_data {
+        Some(compressed_data) => (
+            CompressionMode::Compressed {
+                compressed_length: compressed_data.len() as i32,
+                decompressed_length: data.len() as i32,
+            },
+            compressed_data.as_slice(),
+        ),
+        None => (
+            CompressionMode::Uncompressed {
+                file_size: data.len() as i32,
+            },
+            data,
+        ),
+    };
+
+    let block_header = BlockHeader {
+        size: BLOCK_HEADER_SIZE,
+        compression,
+    };
+    block_header.write(&mut writer).ok()?;
+
+    writer.write_all(block_data).ok()?;
+
+    // blocks are aligned to 128 bytes
+    let unpadded_size = BLOCK_HEADER_SIZE as usize + block_data.len();
+    let padded_size = (unpadded_size + 127) & !127;
+    writer
+        .write_all(&vec![0; padded_size - unpadded_size])
+        .ok()?;
+
+    Some(padded_size as u32)
+}
+
+/// Builds a new index and dat file pair out of loose files.
+pub struct SqPackWriter {
+    /// Files keyed by their index hash, which is the order they are written in.
+    files: BTreeMap<u64, (String, ByteBuffer)>,
+}
+
+impl Default for SqPackWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SqPackWriter {
+    /// Creates a new, empty writer.
+    pub fn new() -> Self {
+        SqPackWriter {
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a file under the game `path`, replacing any file previously added with the same path.
+    pub fn add_file(&mut self, path: &str, data: &[u8]) {
+        let path = path.to_lowercase();
+
+        self.files
+            .insert(IndexFile::calculate_hash(&path), (path, data.to_vec()));
+    }
+
+    /// Models have their own entry type, anything that fails to parse as one is stored as a standard file.
+    #[cfg_attr(not(feature = "visual_data"), allow(unused_variables))]
+    pub(crate) fn write_entry(path: &str, data: &[u8]) -> Option<ByteBuffer> {
+        #[cfg(feature = "visual_data")]
+        if path.ends_with(".mdl") {
+            if let Some(entry) = DatFile::write_model_file(data) {
+                return Some(entry);
+            }
+        }
+
+        DatFile::write_standard_file(data)
+    }
+
+    /// Writes every file into a new dat, returning the contents of the `index` and `dat0` file respectively.
+    /// Returns None if any of the files can't be written.
+    pub fn finish(self) -> Option<(ByteBuffer, ByteBuffer)> {
+        let mut entries = Vec::with_capacity(self.files.len());
+
+        let mut data = ByteBuffer::new();
+        for (hash, (path, file_data)) in &self.files {
+            entries.push(IndexHashTableEntry {
+                hash: *hash,
+                is_synonym: false,
+                data_file_id: 0,
+                offset: (SQPACK_HEADER_SIZE as usize * 2 + data.len()) as u64,
+            });
+
+            data.append(&mut Self::write_entry(path, file_data)?);
+        }
+
+        let index = write_index(Platform::Win32, &entries)?;
+
+        let data_header = SqPackDataHeader {
+            size: SQPACK_HEADER_SIZE,
+            unknown: 0x10,
+            data_size: data.len() as u32,
+            spanned_dat: 1,
+            max_file_size: 2_000_000_000,
+            data_hash: Sha1::from(&data).digest().bytes(),
+        };
+
+        let mut dat = write_header(&SqPackHeader::new(Platform::Win32, SQPACK_FILE_TYPE_DATA))?;
+        dat.append(&mut write_header(&data_header)?);
+        dat.append(&mut data);
+
+        Some((index, dat))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_writer_round_trip() {
+        let mut dir = std::env::temp_dir();
+        dir.push("physis-sqpack-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let small_file = b"Hello, world!".to_vec();
+        // large enough to be split into several blocks
+        let large_file: Vec<u8> = (0..40000u32).map(|x| (x % 251) as u8).collect();
+
+        let mut writer = SqPackWriter::new();
+        writer.add_file("common/test.txt", &small_file);
+        writer.add_file("common/folder/large.bin", &large_file);
+
+        let (index, dat) = writer.finish().unwrap();
+
+        let index_path = dir.join("000000.win32.index");
+        let dat_path = dir.join("000000.win32.dat0");
+        write(&index_path, index).unwrap();
+        write(&dat_path, dat).unwrap();
+
+        let index_file = IndexFile::from_existing(index_path.to_str().unwrap()).unwrap();
+        let mut dat_file = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+
+        let entry = index_file.find_entry("common/test.txt").unwrap();
+        assert_eq!(dat_file.read_from_offset(entry.offset).unwrap(), small_file);
+
+        let entry = index_file.find_entry("common/folder/large.bin").unwrap();
+        assert_eq!(dat_file.read_from_offset(entry.offset).unwrap(), large_file);
+
+        assert!(index_file.find_entry("common/missing.txt").is_none());
+    }
+
+    #[test]
+    fn test_read_data_block_header_size() {
+        let data = b"Hello, world!";
+
+        let mut block = Cursor::new(ByteBuffer::new());
+        write_data_block(&mut block, data).unwrap();
+        let mut block = block.into_inner();
+        assert_eq!(read_data_block(Cursor::new(&block), 0).unwrap(), data);
+
+        // the header size doubles as a magic, as every block written by the game has the same one
+        block[..4].copy_from_slice(&32u32.to_le_bytes());
+        assert!(read_data_block(Cursor::new(&block), 0).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "visual_data")]
+    fn test_model_round_trip() {
+        use crate::model::MDL;
+        use std::fs::read;
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mdl_file = read(d).unwrap();
+
+        let mut dir = std::env::temp_dir();
+        dir.push("physis-sqpack-model-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = "chara/equipment/e0038/model/c0201e0038_top.mdl";
+
+        let mut writer = SqPackWriter::new();
+        writer.add_file(path, &mdl_file);
+
+        let (index, dat) = writer.finish().unwrap();
+
+        let index_path = dir.join("040000.win32.index");
+        let dat_path = dir.join("040000.win32.dat0");
+        write(&index_path, index).unwrap();
+        write(&dat_path, dat).unwrap();
+
+        let index_file = IndexFile::from_existing(index_path.to_str().unwrap()).unwrap();
+        let mut dat_file = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+
+        let entry = index_file.find_entry(path).unwrap();
+        let extracted = dat_file.read_from_offset(entry.offset).unwrap();
+
+        // The extracted model should be usable as-is by the model parser
+        let mdl = MDL::from_existing(&extracted).unwrap();
+        assert_eq!(mdl.lods.len(), 3);
+        assert_eq!(mdl.material_names.len(), 2);
+        assert_eq!(extracted, mdl_file);
+    }
+
+    #[test]
+    #[cfg(feature = "visual_data")]
+    fn test_model_edge_geometry_round_trip() {
+        use crate::model::MDL;
+        use std::fs::read;
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/tests");
+        d.push("c0201e0038_top_zeroed.mdl");
+
+        let mut mdl = MDL::from_existing(&read(d).unwrap()).unwrap();
+        let edge_geometry: Vec<u8> = (0..100).collect();
+        mdl.lods[1].edge_geometry = edge_geometry.clone();
+        mdl.update_headers();
+        let mdl_file = mdl.write_to_buffer().unwrap();
+
+        let mut dir = std::env::temp_dir();
+        dir.push("physis-sqpack-model-edge-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = "chara/equipment/e0038/model/c0201e0038_top.mdl";
+
+        let mut writer = SqPackWriter::new();
+        writer.add_file(path, &mdl_file);
+
+        let (index, dat) = writer.finish().unwrap();
+
+        let index_path = dir.join("040000.win32.index");
+        let dat_path = dir.join("040000.win32.dat0");
+        write(&index_path, index).unwrap();
+        write(&dat_path, dat).unwrap();
+
+        let index_file = IndexFile::from_existing(index_path.to_str().unwrap()).unwrap();
+        let mut dat_file = DatFile::from_existing(dat_path.to_str().unwrap()).unwrap();
+
+        let entry = index_file.find_entry(path).unwrap();
+        let extracted = dat_file.read_from_offset(entry.offset).unwrap();
+        assert_eq!(extracted, mdl_file);
+
+        let new_mdl = MDL::from_existing(&extracted).unwrap();
+        assert_eq!(new_mdl.lods[1].edge_geometry, edge_geometry);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
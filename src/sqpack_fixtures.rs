@@ -0,0 +1,52 @@
+// SPDX-FileCopyrightText: 2024 Joshua Goins <josh@redstrate.com>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Builds packed game directories for the tests that read through [GameData].
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::common::Platform;
+use crate::gamedata::GameData;
+use crate::repository::{category_to_id, string_to_category};
+use crate::sqpack::SqPackWriter;
+
+/// Packs `files` into the base repository of a new temporary game directory named `name`.
+pub(crate) fn packed_setup_data(name: &str, files: &[(&str, &[u8])]) -> GameData {
+    let mut d = std::env::temp_dir();
+    d.push(name);
+    if d.exists() {
+        fs::remove_dir_all(&d).unwrap();
+    }
+
+    let sqpack_dir = d.join("sqpack").join("ffxiv");
+    fs::create_dir_all(&sqpack_dir).unwrap();
+    fs::write(d.join("ffxivgame.ver"), "2012.01.01.0000.0000").unwrap();
+
+    // each category has its own index and dat
+    let mut writers: HashMap<u8, SqPackWriter> = HashMap::new();
+    for (path, data) in files {
+        let category = string_to_category(path.split('/').next().unwrap()).unwrap();
+
+        writers
+            .entry(category_to_id(category))
+            .or_default()
+            .add_file(path, data);
+    }
+
+    for (category, writer) in writers {
+        let (index, dat) = writer.finish().unwrap();
+        fs::write(
+            sqpack_dir.join(format!("{category:02x}0000.win32.index")),
+            index,
+        )
+        .unwrap();
+        fs::write(
+            sqpack_dir.join(format!("{category:02x}0000.win32.dat0")),
+            dat,
+        )
+        .unwrap();
+    }
+
+    GameData::from_existing(Platform::Win32, d.to_str().unwrap()).unwrap()
+}
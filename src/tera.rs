@@ -33,15 +33,21 @@ struct TerrainHeader {
     positions: Vec<PlatePosition>,
 }
 
+/// A single terrain plate, which is a model placed on the terrain grid.
 #[derive(Debug)]
-pub struct PlateModel {
-    pub position: (f32, f32),
+pub struct TerrainPlate {
+    /// Index of the plate, which is also the number in its filename.
+    pub index: u32,
+    /// Position of the center of the plate on the X and Z axes.
+    pub position: [f32; 2],
+    /// Filename of the plate model, relative to the "bgplate" directory, e.g. "0000.mdl".
     pub filename: String,
 }
 
+/// Terrain (TERA) files, which describe how the plates of a zone are laid out.
 #[derive(Debug)]
 pub struct Terrain {
-    pub plates: Vec<PlateModel>,
+    pub plates: Vec<TerrainPlate>,
 }
 
 impl Terrain {
@@ -52,12 +58,13 @@ impl Terrain {
 
         let mut plates = vec![];
 
-        for i in 0..header.plate_count {
-            plates.push(PlateModel {
-                position: (
-                    header.plate_size as f32 * (header.positions[i as usize].x as f32 + 0.5),
-                    header.plate_size as f32 * (header.positions[i as usize].y as f32 + 0.5),
-                ),
+        for (i, position) in header.positions.iter().enumerate() {
+            plates.push(TerrainPlate {
+                index: i as u32,
+                position: [
+                    header.plate_size as f32 * (position.x as f32 + 0.5),
+                    header.plate_size as f32 * (position.y as f32 + 0.5),
+                ],
                 filename: format!("{:04}.mdl", i),
             })
         }
@@ -73,6 +80,31 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_parsing() {
+        let mut buffer = vec![];
+        buffer.extend_from_slice(&1u32.to_le_bytes());
+        buffer.extend_from_slice(&2u32.to_le_bytes());
+        buffer.extend_from_slice(&128u32.to_le_bytes());
+        buffer.extend_from_slice(&1000.0f32.to_le_bytes());
+        buffer.extend_from_slice(&0.0f32.to_le_bytes());
+        buffer.resize(buffer.len() + 32, 0);
+        for position in [0i16, 0, -1, 2] {
+            buffer.extend_from_slice(&position.to_le_bytes());
+        }
+
+        let terrain = Terrain::from_existing(&buffer).unwrap();
+        assert_eq!(terrain.plates.len(), 2);
+
+        assert_eq!(terrain.plates[0].index, 0);
+        assert_eq!(terrain.plates[0].position, [64.0, 64.0]);
+        assert_eq!(terrain.plates[0].filename, "0000.mdl");
+
+        assert_eq!(terrain.plates[1].index, 1);
+        assert_eq!(terrain.plates[1].position, [-64.0, 320.0]);
+        assert_eq!(terrain.plates[1].filename, "0001.mdl");
+    }
+
     #[test]
     fn test_invalid() {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
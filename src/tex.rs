@@ -5,11 +5,13 @@
 
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
-use crate::ByteSpan;
+use crate::common::Platform;
+use crate::{ByteBuffer, ByteSpan};
 use binrw::binrw;
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite, Endian};
 use bitflags::bitflags;
 use texture2ddecoder::{decode_bc1, decode_bc3, decode_bc5};
+use tracing::info_span;
 
 // Attributes and Format are adapted from Lumina (https://github.com/NotAdam/Lumina/blob/master/src/Lumina/Data/Files/TexFile.cs)
 bitflags! {
@@ -42,21 +44,79 @@ bitflags! {
     }
 }
 
+/// The format the pixel data is stored in.
 #[binrw]
 #[brw(repr = u32)]
-#[derive(Debug)]
-enum TextureFormat {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureFormat {
+    /// 16-bit uncompressed, with 4 bits per channel.
     B4G4R4A4 = 0x1440,
+    /// 32-bit uncompressed, with 8 bits per channel.
     B8G8R8A8 = 0x1450,
+    /// Block compressed, also known as DXT1.
     BC1 = 0x3420,
+    /// Block compressed, also known as DXT5.
     BC3 = 0x3431,
+    /// Block compressed with two channels, usually used for normal maps.
     BC5 = 0x6230,
 }
 
+/// What a texture is used for, see [TextureFormat::best_for].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureUsage {
+    /// A color texture without transparency.
+    Diffuse,
+    /// A color texture with transparency.
+    DiffuseAlpha,
+    /// A normal map, where only the X and Y components are stored.
+    Normal,
+    /// A mask, such as a specular or multi texture, which uses all four channels.
+    Mask,
+}
+
+impl TextureFormat {
+    /// Suggests the format a texture used for `usage` should be compressed to, matching what the game uses.
+    pub fn best_for(usage: TextureUsage) -> TextureFormat {
+        match usage {
+            TextureUsage::Diffuse => TextureFormat::BC1,
+            TextureUsage::DiffuseAlpha => TextureFormat::BC3,
+            TextureUsage::Normal => TextureFormat::BC5,
+            TextureUsage::Mask => TextureFormat::BC3,
+        }
+    }
+
+    /// Whether this format is block compressed, in blocks of 4x4 pixels.
+    pub fn is_compressed(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::BC1 | TextureFormat::BC3 | TextureFormat::BC5
+        )
+    }
+
+    /// The size in bytes of the largest mip level of a texture in this format, which is the least a file has to
+    /// contain to be decoded.
+    pub fn surface_size(&self, width: usize, height: usize, depth: usize) -> usize {
+        if self.is_compressed() {
+            width.div_ceil(4) * height.div_ceil(4) * depth * self.bytes_per_block()
+        } else {
+            width * height * depth * self.bytes_per_block()
+        }
+    }
+
+    /// The size of a single block in bytes. Uncompressed formats have a block per pixel.
+    pub fn bytes_per_block(&self) -> usize {
+        match self {
+            TextureFormat::B4G4R4A4 => 2,
+            TextureFormat::B8G8R8A8 => 4,
+            TextureFormat::BC1 => 8,
+            TextureFormat::BC3 | TextureFormat::BC5 => 16,
+        }
+    }
+}
+
 #[binrw]
 #[derive(Debug)]
 #[allow(dead_code)]
-#[brw(little)]
 struct TexHeader {
     attribute: TextureAttribute,
     format: TextureFormat,
@@ -87,24 +147,140 @@ pub struct Texture {
     pub height: u32,
     /// Depth of the texture in pixels
     pub depth: u32,
-    /// Raw RGBA data
+    /// The format the texture was stored in, before being decoded into `rgba`
+    pub format: TextureFormat,
+    /// Number of mip levels stored in the file, only the first one is decoded into `rgba`
+    pub mip_levels: u32,
+    /// Raw RGBA data, with 8 bits per channel. Formats stored in another channel order (such as BGRA) are converted
     pub rgba: Vec<u8>,
 }
 
+/// A PC texture along with the header it was read with, so it can be written back with the same attributes, format
+/// and mip levels.
+#[derive(Debug)]
+pub struct TexturePreserved {
+    /// The decoded texture, its pixels can be changed with [Texture::replace_rgba]
+    pub texture: Texture,
+    header: TexHeader,
+}
+
 type DecodeFunction = fn(&[u8], usize, usize, &mut [u32]) -> Result<(), &'static str>;
 
 impl Texture {
     /// Reads an existing TEX file
     pub fn from_existing(buffer: ByteSpan) -> Option<Texture> {
-        let mut cursor = Cursor::new(buffer);
-        let header = TexHeader::read(&mut cursor).ok()?;
+        Texture::from_existing_platform(buffer, Platform::Win32)
+    }
+
+    /// Whether `buffer` starts with a plausible texture header, which has a texture type, a known format and a size.
+    /// TEX files don't have a magic, so this is the best we can do.
+    pub(crate) fn is_texture(buffer: ByteSpan) -> bool {
+        let Ok(header) = TexHeader::read_le(&mut Cursor::new(buffer)) else {
+            return false;
+        };
+
+        header
+            .attribute
+            .intersects(TextureAttribute::TEXTURE_TYPE_MASK)
+            && header.width != 0
+            && header.height != 0
+    }
+
+    /// Reads several TEX files at once, returning them in the same order as `buffers`. Textures that fail to
+    /// decode are `None`.
+    ///
+    /// With the `parallel` feature, the textures are decoded concurrently, which is much faster when decoding
+    /// lots of block compressed textures.
+    pub fn from_existing_batch(buffers: &[ByteSpan]) -> Vec<Option<Texture>> {
+        let _span = info_span!("decode_textures", count = buffers.len()).entered();
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            buffers
+                .par_iter()
+                .map(|buffer| Texture::from_existing(buffer))
+                .collect()
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        buffers
+            .iter()
+            .map(|buffer| Texture::from_existing(buffer))
+            .collect()
+    }
 
-        cursor
+    /// Reads an existing TEX file built for `platform`. Big-endian (PS3) textures are byte-swapped,
+    /// and swizzled uncompressed PS3 textures are detiled.
+    ///
+    /// Block compressed formats are assumed to already be in PC order, and PS4 tiling isn't supported yet.
+    pub fn from_existing_platform(buffer: ByteSpan, platform: Platform) -> Option<Texture> {
+        let _span = info_span!("parse_texture", bytes = buffer.len()).entered();
+
+        Texture::from_reader_platform(&mut Cursor::new(buffer), platform)
+    }
+
+    /// Reads an existing TEX file from `reader`, such as a file handle, instead of a buffer holding all of it.
+    /// The texture has to start at the beginning of `reader`.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Option<Texture> {
+        Texture::from_reader_platform(reader, Platform::Win32)
+    }
+
+    /// Same as [Texture::from_existing_platform], but reads from `reader` like [Texture::from_reader].
+    pub fn from_reader_platform<R: Read + Seek>(
+        reader: &mut R,
+        platform: Platform,
+    ) -> Option<Texture> {
+        Texture::read_with_header(reader, platform).map(|(texture, _)| texture)
+    }
+
+    fn read_with_header<R: Read + Seek>(
+        reader: &mut R,
+        platform: Platform,
+    ) -> Option<(Texture, TexHeader)> {
+        let endian = platform.endianness();
+
+        let header = TexHeader::read_options(reader, endian, ()).ok()?;
+
+        reader
             .seek(SeekFrom::Start(std::mem::size_of::<TexHeader>() as u64))
             .ok()?;
 
-        let mut src = vec![0u8; buffer.len() - std::mem::size_of::<TexHeader>()];
-        cursor.read_exact(src.as_mut_slice()).ok()?;
+        let mut src = vec![];
+        reader.read_to_end(&mut src).ok()?;
+
+        // a truncated file would otherwise decode into garbage, or read past the end of src
+        if src.len()
+            < header.format.surface_size(
+                header.width as usize,
+                header.height as usize,
+                header.depth as usize,
+            )
+        {
+            return None;
+        }
+
+        if !header.format.is_compressed() {
+            let bytes_per_pixel = header.format.bytes_per_block();
+
+            // pixels are stored as a single integer, so their bytes are in the opposite order
+            if endian == Endian::Big {
+                src.chunks_exact_mut(bytes_per_pixel)
+                    .for_each(|pixel| pixel.reverse());
+            }
+
+            if platform == Platform::PS3
+                && header.attribute.contains(TextureAttribute::TEXTURE_SWIZZLE)
+            {
+                Texture::unswizzle(
+                    &mut src,
+                    header.width as usize,
+                    header.height as usize,
+                    bytes_per_pixel,
+                );
+            }
+        }
 
         let mut dst: Vec<u8>;
 
@@ -165,7 +341,7 @@ impl Texture {
                     header.width as usize,
                     header.height as usize * header.depth as usize,
                     decode_bc1,
-                );
+                )?;
             }
             TextureFormat::BC3 => {
                 dst = Texture::decode(
@@ -173,7 +349,7 @@ impl Texture {
                     header.width as usize,
                     header.height as usize * header.depth as usize,
                     decode_bc3,
-                );
+                )?;
             }
             TextureFormat::BC5 => {
                 dst = Texture::decode(
@@ -181,11 +357,11 @@ impl Texture {
                     header.width as usize,
                     header.height as usize * header.depth as usize,
                     decode_bc5,
-                );
+                )?;
             }
         }
 
-        Some(Texture {
+        let texture = Texture {
             texture_type: if header.attribute.contains(TextureAttribute::TEXTURE_TYPE3_D) {
                 TextureType::ThreeDimensional
             } else {
@@ -194,21 +370,341 @@ impl Texture {
             width: header.width as u32,
             height: header.height as u32,
             depth: header.depth as u32,
+            format: header.format,
+            mip_levels: header.mip_levels as u32,
             rgba: dst,
-        })
+        };
+
+        Some((texture, header))
     }
 
-    fn decode(src: &[u8], width: usize, height: usize, decode_func: DecodeFunction) -> Vec<u8> {
-        let mut image: Vec<u32> = vec![0; width * height];
-        decode_func(src, width, height, &mut image).unwrap();
+    /// Replaces the pixels of the texture with `rgba`, which has to be the same size as [Texture::rgba]. Returns false
+    /// and leaves the texture untouched otherwise.
+    pub fn replace_rgba(&mut self, rgba: &[u8]) -> bool {
+        if rgba.len() != self.rgba.len() {
+            return false;
+        }
+
+        self.rgba.copy_from_slice(rgba);
+
+        true
+    }
 
-        image
+    /// Halves the size of an RGBA image, by averaging each 2x2 square of pixels.
+    fn downscale(rgba: &[u8], width: usize, height: usize) -> (Vec<u8>, usize, usize) {
+        let new_width = (width / 2).max(1);
+        let new_height = (height / 2).max(1);
+
+        let mut dst = vec![0u8; new_width * new_height * 4];
+        for y in 0..new_height {
+            for x in 0..new_width {
+                for channel in 0..4 {
+                    let mut sum = 0u32;
+                    for (sample_x, sample_y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let src_x = (x * 2 + sample_x).min(width - 1);
+                        let src_y = (y * 2 + sample_y).min(height - 1);
+                        sum += rgba[(src_y * width + src_x) * 4 + channel] as u32;
+                    }
+
+                    dst[(y * new_width + x) * 4 + channel] = ((sum + 2) / 4) as u8;
+                }
+            }
+        }
+
+        (dst, new_width, new_height)
+    }
+
+    /// Converts an RGBA image into `format`, the opposite of what's done when reading a texture.
+    fn encode(rgba: &[u8], width: usize, height: usize, format: TextureFormat) -> Vec<u8> {
+        match format {
+            TextureFormat::B4G4R4A4 => rgba
+                .chunks_exact(4)
+                .flat_map(|pixel| {
+                    let to_4_bits = |channel: u8| (channel as u16 + 8) / 17;
+
+                    (to_4_bits(pixel[2])
+                        | (to_4_bits(pixel[1]) << 4)
+                        | (to_4_bits(pixel[0]) << 8)
+                        | (to_4_bits(pixel[3]) << 12))
+                        .to_be_bytes()
+                })
+                .collect(),
+            TextureFormat::B8G8R8A8 => rgba
+                .chunks_exact(4)
+                .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+                .collect(),
+            TextureFormat::BC1 | TextureFormat::BC3 | TextureFormat::BC5 => {
+                let mut dst = vec![];
+
+                for block_y in 0..height.div_ceil(4) {
+                    for block_x in 0..width.div_ceil(4) {
+                        // pixels past the edge repeat the last row or column
+                        let mut block = [[0u8; 4]; 16];
+                        for (i, texel) in block.iter_mut().enumerate() {
+                            let x = (block_x * 4 + i % 4).min(width - 1);
+                            let y = (block_y * 4 + i / 4).min(height - 1);
+                            let offset = (y * width + x) * 4;
+                            texel.copy_from_slice(&rgba[offset..offset + 4]);
+                        }
+
+                        let channel = |channel: usize| block.map(|texel| texel[channel]);
+
+                        match format {
+                            TextureFormat::BC1 => {
+                                dst.extend_from_slice(&Texture::encode_color_block(&block))
+                            }
+                            TextureFormat::BC3 => {
+                                dst.extend_from_slice(&Texture::encode_alpha_block(&channel(3)));
+                                dst.extend_from_slice(&Texture::encode_color_block(&block));
+                            }
+                            _ => {
+                                dst.extend_from_slice(&Texture::encode_alpha_block(&channel(0)));
+                                dst.extend_from_slice(&Texture::encode_alpha_block(&channel(1)));
+                            }
+                        }
+                    }
+                }
+
+                dst
+            }
+        }
+    }
+
+    /// Compresses the colors of a 4x4 block, using the two most different colors as the endpoints. The block is
+    /// always in four color mode, so BC1 alpha is dropped.
+    fn encode_color_block(block: &[[u8; 4]; 16]) -> [u8; 8] {
+        let to_565 = |color: [u8; 3]| {
+            ((color[0] as u16 >> 3) << 11) | ((color[1] as u16 >> 2) << 5) | (color[2] as u16 >> 3)
+        };
+        let from_565 = |color: u16| {
+            let r = (color >> 11) as i32 & 0x1F;
+            let g = (color >> 5) as i32 & 0x3F;
+            let b = color as i32 & 0x1F;
+
+            [
+                (r << 3) | (r >> 2),
+                (g << 2) | (g >> 4),
+                (b << 3) | (b >> 2),
+            ]
+        };
+
+        let distance = |a: &[u8; 4], b: &[u8; 4]| -> i32 {
+            (0..3)
+                .map(|channel| (a[channel] as i32 - b[channel] as i32).pow(2))
+                .sum()
+        };
+
+        // the two texels furthest apart are the endpoints
+        let mut endpoints = (block[0], block[0]);
+        for (i, a) in block.iter().enumerate() {
+            for b in &block[i + 1..] {
+                if distance(a, b) > distance(&endpoints.0, &endpoints.1) {
+                    endpoints = (*a, *b);
+                }
+            }
+        }
+
+        let mut color0 = to_565([endpoints.0[0], endpoints.0[1], endpoints.0[2]]);
+        let mut color1 = to_565([endpoints.1[0], endpoints.1[1], endpoints.1[2]]);
+        // the first color has to be larger, otherwise the block has three colors and transparency
+        if color0 < color1 {
+            std::mem::swap(&mut color0, &mut color1);
+        }
+
+        let mut data = [0u8; 8];
+        data[..2].copy_from_slice(&color0.to_le_bytes());
+        data[2..4].copy_from_slice(&color1.to_le_bytes());
+
+        // with equal endpoints, every texel is the first one
+        if color0 == color1 {
+            return data;
+        }
+
+        let endpoint0 = from_565(color0);
+        let endpoint1 = from_565(color1);
+        let palette: [[i32; 3]; 4] = [
+            endpoint0,
+            endpoint1,
+            std::array::from_fn(|i| (2 * endpoint0[i] + endpoint1[i]) / 3),
+            std::array::from_fn(|i| (endpoint0[i] + 2 * endpoint1[i]) / 3),
+        ];
+
+        let mut indices = 0u32;
+        for (i, texel) in block.iter().enumerate() {
+            let index = Texture::nearest(&palette, |color| {
+                (0..3)
+                    .map(|channel| (color[channel] - texel[channel] as i32).pow(2))
+                    .sum()
+            });
+            indices |= (index as u32) << (i * 2);
+        }
+        data[4..].copy_from_slice(&indices.to_le_bytes());
+
+        data
+    }
+
+    /// Compresses a single channel of a 4x4 block, such as the alpha of BC3 or either channel of BC5, using the
+    /// minimum and maximum as the endpoints.
+    fn encode_alpha_block(values: &[u8; 16]) -> [u8; 8] {
+        let max = *values.iter().max().unwrap_or(&0);
+        let min = *values.iter().min().unwrap_or(&0);
+
+        let mut data = [0u8; 8];
+        data[0] = max;
+        data[1] = min;
+
+        // with equal endpoints, every value is the first one
+        if max == min {
+            return data;
+        }
+
+        // the first endpoint being larger selects the mode with six interpolated values
+        let palette: [i32; 8] = std::array::from_fn(|i| match i {
+            0 => max as i32,
+            1 => min as i32,
+            _ => ((8 - i as i32) * max as i32 + (i as i32 - 1) * min as i32) / 7,
+        });
+
+        let mut indices = 0u64;
+        for (i, value) in values.iter().enumerate() {
+            let index = Texture::nearest(&palette, |entry| (entry - *value as i32).abs());
+            indices |= (index as u64) << (i * 3);
+        }
+        data[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+
+        data
+    }
+
+    /// Returns the index of the entry in `palette` with the smallest `distance`.
+    fn nearest<T>(palette: &[T], distance: impl Fn(&T) -> i32) -> usize {
+        palette
             .iter()
-            .flat_map(|x| {
-                let v = x.to_le_bytes();
-                [v[2], v[1], v[0], v[3]]
-            })
-            .collect::<Vec<u8>>()
+            .enumerate()
+            .min_by_key(|(_, entry)| distance(entry))
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// Rebuilds the blue channel of a normal map from its red and green channels, as `B = sqrt(1 - R² - G²)` with
+    /// each channel mapped from [0, 255] to [-1, 1]. This is meant for [TextureFormat::BC5] textures, which only
+    /// store the X and Y of each normal, so they can be used as regular normal maps.
+    pub fn reconstruct_normal_z(&mut self) {
+        let to_unit = |channel: u8| channel as f32 / 255.0 * 2.0 - 1.0;
+
+        for texel in self.rgba.chunks_exact_mut(4) {
+            let x = to_unit(texel[0]);
+            let y = to_unit(texel[1]);
+            let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+
+            texel[2] = ((z + 1.0) / 2.0 * 255.0).round() as u8;
+        }
+    }
+
+    /// Reorders the first surface of a texture swizzled in Morton (Z) order, such as on the PS3, into
+    /// linear order. Textures whose size isn't a power of two are never swizzled, and are left as-is.
+    fn unswizzle(src: &mut [u8], width: usize, height: usize, bytes_per_pixel: usize) {
+        let size = width * height * bytes_per_pixel;
+        if !width.is_power_of_two() || !height.is_power_of_two() || src.len() < size {
+            return;
+        }
+
+        let swizzled = src[..size].to_vec();
+
+        for y in 0..height {
+            for x in 0..width {
+                // interleave the bits of x and y, until one of them runs out
+                let (mut remaining_x, mut remaining_y) = (x, y);
+                let (mut bits_x, mut bits_y) = (width, height);
+                let mut index = 0;
+                let mut shift = 0;
+                while bits_x > 1 || bits_y > 1 {
+                    if bits_x > 1 {
+                        index |= (remaining_x & 1) << shift;
+                        remaining_x >>= 1;
+                        bits_x >>= 1;
+                        shift += 1;
+                    }
+                    if bits_y > 1 {
+                        index |= (remaining_y & 1) << shift;
+                        remaining_y >>= 1;
+                        bits_y >>= 1;
+                        shift += 1;
+                    }
+                }
+
+                let linear = (y * width + x) * bytes_per_pixel;
+                let index = index * bytes_per_pixel;
+                src[linear..linear + bytes_per_pixel]
+                    .copy_from_slice(&swizzled[index..index + bytes_per_pixel]);
+            }
+        }
+    }
+
+    fn decode(
+        src: &[u8],
+        width: usize,
+        height: usize,
+        decode_func: DecodeFunction,
+    ) -> Option<Vec<u8>> {
+        let mut image: Vec<u32> = vec![0; width * height];
+        decode_func(src, width, height, &mut image).ok()?;
+
+        Some(
+            image
+                .iter()
+                .flat_map(|x| {
+                    let v = x.to_le_bytes();
+                    [v[2], v[1], v[0], v[3]]
+                })
+                .collect::<Vec<u8>>(),
+        )
+    }
+}
+
+impl TexturePreserved {
+    /// Reads an existing PC TEX file, keeping its header around for
+    /// [TexturePreserved::write_to_buffer_preserving_header].
+    pub fn from_existing(buffer: ByteSpan) -> Option<TexturePreserved> {
+        let (texture, header) =
+            Texture::read_with_header(&mut Cursor::new(buffer), Platform::Win32)?;
+
+        Some(TexturePreserved { texture, header })
+    }
+
+    /// Writes the texture with the header it was read with, so its attributes, format, mip levels and their offsets
+    /// stay the same. The pixels are compressed back into the original format, and every mip level is regenerated
+    /// from [Texture::rgba].
+    ///
+    /// Returns None for 3D textures.
+    pub fn write_to_buffer_preserving_header(&self) -> Option<ByteBuffer> {
+        let header = &self.header;
+        if header.depth > 1 {
+            return None;
+        }
+
+        let mut buffer = ByteBuffer::new();
+        header.write_le(&mut Cursor::new(&mut buffer)).ok()?;
+
+        let mut width = header.width as usize;
+        let mut height = header.height as usize;
+        let mut level = self.texture.rgba.clone();
+
+        let mip_levels = (header.mip_levels as usize).clamp(1, header.offset_to_surface.len());
+        for mip in 0..mip_levels {
+            let offset = header.offset_to_surface[mip] as usize;
+            if offset < std::mem::size_of::<TexHeader>() {
+                return None;
+            }
+
+            let encoded = Texture::encode(&level, width, height, header.format);
+            if buffer.len() < offset + encoded.len() {
+                buffer.resize(offset + encoded.len(), 0);
+            }
+            buffer[offset..offset + encoded.len()].copy_from_slice(&encoded);
+
+            (level, width, height) = Texture::downscale(&level, width, height);
+        }
+
+        Some(buffer)
     }
 }
 
@@ -227,5 +723,342 @@ mod tests {
 
         // Feeding it invalid data should not panic
         Texture::from_existing(&read(d).unwrap());
+
+        // a valid header, but missing the last pixel
+        let data = build_texture(Endian::Little, false, TextureFormat::B8G8R8A8, 4, 2, 1);
+        assert!(Texture::from_existing(&data[..data.len() - 4]).is_none());
+
+        // a 8x8 BC1 texture needs four blocks, but only has one
+        let data = build_texture(Endian::Little, false, TextureFormat::BC1, 8, 8, 1);
+        assert_eq!(data.len(), 80 + 4 * 8);
+        assert!(Texture::from_existing(&data[..80 + 8]).is_none());
+        assert!(Texture::from_existing(&data).is_some());
+    }
+
+    #[test]
+    fn test_best_format() {
+        assert_eq!(
+            TextureFormat::best_for(TextureUsage::Diffuse),
+            TextureFormat::BC1
+        );
+        assert_eq!(
+            TextureFormat::best_for(TextureUsage::DiffuseAlpha),
+            TextureFormat::BC3
+        );
+        assert_eq!(
+            TextureFormat::best_for(TextureUsage::Normal),
+            TextureFormat::BC5
+        );
+        assert_eq!(
+            TextureFormat::best_for(TextureUsage::Mask),
+            TextureFormat::BC3
+        );
+
+        assert!(TextureFormat::BC1.is_compressed());
+        assert!(!TextureFormat::B8G8R8A8.is_compressed());
+        assert_eq!(TextureFormat::BC1.bytes_per_block(), 8);
+        assert_eq!(TextureFormat::BC5.bytes_per_block(), 16);
+        assert_eq!(TextureFormat::B4G4R4A4.bytes_per_block(), 2);
+
+        // partial blocks still take up a whole block
+        assert_eq!(TextureFormat::BC1.surface_size(6, 4, 1), 16);
+        assert_eq!(TextureFormat::B8G8R8A8.surface_size(4, 2, 3), 96);
+    }
+
+    /// Builds a `width`x`height` texture with `mip_levels` surfaces stored one after the other. Only the first
+    /// surface of B8G8R8A8 textures has pixels, pixel `i` being (i, 2 * i, 3 * i, 255) in RGBA, the rest is zeroed.
+    /// Swizzled textures have to be 4x2.
+    fn build_texture(
+        endian: Endian,
+        swizzled: bool,
+        format: TextureFormat,
+        width: u16,
+        height: u16,
+        mip_levels: u16,
+    ) -> Vec<u8> {
+        let write_u16 = |data: &mut Vec<u8>, value: u16| match endian {
+            Endian::Big => data.extend_from_slice(&value.to_be_bytes()),
+            Endian::Little => data.extend_from_slice(&value.to_le_bytes()),
+        };
+        let write_u32 = |data: &mut Vec<u8>, value: u32| match endian {
+            Endian::Big => data.extend_from_slice(&value.to_be_bytes()),
+            Endian::Little => data.extend_from_slice(&value.to_le_bytes()),
+        };
+
+        let mut attribute = TextureAttribute::TEXTURE_TYPE2_D;
+        if swizzled {
+            attribute |= TextureAttribute::TEXTURE_SWIZZLE;
+        }
+
+        let mut data = vec![];
+        write_u32(&mut data, attribute.bits());
+        write_u32(&mut data, format as u32);
+        write_u16(&mut data, width);
+        write_u16(&mut data, height);
+        write_u16(&mut data, 1);
+        write_u16(&mut data, mip_levels);
+        for offset in [0u32, 0, 0] {
+            write_u32(&mut data, offset);
+        }
+
+        let mut offset = 80;
+        for mip in 0..mip_levels as usize {
+            write_u32(&mut data, offset as u32);
+            offset += format.surface_size(
+                (width as usize >> mip).max(1),
+                (height as usize >> mip).max(1),
+                1,
+            );
+        }
+        data.resize(80, 0);
+
+        if format == TextureFormat::B8G8R8A8 {
+            // the order pixels are stored in, for a 4x2 texture interleaving the bits as x0, y0, x1
+            let order: Vec<u8> = if swizzled {
+                vec![0, 1, 4, 5, 2, 3, 6, 7]
+            } else {
+                (0..width * height).map(|i| i as u8).collect()
+            };
+
+            for i in order {
+                // a single BGRA pixel
+                write_u32(&mut data, u32::from_le_bytes([3 * i, 2 * i, i, 255]));
+            }
+        }
+        data.resize(offset, 0);
+
+        data
+    }
+
+    #[test]
+    fn test_b4g4r4a4() {
+        // the first byte holds alpha and red, the second green and blue
+        let mut data = build_texture(Endian::Little, false, TextureFormat::B4G4R4A4, 4, 2, 1);
+        for pixel in data[80..].chunks_exact_mut(2) {
+            pixel.copy_from_slice(&[0x12, 0x34]);
+        }
+
+        let texture = Texture::from_existing(&data).unwrap();
+        for pixel in texture.rgba.chunks_exact(4) {
+            assert_eq!(pixel, [0x22, 0x33, 0x44, 0x11]);
+        }
+
+        // big-endian textures store each pixel the other way around
+        let mut data = build_texture(Endian::Big, false, TextureFormat::B4G4R4A4, 4, 2, 1);
+        for pixel in data[80..].chunks_exact_mut(2) {
+            pixel.copy_from_slice(&[0x34, 0x12]);
+        }
+
+        let ps3 = Texture::from_existing_platform(&data, Platform::PS3).unwrap();
+        assert_eq!(ps3.rgba, texture.rgba);
+
+        assert_eq!(
+            Texture::encode(&texture.rgba, 4, 2, TextureFormat::B4G4R4A4),
+            [0x12, 0x34].repeat(8)
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_normal_z() {
+        let mut data = vec![];
+        data.extend_from_slice(&TextureAttribute::TEXTURE_TYPE2_D.bits().to_le_bytes());
+        data.extend_from_slice(&0x6230u32.to_le_bytes()); // BC5
+        for value in [4u16, 4, 1, 1] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.resize(28, 0);
+        data.extend_from_slice(&80u32.to_le_bytes()); // first surface
+        data.resize(80, 0);
+
+        // a single block of flat normals, where both channels are always 128
+        for _ in 0..2 {
+            data.extend_from_slice(&[128, 128, 0, 0, 0, 0, 0, 0]);
+        }
+
+        let mut texture = Texture::from_existing(&data).unwrap();
+        assert_eq!(texture.format, TextureFormat::BC5);
+
+        texture.reconstruct_normal_z();
+        for texel in texture.rgba.chunks_exact(4) {
+            assert_eq!(texel[..3], [128, 128, 255]);
+        }
+    }
+
+    #[test]
+    fn test_big_endian_header() {
+        let data = build_texture(Endian::Big, false, TextureFormat::B8G8R8A8, 4, 2, 1);
+
+        let header = TexHeader::read_options(&mut Cursor::new(&data), Endian::Big, ()).unwrap();
+        assert!(header.attribute.contains(TextureAttribute::TEXTURE_TYPE2_D));
+        assert!(matches!(header.format, TextureFormat::B8G8R8A8));
+        assert_eq!(header.width, 4);
+        assert_eq!(header.height, 2);
+        assert_eq!(header.depth, 1);
+        assert_eq!(header.mip_levels, 1);
+        assert_eq!(header.offset_to_surface[0], 80);
+
+        // reading it as little-endian gives garbage
+        assert!(Texture::from_existing(&data).is_none());
+    }
+
+    #[test]
+    fn test_bgra_channel_order() {
+        let mut data = build_texture(Endian::Little, false, TextureFormat::B8G8R8A8, 4, 2, 1);
+
+        // solid red, stored as BGRA
+        for pixel in data[80..].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[0, 0, 255, 255]);
+        }
+
+        let texture = Texture::from_existing(&data).unwrap();
+        for pixel in texture.rgba.chunks_exact(4) {
+            assert_eq!(pixel, [255, 0, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let data = build_texture(Endian::Big, false, TextureFormat::B8G8R8A8, 4, 2, 1);
+        let expected = Texture::from_existing_platform(&data, Platform::PS3).unwrap();
+
+        let texture =
+            Texture::from_reader_platform(&mut Cursor::new(data.clone()), Platform::PS3).unwrap();
+        assert_eq!(texture.rgba, expected.rgba);
+
+        assert!(Texture::from_reader(&mut Cursor::new(vec![0u8; 4])).is_none());
+    }
+
+    #[test]
+    fn test_batch() {
+        let texture = build_texture(Endian::Little, false, TextureFormat::B8G8R8A8, 4, 2, 1);
+        let invalid = vec![0u8; 4];
+
+        let textures = Texture::from_existing_batch(&[
+            &texture,
+            &invalid,
+            &texture,
+            &build_texture(Endian::Big, false, TextureFormat::B8G8R8A8, 4, 2, 1),
+        ]);
+        assert_eq!(textures.len(), 4);
+
+        let expected = Texture::from_existing(&texture).unwrap();
+        assert_eq!(textures[0].as_ref().unwrap().rgba, expected.rgba);
+        assert!(textures[1].is_none());
+        assert_eq!(textures[2].as_ref().unwrap().rgba, expected.rgba);
+        // the batch is always read as PC textures
+        assert_ne!(
+            textures[3].as_ref().map(|texture| &texture.rgba),
+            Some(&expected.rgba)
+        );
+    }
+
+    #[test]
+    fn test_replace_pixels() {
+        let original = build_texture(Endian::Little, false, TextureFormat::B8G8R8A8, 4, 2, 1);
+        let mut preserved = TexturePreserved::from_existing(&original).unwrap();
+
+        // the size has to match
+        assert!(!preserved.texture.replace_rgba(&[0; 4]));
+
+        let rgba: Vec<u8> = (0..8u8).flat_map(|i| [255 - i, i, 100, 200]).collect();
+        assert!(preserved.texture.replace_rgba(&rgba));
+
+        let buffer = preserved.write_to_buffer_preserving_header().unwrap();
+        assert_eq!(buffer[..80], original[..80]);
+        assert_eq!(buffer.len(), original.len());
+        assert_eq!(Texture::from_existing(&buffer).unwrap().rgba, rgba);
+    }
+
+    #[test]
+    fn test_replace_pixels_compressed() {
+        // an 8x4 BC3 texture with two mip levels, the second one starting at 112
+        let original = build_texture(Endian::Little, false, TextureFormat::BC3, 8, 4, 2);
+        assert_eq!(original.len(), 80 + 2 * 16 + 16);
+
+        let mut preserved = TexturePreserved::from_existing(&original).unwrap();
+
+        // the left block is opaque red, and the right one is translucent blue
+        let rgba: Vec<u8> = (0..32)
+            .flat_map(|i| {
+                if i % 8 < 4 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0, 0, 255, 128]
+                }
+            })
+            .collect();
+        assert!(preserved.texture.replace_rgba(&rgba));
+
+        let buffer = preserved.write_to_buffer_preserving_header().unwrap();
+        assert_eq!(buffer[..80], original[..80]);
+        assert_eq!(buffer.len(), original.len());
+
+        let texture = Texture::from_existing(&buffer).unwrap();
+        assert_eq!(texture.format, TextureFormat::BC3);
+        assert_eq!(texture.mip_levels, 2);
+        assert_eq!(texture.rgba, rgba);
+
+        // the second mip level is half the size, and its two halves still have different alpha
+        let mip = Texture::decode(&buffer[112..], 4, 4, decode_bc3).unwrap();
+        for (i, texel) in mip.chunks_exact(4).take(8).enumerate() {
+            assert_eq!(texel[3], if i % 4 < 2 { 255 } else { 128 });
+        }
+    }
+
+    #[test]
+    fn test_encode_blocks() {
+        // a gradient stays close to the original after being compressed
+        let block: [[u8; 4]; 16] =
+            std::array::from_fn(|i| [i as u8 * 16, 255 - i as u8 * 16, 64, 255]);
+        let encoded = Texture::encode_color_block(&block);
+        let decoded = Texture::decode(&encoded, 4, 4, decode_bc1).unwrap();
+        for (texel, expected) in decoded.chunks_exact(4).zip(&block) {
+            for channel in 0..3 {
+                assert!((texel[channel] as i32 - expected[channel] as i32).abs() <= 48);
+            }
+        }
+
+        let values: [u8; 16] = std::array::from_fn(|i| i as u8 * 17);
+        let mut encoded = Texture::encode_alpha_block(&values).to_vec();
+        encoded.extend_from_slice(&[0; 8]);
+        let decoded = Texture::decode(&encoded, 4, 4, decode_bc5).unwrap();
+        for (texel, expected) in decoded.chunks_exact(4).zip(values) {
+            assert!((texel[0] as i32 - expected as i32).abs() <= 20);
+        }
+    }
+
+    #[test]
+    fn test_platforms() {
+        let expected: Vec<u8> = (0..8u8).flat_map(|i| [i, 2 * i, 3 * i, 255]).collect();
+
+        let texture = Texture::from_existing(&build_texture(
+            Endian::Little,
+            false,
+            TextureFormat::B8G8R8A8,
+            4,
+            2,
+            1,
+        ))
+        .unwrap();
+        assert_eq!(texture.format, TextureFormat::B8G8R8A8);
+        assert_eq!(texture.mip_levels, 1);
+        assert_eq!(texture.width, 4);
+        assert_eq!(texture.height, 2);
+        assert_eq!(texture.depth, 1);
+        assert_eq!(texture.rgba, expected);
+
+        let texture = Texture::from_existing_platform(
+            &build_texture(Endian::Big, false, TextureFormat::B8G8R8A8, 4, 2, 1),
+            Platform::PS3,
+        )
+        .unwrap();
+        assert_eq!(texture.rgba, expected);
+
+        let texture = Texture::from_existing_platform(
+            &build_texture(Endian::Big, true, TextureFormat::B8G8R8A8, 4, 2, 1),
+            Platform::PS3,
+        )
+        .unwrap();
+        assert_eq!(texture.rgba, expected);
     }
 }
@@ -4,9 +4,12 @@
 use std::env;
 use std::fs::read;
 
-use physis::common::Platform;
+use physis::common::{Language, Platform};
+use physis::equipment::{build_equipment_path, resolve_equipment_model_path, Slot};
+use physis::exd::EXD;
 use physis::fiin::FileInfo;
 use physis::index;
+use physis::race::{Gender, Race, Subrace};
 
 #[test]
 #[cfg_attr(not(feature = "retail_game_testing"), ignore)]
@@ -32,6 +35,50 @@ fn test_gamedata_extract() {
     assert!(gamedata.extract("exd/root.exl").is_some());
 }
 
+#[test]
+#[cfg_attr(not(feature = "retail_game_testing"), ignore)]
+fn test_excel_sheet_languages() {
+    let game_dir = env::var("FFXIV_GAME_DIR").unwrap();
+
+    let mut gamedata = physis::gamedata::GameData::from_existing(
+        Platform::Win32,
+        format!("{}/game", game_dir).as_str(),
+    )
+    .unwrap();
+
+    let exh = gamedata.read_excel_sheet_header("Item").unwrap();
+    for language in [
+        Language::English,
+        Language::Japanese,
+        Language::German,
+        Language::French,
+    ] {
+        assert!(exh.languages().contains(&language));
+    }
+}
+
+#[test]
+#[cfg_attr(not(feature = "retail_game_testing"), ignore)]
+fn test_excel_sheet_round_trip() {
+    let game_dir = env::var("FFXIV_GAME_DIR").unwrap();
+
+    let mut gamedata = physis::gamedata::GameData::from_existing(
+        Platform::Win32,
+        format!("{}/game", game_dir).as_str(),
+    )
+    .unwrap();
+
+    let exh = gamedata.read_excel_sheet_header("Item").unwrap();
+    let exd = gamedata
+        .read_excel_sheet("Item", &exh, Language::English, 0)
+        .unwrap();
+
+    let buffer = exd.write_to_buffer(&exh).unwrap();
+    let new_exd = EXD::from_existing(&exh, &buffer).unwrap();
+
+    assert_eq!(new_exd.rows, exd.rows);
+}
+
 #[test]
 #[cfg_attr(not(feature = "retail_game_testing"), ignore)]
 fn test_fiin() {
@@ -43,3 +90,31 @@ fn test_fiin() {
     assert_eq!(fiin.entries[0].file_name, "steam_api.dll");
     assert_eq!(fiin.entries[1].file_name, "steam_api64.dll");
 }
+
+#[test]
+#[cfg_attr(not(feature = "retail_game_testing"), ignore)]
+fn test_resolve_equipment_model_path() {
+    let game_dir = env::var("FFXIV_GAME_DIR").unwrap();
+
+    let mut gamedata = physis::gamedata::GameData::from_existing(
+        Platform::Win32,
+        format!("{}/game", game_dir).as_str(),
+    )
+    .unwrap();
+
+    // Highlander males don't have their own smallclothes, so the Midlander ones are used instead
+    let unique_path =
+        build_equipment_path(0, Race::Hyur, Subrace::Highlander, Gender::Male, Slot::Body);
+    assert!(!gamedata.exists(&unique_path));
+
+    let path = resolve_equipment_model_path(
+        &mut gamedata,
+        0,
+        Race::Hyur,
+        Subrace::Highlander,
+        Gender::Male,
+        Slot::Body,
+    )
+    .unwrap();
+    assert_eq!(path, "chara/equipment/e0000/model/c0101e0000_top.mdl");
+}